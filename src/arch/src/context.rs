@@ -1,19 +1,88 @@
+use utils::{SysError, SysResult};
+
 use super::FeatureSet;
 
+// Initial values of the x87 control word and MXCSR, as set by linux on exec.
+const FPU_CWD_INIT: u16 = 0x37f;
+const MXCSR_INIT: u32 = 0x1f80;
+// MXCSR mask assumed when the cpu reports none, in which case DAZ is not supported.
+const MXCSR_DEFAULT_MASK: u32 = 0xffbf;
+
 #[derive(Debug)]
 pub struct ArchContext {
     pub regs: libc::user_regs_struct,
+    // fpregs holds the x87/SSE register file in the FXSAVE layout.
+    pub fpregs: libc::user_fpregs_struct,
     pub feature_set: FeatureSet,
 }
 
 impl ArchContext {
     pub fn new() -> Self {
+        let mut fpregs: libc::user_fpregs_struct = unsafe { std::mem::zeroed() };
+        fpregs.cwd = FPU_CWD_INIT;
+        fpregs.mxcsr = MXCSR_INIT;
         Self {
             regs: utils::init_libc_regs(),
+            fpregs,
             feature_set: FeatureSet::new(),
         }
     }
 
+    // grab_fp_regs fetches the floating point registers of the stopped tracee pid.
+    pub fn grab_fp_regs(&mut self, pid: libc::pid_t) -> SysResult<()> {
+        let res = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETFPREGS,
+                pid,
+                std::ptr::null_mut::<libc::c_void>(),
+                &mut self.fpregs as *mut libc::user_fpregs_struct,
+            )
+        };
+        if res < 0 {
+            return Err(SysError::from_io_error(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    // restore_fp_regs writes the floating point registers back to the stopped tracee pid.
+    pub fn restore_fp_regs(&self, pid: libc::pid_t) -> SysResult<()> {
+        let res = unsafe {
+            libc::ptrace(
+                libc::PTRACE_SETFPREGS,
+                pid,
+                std::ptr::null_mut::<libc::c_void>(),
+                &self.fpregs as *const libc::user_fpregs_struct,
+            )
+        };
+        if res < 0 {
+            return Err(SysError::from_io_error(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    pub fn fp_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(
+                &self.fpregs as *const _ as *const u8,
+                std::mem::size_of::<libc::user_fpregs_struct>(),
+            )
+        }
+    }
+
+    // set_fp_bytes replaces the floating point registers with the FXSAVE image in bytes, clearing
+    // the MXCSR bits the cpu doesn't support so that restoring them can't fail.
+    pub fn set_fp_bytes(&mut self, bytes: &[u8]) {
+        assert_eq!(bytes.len(), std::mem::size_of::<libc::user_fpregs_struct>());
+        let mxcr_mask = self.fpregs.mxcr_mask;
+        let mxcsr_mask = match mxcr_mask {
+            0 => MXCSR_DEFAULT_MASK,
+            mask => mask,
+        };
+        self.fpregs = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const _) };
+        self.fpregs.mxcsr &= mxcsr_mask;
+        self.fpregs.mxcr_mask = mxcr_mask;
+    }
+
     pub fn cpuid_emulate(&mut self) {
         let orig_rax = self.regs.rax as u32;
         let orig_rcx = self.regs.rcx as u32;
@@ -28,3 +97,45 @@ impl ArchContext {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Requires permission to ptrace a child, so run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn restore_fp_regs_after_clobber() {
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0);
+        if pid == 0 {
+            unsafe {
+                libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0);
+                libc::raise(libc::SIGSTOP);
+                libc::_exit(0);
+            }
+        }
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFSTOPPED(status));
+
+        let mut ctx = ArchContext::new();
+        assert_eq!(ctx.grab_fp_regs(pid), Ok(()));
+        let saved = ctx.fp_bytes().to_vec();
+
+        ctx.fpregs.xmm_space[..4].copy_from_slice(&[0xdeadbeef; 4]);
+        assert_eq!(ctx.restore_fp_regs(pid), Ok(()));
+        assert_eq!(ctx.grab_fp_regs(pid), Ok(()));
+        assert_eq!(ctx.fpregs.xmm_space[..4], [0xdeadbeef; 4]);
+
+        ctx.set_fp_bytes(&saved);
+        assert_eq!(ctx.restore_fp_regs(pid), Ok(()));
+        assert_eq!(ctx.grab_fp_regs(pid), Ok(()));
+        assert_eq!(ctx.fp_bytes(), &saved[..]);
+
+        unsafe {
+            libc::kill(pid, libc::SIGKILL);
+            libc::waitpid(pid, &mut status, 0);
+        }
+    }
+}
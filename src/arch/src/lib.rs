@@ -47,10 +47,12 @@ pub struct MmapLayout {
     pub top_down_base: Addr,
     pub default_direction: MmapDirection,
     pub max_stack_rand: u64,
+    // randomize is cleared under personality(ADDR_NO_RANDOMIZE), making the layout deterministic.
+    pub randomize: bool,
 }
 
 impl MmapLayout {
-    pub fn new(min: Addr, max: Addr, limits: &LimitSet) -> SysResult<MmapLayout> {
+    pub fn new(min: Addr, max: Addr, limits: &LimitSet, randomize: bool) -> SysResult<MmapLayout> {
         let min = min.round_up().ok_or_else(|| SysError::new(libc::EINVAL))?;
         let max = std::cmp::min(max, MAX_ADDR).round_down();
         if min > max {
@@ -75,7 +77,11 @@ impl MmapLayout {
             }
         }
 
-        let rnd = mmap_rand(max_rand.0);
+        let rnd = if randomize {
+            mmap_rand(max_rand.0)
+        } else {
+            Addr(0)
+        };
         let layout = MmapLayout {
             min_addr: min,
             max_addr: max,
@@ -83,6 +89,7 @@ impl MmapLayout {
             top_down_base: (max - gap - rnd).round_down(),
             default_direction,
             max_stack_rand: max_rand.0,
+            randomize,
         };
         logger::info!("topdownbase: {}", (max - gap - rnd).round_down());
         if !layout.is_valid() {
@@ -97,7 +104,20 @@ impl MmapLayout {
         if max > self.max_addr {
             base = Addr(self.top_down_base.0 / 3 * 2);
         }
-        base + mmap_rand(MAX_MMAP_RAND)
+        base + self.rand(MAX_MMAP_RAND)
+    }
+
+    // stack_rand returns the random gap to leave between the stack and max_addr.
+    pub fn stack_rand(&self) -> Addr {
+        self.rand(self.max_stack_rand)
+    }
+
+    fn rand(&self, max: u64) -> Addr {
+        if self.randomize && max > 0 {
+            mmap_rand(max)
+        } else {
+            Addr(0)
+        }
     }
 
     pub fn new_test(
@@ -134,3 +154,27 @@ fn mmap_rand(max: u64) -> Addr {
     let mut rng = rand::thread_rng();
     Addr(rng.gen_range(0..max)).round_down()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic_layout() {
+        let limits = LimitSet::default();
+        let new = || MmapLayout::new(Addr(0x10000), MAX_ADDR, &limits, false).unwrap();
+        let (a, b) = (new(), new());
+        assert_eq!(
+            (a.bottom_up_base, a.top_down_base),
+            (b.bottom_up_base, b.top_down_base)
+        );
+        assert_eq!(a.pie_load_address(), b.pie_load_address());
+        assert_eq!(a.pie_load_address(), PREFERRED_PIE_LOAD_ADDR);
+        assert_eq!(a.stack_rand(), Addr(0));
+        assert!(!a.randomize);
+
+        let c = MmapLayout::new(Addr(0x10000), MAX_ADDR, &limits, true).unwrap();
+        assert!(c.top_down_base <= a.top_down_base);
+        assert!(c.bottom_up_base >= a.bottom_up_base);
+    }
+}
@@ -1,8 +1,22 @@
-use mem::Addr;
+use mem::{io::Io, Addr, IoOpts};
+use utils::{bail_libc, SysError, SysResult};
+
+use crate::{ArchContext, Stack, StackVal};
 
 pub const SIGNAL_STACK_FLAG_ON_STACK: u32 = 1;
 pub const SIGNAL_STACK_FLAG_DISABLE: u32 = 2;
 
+// The amd64 ABI allows leaf functions to use the 128 bytes below the stack pointer, so the signal
+// frame must be placed below it.
+const RED_ZONE_SIZE: u64 = 128;
+
+// Alignment of the FPU state in the signal frame, which xsave and xrstor require.
+const FP_STATE_ALIGN: u64 = 64;
+
+const EFLAGS_TF: u64 = 1 << 8;
+const EFLAGS_DF: u64 = 1 << 10;
+const EFLAGS_RF: u64 = 1 << 16;
+
 #[derive(Default, Clone, Copy, Debug)]
 #[repr(C)]
 pub struct SignalStack {
@@ -17,6 +31,14 @@ impl SignalStack {
         addr < sp.0 && sp.0 <= (addr + self.size)
     }
 
+    pub fn is_enabled(&self) -> bool {
+        self.flags & SIGNAL_STACK_FLAG_DISABLE == 0 && self.size != 0
+    }
+
+    pub fn top(&self) -> Addr {
+        Addr(self.addr as u64 + self.size)
+    }
+
     pub unsafe fn as_bytes(&self) -> &[u8] {
         let size = std::mem::size_of::<SignalStack>();
         std::slice::from_raw_parts((self as *const SignalStack) as *const u8, size)
@@ -26,3 +48,258 @@ impl SignalStack {
         std::ptr::read(bytes.as_ptr() as *const SignalStack)
     }
 }
+
+// SignalInfo mirrors linux's siginfo_t. Only the fields used for fault signals are exposed.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct SignalInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    _pad: i32,
+    fields: [u8; 112],
+}
+
+impl SignalInfo {
+    pub fn new(signo: i32, code: i32) -> Self {
+        Self {
+            signo,
+            errno: 0,
+            code,
+            _pad: 0,
+            fields: [0; 112],
+        }
+    }
+
+    // addr returns si_addr, which is valid for SIGILL, SIGFPE, SIGSEGV and SIGBUS.
+    pub fn addr(&self) -> Addr {
+        let mut b = [0; 8];
+        b.copy_from_slice(&self.fields[..8]);
+        Addr(u64::from_le_bytes(b))
+    }
+
+    pub fn set_addr(&mut self, addr: Addr) {
+        self.fields[..8].copy_from_slice(&addr.0.to_le_bytes());
+    }
+
+    /// as_bytes returns the raw bytes of the siginfo_t to be copied to the guest.
+    ///
+    /// # Safety
+    ///
+    /// SignalInfo is repr(C) and spells out its padding in _pad, so every byte of it is
+    /// initialized. Callers must keep it that way if they add fields.
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        let size = std::mem::size_of::<SignalInfo>();
+        std::slice::from_raw_parts((self as *const SignalInfo) as *const u8, size)
+    }
+}
+
+// SignalContext64 mirrors linux's struct sigcontext on amd64.
+#[derive(Default, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct SignalContext64 {
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub r11: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub rdx: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rsp: u64,
+    pub rip: u64,
+    pub eflags: u64,
+    pub cs: u16,
+    pub gs: u16,
+    pub fs: u16,
+    pub ss: u16,
+    pub err: u64,
+    pub trapno: u64,
+    pub oldmask: linux::SignalSet,
+    pub cr2: u64,
+    pub fpstate: u64,
+    pub reserved: [u64; 8],
+}
+
+// UContext64 mirrors linux's struct ucontext on amd64.
+#[derive(Default, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct UContext64 {
+    pub flags: u64,
+    pub link: u64,
+    pub stack: SignalStack,
+    pub mcontext: SignalContext64,
+    pub sigset: linux::SignalSet,
+}
+
+impl UContext64 {
+    /// as_bytes returns the raw bytes of the ucontext to be copied to the guest.
+    ///
+    /// # Safety
+    ///
+    /// The slice includes the padding after stack.flags, which may be uninitialized. Callers may
+    /// only copy the bytes out and must not read their values.
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        let size = std::mem::size_of::<UContext64>();
+        std::slice::from_raw_parts((self as *const UContext64) as *const u8, size)
+    }
+
+    // from_bytes reads a UContext64 from the start of bytes, which needn't be aligned. It's safe
+    // since all of UContext64's fields are integers, for which any bit pattern is valid.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        assert!(bytes.len() >= std::mem::size_of::<UContext64>());
+        unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const UContext64) }
+    }
+}
+
+impl ArchContext {
+    // signal_setup builds a signal frame on the given stack and redirects the registers to the
+    // handler. The stack's bottom must already point at the stack to be used for the handler,
+    // which is either the current stack or the alternate signal stack.
+    pub fn signal_setup(
+        &mut self,
+        st: &mut Stack,
+        act: &linux::SigAction,
+        info: &SignalInfo,
+        alt: &SignalStack,
+        sigset: linux::SignalSet,
+        mm: &mut dyn Io,
+    ) -> SysResult<()> {
+        // amd64 requires a restorer.
+        if act.flags & linux::SA_RESTORER == 0 {
+            bail_libc!(libc::EFAULT);
+        }
+
+        // Like linux, the FPU state is saved above the frame, aligned for xsave.
+        let fp_bytes = self.fp_bytes();
+        let fpstate = st
+            .bottom()
+            .checked_sub(RED_ZONE_SIZE + fp_bytes.len() as u64)
+            .ok_or_else(|| SysError::new(libc::EFAULT))?
+            & !(FP_STATE_ALIGN - 1);
+        let n = mm.copy_out(Addr(fpstate), fp_bytes, &IoOpts::default())?;
+        if n != fp_bytes.len() {
+            bail_libc!(libc::EFAULT);
+        }
+
+        let regs = &self.regs;
+        let uc = UContext64 {
+            flags: 0,
+            link: 0,
+            stack: *alt,
+            mcontext: SignalContext64 {
+                r8: regs.r8,
+                r9: regs.r9,
+                r10: regs.r10,
+                r11: regs.r11,
+                r12: regs.r12,
+                r13: regs.r13,
+                r14: regs.r14,
+                r15: regs.r15,
+                rdi: regs.rdi,
+                rsi: regs.rsi,
+                rbp: regs.rbp,
+                rbx: regs.rbx,
+                rdx: regs.rdx,
+                rax: regs.rax,
+                rcx: regs.rcx,
+                rsp: regs.rsp,
+                rip: regs.rip,
+                eflags: regs.eflags,
+                cs: regs.cs as u16,
+                gs: regs.gs as u16,
+                fs: regs.fs as u16,
+                ss: regs.ss as u16,
+                err: 0,
+                trapno: 0,
+                oldmask: sigset,
+                cr2: info.addr().0,
+                fpstate,
+                reserved: [0; 8],
+            },
+            sigset,
+        };
+
+        // The frame consists of siginfo, ucontext and the restorer's address. Align it so that the
+        // handler sees the stack as if it was just called, i.e. (rsp + 8) is 16-byte aligned.
+        let uc_bytes = unsafe { uc.as_bytes() };
+        let info_bytes = unsafe { info.as_bytes() };
+        let frame_size = (uc_bytes.len() + info_bytes.len() + 8) as u64;
+        let sp = fpstate
+            .checked_sub(frame_size)
+            .ok_or_else(|| SysError::new(libc::EFAULT))?;
+        let frame_bottom = (sp & !15) - 8;
+        *st = Stack::new(Addr(frame_bottom + frame_size));
+
+        let info_addr = st.push(StackVal::Raw(info_bytes), mm)?;
+        let uc_addr = st.push(StackVal::Raw(uc_bytes), mm)?;
+        st.push(StackVal::Addr(Addr(act.restorer)), mm)?;
+
+        let regs = &mut self.regs;
+        regs.rip = act.handler;
+        regs.rsp = st.bottom();
+        regs.rdi = info.signo as u64;
+        regs.rsi = info_addr.0;
+        regs.rdx = uc_addr.0;
+        regs.rax = 0;
+        regs.eflags &= !(EFLAGS_DF | EFLAGS_RF | EFLAGS_TF);
+        Ok(())
+    }
+
+    // signal_restore restores the registers saved by signal_setup, and returns the signal mask and
+    // the alternate signal stack which were in effect before the signal was delivered.
+    pub fn signal_restore(
+        &mut self,
+        st: &Stack,
+        mm: &mut dyn Io,
+    ) -> SysResult<(linux::SignalSet, SignalStack)> {
+        // The restorer's address has already been popped by the handler's ret instruction.
+        let mut buf = vec![0; std::mem::size_of::<UContext64>()];
+        let n = mm.copy_in(Addr(st.bottom()), &mut buf, &IoOpts::default())?;
+        if n != buf.len() {
+            bail_libc!(libc::EFAULT);
+        }
+        let uc = UContext64::from_bytes(&buf);
+
+        let mc = &uc.mcontext;
+        let regs = &mut self.regs;
+        regs.r8 = mc.r8;
+        regs.r9 = mc.r9;
+        regs.r10 = mc.r10;
+        regs.r11 = mc.r11;
+        regs.r12 = mc.r12;
+        regs.r13 = mc.r13;
+        regs.r14 = mc.r14;
+        regs.r15 = mc.r15;
+        regs.rdi = mc.rdi;
+        regs.rsi = mc.rsi;
+        regs.rbp = mc.rbp;
+        regs.rbx = mc.rbx;
+        regs.rdx = mc.rdx;
+        regs.rax = mc.rax;
+        regs.rcx = mc.rcx;
+        regs.rsp = mc.rsp;
+        regs.rip = mc.rip;
+        regs.eflags = mc.eflags;
+        // Prevent the syscall return path from being taken again.
+        regs.orig_rax = u64::MAX;
+
+        // A NULL fpstate means the handler discarded it, and the current one is kept.
+        if mc.fpstate != 0 {
+            let mut fp_buf = vec![0; self.fp_bytes().len()];
+            let n = mm.copy_in(Addr(mc.fpstate), &mut fp_buf, &IoOpts::default())?;
+            if n != fp_buf.len() {
+                bail_libc!(libc::EFAULT);
+            }
+            self.set_fp_bytes(&fp_buf);
+        }
+        Ok((uc.sigset, uc.stack))
+    }
+}
@@ -25,6 +25,9 @@ impl Stack {
                 self.copy_out_byte(0, mm)?;
                 self.copy_out_bytes(bytes, mm)?;
             }
+            StackVal::Raw(bytes) => {
+                self.copy_out_bytes(bytes, mm)?;
+            }
             StackVal::Addr(addr) => {
                 self.copy_out_u64(addr.0, mm)?;
             }
@@ -145,6 +148,7 @@ pub struct StackLayout {
 pub enum StackVal<'a> {
     Byte(u8),
     Bytes(&'a [u8]),
+    Raw(&'a [u8]),
     Addr(Addr),
     AddrSlice(&'a [Addr]),
 }
@@ -7,8 +7,9 @@ use std::{
 };
 
 use once_cell::sync::Lazy;
+use utils::{bail_libc, SysError, SysResult};
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub struct Id {
     pub major: u64,
     pub minor: u64,
@@ -44,6 +45,24 @@ impl Registry {
         self.devices.insert(id, Arc::clone(&d));
         d
     }
+
+    // register_device registers a device with the given id, e.g. 1:3 for /dev/null. It fails
+    // with EEXIST if the id is already taken.
+    pub fn register_device(&mut self, id: Id) -> SysResult<Arc<Mutex<Device>>> {
+        if self.devices.contains_key(&id) {
+            bail_libc!(libc::EEXIST);
+        }
+        let d = Arc::new(Mutex::new(Device {
+            id,
+            last: AtomicU64::new(0),
+        }));
+        self.devices.insert(id, Arc::clone(&d));
+        Ok(d)
+    }
+
+    pub fn get_device(&self, id: Id) -> Option<Arc<Mutex<Device>>> {
+        self.devices.get(&id).cloned()
+    }
 }
 
 pub struct Device {
@@ -56,6 +75,14 @@ impl Device {
         SIMPLE_DEVICES.lock().unwrap().new_anonymous_device()
     }
 
+    pub fn register_device(id: Id) -> SysResult<Arc<Mutex<Device>>> {
+        SIMPLE_DEVICES.lock().unwrap().register_device(id)
+    }
+
+    pub fn get_device(id: Id) -> Option<Arc<Mutex<Device>>> {
+        SIMPLE_DEVICES.lock().unwrap().get_device(id)
+    }
+
     pub fn device_id(&self) -> u64 {
         linux::dev::make_device_id(self.id.major as u16, self.id.minor as u32) as u64
     }
@@ -72,3 +99,33 @@ static SIMPLE_DEVICES: Lazy<Mutex<Registry>> = Lazy::new(|| {
         devices: HashMap::new(),
     })
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Registry {
+        Registry {
+            last_anonymous_device_minor: AtomicU64::new(0),
+            devices: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn register_device() {
+        let mut registry = registry();
+        let null = Id { major: 1, minor: 3 };
+        let d = registry
+            .register_device(null)
+            .expect("failed to register 1:3");
+        assert_eq!(d.lock().unwrap().device_id(), null.device_id());
+        assert_eq!(d.lock().unwrap().device_id(), (1 << 8) | 3);
+        assert!(Arc::ptr_eq(&registry.get_device(null).unwrap(), &d));
+
+        assert_eq!(
+            registry.register_device(null).err(),
+            Some(SysError::new(libc::EEXIST))
+        );
+        assert!(registry.get_device(Id { major: 1, minor: 5 }).is_none());
+    }
+}
@@ -305,7 +305,34 @@ pub struct UnstableAttr {
     pub links: u64,
 }
 
+// TimeSpec holds the timestamps to set on an inode. A timestamp which is None is left as is.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct TimeSpec {
+    pub access_time: Option<Time>,
+    pub modification_time: Option<Time>,
+}
+
+impl TimeSpec {
+    pub fn is_empty(&self) -> bool {
+        self.access_time.is_none() && self.modification_time.is_none()
+    }
+}
+
 impl UnstableAttr {
+    // set_timestamps applies ts, which changes the status of the inode at now.
+    pub fn set_timestamps(&mut self, ts: TimeSpec, now: Time) {
+        if ts.is_empty() {
+            return;
+        }
+        if let Some(t) = ts.access_time {
+            self.access_time = t;
+        }
+        if let Some(t) = ts.modification_time {
+            self.modification_time = t;
+        }
+        self.status_change_time = now;
+    }
+
     pub fn record_current_time<F: Fn() -> Time>(mut self, time: F) -> Self {
         let t = time();
         self.access_time = t;
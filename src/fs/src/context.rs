@@ -6,7 +6,7 @@ use std::{
 use mem::{Addr, IoOpts, IoSequence};
 use utils::SysResult;
 
-use crate::{inode::Inode, Dirent, FdFlags, File};
+use crate::{inode::Inode, proc::SelfFile, Dirent, FdFlags, File};
 
 use super::attr::{FileOwner, PermMask};
 
@@ -23,6 +23,7 @@ use smoltcp::{
     phy::TunTapInterface,
     socket::Socket,
     time::Duration,
+    wire::IpAddress,
 };
 #[cfg(test)]
 use std::{
@@ -31,7 +32,7 @@ use std::{
     sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 #[cfg(test)]
-use time::{Clock, HostClock, Time};
+use time::{Clock, HostClock, MonotonicClock, Time};
 
 pub trait Context:
     auth::Context
@@ -51,6 +52,9 @@ pub trait Context:
     // TODO: feels weird to place these methods here..
     fn single_io_sequence(&self, addr: Addr, length: i32, opts: IoOpts) -> SysResult<IoSequence>;
     fn new_fd_from(&self, fd: i32, file: &Rc<RefCell<File>>, flags: FdFlags) -> SysResult<i32>;
+    // proc_self_file generates the contents of the given file under /proc/self for the calling
+    // task.
+    fn proc_self_file(&self, file: SelfFile) -> Vec<u8>;
 }
 
 pub type DirentRef = Rc<RefCell<Dirent>>;
@@ -135,6 +139,10 @@ impl time::Context for TestContext {
     fn now(&self) -> Time {
         HostClock.now()
     }
+
+    fn monotonic_now(&self) -> Time {
+        MonotonicClock.now()
+    }
 }
 
 #[cfg(test)]
@@ -172,6 +180,18 @@ impl net::Context for TestContext {
     fn remove_local_port(&self, _p: u16) {
         unimplemented!()
     }
+    fn bind_local_port(&self, _protocol: i32, _p: u16, _reuse: bool) -> bool {
+        unimplemented!()
+    }
+    fn unbind_local_port(&self, _protocol: i32, _p: u16) {
+        unimplemented!()
+    }
+    fn join_multicast_group(&self, _group: IpAddress) -> SysResult<()> {
+        unimplemented!()
+    }
+    fn leave_multicast_group(&self, _group: IpAddress) {
+        unimplemented!()
+    }
     fn wait(&self, _duration: Option<Duration>) {
         unimplemented!()
     }
@@ -254,6 +274,9 @@ impl Context for TestContext {
     fn new_fd_from(&self, _fd: i32, _file: &Rc<RefCell<File>>, _flags: FdFlags) -> SysResult<i32> {
         unimplemented!()
     }
+    fn proc_self_file(&self, file: SelfFile) -> Vec<u8> {
+        format!("{}\n", file.name()).into_bytes()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,211 @@
+use std::{
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use dev::{Device, Id};
+use linux::FileMode;
+use mem::PAGE_SIZE;
+use utils::{err_libc, SysError, SysResult};
+
+use crate::{
+    attr::{FileOwner, FilePermissions, InodeType, StableAttr, TimeSpec, UnstableAttr},
+    fsutils::inode::InodeSimpleAttributes,
+    inode::Inode,
+    inode_operations::RenameUnderParents,
+    mount::MountSource,
+    Context, File, InodeOperations,
+};
+
+use super::{
+    full::FullDeviceFileOperations, null::NullDeviceFileOperations,
+    random::RandomDeviceFileOperations, zero::ZeroDeviceFileOperations,
+};
+
+const MEM_DEV_MAJOR: u64 = 1;
+
+// MemDevice is one of the character devices of major 1 that sentinel implements by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemDevice {
+    Null,
+    Zero,
+    Full,
+    Random,
+    Urandom,
+}
+
+impl MemDevice {
+    pub const ALL: [MemDevice; 5] = [
+        MemDevice::Null,
+        MemDevice::Zero,
+        MemDevice::Full,
+        MemDevice::Random,
+        MemDevice::Urandom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemDevice::Null => "null",
+            MemDevice::Zero => "zero",
+            MemDevice::Full => "full",
+            MemDevice::Random => "random",
+            MemDevice::Urandom => "urandom",
+        }
+    }
+
+    pub fn id(&self) -> Id {
+        let minor = match self {
+            MemDevice::Null => 3,
+            MemDevice::Zero => 5,
+            MemDevice::Full => 7,
+            MemDevice::Random => 8,
+            MemDevice::Urandom => 9,
+        };
+        Id {
+            major: MEM_DEV_MAJOR,
+            minor,
+        }
+    }
+
+    // device returns the device registered for this, registering it on the first call.
+    fn device(&self) -> Arc<Mutex<Device>> {
+        let id = self.id();
+        Device::get_device(id).unwrap_or_else(|| {
+            Device::register_device(id).expect("memory device is registered twice")
+        })
+    }
+
+    // new_inode returns a device file of this, which is owned by root just like the ones in the
+    // host /dev.
+    pub fn new_inode(&self, msrc: Rc<MountSource>, ctx: &dyn Context) -> Inode {
+        let iops = MemDeviceInode::new(*self, FileOwner::root(), FileMode(0o666), ctx);
+        let device = self.device();
+        let device = device.lock().unwrap();
+        let id = self.id();
+        let sattr = StableAttr {
+            typ: InodeType::CharacterDevice,
+            device_id: device.device_id(),
+            inode_id: device.next_ino(),
+            block_size: PAGE_SIZE as i64,
+            device_file_major: id.major as u16,
+            device_file_minor: id.minor as u32,
+        };
+        Inode::new(Box::new(iops), msrc, sattr)
+    }
+}
+
+#[derive(Debug)]
+pub struct MemDeviceInode {
+    simple_attr: InodeSimpleAttributes,
+    kind: MemDevice,
+}
+
+impl MemDeviceInode {
+    pub fn new(kind: MemDevice, owner: FileOwner, mode: FileMode, ctx: &dyn Context) -> Self {
+        let simple_attr = InodeSimpleAttributes::new(
+            owner,
+            FilePermissions::from_mode(mode),
+            linux::TMPFS_MAGIC,
+            &|| ctx.now(),
+        );
+        Self { simple_attr, kind }
+    }
+}
+
+impl InodeOperations for MemDeviceInode {
+    fn lookup(&mut self, _: &str, _: &dyn Context) -> SysResult<crate::DirentRef> {
+        err_libc!(libc::ENOTDIR)
+    }
+    fn get_file(&self, dirent: crate::DirentRef, mut flags: crate::FileFlags) -> SysResult<File> {
+        flags.pread = true;
+        flags.pwrite = true;
+        let fops: Box<dyn crate::FileOperations> = match self.kind {
+            MemDevice::Null => Box::new(NullDeviceFileOperations { dirent }),
+            MemDevice::Zero => Box::new(ZeroDeviceFileOperations { dirent }),
+            MemDevice::Full => Box::new(FullDeviceFileOperations { dirent }),
+            MemDevice::Random | MemDevice::Urandom => {
+                Box::new(RandomDeviceFileOperations { dirent })
+            }
+        };
+        Ok(File::new(flags, fops))
+    }
+    fn unstable_attr(&self, msrc: &Rc<MountSource>, sattr: StableAttr) -> SysResult<UnstableAttr> {
+        self.simple_attr.unstable_attr(msrc, sattr)
+    }
+    fn get_link(&self) -> SysResult<crate::DirentRef> {
+        err_libc!(libc::ENOLINK)
+    }
+    fn read_link(&self) -> SysResult<String> {
+        err_libc!(libc::ENOLINK)
+    }
+    fn truncate(&mut self, _: i64, _: &dyn Context) -> SysResult<()> {
+        Ok(())
+    }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        err_libc!(libc::ENODEV)
+    }
+    fn create(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: crate::FileFlags,
+        _: FilePermissions,
+        _: &dyn Context,
+    ) -> SysResult<File> {
+        err_libc!(libc::ENOTDIR)
+    }
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        err_libc!(libc::ENOTDIR)
+    }
+    fn link(&mut self, _: &Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        err_libc!(libc::ENOTDIR)
+    }
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        err_libc!(libc::ENOTDIR)
+    }
+    fn rename(
+        &self,
+        _: RenameUnderParents<&mut Inode>,
+        _: &str,
+        _: String,
+        _: bool,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        err_libc!(libc::EINVAL)
+    }
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.simple_attr.set_timestamps(ts, ctx)
+    }
+    fn get_xattr(&self, _: &str) -> SysResult<Vec<u8>> {
+        err_libc!(libc::EOPNOTSUPP)
+    }
+    fn set_xattr(&mut self, _: &str, _: &[u8], _: i32) -> SysResult<()> {
+        err_libc!(libc::EOPNOTSUPP)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        err_libc!(libc::EOPNOTSUPP)
+    }
+    fn remove_xattr(&mut self, _: &str) -> SysResult<()> {
+        err_libc!(libc::EOPNOTSUPP)
+    }
+    fn add_link(&self) {
+        self.simple_attr.add_link()
+    }
+    fn drop_link(&self) {
+        self.simple_attr.drop_link()
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
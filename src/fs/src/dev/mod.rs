@@ -1 +1,7 @@
+pub mod full;
+mod mem_device;
 pub mod null;
+pub mod random;
+pub mod zero;
+
+pub use mem_device::*;
@@ -0,0 +1,102 @@
+use mem::PAGE_SIZE;
+use utils::{err_libc, SysError, SysResult};
+
+use crate::{fsutils::seek_with_dir_cursor, Context, FileOperations};
+
+// fill_random fills buf with random bytes from the host.
+fn fill_random(buf: &mut [u8]) -> SysResult<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let rest = &mut buf[filled..];
+        let n = unsafe { libc::getrandom(rest.as_mut_ptr() as *mut libc::c_void, rest.len(), 0) };
+        if n < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(SysError::from_io_error(err));
+        }
+        filled += n as usize;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct RandomDeviceFileOperations {
+    pub dirent: crate::DirentRef,
+}
+
+impl FileOperations for RandomDeviceFileOperations {
+    fn dirent(&self) -> crate::DirentRef {
+        self.dirent.clone()
+    }
+    // read fills dst page by page, so that the bytes before a faulting page are still reported.
+    fn read(
+        &self,
+        _: crate::FileFlags,
+        dst: &mut mem::IoSequence,
+        _: i64,
+        _: &dyn Context,
+    ) -> SysResult<usize> {
+        let mut buf = [0u8; PAGE_SIZE as usize];
+        let mut done = 0;
+        while dst.num_bytes() > 0 {
+            let want = std::cmp::min(dst.num_bytes(), buf.len());
+            fill_random(&mut buf[..want])?;
+            let n = dst.copy_out(&buf[..want])?;
+            done += n;
+            dst.drop_first(n);
+            if n < want {
+                break;
+            }
+        }
+        Ok(done)
+    }
+    fn write(
+        &self,
+        _: crate::FileFlags,
+        src: &mut mem::IoSequence,
+        _: i64,
+        _: &dyn Context,
+    ) -> SysResult<usize> {
+        Ok(src.num_bytes() as usize)
+    }
+    fn configure_mmap(&mut self, _: &mut memmap::mmap_opts::MmapOpts) -> SysResult<()> {
+        err_libc!(libc::ENODEV)
+    }
+    fn flush(&self) -> SysResult<()> {
+        Ok(())
+    }
+    fn close(&self) -> SysResult<()> {
+        Ok(())
+    }
+    fn ioctl(&self, _: &libc::user_regs_struct, _: &dyn Context) -> SysResult<usize> {
+        err_libc!(libc::ENOTTY)
+    }
+    fn seek(
+        &mut self,
+        inode: &crate::inode::Inode,
+        whence: crate::seek::SeekWhence,
+        current_offset: i64,
+        offset: i64,
+    ) -> SysResult<i64> {
+        seek_with_dir_cursor(inode, whence, current_offset, offset, None)
+    }
+    fn readdir(
+        &mut self,
+        _: i64,
+        _: &mut dyn crate::dentry::DentrySerializer,
+        _: &dyn Context,
+    ) -> crate::ReaddirResult<i64> {
+        Err(crate::ReaddirError::new(0, libc::ENOTDIR))
+    }
+    fn readiness(&self, mask: u64, _: &dyn Context) -> u64 {
+        mask
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
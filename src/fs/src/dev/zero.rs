@@ -0,0 +1,70 @@
+use utils::{err_libc, SysError, SysResult};
+
+use crate::{fsutils::seek_with_dir_cursor, Context, FileOperations};
+
+#[derive(Debug)]
+pub struct ZeroDeviceFileOperations {
+    pub dirent: crate::DirentRef,
+}
+
+impl FileOperations for ZeroDeviceFileOperations {
+    fn dirent(&self) -> crate::DirentRef {
+        self.dirent.clone()
+    }
+    fn read(
+        &self,
+        _: crate::FileFlags,
+        dst: &mut mem::IoSequence,
+        _: i64,
+        _: &dyn Context,
+    ) -> SysResult<usize> {
+        dst.zero()
+    }
+    fn write(
+        &self,
+        _: crate::FileFlags,
+        src: &mut mem::IoSequence,
+        _: i64,
+        _: &dyn Context,
+    ) -> SysResult<usize> {
+        Ok(src.num_bytes() as usize)
+    }
+    fn configure_mmap(&mut self, _: &mut memmap::mmap_opts::MmapOpts) -> SysResult<()> {
+        err_libc!(libc::ENODEV)
+    }
+    fn flush(&self) -> SysResult<()> {
+        Ok(())
+    }
+    fn close(&self) -> SysResult<()> {
+        Ok(())
+    }
+    fn ioctl(&self, _: &libc::user_regs_struct, _: &dyn Context) -> SysResult<usize> {
+        err_libc!(libc::ENOTTY)
+    }
+    fn seek(
+        &mut self,
+        inode: &crate::inode::Inode,
+        whence: crate::seek::SeekWhence,
+        current_offset: i64,
+        offset: i64,
+    ) -> SysResult<i64> {
+        seek_with_dir_cursor(inode, whence, current_offset, offset, None)
+    }
+    fn readdir(
+        &mut self,
+        _: i64,
+        _: &mut dyn crate::dentry::DentrySerializer,
+        _: &dyn Context,
+    ) -> crate::ReaddirResult<i64> {
+        Err(crate::ReaddirError::new(0, libc::ENOTDIR))
+    }
+    fn readiness(&self, mask: u64, _: &dyn Context) -> u64 {
+        mask
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
@@ -79,6 +79,16 @@ impl Dirent {
         self.mounted || self.is_root()
     }
 
+    #[inline]
+    pub(crate) fn set_mounted(&mut self) {
+        self.mounted = true;
+    }
+
+    #[inline]
+    pub(crate) fn set_parent(&mut self, parent: DirentWeakRef) {
+        self.parent = parent;
+    }
+
     #[inline]
     pub fn stable_attr(&self) -> StableAttr {
         self.inode.stable_attr()
@@ -169,6 +179,24 @@ impl Dirent {
         Ok(file)
     }
 
+    // create_link creates a symbolic link named name in this directory that points to target.
+    pub fn create_link(
+        &mut self,
+        root: &DirentRef,
+        name: &str,
+        target: &str,
+        self_ptr: DirentRef,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        if self.exists(root, name, self_ptr, ctx) {
+            bail_libc!(libc::EEXIST);
+        }
+        let parent_uattr = self.inode.unstable_attr()?;
+        let msrc = self.inode.mount_source().clone();
+        self.inode
+            .create_link(name, target, parent_uattr, msrc, ctx)
+    }
+
     fn finish_create(&self, child: DirentRef, name: &str) {
         if child.borrow().name() != name {
             panic!(
@@ -209,6 +237,7 @@ impl Mappable for Dirent {
 
 pub trait DirentOperations {
     fn is_descendant_of(&self, p: &DirentRef) -> bool;
+    fn mount_root(&self) -> DirentRef;
 }
 
 impl DirentOperations for DirentRef {
@@ -225,6 +254,16 @@ impl DirentOperations for DirentRef {
                 .is_descendant_of(p)
         }
     }
+
+    // mount_root returns the root of the mount that this belongs to.
+    fn mount_root(&self) -> DirentRef {
+        let d = self.borrow();
+        if d.mounted || d.is_root() {
+            self.clone()
+        } else {
+            d.parent().upgrade().unwrap().mount_root()
+        }
+    }
 }
 
 fn get_dot_attrs(d: &DirentRef, root: &DirentRef) -> (DentAttr, DentAttr) {
@@ -359,17 +398,23 @@ fn dirent_readdir_impl(
     }
 }
 
+// rename moves old_name in old_parent to new_name in new_parent, replacing what is there unless
+// no_replace is set.
 pub fn rename(
     root: &DirentRef,
     old_parent: &DirentRef,
     old_name: Component,
     new_parent: &DirentRef,
     new_name: String,
+    no_replace: bool,
     ctx: &dyn Context,
 ) -> SysResult<()> {
     let new_name_component = Component::Normal(new_name.as_ref());
     if Rc::as_ptr(old_parent) == Rc::as_ptr(new_parent) {
-        return rename_in_same_parent(root, old_parent, old_name, new_name, ctx);
+        return rename_in_same_parent(root, old_parent, old_name, new_name, no_replace, ctx);
+    }
+    if !Rc::ptr_eq(&old_parent.mount_root(), &new_parent.mount_root()) {
+        bail_libc!(libc::EXDEV);
     }
     {
         let old_parent = old_parent.borrow();
@@ -415,6 +460,7 @@ pub fn rename(
         let mut new_parent = new_parent.borrow_mut();
 
         let is_replaced = match new_parent.walk(root, new_name_component, cloned, ctx) {
+            Ok(_) if no_replace => bail_libc!(libc::EEXIST),
             Ok(d) => {
                 new_parent.can_delete(&d, ctx)?;
                 if old_parent.is_descendant_of(&d) {
@@ -458,6 +504,7 @@ fn rename_in_same_parent(
     parent: &DirentRef,
     old_name: Component,
     new_name: String,
+    no_replace: bool,
     ctx: &dyn Context,
 ) -> SysResult<()> {
     let new_name_component = Component::Normal(new_name.as_ref());
@@ -506,6 +553,7 @@ fn rename_in_same_parent(
         let mut parent_mut = parent.borrow_mut();
 
         let is_replaced = match parent_mut.walk(root, new_name_component, cloned, ctx) {
+            Ok(_) if no_replace => bail_libc!(libc::EEXIST),
             Ok(d) => {
                 parent_mut.can_delete(&d, ctx)?;
                 if parent.is_descendant_of(&d) {
@@ -544,3 +592,179 @@ fn rename_in_same_parent(
 
     Ok(())
 }
+
+// exchange atomically swaps old_name in old_parent and new_name in new_parent, both of which must
+// exist. Since inode operations only know how to move one entry at a time, old_name is first moved
+// aside to a temporary name in old_parent.
+pub fn exchange(
+    root: &DirentRef,
+    old_parent: &DirentRef,
+    old_name: &str,
+    new_parent: &DirentRef,
+    new_name: &str,
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    let same_parent = Rc::ptr_eq(old_parent, new_parent);
+    if same_parent && old_name == new_name {
+        return Ok(());
+    }
+    if !same_parent && !Rc::ptr_eq(&old_parent.mount_root(), &new_parent.mount_root()) {
+        bail_libc!(libc::EXDEV);
+    }
+    let mask = PermMask {
+        read: false,
+        write: true,
+        execute: true,
+    };
+    old_parent.borrow().inode.check_permission(mask, ctx)?;
+    new_parent.borrow().inode.check_permission(mask, ctx)?;
+
+    let old = old_parent.borrow_mut().walk(
+        root,
+        Component::Normal(old_name.as_ref()),
+        old_parent.clone(),
+        ctx,
+    )?;
+    let new = new_parent.borrow_mut().walk(
+        root,
+        Component::Normal(new_name.as_ref()),
+        new_parent.clone(),
+        ctx,
+    )?;
+    old_parent.borrow().can_delete(&old, ctx)?;
+    new_parent.borrow().can_delete(&new, ctx)?;
+    if old.borrow().is_mount_point_locked() || new.borrow().is_mount_point_locked() {
+        bail_libc!(libc::EBUSY);
+    }
+    if new_parent.is_descendant_of(&old) || old_parent.is_descendant_of(&new) {
+        bail_libc!(libc::EINVAL);
+    }
+    if !same_parent {
+        for d in [&old, &new].iter() {
+            let d = d.borrow();
+            if d.stable_attr().is_directory() {
+                d.inode.check_permission(
+                    PermMask {
+                        read: false,
+                        write: true,
+                        execute: false,
+                    },
+                    ctx,
+                )?;
+            }
+        }
+    }
+
+    let mut tmp_name = format!(".exchange.{}", old.borrow().stable_attr().inode_id);
+    while old_parent
+        .borrow_mut()
+        .exists(root, &tmp_name, old_parent.clone(), ctx)
+    {
+        tmp_name.push('_');
+    }
+    move_dirent(&old, old_parent, old_parent, tmp_name, ctx)?;
+    if let Err(err) = move_dirent(&new, new_parent, old_parent, old_name.to_string(), ctx) {
+        move_dirent(&old, old_parent, old_parent, old_name.to_string(), ctx)?;
+        return Err(err);
+    }
+    move_dirent(&old, old_parent, new_parent, new_name.to_string(), ctx)
+}
+
+// move_dirent moves renamed from the directory from to the directory to under new_name. The
+// callers are responsible for the checks, and new_name must not exist in to.
+fn move_dirent(
+    renamed: &DirentRef,
+    from: &DirentRef,
+    to: &DirentRef,
+    new_name: String,
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    {
+        let renamed = renamed.borrow();
+        if Rc::ptr_eq(from, to) {
+            let mut parent = from.borrow_mut();
+            renamed.inode.rename(
+                RenameUnderParents::Same(&mut parent.inode),
+                &renamed.name,
+                new_name.clone(),
+                false,
+                ctx,
+            )?;
+        } else {
+            let mut from = from.borrow_mut();
+            let mut to = to.borrow_mut();
+            let parents = RenameUnderParents::Different {
+                old: from.inode_mut(),
+                new: to.inode_mut(),
+            };
+            renamed
+                .inode
+                .rename(parents, &renamed.name, new_name.clone(), false, ctx)?;
+        }
+    }
+    let mut renamed = renamed.borrow_mut();
+    renamed.name = new_name;
+    renamed.parent = Rc::downgrade(to);
+    Ok(())
+}
+
+// link creates new_name in new_parent as a hard link to target.
+pub fn link(
+    root: &DirentRef,
+    target: &DirentRef,
+    new_parent: &DirentRef,
+    new_name: &str,
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    if target.borrow().stable_attr().is_directory() {
+        bail_libc!(libc::EPERM);
+    }
+    if !Rc::ptr_eq(&target.mount_root(), &new_parent.mount_root()) {
+        bail_libc!(libc::EXDEV);
+    }
+    new_parent.borrow().inode.check_permission(
+        PermMask {
+            read: false,
+            write: true,
+            execute: true,
+        },
+        ctx,
+    )?;
+    let cloned = new_parent.clone();
+    let mut new_parent = new_parent.borrow_mut();
+    if new_parent.exists(root, new_name, cloned, ctx) {
+        bail_libc!(libc::EEXIST);
+    }
+    let target = target.borrow();
+    new_parent.inode.link(target.inode(), new_name, ctx)
+}
+
+// unlink removes name, which must not be a directory, from parent.
+pub fn unlink(
+    root: &DirentRef,
+    parent: &DirentRef,
+    name: &str,
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    parent.borrow().inode.check_permission(
+        PermMask {
+            read: false,
+            write: true,
+            execute: true,
+        },
+        ctx,
+    )?;
+    let child =
+        parent
+            .borrow_mut()
+            .walk(root, Component::Normal(name.as_ref()), parent.clone(), ctx)?;
+    if child.borrow().stable_attr().is_directory() {
+        bail_libc!(libc::EISDIR);
+    }
+    parent.borrow().can_delete(&child, ctx)?;
+    if child.borrow().is_mount_point_locked() {
+        bail_libc!(libc::EBUSY);
+    }
+    drop(child);
+    parent.borrow_mut().inode.unlink(name, ctx)
+}
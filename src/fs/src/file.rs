@@ -1,4 +1,7 @@
-use std::sync::atomic::{AtomicI64, Ordering};
+use std::{
+    cmp::min,
+    sync::atomic::{AtomicI64, Ordering},
+};
 
 use mem::IoSequence;
 use memmap::mmap_opts::MmapOpts;
@@ -11,6 +14,10 @@ use super::{attr::UnstableAttr, context::Context, FileOperations};
 
 pub const FILE_MAX_OFFSET: i64 = i64::MAX;
 
+// COPY_BUFFER_SIZE is the size of the buffer copy_range_from copies through between files that
+// have no faster way to copy.
+const COPY_BUFFER_SIZE: usize = 1 << 16;
+
 #[derive(Debug)]
 pub struct File {
     flags: FileFlags,
@@ -43,7 +50,6 @@ impl File {
     }
 
     pub fn set_flags(&mut self, new_flags: SettableFileFlags) {
-        self.flags.direct = new_flags.direct;
         self.flags.non_blocking = new_flags.non_blocking;
         self.flags.append = new_flags.append;
         self.flags.async_ = new_flags.async_;
@@ -81,15 +87,21 @@ impl File {
         Ok(n)
     }
 
+    // pwritev writes src at offset without moving the file offset. Like Linux, a file opened
+    // with O_APPEND is always written at its end, ignoring offset.
     pub fn pwritev(
         &self,
         src: &mut IoSequence,
         offset: i64,
         ctx: &dyn Context,
     ) -> SysResult<usize> {
-        if self.flags.append {
-            self.offset_for_append(&self.offset)?;
-        }
+        let offset = if self.flags.append {
+            let end = AtomicI64::new(offset);
+            self.offset_for_append(&end)?;
+            end.into_inner()
+        } else {
+            offset
+        };
 
         let (limit, ok) = self.check_limit(offset, ctx);
         if ok && limit == 0 {
@@ -133,6 +145,38 @@ impl File {
         Ok(total)
     }
 
+    // copy_range_from implements copy_file_range(2) and sendfile(2), copying up to len bytes at
+    // src_offset of src to offset of this file. A None offset stands for the file offset, which is
+    // then advanced by the bytes copied. How the bytes are copied is up to the file operations of
+    // this file.
+    pub fn copy_range_from(
+        &self,
+        src: &File,
+        src_offset: Option<i64>,
+        offset: Option<i64>,
+        len: usize,
+        ctx: &dyn Context,
+    ) -> SysResult<usize> {
+        let in_offset = src_offset.unwrap_or_else(|| src.offset());
+        let out_offset = offset.unwrap_or_else(|| self.offset());
+        let (limit, ok) = self.check_limit(out_offset, ctx);
+        if ok && limit == 0 {
+            return Err(SysError::exceeds_file_size_limit());
+        }
+        let len = if ok { min(len, limit as usize) } else { len };
+
+        let n = self
+            .file_operations
+            .copy_range_from(self.flags, src, in_offset, out_offset, len, ctx)?;
+        if src_offset.is_none() && !src.flags.non_seekable {
+            src.offset.fetch_add(n as i64, Ordering::SeqCst);
+        }
+        if offset.is_none() && !self.flags.non_seekable {
+            self.offset.fetch_add(n as i64, Ordering::SeqCst);
+        }
+        Ok(n)
+    }
+
     pub fn get_file_size(&self) -> SysResult<usize> {
         let dirent = self.dirent();
         let dirent = dirent.borrow();
@@ -143,6 +187,9 @@ impl File {
         Ok(uattr.size as usize)
     }
 
+    // check_limit returns how many bytes may be written at offset under RLIMIT_FSIZE, and whether
+    // the limit applies at all. Writes at or past the limit fail with EFBIG; unlike Linux, no
+    // SIGXFSZ is sent.
     fn check_limit(&self, offset: i64, ctx: &dyn Context) -> (i64, bool) {
         let attr = self.dirent().borrow().inode().stable_attr();
         if attr.is_regular() {
@@ -221,6 +268,46 @@ impl File {
     }
 }
 
+// copy_range_through_buffer copies up to len bytes at src_offset of src to offset of the file of
+// fops, reading and writing through a buffer. It stops at the end of src or at the first failure
+// after some bytes were copied.
+pub(crate) fn copy_range_through_buffer<F: FileOperations + ?Sized>(
+    fops: &F,
+    flags: FileFlags,
+    src: &File,
+    src_offset: i64,
+    offset: i64,
+    len: usize,
+    ctx: &dyn Context,
+) -> SysResult<usize> {
+    let mut buf = vec![0; min(len, COPY_BUFFER_SIZE)];
+    let mut done = 0;
+    while done < len {
+        let want = min(len - done, buf.len());
+        let mut dst = IoSequence::bytes_sequence(&mut buf[..want]);
+        let n = match src.preadv(&mut dst, src_offset + done as i64, ctx) {
+            Ok(n) => n,
+            Err(err) if err.code() == libc::EOF => 0,
+            Err(err) if done == 0 => return Err(err),
+            Err(_) => break,
+        };
+        if n == 0 {
+            break;
+        }
+        let mut srcs = IoSequence::bytes_sequence(&mut buf[..n]);
+        let written = match fops.write(flags, &mut srcs, offset + done as i64, ctx) {
+            Ok(written) => written,
+            Err(err) if done == 0 => return Err(err),
+            Err(_) => break,
+        };
+        done += written;
+        if written < want {
+            break;
+        }
+    }
+    Ok(done)
+}
+
 #[derive(Default, Clone, Copy, Debug)]
 pub struct FileFlags {
     pub direct: bool,
@@ -314,7 +401,6 @@ impl FileFlags {
 
     pub fn as_settable(&self) -> SettableFileFlags {
         SettableFileFlags {
-            direct: self.direct,
             non_blocking: self.non_blocking,
             append: self.append,
             async_: self.async_,
@@ -324,7 +410,6 @@ impl FileFlags {
 
 #[derive(Copy, Clone, Default)]
 pub struct SettableFileFlags {
-    pub direct: bool,
     pub non_blocking: bool,
     pub append: bool,
     pub async_: bool,
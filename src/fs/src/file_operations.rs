@@ -5,7 +5,8 @@ use memmap::mmap_opts::MmapOpts;
 use utils::SysResult;
 
 use crate::{
-    dentry::DentrySerializer, inode, seek::SeekWhence, DirentRef, FileFlags, ReaddirResult,
+    dentry::DentrySerializer, file::copy_range_through_buffer, inode, seek::SeekWhence, DirentRef,
+    File, FileFlags, ReaddirResult,
 };
 
 use super::Context;
@@ -44,6 +45,19 @@ pub trait FileOperations: std::fmt::Debug {
         ctx: &dyn Context,
     ) -> ReaddirResult<i64>;
     fn readiness(&self, mask: u64, ctx: &dyn Context) -> u64;
+    // copy_range_from copies up to len bytes at src_offset of src to offset of this file, for
+    // copy_file_range(2) and sendfile(2). By default the bytes go through a buffer.
+    fn copy_range_from(
+        &self,
+        flags: FileFlags,
+        src: &File,
+        src_offset: i64,
+        offset: i64,
+        len: usize,
+        ctx: &dyn Context,
+    ) -> SysResult<usize> {
+        copy_range_through_buffer(self, flags, src, src_offset, offset, len, ctx)
+    }
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
@@ -4,7 +4,7 @@ use time::Time;
 use utils::{bail_libc, SysError, SysResult};
 
 use crate::{
-    attr::{FileOwner, FilePermissions, StableAttr, UnstableAttr},
+    attr::{FileOwner, FilePermissions, StableAttr, TimeSpec, UnstableAttr},
     context::Context,
     inode,
     inode_operations::RenameUnderParents,
@@ -61,6 +61,9 @@ impl InodeOperations for InodeSimpleAttributes {
     fn truncate(&mut self, _: i64, _: &dyn Context) -> SysResult<()> {
         bail_libc!(libc::EINVAL)
     }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
     fn create(
         &mut self,
         _: UnstableAttr,
@@ -72,6 +75,22 @@ impl InodeOperations for InodeSimpleAttributes {
     ) -> SysResult<File> {
         bail_libc!(libc::ENOTDIR)
     }
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn link(&mut self, _: &inode::Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
     fn rename(
         &self,
         _: RenameUnderParents<&mut inode::Inode>,
@@ -83,6 +102,22 @@ impl InodeOperations for InodeSimpleAttributes {
         logger::warn!("renaming is only allowed for the files that were created by user");
         bail_libc!(libc::EPERM)
     }
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.uattr.write().unwrap().set_timestamps(ts, ctx.now());
+        Ok(())
+    }
+    fn get_xattr(&self, _: &str) -> SysResult<Vec<u8>> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn set_xattr(&mut self, _: &str, _: &[u8], _: i32) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn remove_xattr(&mut self, _: &str) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
     fn add_link(&self) {
         self.uattr.write().unwrap().links += 1;
     }
@@ -134,6 +169,9 @@ impl InodeOperations for SimpleFileInode {
     fn truncate(&mut self, _: i64, _: &dyn Context) -> SysResult<()> {
         bail_libc!(libc::EINVAL)
     }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
     fn create(
         &mut self,
         _: UnstableAttr,
@@ -145,6 +183,22 @@ impl InodeOperations for SimpleFileInode {
     ) -> SysResult<File> {
         bail_libc!(libc::ENOTDIR)
     }
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn link(&mut self, _: &inode::Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
     fn rename(
         &self,
         _: RenameUnderParents<&mut inode::Inode>,
@@ -155,6 +209,21 @@ impl InodeOperations for SimpleFileInode {
     ) -> SysResult<()> {
         bail_libc!(libc::ENOTDIR)
     }
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.attrs.set_timestamps(ts, ctx)
+    }
+    fn get_xattr(&self, _: &str) -> SysResult<Vec<u8>> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn set_xattr(&mut self, _: &str, _: &[u8], _: i32) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn remove_xattr(&mut self, _: &str) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
     fn add_link(&self) {
         self.attrs.add_link()
     }
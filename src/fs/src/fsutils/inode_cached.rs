@@ -17,7 +17,7 @@ use time::Time;
 use utils::{bail_libc, SysError, SysResult};
 
 use crate::{
-    attr::{AttrMask, FilePermissions, StableAttr, UnstableAttr},
+    attr::{AttrMask, FilePermissions, StableAttr, TimeSpec, UnstableAttr},
     context::Context,
     host::RegularFileObject,
     inode,
@@ -133,6 +133,9 @@ impl InodeOperations for CachingInodeOperations {
         self.cache.truncate(size as u64, ctx);
         Ok(())
     }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
     fn create(
         &mut self,
         _: UnstableAttr,
@@ -144,6 +147,22 @@ impl InodeOperations for CachingInodeOperations {
     ) -> SysResult<File> {
         unreachable!()
     }
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        unreachable!()
+    }
+    fn link(&mut self, _: &inode::Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        unreachable!()
+    }
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        unreachable!()
+    }
     fn rename(
         &self,
         _: RenameUnderParents<&mut inode::Inode>,
@@ -155,6 +174,22 @@ impl InodeOperations for CachingInodeOperations {
         logger::warn!("renaming is only allowed for the files that were created by user");
         bail_libc!(libc::EPERM)
     }
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.uattr.set_timestamps(ts, ctx.now());
+        Ok(())
+    }
+    fn get_xattr(&self, _: &str) -> SysResult<Vec<u8>> {
+        unreachable!()
+    }
+    fn set_xattr(&mut self, _: &str, _: &[u8], _: i32) -> SysResult<()> {
+        unreachable!()
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        unreachable!()
+    }
+    fn remove_xattr(&mut self, _: &str) -> SysResult<()> {
+        unreachable!()
+    }
     fn add_link(&self) {}
     fn drop_link(&self) {}
     fn as_any(&self) -> &dyn std::any::Any {
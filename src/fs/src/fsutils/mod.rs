@@ -6,7 +6,9 @@ pub use host_file_mapper::*;
 
 use mem::{block_seq::zero_seq, AccessType, Addr};
 use memmap::file::MemmapFile;
+use pgalloc::{AllocOpts, Direction};
 use segment::{Seg, Set, SetOperations};
+use usage::MemoryKind;
 use utils::{bail_libc, FileRange, Range, SysError, SysResult};
 
 use crate::{attr::InodeType, file::FILE_MAX_OFFSET, inode::Inode, seek::SeekWhence};
@@ -41,6 +43,9 @@ impl SetOperations for FileRangeSetOperations {
 pub trait SetU64Operations {
     fn truncate(&mut self, end: u64, ctx: &dyn Context);
     fn file_range_of(&self, seg: &Seg<u64>, r: Range<u64>) -> Range<u64>;
+    fn fill(&mut self, mr: Range<u64>, kind: MemoryKind, ctx: &dyn Context) -> SysResult<()>;
+    fn zero(&self, mr: Range<u64>, ctx: &dyn Context) -> SysResult<()>;
+    fn decommit(&self, mr: Range<u64>, ctx: &dyn Context) -> SysResult<()>;
 }
 
 impl SetU64Operations for FileRangeSet {
@@ -77,6 +82,52 @@ impl SetU64Operations for FileRangeSet {
             end: frstart + r.len(),
         }
     }
+
+    // fill allocates pages for the gaps in the page-aligned range mr. The new pages read back as
+    // zeros.
+    fn fill(&mut self, mr: Range<u64>, kind: MemoryKind, ctx: &dyn Context) -> SysResult<()> {
+        let mut mf = ctx.memory_file_provider().memory_file_write_lock();
+        let mut start = mr.start;
+        while start < mr.end {
+            if let Some(seg) = self.find_segment(start) {
+                start = seg.end();
+                continue;
+            }
+            let gap = self.find_gap(start).unwrap();
+            let g = gap.range().intersect(&mr);
+            let fr = mf.allocate(
+                g.len(),
+                AllocOpts {
+                    kind,
+                    dir: Direction::BottomUp,
+                },
+            )?;
+            self.insert(g, fr.start);
+            start = g.end;
+        }
+        Ok(())
+    }
+
+    // zero overwrites the pages in mr with zeros, leaving them allocated.
+    fn zero(&self, mr: Range<u64>, ctx: &dyn Context) -> SysResult<()> {
+        let mut mf = ctx.memory_file_provider().memory_file_write_lock();
+        for seg in self.find_overlapping(mr) {
+            let fr = self.file_range_of(&seg, seg.range().intersect(&mr));
+            let ims = mf.map_internal(fr, AccessType::write())?;
+            zero_seq(ims.as_view())?;
+        }
+        Ok(())
+    }
+
+    // decommit releases the backing store of the pages in the page-aligned range mr, which read
+    // back as zeros afterwards.
+    fn decommit(&self, mr: Range<u64>, ctx: &dyn Context) -> SysResult<()> {
+        let mut mf = ctx.memory_file_provider().memory_file_write_lock();
+        for seg in self.find_overlapping(mr) {
+            mf.decommit(self.file_range_of(&seg, seg.range().intersect(&mr)))?;
+        }
+        Ok(())
+    }
 }
 
 pub struct FdReadWriter {
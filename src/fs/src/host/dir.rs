@@ -11,10 +11,10 @@ use usage::MemoryKind;
 use utils::{bail_libc, SysError, SysResult};
 
 use crate::{
-    attr::{FilePermissions, InodeType, StableAttr, UnstableAttr},
+    attr::{FilePermissions, InodeType, StableAttr, TimeSpec, UnstableAttr},
     context::Context,
     dentry::{generic_readdir, DentAttr, DentrySerializer, DirIterCtx},
-    dev::null::NullDevice,
+    dev::{MemDevice, MemDeviceInode},
     dirent_readdir,
     fsutils::{inode::InodeSimpleAttributes, seek_with_dir_cursor},
     inode,
@@ -22,6 +22,7 @@ use crate::{
     mount::{MountSource, MountSourceFlags},
     seek::SeekWhence,
     tmpfs::{self, TMPFS_DEVICE},
+    xattr::{self, Xattrs},
     DirIterator, Dirent, DirentRef, File, FileFlags, FileOperations, InodeOperations, ReaddirError,
     ReaddirResult,
 };
@@ -46,23 +47,46 @@ impl DirChildren {
         }
     }
 
-    fn dirents(&mut self, dir_path: &PathBuf, ctx: &dyn Context) -> &HashMap<String, DirentRef> {
+    // new_empty returns the children of a directory that does not exist on the host.
+    fn new_empty() -> Self {
+        Self {
+            already_read: true,
+            ..Self::new()
+        }
+    }
+
+    fn dirents(
+        &mut self,
+        dir_path: &PathBuf,
+        flags: MountSourceFlags,
+        ctx: &dyn Context,
+    ) -> &HashMap<String, DirentRef> {
         if !self.already_read {
-            self.retrieve_children(dir_path, ctx);
+            self.retrieve_children(dir_path, flags, ctx);
             self.already_read = true;
         }
         &self.dirents
     }
 
-    fn dentry_map(&mut self, dir_path: &PathBuf, ctx: &dyn Context) -> &BTreeMap<String, DentAttr> {
+    fn dentry_map(
+        &mut self,
+        dir_path: &PathBuf,
+        flags: MountSourceFlags,
+        ctx: &dyn Context,
+    ) -> &BTreeMap<String, DentAttr> {
         if !self.already_read {
-            self.retrieve_children(dir_path, ctx);
+            self.retrieve_children(dir_path, flags, ctx);
             self.already_read = true;
         }
         &self.dentry_map
     }
 
-    fn retrieve_children(&mut self, dir_path: &PathBuf, ctx: &dyn Context) {
+    fn retrieve_children(
+        &mut self,
+        dir_path: &PathBuf,
+        flags: MountSourceFlags,
+        ctx: &dyn Context,
+    ) {
         let mut dir = nix::dir::Dir::open(
             dir_path,
             nix::fcntl::OFlag::O_RDONLY | nix::fcntl::OFlag::O_DIRECTORY,
@@ -72,7 +96,8 @@ impl DirChildren {
         for d in dir.iter() {
             let d = d.unwrap();
             let name = d.file_name().to_str().unwrap().to_string();
-            if &name == "." || &name == ".." {
+            // Synthetic children shadow the host files of the same name.
+            if &name == "." || &name == ".." || self.dirents.contains_key(&name) {
                 continue;
             }
             let joined = Path::new(dir_path).join(&name);
@@ -83,11 +108,8 @@ impl DirChildren {
                     return;
                 }
             };
-            let iops = dir_or_file(sattr, joined, ctx);
-            let msrc = {
-                let flags = MountSourceFlags::default();
-                MountSource::new(flags)
-            };
+            let msrc = MountSource::new_host(flags, &joined);
+            let iops = dir_or_file(sattr, joined, flags, ctx);
             let inode = inode::Inode::new(iops, Rc::new(msrc), sattr);
             let d = Dirent::new(inode, name.to_string());
             self.dirents.insert(name.clone(), d);
@@ -106,6 +128,10 @@ pub struct Dir {
     attr: InodeSimpleAttributes,
     children: DirChildren,
     host_absolute_path: PathBuf,
+    // mount_flags are inherited by the children of this directory.
+    mount_flags: MountSourceFlags,
+    // xattrs are the extended attributes of a directory that is not backed by the host.
+    xattrs: Xattrs,
 }
 
 impl InodeOperations for Dir {
@@ -137,6 +163,9 @@ impl InodeOperations for Dir {
     fn truncate(&mut self, _: i64, _: &dyn Context) -> SysResult<()> {
         bail_libc!(libc::EISDIR)
     }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EISDIR)
+    }
     fn create(
         &mut self,
         parent_uattr: UnstableAttr,
@@ -156,6 +185,60 @@ impl InodeOperations for Dir {
         let dirent = dirent.borrow();
         dirent.inode().get_file(dirent_to_add, flags)
     }
+    fn create_link(
+        &mut self,
+        parent_uattr: UnstableAttr,
+        mount_source: Rc<MountSource>,
+        name: &str,
+        target: &str,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        if name.len() > linux::NAME_MAX {
+            bail_libc!(libc::ENAMETOOLONG);
+        }
+        let inode = self.new_symlink(parent_uattr, mount_source, target, ctx);
+        let dirent = Dirent::new(inode, name.to_string());
+        self.add_child(name.to_string(), dirent, ctx);
+        Ok(())
+    }
+    fn link(&mut self, target: &inode::Inode, name: &str, ctx: &dyn Context) -> SysResult<()> {
+        if name.len() > linux::NAME_MAX {
+            bail_libc!(libc::ENAMETOOLONG);
+        }
+        let file = match target.try_inode_operations::<tmpfs::RegularFile>() {
+            Some(f) => f,
+            None => {
+                logger::warn!(
+                    "hard links are only allowed for the files that were created by user"
+                );
+                bail_libc!(libc::EPERM);
+            }
+        };
+        let inode = inode::Inode::new(
+            Box::new(file.new_link()),
+            target.mount_source().clone(),
+            target.stable_attr(),
+        );
+        let dirent = Dirent::new(inode, name.to_string());
+        self.add_child(name.to_string(), dirent, ctx);
+        Ok(())
+    }
+    fn unlink(&mut self, name: &str, ctx: &dyn Context) -> SysResult<()> {
+        let child = self.walk(name, ctx)?;
+        let is_in_memory = {
+            let child = child.borrow();
+            let inode = child.inode();
+            inode.try_inode_operations::<tmpfs::RegularFile>().is_some()
+                || inode.try_inode_operations::<tmpfs::Symlink>().is_some()
+        };
+        if !is_in_memory {
+            logger::warn!("unlinking is only allowed for the files that were created by user");
+            bail_libc!(libc::EPERM);
+        }
+        drop(child);
+        let child = self.remove_child(name, ctx)?;
+        tmpfs::release_if_unreachable(&child, ctx)
+    }
     fn rename(
         &self,
         _: RenameUnderParents<&mut inode::Inode>,
@@ -167,6 +250,37 @@ impl InodeOperations for Dir {
         logger::warn!("renaming is only allowed for the files that were created by user");
         bail_libc!(libc::EPERM)
     }
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.attr.set_timestamps(ts, ctx)
+    }
+    fn get_xattr(&self, name: &str) -> SysResult<Vec<u8>> {
+        if self.is_in_memory() {
+            self.xattrs.get(name)
+        } else {
+            xattr::get_host(&self.host_absolute_path, name)
+        }
+    }
+    fn set_xattr(&mut self, name: &str, value: &[u8], flags: i32) -> SysResult<()> {
+        if !self.is_in_memory() {
+            logger::warn!("modifying host::Dir is not allowed");
+            bail_libc!(libc::EPERM);
+        }
+        self.xattrs.set(name, value, flags)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        if self.is_in_memory() {
+            Ok(self.xattrs.list())
+        } else {
+            xattr::list_host(&self.host_absolute_path)
+        }
+    }
+    fn remove_xattr(&mut self, name: &str) -> SysResult<()> {
+        if !self.is_in_memory() {
+            logger::warn!("modifying host::Dir is not allowed");
+            bail_libc!(libc::EPERM);
+        }
+        self.xattrs.remove(name)
+    }
     fn add_link(&self) {
         self.attr.add_link();
     }
@@ -183,6 +297,14 @@ impl InodeOperations for Dir {
 
 impl Dir {
     pub fn new<P: AsRef<Path>, F: Fn() -> Time>(path: P, timer: F) -> Self {
+        Self::new_with_flags(path, MountSourceFlags::default(), timer)
+    }
+
+    pub fn new_with_flags<P: AsRef<Path>, F: Fn() -> Time>(
+        path: P,
+        mount_flags: MountSourceFlags,
+        timer: F,
+    ) -> Self {
         let uattr = UnstableAttr::from_path(path.as_ref())
             .expect("failed to retrieve UnstableAttr from path")
             .record_current_time(timer);
@@ -190,11 +312,53 @@ impl Dir {
             attr: InodeSimpleAttributes::new_with_unstable(uattr, linux::RAMFS_MAGIC),
             children: DirChildren::new(),
             host_absolute_path: path.as_ref().to_path_buf(),
+            mount_flags,
+            xattrs: Xattrs::default(),
         }
     }
 
+    // new_in_memory returns an empty directory which is not backed by the host, e.g. the root
+    // of a tmpfs mount. Files created in it live in memory just like the ones created in
+    // host directories.
+    pub fn new_in_memory(
+        mode: linux::FileMode,
+        mount_flags: MountSourceFlags,
+        ctx: &dyn Context,
+    ) -> (Self, StableAttr) {
+        let uattr = UnstableAttr {
+            owner: ctx.file_owner(),
+            perms: FilePermissions::from_mode(mode),
+            links: 2,
+            ..UnstableAttr::default()
+        };
+        let uattr = uattr.record_current_time(|| ctx.now());
+        let dir = Self {
+            attr: InodeSimpleAttributes::new_with_unstable(uattr, linux::TMPFS_MAGIC),
+            children: DirChildren::new_empty(),
+            host_absolute_path: PathBuf::new(),
+            mount_flags,
+            xattrs: Xattrs::default(),
+        };
+        let tmpfs_dev = TMPFS_DEVICE.lock().unwrap();
+        let sattr = StableAttr {
+            typ: InodeType::Directory,
+            device_id: tmpfs_dev.device_id(),
+            inode_id: tmpfs_dev.next_ino(),
+            block_size: PAGE_SIZE as i64,
+            device_file_major: 0,
+            device_file_minor: 0,
+        };
+        (dir, sattr)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        self.host_absolute_path.as_os_str().is_empty()
+    }
+
     fn walk(&mut self, name: &str, ctx: &dyn Context) -> SysResult<DirentRef> {
-        let children_dirents = self.children.dirents(&self.host_absolute_path, ctx);
+        let children_dirents =
+            self.children
+                .dirents(&self.host_absolute_path, self.mount_flags, ctx);
         children_dirents
             .get(name)
             .cloned()
@@ -234,6 +398,35 @@ impl Dir {
         ))
     }
 
+    fn new_symlink(
+        &self,
+        parent_uattr: UnstableAttr,
+        dir_mount_source: Rc<MountSource>,
+        target: &str,
+        ctx: &dyn Context,
+    ) -> inode::Inode {
+        let mut owner = ctx.file_owner();
+        if parent_uattr.perms.set_gid {
+            owner.gid = parent_uattr.owner.gid;
+        }
+        let perms = FilePermissions::from_mode(linux::FileMode(0o777));
+        let attr = InodeSimpleAttributes::new(owner, perms, linux::RAMFS_MAGIC, &|| ctx.now());
+        let iops = tmpfs::Symlink::new(attr, target.to_string());
+        let tmpfs_dev = TMPFS_DEVICE.lock().unwrap();
+        inode::Inode::new(
+            Box::new(iops),
+            dir_mount_source,
+            StableAttr {
+                typ: InodeType::Symlink,
+                device_id: tmpfs_dev.device_id(),
+                inode_id: tmpfs_dev.next_ino(),
+                block_size: PAGE_SIZE as i64,
+                device_file_major: 0,
+                device_file_minor: 0,
+            },
+        )
+    }
+
     fn add_child(&mut self, name: String, d: DirentRef, ctx: &dyn Context) {
         let d_ref = d.borrow();
         let inode = d_ref.inode();
@@ -257,6 +450,21 @@ impl Dir {
         self.attr.uattr.write().unwrap().status_change_time = now;
     }
 
+    // add_synthetic_child adds d, which is not backed by the host, as a child of this directory.
+    // Unlike add_child, this is not a modification of the directory seen by the user, e.g.
+    // /proc/self hiding the host's one.
+    pub(crate) fn add_synthetic_child(&mut self, name: String, d: DirentRef) {
+        let sattr = d.borrow().inode().stable_attr();
+        self.children.dirents.insert(name.clone(), d);
+        self.children.dentry_map.insert(
+            name,
+            DentAttr {
+                typ: sattr.typ,
+                inode_id: sattr.inode_id,
+            },
+        );
+    }
+
     fn remove_child(&mut self, name: &str, ctx: &dyn Context) -> SysResult<DirentRef> {
         let dirent = self
             .children
@@ -289,9 +497,10 @@ impl Dir {
     }
 }
 
-fn dir_or_file(
+pub fn dir_or_file(
     sattr: StableAttr,
     absolute_path: PathBuf,
+    mount_flags: MountSourceFlags,
     ctx: &dyn Context,
 ) -> Box<dyn InodeOperations> {
     match sattr.typ {
@@ -299,33 +508,33 @@ fn dir_or_file(
             Box::new(RegularFile::new(absolute_path))
         }
         InodeType::Directory | InodeType::SpecialDirectory => {
-            Box::new(Dir::new(absolute_path, &|| ctx.now()))
+            Box::new(Dir::new_with_flags(absolute_path, mount_flags, &|| {
+                ctx.now()
+            }))
         }
         InodeType::Symlink => {
             let file_owner = ctx.file_owner();
             let perms = FilePermissions::from_mode(linux::FileMode(0o777));
             let simple_attr =
                 InodeSimpleAttributes::new(file_owner, perms, linux::RAMFS_MAGIC, &|| ctx.now());
-            let target = PathBuf::from(
-                nix::fcntl::readlink(&absolute_path).expect("failed to read symlink"),
-            );
-            Box::new(Symlink::new(simple_attr, target))
+            Box::new(Symlink::new(simple_attr, absolute_path))
         }
         InodeType::CharacterDevice => {
-            // FIXME
-            if absolute_path == Path::new("/dev/null") {
-                let file_owner = ctx.file_owner();
-                let mode = linux::FileMode(0o666);
-                Box::new(NullDevice::new(file_owner, mode, ctx))
-            } else {
-                logger::warn!(
-                    "unhandled filename {:?}. Just handling this just like /dev/null",
-                    absolute_path
-                );
-                let file_owner = ctx.file_owner();
-                let mode = linux::FileMode(0o666);
-                Box::new(NullDevice::new(file_owner, mode, ctx))
-            }
+            let file_owner = ctx.file_owner();
+            let mode = linux::FileMode(0o666);
+            let kind = MemDevice::ALL
+                .iter()
+                .find(|kind| absolute_path == Path::new("/dev").join(kind.name()))
+                .copied()
+                .unwrap_or_else(|| {
+                    // FIXME
+                    logger::warn!(
+                        "unhandled filename {:?}. Just handling this just like /dev/null",
+                        absolute_path
+                    );
+                    MemDevice::Null
+                });
+            Box::new(MemDeviceInode::new(kind, file_owner, mode, ctx))
         }
         InodeType::BlockDevice => {
             // FIXME
@@ -335,7 +544,7 @@ fn dir_or_file(
             );
             let file_owner = ctx.file_owner();
             let mode = linux::FileMode(0o666);
-            Box::new(NullDevice::new(file_owner, mode, ctx))
+            Box::new(MemDeviceInode::new(MemDevice::Null, file_owner, mode, ctx))
         }
         _ => todo!("unhandled case: {:?}", sattr.typ),
     }
@@ -436,7 +645,8 @@ impl DirIterator for DirFileIter {
         let dir = inode.inode_operations_mut::<Dir>();
         match generic_readdir(
             dir_ctx,
-            dir.children.dentry_map(&dir.host_absolute_path, ctx),
+            dir.children
+                .dentry_map(&dir.host_absolute_path, dir.mount_flags, ctx),
         ) {
             Ok(n) => Ok(offset + n),
             Err(err) => Err(ReaddirError::new(offset + err.value(), err.code())),
@@ -444,6 +654,23 @@ impl DirIterator for DirFileIter {
     }
 }
 
+// check_replaceable fails with ENOTEMPTY if replaced is a directory that still has children.
+fn check_replaceable(replaced: &DirentRef, ctx: &dyn Context) -> SysResult<()> {
+    let mut replaced = replaced.borrow_mut();
+    if !replaced.inode().stable_attr().is_directory() {
+        return Ok(());
+    }
+    let dir = replaced.inode_mut().inode_operations_mut::<Dir>();
+    let is_empty = dir
+        .children
+        .dentry_map(&dir.host_absolute_path, dir.mount_flags, ctx)
+        .is_empty();
+    if !is_empty {
+        bail_libc!(libc::ENOTEMPTY);
+    }
+    Ok(())
+}
+
 pub fn rename(
     parents: RenameUnderParents<&mut Dir>,
     old_name: &str,
@@ -462,12 +689,9 @@ pub fn rename(
                     .dirents
                     .get(&new_name)
                     .expect("no child while this rename operation is a replacement");
-                let replaced = replaced.borrow();
-                if replaced.inode().stable_attr().is_directory() {
-                    todo!()
-                }
-                drop(replaced);
-                parent.remove_child(&new_name, ctx)?;
+                check_replaceable(replaced, ctx)?;
+                let replaced = parent.remove_child(&new_name, ctx)?;
+                tmpfs::release_if_unreachable(&replaced, ctx)?;
             }
 
             let d = parent.remove_child(old_name, ctx)?;
@@ -481,12 +705,9 @@ pub fn rename(
                     .dirents
                     .get(&new_name)
                     .expect("no child while this rename operation is a replacement");
-                let replaced = replaced.borrow();
-                if replaced.inode().stable_attr().is_directory() {
-                    todo!()
-                }
-                drop(replaced);
-                new.remove_child(&new_name, ctx)?;
+                check_replaceable(replaced, ctx)?;
+                let replaced = new.remove_child(&new_name, ctx)?;
+                tmpfs::release_if_unreachable(&replaced, ctx)?;
             }
 
             let d = old.remove_child(old_name, ctx)?;
@@ -14,7 +14,7 @@ use nix::{
 use utils::{bail_libc, SysError, SysResult};
 
 use crate::{
-    attr::{AttrMask, FilePermissions, StableAttr, UnstableAttr},
+    attr::{AttrMask, FilePermissions, StableAttr, TimeSpec, UnstableAttr},
     context::Context,
     dentry::DentrySerializer,
     fsutils::{
@@ -25,7 +25,8 @@ use crate::{
     inode_operations::RenameUnderParents,
     mount::MountSource,
     seek::SeekWhence,
-    DirentRef, File, FileFlags, FileOperations, InodeOperations, ReaddirError, ReaddirResult,
+    xattr, DirentRef, File, FileFlags, FileOperations, InodeOperations, ReaddirError,
+    ReaddirResult,
 };
 
 #[derive(Debug)]
@@ -76,6 +77,10 @@ impl InodeOperations for RegularFile {
         logger::error!("modifying host::RegularFile is not allowed");
         bail_libc!(libc::EPERM);
     }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        logger::error!("modifying host::RegularFile is not allowed");
+        bail_libc!(libc::EPERM);
+    }
     fn create(
         &mut self,
         _: UnstableAttr,
@@ -87,6 +92,22 @@ impl InodeOperations for RegularFile {
     ) -> SysResult<File> {
         bail_libc!(libc::ENOTDIR)
     }
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn link(&mut self, _: &inode::Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
     fn rename(
         &self,
         _: RenameUnderParents<&mut inode::Inode>,
@@ -98,6 +119,24 @@ impl InodeOperations for RegularFile {
         logger::warn!("renaming is only allowed for the files that were created by user");
         bail_libc!(libc::EPERM)
     }
+    fn set_timestamps(&mut self, _: TimeSpec, _: &dyn Context) -> SysResult<()> {
+        logger::error!("modifying host::RegularFile is not allowed");
+        bail_libc!(libc::EPERM);
+    }
+    fn get_xattr(&self, name: &str) -> SysResult<Vec<u8>> {
+        xattr::get_host(&self.file_object.absolute_path, name)
+    }
+    fn set_xattr(&mut self, _: &str, _: &[u8], _: i32) -> SysResult<()> {
+        logger::warn!("modifying host::RegularFile is not allowed");
+        bail_libc!(libc::EPERM)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        xattr::list_host(&self.file_object.absolute_path)
+    }
+    fn remove_xattr(&mut self, _: &str) -> SysResult<()> {
+        logger::warn!("modifying host::RegularFile is not allowed");
+        bail_libc!(libc::EPERM)
+    }
     fn add_link(&self) {}
     fn drop_link(&self) {}
     fn as_any(&self) -> &dyn std::any::Any {
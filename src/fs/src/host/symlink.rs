@@ -6,14 +6,14 @@ use utils::{bail_libc, SysError, SysResult};
 
 use crate::{
     attr, context::Context, dentry::DentrySerializer, fsutils::inode::InodeSimpleAttributes, inode,
-    inode_operations::RenameUnderParents, mount::MountSource, seek::SeekWhence, DirentRef, File,
-    FileFlags, FileOperations, InodeOperations, ReaddirError, ReaddirResult,
+    inode_operations::RenameUnderParents, mount::MountSource, seek::SeekWhence, xattr, DirentRef,
+    File, FileFlags, FileOperations, InodeOperations, ReaddirError, ReaddirResult,
 };
 
 #[derive(Debug)]
 pub struct Symlink {
     simple_attr: InodeSimpleAttributes,
-    target: PathBuf,
+    host_absolute_path: PathBuf,
 }
 
 impl InodeOperations for Symlink {
@@ -29,7 +29,7 @@ impl InodeOperations for Symlink {
         sattr: attr::StableAttr,
     ) -> SysResult<attr::UnstableAttr> {
         let mut uattr = self.simple_attr.unstable_attr(msrc, sattr)?;
-        uattr.size = self.target.as_path().to_str().unwrap().len() as i64;
+        uattr.size = self.read_link()?.len() as i64;
         uattr.usage = uattr.size;
         Ok(uattr)
     }
@@ -37,12 +37,18 @@ impl InodeOperations for Symlink {
         Err(SysError::resolve_via_readlink())
     }
     fn read_link(&self) -> SysResult<String> {
-        // FIXME
-        Ok(self.target.to_str().unwrap().to_string())
+        let target = nix::fcntl::readlinkat(libc::AT_FDCWD, &self.host_absolute_path)
+            .map_err(SysError::from_nix_errno)?;
+        target
+            .into_string()
+            .map_err(|_| SysError::new(libc::EINVAL))
     }
     fn truncate(&mut self, _: i64, _: &dyn Context) -> SysResult<()> {
         bail_libc!(libc::EINVAL)
     }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENODEV)
+    }
     fn create(
         &mut self,
         _: attr::UnstableAttr,
@@ -54,6 +60,22 @@ impl InodeOperations for Symlink {
     ) -> SysResult<File> {
         bail_libc!(libc::ENOTDIR)
     }
+    fn create_link(
+        &mut self,
+        _: attr::UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn link(&mut self, _: &inode::Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
     fn rename(
         &self,
         _: RenameUnderParents<&mut inode::Inode>,
@@ -65,6 +87,23 @@ impl InodeOperations for Symlink {
         logger::warn!("renaming is only allowed for the files that were created by user");
         bail_libc!(libc::EPERM)
     }
+    fn set_timestamps(&mut self, ts: attr::TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.simple_attr.set_timestamps(ts, ctx)
+    }
+    fn get_xattr(&self, name: &str) -> SysResult<Vec<u8>> {
+        xattr::get_host(&self.host_absolute_path, name)
+    }
+    fn set_xattr(&mut self, _: &str, _: &[u8], _: i32) -> SysResult<()> {
+        logger::warn!("modifying host::Symlink is not allowed");
+        bail_libc!(libc::EPERM)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        xattr::list_host(&self.host_absolute_path)
+    }
+    fn remove_xattr(&mut self, _: &str) -> SysResult<()> {
+        logger::warn!("modifying host::Symlink is not allowed");
+        bail_libc!(libc::EPERM)
+    }
     fn add_link(&self) {}
     fn drop_link(&self) {}
     fn as_any(&self) -> &dyn std::any::Any {
@@ -76,17 +115,17 @@ impl InodeOperations for Symlink {
 }
 
 impl Symlink {
-    pub fn new(simple_attr: InodeSimpleAttributes, target: PathBuf) -> Self {
+    pub fn new(simple_attr: InodeSimpleAttributes, host_absolute_path: PathBuf) -> Self {
         Self {
             simple_attr,
-            target,
+            host_absolute_path,
         }
     }
 }
 
 #[derive(Clone, Debug)]
-struct SymlinkFileOperations {
-    dirent: DirentRef,
+pub struct SymlinkFileOperations {
+    pub dirent: DirentRef,
 }
 
 impl FileOperations for SymlinkFileOperations {
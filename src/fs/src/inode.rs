@@ -9,11 +9,12 @@ use utils::{bail_libc, err_libc, SysError, SysResult};
 use crate::{inode_operations::RenameUnderParents, DirentRef};
 
 use super::{
-    attr::{FileOwner, FilePermissions, InodeType, PermMask, StableAttr, UnstableAttr},
+    attr::{FileOwner, FilePermissions, InodeType, PermMask, StableAttr, TimeSpec, UnstableAttr},
     context::Context,
     fsutils::inode::InodeSimpleAttributes,
+    lock::{FileLocks, RecordLocks},
     mount::MountSource,
-    File, FileFlags, InodeOperations,
+    xattr, File, FileFlags, InodeOperations,
 };
 
 #[derive(Debug)]
@@ -21,6 +22,8 @@ pub struct Inode {
     inode_operations: Box<dyn InodeOperations>,
     stable_attr: StableAttr,
     mount_source: Rc<MountSource>,
+    locks: FileLocks,
+    record_locks: RecordLocks,
 }
 
 impl Inode {
@@ -33,6 +36,8 @@ impl Inode {
             inode_operations,
             stable_attr,
             mount_source,
+            locks: FileLocks::default(),
+            record_locks: RecordLocks::default(),
         }
     }
 
@@ -70,6 +75,18 @@ impl Inode {
         &self.mount_source
     }
 
+    // locks_mut returns the flock(2) locks on this inode.
+    #[inline]
+    pub fn locks_mut(&mut self) -> &mut FileLocks {
+        &mut self.locks
+    }
+
+    // record_locks_mut returns the fcntl(2) record locks on this inode.
+    #[inline]
+    pub fn record_locks_mut(&mut self) -> &mut RecordLocks {
+        &mut self.record_locks
+    }
+
     pub fn unstable_attr(&self) -> SysResult<UnstableAttr> {
         self.inode_operations
             .unstable_attr(&self.mount_source, self.stable_attr)
@@ -94,6 +111,10 @@ impl Inode {
             .expect("failed to cast InodeOperations")
     }
 
+    pub fn try_inode_operations<T: 'static>(&self) -> Option<&T> {
+        self.inode_operations.as_any().downcast_ref::<T>()
+    }
+
     pub fn check_permission(&self, p: PermMask, ctx: &dyn Context) -> SysResult<()> {
         if p.write && self.mount_source.flags().read_only {
             bail_libc!(libc::EROFS);
@@ -142,6 +163,54 @@ impl Inode {
         self.inode_operations.truncate(size, ctx)
     }
 
+    pub fn allocate(
+        &mut self,
+        mode: i32,
+        offset: i64,
+        length: i64,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        if self.stable_attr.is_directory() {
+            bail_libc!(libc::EISDIR);
+        }
+        self.inode_operations.allocate(mode, offset, length, ctx)
+    }
+
+    pub fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        if ts.is_empty() {
+            return Ok(());
+        }
+        self.inode_operations.set_timestamps(ts, ctx)
+    }
+
+    pub fn get_xattr(&self, name: &str, ctx: &dyn Context) -> SysResult<Vec<u8>> {
+        xattr::check_permission(self, name, false, ctx)?;
+        self.inode_operations.get_xattr(name)
+    }
+
+    pub fn set_xattr(
+        &mut self,
+        name: &str,
+        value: &[u8],
+        flags: i32,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        xattr::check_permission(self, name, true, ctx)?;
+        self.inode_operations.set_xattr(name, value, flags)
+    }
+
+    // list_xattr returns the names of the extended attributes that the caller may see.
+    pub fn list_xattr(&self, ctx: &dyn Context) -> SysResult<Vec<String>> {
+        let mut names = self.inode_operations.list_xattr()?;
+        names.retain(|name| xattr::is_listable(name, ctx));
+        Ok(names)
+    }
+
+    pub fn remove_xattr(&mut self, name: &str, ctx: &dyn Context) -> SysResult<()> {
+        xattr::check_permission(self, name, true, ctx)?;
+        self.inode_operations.remove_xattr(name)
+    }
+
     pub fn create(
         &mut self,
         name: &str,
@@ -155,6 +224,26 @@ impl Inode {
             .create(parent_uattr, mount_source, name, flags, perms, ctx)
     }
 
+    pub fn create_link(
+        &mut self,
+        name: &str,
+        target: &str,
+        parent_uattr: UnstableAttr,
+        mount_source: Rc<MountSource>,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        self.inode_operations
+            .create_link(parent_uattr, mount_source, name, target, ctx)
+    }
+
+    pub fn link(&mut self, target: &Inode, name: &str, ctx: &dyn Context) -> SysResult<()> {
+        self.inode_operations.link(target, name, ctx)
+    }
+
+    pub fn unlink(&mut self, name: &str, ctx: &dyn Context) -> SysResult<()> {
+        self.inode_operations.unlink(name, ctx)
+    }
+
     pub fn rename(
         &self,
         parents: RenameUnderParents<&mut Inode>,
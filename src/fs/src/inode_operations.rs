@@ -3,7 +3,7 @@ use std::{any::Any, rc::Rc};
 use utils::SysResult;
 
 use crate::{
-    attr::{FilePermissions, StableAttr, UnstableAttr},
+    attr::{FilePermissions, StableAttr, TimeSpec, UnstableAttr},
     inode::Inode,
     mount::MountSource,
     DirentRef, File, FileFlags,
@@ -23,6 +23,8 @@ pub trait InodeOperations: std::fmt::Debug {
     fn get_link(&self) -> SysResult<DirentRef>;
     fn read_link(&self) -> SysResult<String>;
     fn truncate(&mut self, size: i64, ctx: &dyn Context) -> SysResult<()>;
+    fn allocate(&mut self, mode: i32, offset: i64, length: i64, ctx: &dyn Context)
+        -> SysResult<()>;
     fn create(
         &mut self,
         parent_uattr: UnstableAttr,
@@ -32,6 +34,16 @@ pub trait InodeOperations: std::fmt::Debug {
         perms: FilePermissions,
         ctx: &dyn Context,
     ) -> SysResult<File>;
+    fn create_link(
+        &mut self,
+        parent_uattr: UnstableAttr,
+        mount_source: Rc<MountSource>,
+        name: &str,
+        target: &str,
+        ctx: &dyn Context,
+    ) -> SysResult<()>;
+    fn link(&mut self, target: &Inode, name: &str, ctx: &dyn Context) -> SysResult<()>;
+    fn unlink(&mut self, name: &str, ctx: &dyn Context) -> SysResult<()>;
     fn rename(
         &self,
         parents: RenameUnderParents<&mut Inode>,
@@ -40,6 +52,11 @@ pub trait InodeOperations: std::fmt::Debug {
         is_replacement: bool,
         ctx: &dyn Context,
     ) -> SysResult<()>;
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()>;
+    fn get_xattr(&self, name: &str) -> SysResult<Vec<u8>>;
+    fn set_xattr(&mut self, name: &str, value: &[u8], flags: i32) -> SysResult<()>;
+    fn list_xattr(&self) -> SysResult<Vec<String>>;
+    fn remove_xattr(&mut self, name: &str) -> SysResult<()>;
     fn add_link(&self);
     fn drop_link(&self);
 
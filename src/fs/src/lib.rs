@@ -10,17 +10,21 @@ pub mod fsutils;
 pub mod host;
 pub mod inode;
 mod inode_operations;
+pub mod lock;
 pub mod mount;
 pub mod offset;
+pub mod proc;
 pub mod seek;
 pub mod socket;
 pub mod tmpfs;
 pub mod utils;
+pub mod xattr;
 
 pub mod file_test_utils;
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 use anyhow::Context as AnyhowCtx;
 use attr::PermMask;
@@ -33,11 +37,17 @@ pub use inode_operations::*;
 
 use ::utils::{bail_libc, SysError, SysResult};
 use libcontainer::rootfs::RootFS;
-use nix::mount::{MntFlags, MsFlags};
+use nix::{
+    errno::Errno,
+    mount::{MntFlags, MsFlags},
+};
 use oci_spec::runtime::{LinuxNamespaceType, Spec};
 use sentinel_oci::SentinelNamespaces;
 
-use self::mount::MountNamespace;
+use self::{
+    dev::MemDevice,
+    mount::{MountNamespace, MountSource, MountSourceFlags},
+};
 
 #[derive(Debug, Default)]
 pub struct DockerImageInfo {
@@ -52,7 +62,7 @@ pub fn setup_fs(
     spec: &Spec,
     namespace: &SentinelNamespaces,
     hostname: String,
-    mounts: MountNamespace,
+    mut mounts: MountNamespace,
     command: &[String],
     ctx: &dyn Context,
 ) -> anyhow::Result<DockerImageInfo> {
@@ -81,7 +91,19 @@ pub fn setup_fs(
         .adjust_root_mount_propagation(linux)
         .with_context(|| "failed to set propagation type of root mount")?;
 
+    let readonly_paths = linux.readonly_paths().as_deref().unwrap_or_default();
+    let masked_paths = linux.masked_paths().as_deref().unwrap_or_default();
+    for path in readonly_paths {
+        readonly_path(path)?;
+    }
+    for path in masked_paths {
+        masked_path(path)?;
+    }
+
     remount_read_only()?;
+    mount_spec_mounts(spec, &mut mounts, ctx)?;
+    mount_devices(&mut mounts, ctx)?;
+    protect_paths(readonly_paths, masked_paths, &mut mounts, ctx)?;
 
     let root = {
         let mut remaining_traversals = linux::MAX_SYMLINK_TRAVERSALS as u32;
@@ -137,6 +159,158 @@ fn remount_read_only() -> anyhow::Result<()> {
     .with_context(|| "failed to mount read only")
 }
 
+// mount_spec_mounts reflects the mounts listed in the spec on the sandbox. Their sources have
+// already been mounted at their destinations on the host by prepare_rootfs, so bind mounts
+// expose the destinations.
+fn mount_spec_mounts(
+    spec: &Spec,
+    mounts: &mut MountNamespace,
+    ctx: &dyn Context,
+) -> anyhow::Result<()> {
+    let spec_mounts = match spec.mounts() {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    for m in spec_mounts {
+        let destination = m.destination();
+        let typ = m.typ().as_deref().unwrap_or("none");
+        let (flags, data) = parse_mount_options(m.options().as_deref().unwrap_or_default());
+        let fstype = if flags.contains(MsFlags::MS_BIND) {
+            "bind"
+        } else {
+            typ
+        };
+        match fstype {
+            "bind" | "proc" => (),
+            // Device files are served from the host /dev, which an empty tmpfs would hide.
+            "tmpfs" if destination != Path::new("/dev") => (),
+            _ => {
+                logger::info!("skipping {} mount on {:?}", typ, destination);
+                continue;
+            }
+        }
+        mounts
+            .mount(destination, destination, fstype, flags, &data, ctx)
+            .with_context(|| format!("failed to mount {:?}", destination))?;
+    }
+    Ok(())
+}
+
+// mount_devices overlays the device files sentinel implements by itself on their counterparts in
+// /dev, so that they never reach the host devices. Files missing from the image are skipped.
+fn mount_devices(mounts: &mut MountNamespace, ctx: &dyn Context) -> anyhow::Result<()> {
+    let msrc = Rc::new(MountSource::new_host(MountSourceFlags::default(), "/dev"));
+    for kind in MemDevice::ALL.iter() {
+        let target = Path::new("/dev").join(kind.name());
+        match mounts.mount_inode(&target, kind.new_inode(msrc.clone(), ctx), ctx) {
+            Err(err) if err.code() == libc::ENOENT => (),
+            res => res.with_context(|| format!("failed to mount {:?}", target))?,
+        }
+    }
+    Ok(())
+}
+
+// readonly_path makes path read-only on the host just like runc does. Missing paths are skipped.
+fn readonly_path(path: &str) -> anyhow::Result<()> {
+    match nix::mount::mount(
+        Some(path),
+        path,
+        None::<&str>,
+        MsFlags::MS_BIND | MsFlags::MS_REC,
+        None::<&str>,
+    ) {
+        Err(Errno::ENOENT) => return Ok(()),
+        res => res.with_context(|| format!("failed to bind {}", path))?,
+    }
+    nix::mount::mount(
+        Some(path),
+        path,
+        None::<&str>,
+        MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY | MsFlags::MS_REC,
+        None::<&str>,
+    )
+    .with_context(|| format!("failed to remount {} read only", path))
+}
+
+// masked_path hides path on the host just like runc does, by binding /dev/null over a file or
+// mounting a read-only tmpfs over a directory. Missing paths are skipped.
+fn masked_path(path: &str) -> anyhow::Result<()> {
+    match nix::mount::mount(
+        Some("/dev/null"),
+        path,
+        None::<&str>,
+        MsFlags::MS_BIND,
+        None::<&str>,
+    ) {
+        Err(Errno::ENOENT) => Ok(()),
+        Err(Errno::ENOTDIR) => nix::mount::mount(
+            Some("tmpfs"),
+            path,
+            Some("tmpfs"),
+            MsFlags::MS_RDONLY,
+            None::<&str>,
+        )
+        .with_context(|| format!("failed to mask {}", path)),
+        res => res.with_context(|| format!("failed to mask {}", path)),
+    }
+}
+
+// protect_paths reflects the read-only and masked paths on the sandbox, so that writes to them
+// fail with EROFS. Masked files are left as they are since they are /dev/null.
+fn protect_paths(
+    readonly_paths: &[String],
+    masked_paths: &[String],
+    mounts: &mut MountNamespace,
+    ctx: &dyn Context,
+) -> anyhow::Result<()> {
+    for path in readonly_paths.iter().chain(masked_paths) {
+        let is_masked_file =
+            masked_paths.contains(path) && !matches!(std::fs::metadata(path), Ok(m) if m.is_dir());
+        if is_masked_file {
+            continue;
+        }
+        match mounts.mount(
+            path.as_str(),
+            path.as_str(),
+            "bind",
+            MsFlags::MS_BIND | MsFlags::MS_RDONLY,
+            "",
+            ctx,
+        ) {
+            Err(err) if err.code() == libc::ENOENT => (),
+            res => res.with_context(|| format!("failed to protect {}", path))?,
+        }
+    }
+    Ok(())
+}
+
+// parse_mount_options splits the options of a mount in the spec into mount flags and the
+// file system specific data.
+fn parse_mount_options(options: &[String]) -> (MsFlags, String) {
+    let mut flags = MsFlags::empty();
+    let mut data = Vec::new();
+    for opt in options {
+        match opt.as_str() {
+            "ro" => flags.insert(MsFlags::MS_RDONLY),
+            "rw" => flags.remove(MsFlags::MS_RDONLY),
+            "noexec" => flags.insert(MsFlags::MS_NOEXEC),
+            "exec" => flags.remove(MsFlags::MS_NOEXEC),
+            "nosuid" => flags.insert(MsFlags::MS_NOSUID),
+            "suid" => flags.remove(MsFlags::MS_NOSUID),
+            "nodev" => flags.insert(MsFlags::MS_NODEV),
+            "dev" => flags.remove(MsFlags::MS_NODEV),
+            "noatime" => flags.insert(MsFlags::MS_NOATIME),
+            "atime" => flags.remove(MsFlags::MS_NOATIME),
+            "bind" => flags.insert(MsFlags::MS_BIND),
+            "rbind" => flags.insert(MsFlags::MS_BIND | MsFlags::MS_REC),
+            "private" | "rprivate" | "shared" | "rshared" | "slave" | "rslave" | "unbindable"
+            | "runbindable" => (),
+            _ => data.push(opt.as_str()),
+        }
+    }
+    (flags, data.join(","))
+}
+
 fn construct_env(env: &Vec<String>, hostname: String) -> HashMap<String, String> {
     let mut envv = HashMap::new();
     for e in env {
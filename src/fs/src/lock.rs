@@ -0,0 +1,298 @@
+use std::collections::{BTreeSet, HashSet};
+
+use segment::{Set, SetOperations};
+use utils::{bail_libc, Range, SysError, SysResult};
+
+// LockOwner identifies the open file description holding a lock.
+pub type LockOwner = usize;
+
+// FileLocks keeps the flock(2) locks on an inode. Either any number of open file descriptions
+// share the lock, or a single one holds it exclusively.
+#[derive(Debug, Default)]
+pub struct FileLocks {
+    shared: HashSet<LockOwner>,
+    exclusive: Option<LockOwner>,
+}
+
+impl FileLocks {
+    // lock takes the lock for owner, converting the one it already holds if any. It fails with
+    // EWOULDBLOCK if another owner holds a conflicting lock.
+    pub fn lock(&mut self, owner: LockOwner, exclusive: bool) -> SysResult<()> {
+        if self.exclusive.map_or(false, |o| o != owner) {
+            bail_libc!(libc::EWOULDBLOCK);
+        }
+        if exclusive {
+            if self.shared.iter().any(|o| *o != owner) {
+                bail_libc!(libc::EWOULDBLOCK);
+            }
+            self.shared.clear();
+            self.exclusive = Some(owner);
+        } else {
+            self.exclusive = None;
+            self.shared.insert(owner);
+        }
+        Ok(())
+    }
+
+    // unlock releases the lock held by owner, if any.
+    pub fn unlock(&mut self, owner: LockOwner) {
+        if self.exclusive == Some(owner) {
+            self.exclusive = None;
+        }
+        self.shared.remove(&owner);
+    }
+}
+
+// LOCK_EOF is the end of a record lock reaching past the end of the file, however it grows.
+pub const LOCK_EOF: u64 = u64::MAX;
+
+// LockKind is the kind of a record lock taken by fcntl(2).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockKind {
+    Read,
+    Write,
+}
+
+// RangeLock is the state of the record locks on a range of bytes, which is either read locked by
+// any number of owners or write locked by one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RangeLock {
+    readers: BTreeSet<LockOwner>,
+    writer: Option<LockOwner>,
+}
+
+impl RangeLock {
+    // conflict returns the kind and owner of a lock on this range that prevents owner from taking
+    // a lock of kind.
+    fn conflict(&self, owner: LockOwner, kind: LockKind) -> Option<(LockKind, LockOwner)> {
+        if let Some(writer) = self.writer.filter(|w| *w != owner) {
+            return Some((LockKind::Write, writer));
+        }
+        if kind == LockKind::Read {
+            return None;
+        }
+        self.readers
+            .iter()
+            .find(|r| **r != owner)
+            .map(|r| (LockKind::Read, *r))
+    }
+
+    fn add(&mut self, owner: LockOwner, kind: LockKind) {
+        match kind {
+            LockKind::Read => {
+                self.writer = None;
+                self.readers.insert(owner);
+            }
+            LockKind::Write => {
+                self.readers.clear();
+                self.writer = Some(owner);
+            }
+        }
+    }
+
+    fn remove(&mut self, owner: LockOwner) {
+        if self.writer == Some(owner) {
+            self.writer = None;
+        }
+        self.readers.remove(&owner);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.writer.is_none() && self.readers.is_empty()
+    }
+}
+
+struct RangeLockSetOperations;
+
+impl SetOperations for RangeLockSetOperations {
+    type K = u64;
+    type V = RangeLock;
+
+    fn merge(
+        &self,
+        _: Range<Self::K>,
+        v1: &Self::V,
+        _: Range<Self::K>,
+        v2: &Self::V,
+    ) -> Option<Self::V> {
+        if v1 == v2 {
+            Some(v1.clone())
+        } else {
+            None
+        }
+    }
+
+    fn split(&self, _: Range<Self::K>, v: &Self::V, _: Self::K) -> (Self::V, Self::V) {
+        (v.clone(), v.clone())
+    }
+}
+
+// RecordLocks keeps the fcntl(2) record locks on the byte ranges of an inode.
+#[derive(Debug)]
+pub struct RecordLocks {
+    set: Set<u64, RangeLock>,
+}
+
+impl Default for RecordLocks {
+    fn default() -> Self {
+        Self {
+            set: Set::new(Box::new(RangeLockSetOperations)),
+        }
+    }
+}
+
+impl RecordLocks {
+    // test returns the range, kind and owner of a lock conflicting with a lock of kind on r by
+    // owner, if any.
+    pub fn test(
+        &self,
+        owner: LockOwner,
+        kind: LockKind,
+        r: Range<u64>,
+    ) -> Option<(Range<u64>, LockKind, LockOwner)> {
+        self.set.find_overlapping(r).find_map(|seg| {
+            self.set
+                .value(&seg)
+                .conflict(owner, kind)
+                .map(|(kind, owner)| (seg.range(), kind, owner))
+        })
+    }
+
+    // lock takes a lock of kind on r for owner, replacing the locks it already holds there. It
+    // fails with EAGAIN if another owner holds a conflicting lock.
+    pub fn lock(&mut self, owner: LockOwner, kind: LockKind, r: Range<u64>) -> SysResult<()> {
+        if self.test(owner, kind, r).is_some() {
+            bail_libc!(libc::EAGAIN);
+        }
+        let mut new_lock = RangeLock::default();
+        new_lock.add(owner, kind);
+        let mut locks = Vec::new();
+        let mut start = r.start;
+        for (range, mut lock) in self.set.remove_range(r) {
+            if start < range.start {
+                locks.push((
+                    Range {
+                        start,
+                        end: range.start,
+                    },
+                    new_lock.clone(),
+                ));
+            }
+            lock.add(owner, kind);
+            locks.push((range, lock));
+            start = range.end;
+        }
+        if start < r.end {
+            locks.push((Range { start, end: r.end }, new_lock));
+        }
+        self.set
+            .extend_merging(locks)
+            .expect("removed range should be free");
+        Ok(())
+    }
+
+    // unlock releases the locks owner holds on r.
+    pub fn unlock(&mut self, owner: LockOwner, r: Range<u64>) {
+        let locks = self
+            .set
+            .remove_range(r)
+            .into_iter()
+            .filter_map(|(range, mut lock)| {
+                lock.remove(owner);
+                if lock.is_empty() {
+                    None
+                } else {
+                    Some((range, lock))
+                }
+            });
+        self.set
+            .extend_merging(locks)
+            .expect("removed range should be free");
+    }
+
+    // release releases every lock owner holds on the inode.
+    pub fn release(&mut self, owner: LockOwner) {
+        self.unlock(
+            owner,
+            Range {
+                start: 0,
+                end: LOCK_EOF,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock() {
+        let mut locks = FileLocks::default();
+        locks.lock(1, false).unwrap();
+        locks.lock(2, false).unwrap();
+        assert_eq!(locks.lock(1, true), Err(SysError::new(libc::EWOULDBLOCK)));
+
+        locks.unlock(2);
+        locks.lock(1, true).unwrap();
+        assert_eq!(locks.lock(2, false), Err(SysError::new(libc::EWOULDBLOCK)));
+        assert_eq!(locks.lock(2, true), Err(SysError::new(libc::EWOULDBLOCK)));
+
+        // The holder may downgrade its lock, which lets others share it.
+        locks.lock(1, false).unwrap();
+        locks.lock(2, false).unwrap();
+        locks.unlock(1);
+        locks.unlock(2);
+        locks.lock(3, true).unwrap();
+    }
+
+    fn range(start: u64, end: u64) -> Range<u64> {
+        Range { start, end }
+    }
+
+    #[test]
+    fn record_locks_conflict() {
+        let mut locks = RecordLocks::default();
+        locks.lock(1, LockKind::Read, range(0, 100)).unwrap();
+        locks.lock(2, LockKind::Read, range(50, 150)).unwrap();
+
+        // Overlapping read locks block a write lock, but not past them.
+        assert_eq!(
+            locks.test(3, LockKind::Write, range(120, 200)),
+            Some((range(100, 150), LockKind::Read, 2))
+        );
+        assert_eq!(
+            locks.lock(3, LockKind::Write, range(0, 10)),
+            Err(SysError::new(libc::EAGAIN))
+        );
+        assert_eq!(locks.test(3, LockKind::Read, range(0, 200)), None);
+        locks
+            .lock(3, LockKind::Write, range(150, LOCK_EOF))
+            .unwrap();
+        assert_eq!(
+            locks.test(1, LockKind::Read, range(1000, 1001)),
+            Some((range(150, LOCK_EOF), LockKind::Write, 3))
+        );
+
+        // A read lock held by the owner alone can be upgraded.
+        assert_eq!(
+            locks.lock(1, LockKind::Write, range(0, 100)),
+            Err(SysError::new(libc::EAGAIN))
+        );
+        locks.lock(1, LockKind::Write, range(0, 50)).unwrap();
+        assert_eq!(
+            locks.test(2, LockKind::Read, range(40, 60)),
+            Some((range(0, 50), LockKind::Write, 1))
+        );
+
+        locks.unlock(1, range(0, 50));
+        assert_eq!(locks.test(2, LockKind::Write, range(0, 50)), None);
+        locks.release(3);
+        assert_eq!(locks.test(1, LockKind::Write, range(150, LOCK_EOF)), None);
+        locks.release(2);
+        assert_eq!(
+            locks.test(2, LockKind::Write, range(0, LOCK_EOF)),
+            Some((range(50, 100), LockKind::Read, 1))
+        );
+    }
+}
@@ -1,11 +1,21 @@
 use std::{
-    path::{Component, Path},
+    cell::RefCell,
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
     rc::Rc,
 };
 
+use mem::PAGE_SIZE;
+use nix::{mount::MsFlags, sys::statfs};
+use usage::memory::{total_usable_memory, MEMORY_ACCOUNTING};
 use utils::{bail_libc, err_libc, SysError, SysErrorKind, SysResult};
 
-use crate::{attr::PermMask, DirentRef};
+use crate::{
+    attr::{PermMask, StableAttr},
+    host,
+    inode::Inode,
+    proc, Dirent, DirentRef,
+};
 
 use super::context::Context;
 
@@ -17,14 +27,43 @@ pub struct MountSourceFlags {
     pub no_exec: bool,
 }
 
+// Filesystem is the file system that the inodes of a mount source belong to, which statfs(2)
+// reports.
+#[derive(Debug, Clone)]
+pub enum Filesystem {
+    // Host is the host file system containing the path.
+    Host(PathBuf),
+    Tmpfs,
+    // Pseudo is for the inodes which are not visible in the file system tree, e.g. pipes.
+    Pseudo,
+}
+
 #[derive(Debug)]
 pub struct MountSource {
     flags: MountSourceFlags,
+    filesystem: Filesystem,
 }
 
 impl MountSource {
     pub fn new(flags: MountSourceFlags) -> Self {
-        Self { flags }
+        Self {
+            flags,
+            filesystem: Filesystem::Pseudo,
+        }
+    }
+
+    pub fn new_host<P: AsRef<Path>>(flags: MountSourceFlags, path: P) -> Self {
+        Self {
+            flags,
+            filesystem: Filesystem::Host(path.as_ref().to_path_buf()),
+        }
+    }
+
+    pub fn new_tmpfs(flags: MountSourceFlags) -> Self {
+        Self {
+            flags,
+            filesystem: Filesystem::Tmpfs,
+        }
     }
 
     pub fn new_pseudo() -> Self {
@@ -38,16 +77,190 @@ impl MountSource {
     pub fn flags(&self) -> MountSourceFlags {
         self.flags
     }
+
+    pub fn filesystem(&self) -> &Filesystem {
+        &self.filesystem
+    }
+
+    // statfs describes the file system of this in PAGE_SIZE blocks. The size of a tmpfs is the
+    // memory usable by the sandbox, of which what is not in use yet is free.
+    pub fn statfs(&self, ctx: &dyn Context) -> SysResult<libc::statfs> {
+        let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+        match &self.filesystem {
+            Filesystem::Host(path) => {
+                let host = statfs::statfs(path).map_err(SysError::from_nix_errno)?;
+                let to_pages = |blocks: u64| blocks * host.block_size() as u64 / PAGE_SIZE as u64;
+                st.f_type = host.filesystem_type().0;
+                st.f_blocks = to_pages(host.blocks());
+                st.f_bfree = to_pages(host.blocks_free());
+                st.f_bavail = to_pages(host.blocks_available());
+                st.f_files = host.files();
+                st.f_ffree = host.files_free();
+            }
+            Filesystem::Tmpfs => {
+                let mf = ctx.memory_file_provider().memory_file_read_lock();
+                let mapped = MEMORY_ACCOUNTING.get().map_or(0, |m| m.mapped());
+                let used = mf.total_usage().map_err(SysError::from_nix_errno)? + mapped;
+                let total = total_usable_memory(mf.total_size(), used);
+                st.f_type = linux::TMPFS_MAGIC as libc::__fsword_t;
+                st.f_blocks = total / PAGE_SIZE as u64;
+                st.f_bfree = total.saturating_sub(used) / PAGE_SIZE as u64;
+                st.f_bavail = st.f_bfree;
+            }
+            Filesystem::Pseudo => {
+                st.f_type = linux::ANON_INODE_FS_MAGIC as libc::__fsword_t;
+            }
+        }
+        st.f_bsize = PAGE_SIZE as libc::__fsword_t;
+        st.f_frsize = PAGE_SIZE as libc::__fsword_t;
+        st.f_namelen = linux::NAME_MAX as libc::__fsword_t;
+        let mut flags = 0;
+        if self.flags.read_only {
+            flags |= libc::ST_RDONLY;
+        }
+        if self.flags.no_atime {
+            flags |= libc::ST_NOATIME;
+        }
+        if self.flags.no_exec {
+            flags |= libc::ST_NOEXEC;
+        }
+        st.f_flags = flags as libc::__fsword_t;
+        Ok(st)
+    }
+}
+
+impl MountSourceFlags {
+    pub fn from_ms_flags(flags: MsFlags) -> Self {
+        Self {
+            read_only: flags.contains(MsFlags::MS_RDONLY),
+            no_atime: flags.contains(MsFlags::MS_NOATIME),
+            force_page_cache: false,
+            no_exec: flags.contains(MsFlags::MS_NOEXEC),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Mount {
+    // mount_point is kept alive here so that its address, which is the key of the mount table,
+    // is never reused by another dirent.
+    mount_point: DirentRef,
+    root: DirentRef,
 }
 
 #[derive(Debug, Clone)]
 pub struct MountNamespace {
     root: DirentRef,
+    // mounts maps the dirents that are mounted over to the mounts stacked on them. It is shared
+    // among the clones of this namespace so that every task sees the same mounts.
+    mounts: Rc<RefCell<HashMap<*const RefCell<Dirent>, Mount>>>,
 }
 
 impl MountNamespace {
     pub fn new(root: DirentRef) -> Self {
-        Self { root }
+        Self {
+            root,
+            mounts: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    // mount overlays a new file system of type fstype on target. Supported types are "bind",
+    // which exposes the host path source, "tmpfs", which is an empty in-memory directory whose
+    // mode can be given as "mode=" in data, and "proc". "proc" exposes the host procfs that the
+    // runtime has already mounted on target, except for /proc/self which sentinel synthesizes.
+    pub fn mount<P: AsRef<Path>>(
+        &mut self,
+        source: P,
+        target: P,
+        fstype: &str,
+        flags: MsFlags,
+        data: &str,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        let mount_point = self.mount_point(&target, ctx)?;
+        let mount_flags = MountSourceFlags::from_ms_flags(flags);
+        let msrc = Rc::new(match fstype {
+            "tmpfs" => MountSource::new_tmpfs(mount_flags),
+            _ => MountSource::new_host(mount_flags, source.as_ref()),
+        });
+        let inode = match fstype {
+            "bind" => {
+                let sattr =
+                    StableAttr::from_path(source.as_ref()).map_err(|e| SysError::new(e as i32))?;
+                let iops =
+                    host::dir_or_file(sattr, source.as_ref().to_path_buf(), mount_flags, ctx);
+                Inode::new(iops, msrc, sattr)
+            }
+            "tmpfs" => {
+                let mode = parse_mode(data)?.unwrap_or(linux::FileMode(0o1777));
+                let (iops, sattr) = host::Dir::new_in_memory(mode, mount_flags, ctx);
+                Inode::new(Box::new(iops), msrc, sattr)
+            }
+            "proc" => proc::new_root(target.as_ref(), mount_flags, &msrc, ctx)?,
+            _ => bail_libc!(libc::ENODEV),
+        };
+        self.attach(mount_point, inode)
+    }
+
+    // mount_inode overlays inode, which sentinel synthesizes, on target, e.g. a device file.
+    pub fn mount_inode<P: AsRef<Path>>(
+        &mut self,
+        target: P,
+        inode: Inode,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        let mount_point = self.mount_point(&target, ctx)?;
+        self.attach(mount_point, inode)
+    }
+
+    fn mount_point<P: AsRef<Path>>(&self, target: &P, ctx: &dyn Context) -> SysResult<DirentRef> {
+        let mount_point = {
+            let mut remaining_traversals = linux::MAX_SYMLINK_TRAVERSALS as u32;
+            self.find_inode(&self.root, None, target, &mut remaining_traversals, ctx)?
+        };
+        if Rc::ptr_eq(&mount_point, &self.root) {
+            logger::warn!("mounting over the root directory is not supported");
+            bail_libc!(libc::EBUSY);
+        }
+        Ok(mount_point)
+    }
+
+    // attach makes inode the root of a new mount stacked on mount_point.
+    fn attach(&mut self, mount_point: DirentRef, inode: Inode) -> SysResult<()> {
+        let is_directory = inode.stable_attr().is_directory();
+        if mount_point.borrow().stable_attr().is_directory() != is_directory {
+            bail_libc!(if is_directory {
+                libc::ENOTDIR
+            } else {
+                libc::EISDIR
+            });
+        }
+
+        let root = {
+            let mut mount_point = mount_point.borrow_mut();
+            mount_point.set_mounted();
+            let root = Dirent::new(inode, mount_point.name().to_string());
+            {
+                let mut root = root.borrow_mut();
+                root.set_parent(mount_point.parent().clone());
+                root.set_mounted();
+            }
+            root
+        };
+        self.mounts
+            .borrow_mut()
+            .insert(Rc::as_ptr(&mount_point), Mount { mount_point, root });
+        Ok(())
+    }
+
+    // follow_mounts returns the root of the topmost mount stacked on dirent, or dirent itself
+    // if nothing is mounted on it.
+    fn follow_mounts(&self, mut dirent: DirentRef) -> DirentRef {
+        let mounts = self.mounts.borrow();
+        while let Some(mount) = mounts.get(&Rc::as_ptr(&dirent)) {
+            dirent = mount.root.clone();
+        }
+        dirent
     }
 
     pub fn find_inode<P: AsRef<Path>>(
@@ -108,6 +321,7 @@ impl MountNamespace {
             }
             let cloned = Rc::clone(&current);
             let next = current.borrow_mut().walk(root, first, cloned, ctx)?;
+            let next = self.follow_mounts(next);
 
             first = match components.next() {
                 None => return Ok(next),
@@ -134,6 +348,7 @@ impl MountNamespace {
                 if *remaining_traversals == 0 {
                     err_libc!(libc::ELOOP)
                 } else {
+                    *remaining_traversals -= 1;
                     Ok(target)
                 }
             }
@@ -161,3 +376,106 @@ impl MountNamespace {
         &self.root
     }
 }
+
+// parse_mode extracts the octal "mode=" option from the comma separated mount data.
+fn parse_mode(data: &str) -> SysResult<Option<linux::FileMode>> {
+    for opt in data.split(',') {
+        if let Some(mode) = opt.strip_prefix("mode=") {
+            let mode = u32::from_str_radix(mode, 8).map_err(|_| SysError::new(libc::EINVAL))?;
+            if mode & !0o7777 != 0 {
+                bail_libc!(libc::EINVAL);
+            }
+            return Ok(Some(linux::FileMode(mode as u16)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tmpfs::TMPFS_DEVICE, TestContext};
+
+    // new_namespace returns a namespace rooted at a host dir of its own for the test name, since
+    // tests run in parallel.
+    fn new_namespace(name: &str, ctx: &dyn Context) -> (MountNamespace, std::path::PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("sentinel-mount-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("mnt")).unwrap();
+        let sattr = StableAttr::from_path(&dir).unwrap();
+        let iops = host::Dir::new(&dir, &|| ctx.now());
+        let msrc = Rc::new(MountSource::new_pseudo());
+        let root = Dirent::new(Inode::new(Box::new(iops), msrc, sattr), "/".to_string());
+        (MountNamespace::new(root), dir)
+    }
+
+    #[test]
+    fn mount_read_only_tmpfs() {
+        let ctx = TestContext::init();
+        let (mut mounts, dir) = new_namespace("mount_read_only_tmpfs", &ctx);
+        mounts
+            .mount(
+                "none",
+                "/mnt",
+                "tmpfs",
+                MsFlags::MS_RDONLY,
+                "mode=755",
+                &ctx,
+            )
+            .unwrap();
+
+        let mut remaining_traversals = linux::MAX_SYMLINK_TRAVERSALS as u32;
+        let root = mounts.root().clone();
+        let mnt = mounts
+            .find_inode(&root, None, "/mnt", &mut remaining_traversals, &ctx)
+            .unwrap();
+        {
+            let mnt = mnt.borrow();
+            let inode = mnt.inode();
+            assert_eq!(
+                inode.stable_attr().device_id,
+                TMPFS_DEVICE.lock().unwrap().device_id()
+            );
+            assert_eq!(inode.unstable_attr().unwrap().perms.as_linux_mode(), 0o755);
+            let write = PermMask {
+                read: false,
+                write: true,
+                execute: false,
+            };
+            assert_eq!(
+                inode.check_permission(write, &ctx).unwrap_err().code(),
+                libc::EROFS
+            );
+        }
+
+        let parent = mounts
+            .find_inode(&root, None, "/mnt/..", &mut remaining_traversals, &ctx)
+            .unwrap();
+        assert!(Rc::ptr_eq(&parent, &root));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn statfs_tmpfs() {
+        let ctx = TestContext::init();
+        let (mut mounts, dir) = new_namespace("statfs_tmpfs", &ctx);
+        mounts
+            .mount("none", "/mnt", "tmpfs", MsFlags::MS_NOEXEC, "", &ctx)
+            .unwrap();
+
+        let mut remaining_traversals = linux::MAX_SYMLINK_TRAVERSALS as u32;
+        let root = mounts.root().clone();
+        let mnt = mounts
+            .find_inode(&root, None, "/mnt", &mut remaining_traversals, &ctx)
+            .unwrap();
+        let st = mnt.borrow().inode().mount_source().statfs(&ctx).unwrap();
+        assert_eq!(st.f_type as u64, linux::TMPFS_MAGIC);
+        assert_eq!(st.f_bsize, PAGE_SIZE as libc::__fsword_t);
+        assert_eq!(st.f_namelen, linux::NAME_MAX as libc::__fsword_t);
+        assert!(st.f_bfree <= st.f_blocks);
+        assert_eq!(st.f_flags as u64, libc::ST_NOEXEC);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
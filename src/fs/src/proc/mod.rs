@@ -0,0 +1,71 @@
+use std::{
+    path::Path,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use once_cell::sync::Lazy;
+
+use dev::Device;
+use utils::{SysError, SysResult};
+
+mod self_dir;
+mod seq_file;
+
+pub use self_dir::*;
+pub use seq_file::*;
+
+use crate::{
+    attr::StableAttr,
+    host,
+    inode::Inode,
+    mount::{MountSource, MountSourceFlags},
+    Context, Dirent,
+};
+
+pub static PROC_DEVICE: Lazy<Arc<Mutex<Device>>> = Lazy::new(Device::new_anonymous_device);
+
+// SelfFile is a file under /proc/self. Its contents are generated by the Context every time it
+// is looked up, so a file opened once keeps showing the same snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfFile {
+    Maps,
+    Stat,
+    Status,
+}
+
+impl SelfFile {
+    pub const ALL: [SelfFile; 3] = [SelfFile::Maps, SelfFile::Stat, SelfFile::Status];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelfFile::Maps => "maps",
+            SelfFile::Stat => "stat",
+            SelfFile::Status => "status",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|f| f.name() == name)
+    }
+}
+
+// new_root returns the root of a proc mount. Everything but "self" comes from the host procfs
+// mounted on path, while "self" is synthesized for the calling task.
+pub fn new_root<P: AsRef<Path>>(
+    path: P,
+    mount_flags: MountSourceFlags,
+    msrc: &Rc<MountSource>,
+    ctx: &dyn Context,
+) -> SysResult<Inode> {
+    let sattr = StableAttr::from_path(path.as_ref()).map_err(|e| SysError::new(e as i32))?;
+    let mut dir = host::Dir::new_with_flags(path.as_ref(), mount_flags, &|| ctx.now());
+
+    let (self_dir, self_sattr) = SelfDir::new(Rc::clone(msrc), ctx);
+    let self_inode = Inode::new(Box::new(self_dir), Rc::clone(msrc), self_sattr);
+    dir.add_synthetic_child(
+        "self".to_string(),
+        Dirent::new(self_inode, "self".to_string()),
+    );
+    Ok(Inode::new(Box::new(dir), Rc::clone(msrc), sattr))
+}
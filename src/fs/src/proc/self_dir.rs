@@ -0,0 +1,277 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+use mem::PAGE_SIZE;
+use memmap::mmap_opts::MmapOpts;
+use utils::{bail_libc, SysError, SysResult};
+
+use crate::{
+    attr::{FilePermissions, InodeType, StableAttr, TimeSpec, UnstableAttr},
+    dentry::{generic_readdir, DentAttr, DentrySerializer, DirIterCtx},
+    dirent_readdir,
+    fsutils::{inode::InodeSimpleAttributes, seek_with_dir_cursor},
+    inode,
+    inode_operations::RenameUnderParents,
+    mount::MountSource,
+    seek::SeekWhence,
+    Context, DirIterator, Dirent, DirentRef, File, FileFlags, FileOperations, InodeOperations,
+    ReaddirError, ReaddirResult,
+};
+
+use super::{SelfFile, SeqFile, PROC_DEVICE};
+
+// SelfDir is /proc/self. Its files are created anew on every lookup with the contents generated
+// for the calling task, while their inode numbers stay the same.
+#[derive(Debug)]
+pub struct SelfDir {
+    attr: InodeSimpleAttributes,
+    mount_source: Rc<MountSource>,
+    dentry_map: BTreeMap<String, DentAttr>,
+}
+
+impl SelfDir {
+    pub fn new(mount_source: Rc<MountSource>, ctx: &dyn Context) -> (Self, StableAttr) {
+        let uattr = UnstableAttr {
+            owner: ctx.file_owner(),
+            perms: FilePermissions::from_mode(linux::FileMode(0o555)),
+            links: 2,
+            ..UnstableAttr::default()
+        };
+        let uattr = uattr.record_current_time(|| ctx.now());
+        let proc_dev = PROC_DEVICE.lock().unwrap();
+        let dentry_map = SelfFile::ALL
+            .iter()
+            .map(|f| {
+                let dattr = DentAttr {
+                    typ: InodeType::SpecialFile,
+                    inode_id: proc_dev.next_ino(),
+                };
+                (f.name().to_string(), dattr)
+            })
+            .collect();
+        let dir = Self {
+            attr: InodeSimpleAttributes::new_with_unstable(uattr, linux::PROC_SUPER_MAGIC),
+            mount_source,
+            dentry_map,
+        };
+        let sattr = StableAttr {
+            typ: InodeType::SpecialDirectory,
+            device_id: proc_dev.device_id(),
+            inode_id: proc_dev.next_ino(),
+            block_size: PAGE_SIZE as i64,
+            device_file_major: 0,
+            device_file_minor: 0,
+        };
+        (dir, sattr)
+    }
+}
+
+impl InodeOperations for SelfDir {
+    fn lookup(&mut self, name: &str, ctx: &dyn Context) -> SysResult<DirentRef> {
+        let (file, dattr) = match (SelfFile::from_name(name), self.dentry_map.get(name)) {
+            (Some(file), Some(dattr)) => (file, dattr),
+            _ => bail_libc!(libc::ENOENT),
+        };
+        let iops = SeqFile::new(ctx.proc_self_file(file), ctx);
+        let sattr = StableAttr {
+            typ: dattr.typ,
+            device_id: PROC_DEVICE.lock().unwrap().device_id(),
+            inode_id: dattr.inode_id,
+            block_size: PAGE_SIZE as i64,
+            device_file_major: 0,
+            device_file_minor: 0,
+        };
+        let inode = inode::Inode::new(Box::new(iops), Rc::clone(&self.mount_source), sattr);
+        Ok(Dirent::new(inode, name.to_string()))
+    }
+    fn get_file(&self, dirent: DirentRef, mut flags: FileFlags) -> SysResult<File> {
+        flags.pread = true;
+        Ok(File::new(
+            flags,
+            Box::new(SelfDirFileOperations {
+                dirent,
+                dir_cursor: String::new(),
+            }),
+        ))
+    }
+    fn unstable_attr(&self, msrc: &Rc<MountSource>, sattr: StableAttr) -> SysResult<UnstableAttr> {
+        self.attr.unstable_attr(msrc, sattr)
+    }
+    fn get_link(&self) -> SysResult<DirentRef> {
+        bail_libc!(libc::ENOLINK)
+    }
+    fn read_link(&self) -> SysResult<String> {
+        bail_libc!(libc::ENOLINK)
+    }
+    fn truncate(&mut self, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EISDIR)
+    }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EISDIR)
+    }
+    fn create(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: FileFlags,
+        _: FilePermissions,
+        _: &dyn Context,
+    ) -> SysResult<File> {
+        bail_libc!(libc::EACCES)
+    }
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::EACCES)
+    }
+    fn link(&mut self, _: &inode::Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EACCES)
+    }
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EACCES)
+    }
+    fn rename(
+        &self,
+        _: RenameUnderParents<&mut inode::Inode>,
+        _: &str,
+        _: String,
+        _: bool,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::EPERM)
+    }
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.attr.set_timestamps(ts, ctx)
+    }
+    fn get_xattr(&self, _: &str) -> SysResult<Vec<u8>> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn set_xattr(&mut self, _: &str, _: &[u8], _: i32) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn remove_xattr(&mut self, _: &str) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn add_link(&self) {
+        self.attr.add_link()
+    }
+    fn drop_link(&self) {
+        self.attr.drop_link()
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct SelfDirFileOperations {
+    dirent: DirentRef,
+    dir_cursor: String,
+}
+
+impl FileOperations for SelfDirFileOperations {
+    fn dirent(&self) -> DirentRef {
+        self.dirent.clone()
+    }
+    fn read(
+        &self,
+        _: FileFlags,
+        _: &mut mem::IoSequence,
+        _: i64,
+        _: &dyn Context,
+    ) -> SysResult<usize> {
+        bail_libc!(libc::EISDIR)
+    }
+    fn write(
+        &self,
+        _: FileFlags,
+        _: &mut mem::IoSequence,
+        _: i64,
+        _: &dyn Context,
+    ) -> SysResult<usize> {
+        bail_libc!(libc::EISDIR)
+    }
+    fn configure_mmap(&mut self, _: &mut MmapOpts) -> SysResult<()> {
+        bail_libc!(libc::ENODEV)
+    }
+    fn flush(&self) -> SysResult<()> {
+        Ok(())
+    }
+    fn close(&self) -> SysResult<()> {
+        Ok(())
+    }
+    fn ioctl(&self, _: &libc::user_regs_struct, _: &dyn Context) -> SysResult<usize> {
+        bail_libc!(libc::ENOTTY)
+    }
+    fn seek(
+        &mut self,
+        inode: &inode::Inode,
+        whence: SeekWhence,
+        current_offset: i64,
+        offset: i64,
+    ) -> SysResult<i64> {
+        seek_with_dir_cursor(
+            inode,
+            whence,
+            current_offset,
+            offset,
+            Some(&mut self.dir_cursor),
+        )
+    }
+    fn readdir(
+        &mut self,
+        offset: i64,
+        serializer: &mut dyn DentrySerializer,
+        ctx: &dyn Context,
+    ) -> ReaddirResult<i64> {
+        let root = ctx.root_directory();
+        let dirent = self.dirent.clone();
+        let mut dir_ctx = DirIterCtx {
+            serializer,
+            attrs: HashMap::new(),
+            dir_cursor: Some(&mut self.dir_cursor),
+        };
+        dirent_readdir(&dirent, &SelfDirIter, root, offset, &mut dir_ctx, ctx)
+    }
+    fn readiness(&self, mask: u64, _: &dyn Context) -> u64 {
+        mask
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+struct SelfDirIter;
+
+impl DirIterator for SelfDirIter {
+    fn iterate_dir(
+        &self,
+        inode: &mut inode::Inode,
+        dir_ctx: &mut DirIterCtx,
+        offset: i32,
+        _: &dyn Context,
+    ) -> ReaddirResult<i32> {
+        let dir = inode.inode_operations::<SelfDir>();
+        match generic_readdir(dir_ctx, &dir.dentry_map) {
+            Ok(n) => Ok(offset + n),
+            Err(err) => Err(ReaddirError::new(offset + err.value(), err.code())),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::rc::Rc;
+
+use memmap::mmap_opts::MmapOpts;
+use utils::{bail_libc, SysError, SysResult};
+
+use crate::{
+    attr::{FilePermissions, StableAttr, TimeSpec, UnstableAttr},
+    dentry::DentrySerializer,
+    fsutils::{inode::InodeSimpleAttributes, seek_with_dir_cursor},
+    inode,
+    inode_operations::RenameUnderParents,
+    mount::MountSource,
+    seek::SeekWhence,
+    Context, DirentRef, File, FileFlags, FileOperations, InodeOperations, ReaddirError,
+    ReaddirResult,
+};
+
+// SeqFile is a read-only file whose contents were generated when it was looked up.
+#[derive(Debug)]
+pub struct SeqFile {
+    attr: InodeSimpleAttributes,
+    data: Rc<[u8]>,
+}
+
+impl SeqFile {
+    pub fn new(data: Vec<u8>, ctx: &dyn Context) -> Self {
+        let attr = InodeSimpleAttributes::new(
+            ctx.file_owner(),
+            FilePermissions::from_mode(linux::FileMode(0o444)),
+            linux::PROC_SUPER_MAGIC,
+            &|| ctx.now(),
+        );
+        Self {
+            attr,
+            data: data.into(),
+        }
+    }
+}
+
+impl InodeOperations for SeqFile {
+    fn lookup(&mut self, _: &str, _: &dyn Context) -> SysResult<DirentRef> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn get_file(&self, dirent: DirentRef, mut flags: FileFlags) -> SysResult<File> {
+        flags.pread = true;
+        Ok(File::new(
+            flags,
+            Box::new(SeqFileOperations {
+                dirent,
+                data: Rc::clone(&self.data),
+            }),
+        ))
+    }
+    fn unstable_attr(&self, msrc: &Rc<MountSource>, sattr: StableAttr) -> SysResult<UnstableAttr> {
+        self.attr.unstable_attr(msrc, sattr)
+    }
+    fn get_link(&self) -> SysResult<DirentRef> {
+        bail_libc!(libc::ENOLINK)
+    }
+    fn read_link(&self) -> SysResult<String> {
+        bail_libc!(libc::ENOLINK)
+    }
+    fn truncate(&mut self, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EACCES)
+    }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn create(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: FileFlags,
+        _: FilePermissions,
+        _: &dyn Context,
+    ) -> SysResult<File> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn link(&mut self, _: &inode::Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+    fn rename(
+        &self,
+        _: RenameUnderParents<&mut inode::Inode>,
+        _: &str,
+        _: String,
+        _: bool,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::EPERM)
+    }
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.attr.set_timestamps(ts, ctx)
+    }
+    fn get_xattr(&self, _: &str) -> SysResult<Vec<u8>> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn set_xattr(&mut self, _: &str, _: &[u8], _: i32) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn remove_xattr(&mut self, _: &str) -> SysResult<()> {
+        bail_libc!(libc::EOPNOTSUPP)
+    }
+    fn add_link(&self) {
+        self.attr.add_link()
+    }
+    fn drop_link(&self) {
+        self.attr.drop_link()
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct SeqFileOperations {
+    dirent: DirentRef,
+    data: Rc<[u8]>,
+}
+
+impl FileOperations for SeqFileOperations {
+    fn dirent(&self) -> DirentRef {
+        self.dirent.clone()
+    }
+    fn read(
+        &self,
+        _: FileFlags,
+        dst: &mut mem::IoSequence,
+        offset: i64,
+        _: &dyn Context,
+    ) -> SysResult<usize> {
+        if offset < 0 {
+            bail_libc!(libc::EINVAL);
+        }
+        match self.data.get(offset as usize..) {
+            Some(src) if !src.is_empty() => dst.copy_out(src),
+            _ => Ok(0),
+        }
+    }
+    fn write(
+        &self,
+        _: FileFlags,
+        _: &mut mem::IoSequence,
+        _: i64,
+        _: &dyn Context,
+    ) -> SysResult<usize> {
+        bail_libc!(libc::EINVAL)
+    }
+    fn configure_mmap(&mut self, _: &mut MmapOpts) -> SysResult<()> {
+        bail_libc!(libc::ENODEV)
+    }
+    fn flush(&self) -> SysResult<()> {
+        Ok(())
+    }
+    fn close(&self) -> SysResult<()> {
+        Ok(())
+    }
+    fn ioctl(&self, _: &libc::user_regs_struct, _: &dyn Context) -> SysResult<usize> {
+        bail_libc!(libc::ENOTTY)
+    }
+    fn seek(
+        &mut self,
+        inode: &inode::Inode,
+        whence: SeekWhence,
+        current_offset: i64,
+        offset: i64,
+    ) -> SysResult<i64> {
+        seek_with_dir_cursor(inode, whence, current_offset, offset, None)
+    }
+    fn readdir(
+        &mut self,
+        _: i64,
+        _: &mut dyn DentrySerializer,
+        _: &dyn Context,
+    ) -> ReaddirResult<i64> {
+        Err(ReaddirError::new(0, libc::ENOTDIR))
+    }
+    fn readiness(&self, mask: u64, _: &dyn Context) -> u64 {
+        mask
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     cell::RefCell,
+    os::unix::prelude::RawFd,
     rc::Rc,
     sync::{Arc, Mutex},
 };
@@ -7,10 +8,10 @@ use std::{
 use mem::{Addr, IoOpts, IoSequence, PAGE_SIZE};
 use memmap::mmap_opts::MmapOpts;
 use once_cell::sync::Lazy;
+use smoltcp::wire::{IpAddress, Ipv4Address, Ipv6Address};
 
 use dev::Device;
 use net::{address_and_family, Socket};
-use smoltcp::{socket::TcpSocket, wire::IpEndpoint};
 use utils::{bail_libc, err_libc, SysError, SysResult};
 
 use crate::{
@@ -106,15 +107,8 @@ impl FileOperations for SocketFile {
 }
 
 impl SocketFile {
-    pub fn new(
-        domain: i32,
-        stype: i32,
-        protocol: i32,
-        dirent: DirentRef,
-        ctx: &dyn Context,
-    ) -> SysResult<Self> {
-        let socket = Socket::new(domain, stype, protocol, ctx.as_net_context())?;
-        Ok(Self {
+    pub fn new(socket: Socket, domain: i32, stype: i32, protocol: i32, dirent: DirentRef) -> Self {
+        Self {
             socket,
             domain,
             stype,
@@ -122,17 +116,17 @@ impl SocketFile {
             dirent,
             sockopt_timestamp: Mutex::new(false),
             sockopt_inq: Mutex::new(false),
-        })
+        }
     }
 
     pub fn connect(
         &mut self,
         sock_addr: &[u8],
-        _blocking: bool,
+        blocking: bool,
         ctx: &dyn Context,
     ) -> SysResult<()> {
         self.socket
-            .connect(sock_addr, self.domain, ctx.as_net_context())
+            .connect(sock_addr, self.domain, !blocking, ctx.as_net_context())
     }
 
     pub fn bind(&mut self, sock_addr: &[u8], ctx: &dyn Context) -> SysResult<()> {
@@ -141,7 +135,7 @@ impl SocketFile {
     }
 
     pub fn set_sock_opt(
-        &self,
+        &mut self,
         level: i32,
         name: i32,
         optval: &[u8],
@@ -157,6 +151,14 @@ impl SocketFile {
                     u32::from_le_bytes([optval[0], optval[1], optval[2], optval[3]]) != 0;
                 Ok(())
             }
+            // Like linux, the options describing what the socket is can only be read.
+            libc::SOL_SOCKET
+                if name == libc::SO_TYPE
+                    || name == libc::SO_DOMAIN
+                    || name == libc::SO_PROTOCOL =>
+            {
+                err_libc!(libc::ENOPROTOOPT)
+            }
             libc::SOL_SOCKET => self
                 .socket
                 .set_sock_opt_socket(name, optval, ctx.as_net_context()),
@@ -186,7 +188,7 @@ impl SocketFile {
     }
 
     pub fn get_sock_opt(
-        &self,
+        &mut self,
         level: i32,
         name: i32,
         optval_len: u32,
@@ -209,7 +211,12 @@ impl SocketFile {
                 if optval_len < 4 {
                     bail_libc!(libc::EINVAL);
                 }
-                Ok(self.protocol.to_le_bytes().to_vec())
+                let protocol = if self.protocol == 0 {
+                    self.socket.default_protocol()
+                } else {
+                    self.protocol
+                };
+                Ok(protocol.to_le_bytes().to_vec())
             }
             libc::SOL_SOCKET if name == libc::SO_TIMESTAMP => {
                 if optval_len < 4 {
@@ -259,7 +266,14 @@ impl SocketFile {
         sock_addr_len: Addr,
         ctx: &dyn Context,
     ) -> SysResult<()> {
-        let endpoint = self.socket.local_endpoint(ctx.as_net_context());
+        let mut endpoint = self.socket.local_endpoint(ctx.as_net_context())?;
+        // Unbound and wildcard-bound sockets are reported with the any address of their domain.
+        if endpoint.addr.is_unspecified() {
+            endpoint.addr = match self.domain {
+                libc::AF_INET6 => IpAddress::Ipv6(Ipv6Address::UNSPECIFIED),
+                _ => IpAddress::Ipv4(Ipv4Address::UNSPECIFIED),
+            };
+        }
         self.socket
             .write_socket_addr(endpoint, (sock_addr, sock_addr_len), ctx.as_net_context())
     }
@@ -270,30 +284,29 @@ impl SocketFile {
         sock_addr_len: Addr,
         ctx: &dyn Context,
     ) -> SysResult<()> {
-        match self.socket.remote_endpoint(ctx.as_net_context()) {
-            Some(endpoint) => self.socket.write_socket_addr(
-                endpoint,
-                (sock_addr, sock_addr_len),
-                ctx.as_net_context(),
-            ),
-            None => Ok(()),
-        }
+        let endpoint = self.socket.remote_endpoint(ctx.as_net_context())?;
+        self.socket
+            .write_socket_addr(endpoint, (sock_addr, sock_addr_len), ctx.as_net_context())
     }
 
+    // send_msg sends src to the peer, passing the host file descriptors fds as SCM_RIGHTS if any.
     pub fn send_msg(
         &self,
         src: &mut IoSequence,
         to: Option<&[u8]>,
         flags: i32,
+        fds: &[RawFd],
         ctx: &dyn Context,
     ) -> SysResult<usize> {
         let addr_and_family = to.map(address_and_family).transpose()?;
-        self.socket.send_msg(
-            src,
-            flags & libc::MSG_DONTWAIT != 0,
-            addr_and_family,
-            ctx.as_net_context(),
-        )
+        let non_blocking = flags & libc::MSG_DONTWAIT != 0;
+        if fds.is_empty() {
+            self.socket
+                .send_msg(src, non_blocking, addr_and_family, ctx.as_net_context())
+        } else {
+            self.socket
+                .send_msg_with_fds(src, non_blocking, addr_and_family, fds)
+        }
     }
 
     pub fn recv_msg(
@@ -324,61 +337,77 @@ impl SocketFile {
         )
     }
 
-    // FIXME: Make proper use of `backlog`.
-    pub fn listen(&mut self, _backlog: i32, ctx: &dyn Context) -> SysResult<()> {
-        match &mut self.socket {
-            &mut Socket::Tcp {
-                ref mut local_endpoint,
-                ..
-            } => {
-                if !local_endpoint.is_specified() {
-                    *local_endpoint = IpEndpoint::from(ctx.gen_local_port());
+    // recv_msg_with_fds receives into dst like recv_msg, along with the files passed as SCM_RIGHTS
+    // that fit in control_len bytes of control messages. It also returns the MSG_TRUNC and
+    // MSG_CTRUNC flags of the message.
+    pub fn recv_msg_with_fds(
+        &self,
+        dst: &mut IoSequence,
+        flags: i32,
+        control_len: usize,
+        src_addr_and_len: Option<(Addr, Addr)>,
+        ctx: &dyn Context,
+    ) -> SysResult<(usize, Vec<File>, i32)> {
+        let (n, fds, mut msg_flags) = self.socket.recv_msg_with_fds(
+            dst,
+            flags & libc::MSG_PEEK != 0,
+            flags & libc::MSG_DONTWAIT != 0,
+            control_len,
+            src_addr_and_len,
+            ctx.as_net_context(),
+        )?;
+        let mut files = Vec::with_capacity(fds.len());
+        for fd in fds {
+            match Socket::from_host_fd(fd) {
+                Ok((socket, stype)) => {
+                    files.push(build_socket_file_with(socket, libc::AF_UNIX, stype, 0, ctx))
+                }
+                Err(_) => {
+                    // Only sockets can be passed by the guest, so drop anything else.
+                    unsafe { libc::close(fd) };
+                    msg_flags |= libc::MSG_CTRUNC;
                 }
-                Ok(())
             }
+        }
+        Ok((n, files, msg_flags))
+    }
+
+    // host_fd returns the host file descriptor backing the socket, which only Unix domain sockets
+    // have.
+    pub fn host_fd(&self) -> Option<RawFd> {
+        self.socket.host_fd()
+    }
+
+    pub fn shutdown(&self, how: i32, ctx: &dyn Context) -> SysResult<()> {
+        self.socket.shutdown(how, ctx.as_net_context())
+    }
+
+    // close_socket releases the socket, which should happen once no file descriptor refers to it.
+    pub fn close_socket(&mut self, ctx: &dyn Context) {
+        self.socket.close(ctx.as_net_context())
+    }
+
+    pub fn listen(&mut self, backlog: i32, ctx: &dyn Context) -> SysResult<()> {
+        match self.socket {
+            Socket::Tcp { .. } => self.socket.listen(backlog, ctx.as_net_context()),
             _ => err_libc!(libc::EOPNOTSUPP),
         }
     }
 
     pub fn accept(
-        &self,
+        &mut self,
         file_flags: FileFlags,
         fd_flags: FdFlags,
         addr_and_len: Option<(Addr, Addr)>,
         ctx: &dyn Context,
     ) -> SysResult<i32> {
-        let mut file = build_socket_file(self.domain, self.stype, self.protocol, ctx)?;
-        let socket_file = file.file_operations_mut::<SocketFile>().unwrap();
-        let handle = match socket_file.socket {
-            Socket::Tcp {
-                handle,
-                ref mut local_endpoint,
-            } => {
-                *local_endpoint = self.socket.local_endpoint(ctx.as_net_context());
-                handle
-            }
-            _ => bail_libc!(libc::EOPNOTSUPP),
-        };
-
-        socket_file.socket.listen(ctx.as_net_context())?;
-        loop {
-            ctx.poll_wait(false);
-            let mut iface = ctx.network_interface_mut();
-            let socket = iface.get_socket::<TcpSocket>(handle);
-            if socket.is_active() && socket.may_recv() {
-                if let Some(addr_and_len) = addr_and_len {
-                    self.socket.write_socket_addr(
-                        socket.remote_endpoint(),
-                        addr_and_len,
-                        ctx.as_net_context(),
-                    )?;
-                }
-                break;
-            } else if file_flags.non_blocking {
-                bail_libc!(libc::EWOULDBLOCK);
-            }
+        let (socket, remote_endpoint) = self
+            .socket
+            .accept(file_flags.non_blocking, ctx.as_net_context())?;
+        if let Some(addr_and_len) = addr_and_len {
+            socket.write_socket_addr(remote_endpoint, addr_and_len, ctx.as_net_context())?;
         }
-
+        let file = build_socket_file_with(socket, self.domain, self.stype, self.protocol, ctx);
         ctx.new_fd_from(0, &Rc::new(RefCell::new(file)), fd_flags)
     }
 }
@@ -389,6 +418,32 @@ pub fn build_socket_file(
     protocol: i32,
     ctx: &dyn Context,
 ) -> SysResult<File> {
+    let socket = Socket::new(domain, stype, protocol, ctx.as_net_context())?;
+    Ok(build_socket_file_with(socket, domain, stype, protocol, ctx))
+}
+
+// build_socket_file_pair creates two files wrapping a pair of connected sockets.
+pub fn build_socket_file_pair(
+    domain: i32,
+    stype: i32,
+    protocol: i32,
+    ctx: &dyn Context,
+) -> SysResult<(File, File)> {
+    let (a, b) = Socket::new_pair(domain, stype, protocol)?;
+    Ok((
+        build_socket_file_with(a, domain, stype, protocol, ctx),
+        build_socket_file_with(b, domain, stype, protocol, ctx),
+    ))
+}
+
+// build_socket_file_with wraps an already created socket, e.g. an accepted one, in a new file.
+fn build_socket_file_with(
+    socket: Socket,
+    domain: i32,
+    stype: i32,
+    protocol: i32,
+    ctx: &dyn Context,
+) -> File {
     let file_owner = ctx.file_owner();
     let dev = NET_DEVICE.lock().unwrap();
     let ino = dev.next_ino();
@@ -422,8 +477,8 @@ pub fn build_socket_file(
     );
 
     let dirent = Dirent::new(inode, format!("socket:[{}]", ino));
-    let socket_file = SocketFile::new(domain, stype, protocol, dirent, ctx)?;
-    let file = File::new(
+    let socket_file = SocketFile::new(socket, domain, stype, protocol, dirent);
+    File::new(
         FileFlags {
             read: true,
             write: true,
@@ -431,6 +486,5 @@ pub fn build_socket_file(
             ..FileFlags::default()
         },
         Box::new(socket_file),
-    );
-    Ok(file)
+    )
 }
@@ -1,16 +1,15 @@
-use std::{
-    rc::Rc,
-    sync::{Arc, Mutex},
-};
+use std::sync::{Arc, Mutex};
 
 use once_cell::sync::Lazy;
 
 use dev::Device;
 
 mod regular;
+mod symlink;
 
 pub use regular::*;
-use utils::{bail_libc, SysError, SysResult};
+pub use symlink::*;
+use utils::SysResult;
 
 use crate::{host, inode::Inode, inode_operations::RenameUnderParents, Context};
 
@@ -35,9 +34,6 @@ pub fn rename(
             )
         }
         RenameUnderParents::Different { old, new } => {
-            if Rc::as_ptr(old.mount_source()) != Rc::as_ptr(new.mount_source()) {
-                bail_libc!(libc::EXDEV);
-            }
             let old = old.inode_operations_mut::<host::Dir>();
             let new = new.inode_operations_mut::<host::Dir>();
             host::rename(
@@ -1,12 +1,15 @@
 use std::{
+    cell::RefCell,
     cmp::{max, min},
     rc::Rc,
     sync::RwLock,
 };
 
 use mem::{
-    block_seq::{copy_seq, zero_seq, BlockSeqView},
-    io, AccessType, Addr, AddrRange, IoSequence,
+    block::Block,
+    block_seq::{copy_seq, zero_seq, BlockSeq, BlockSeqView},
+    io::{self, Writer},
+    AccessType, Addr, AddrRange, IoSequence,
 };
 use memmap::{
     file::MemmapFile,
@@ -20,26 +23,39 @@ use usage::MemoryKind;
 use utils::{bail_libc, err_libc, SysError, SysResult};
 
 use crate::{
-    attr::{FilePermissions, StableAttr, UnstableAttr},
+    attr::{FilePermissions, StableAttr, TimeSpec, UnstableAttr},
     context::Context,
     dentry::DentrySerializer,
+    file::copy_range_through_buffer,
     fsutils::{seek_with_dir_cursor, FileRangeSet, FileRangeSetOperations, SetU64Operations},
     inode::Inode,
     inode_operations::RenameUnderParents,
     mount::{MountSource, MountSourceFlags},
     offset::{offset_page_end, read_end_offset, write_end_offset},
     seek::SeekWhence,
+    xattr::Xattrs,
     DirentRef, File, FileFlags, FileOperations, InodeOperations, ReaddirError, ReaddirResult,
 };
 
-// RegularFile implements InodeOperations for a regular tmpfs file.
+// ZERO_CHUNK_SIZE is the largest run of bytes copy_range buffers at once, when it copies a hole
+// or a range within the same file.
+const ZERO_CHUNK_SIZE: u64 = 1 << 20;
+
+// RegularFile implements InodeOperations for a regular tmpfs file. The hard links of a file are
+// RegularFiles sharing the same state.
 #[derive(Debug)]
 pub struct RegularFile {
+    state: Rc<RefCell<FileState>>,
+}
+
+#[derive(Debug)]
+struct FileState {
     attr: RwLock<UnstableAttr>,
     mem_usage: MemoryKind,
     data: FileRangeSet,
     mappings: MappingSet,
     seals: i32,
+    xattrs: Xattrs,
 }
 
 impl Mappable for RegularFile {
@@ -87,9 +103,10 @@ impl InodeOperations for RegularFile {
     }
 
     fn unstable_attr(&self, _: &Rc<MountSource>, _: StableAttr) -> SysResult<UnstableAttr> {
+        let state = self.state.borrow();
         Ok(UnstableAttr {
-            usage: self.data.span() as i64,
-            ..*self.attr.read().unwrap()
+            usage: state.data.span() as i64,
+            ..*state.attr.read().unwrap()
         })
     }
 
@@ -102,11 +119,12 @@ impl InodeOperations for RegularFile {
     }
 
     fn truncate(&mut self, size: i64, ctx: &dyn Context) -> SysResult<()> {
-        let mut attr = self.attr.write().unwrap();
+        let state = &mut *self.state.borrow_mut();
+        let mut attr = state.attr.write().unwrap();
         let old_size = attr.size;
 
-        if (size > old_size && self.seals & linux::F_SEAL_GROW != 0)
-            || (old_size > size && self.seals & linux::F_SEAL_SHRINK != 0)
+        if (size > old_size && state.seals & linux::F_SEAL_GROW != 0)
+            || (old_size > size && state.seals & linux::F_SEAL_SHRINK != 0)
         {
             bail_libc!(libc::EPERM);
         }
@@ -126,7 +144,7 @@ impl InodeOperations for RegularFile {
         let new_pgend = offset_page_end(size);
 
         if new_pgend <= old_pgend {
-            self.mappings.invalidate(
+            state.mappings.invalidate(
                 MappableRange {
                     start: new_pgend,
                     end: old_pgend,
@@ -138,7 +156,100 @@ impl InodeOperations for RegularFile {
             );
         }
 
-        self.data.truncate(size as u64, ctx);
+        state.data.truncate(size as u64, ctx);
+        Ok(())
+    }
+
+    // allocate implements fallocate(2). By default, the pages of the range are allocated and the
+    // file grows to cover it unless FALLOC_FL_KEEP_SIZE is given. FALLOC_FL_PUNCH_HOLE releases
+    // the pages instead, and FALLOC_FL_ZERO_RANGE zeroes the range in addition to allocating it.
+    fn allocate(
+        &mut self,
+        mode: i32,
+        offset: i64,
+        length: i64,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        if mode
+            & !(libc::FALLOC_FL_KEEP_SIZE | libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_ZERO_RANGE)
+            != 0
+        {
+            bail_libc!(libc::EOPNOTSUPP);
+        }
+        let end = offset + length;
+        let state = &mut *self.state.borrow_mut();
+        let mut attr = state.attr.write().unwrap();
+        if mode & (libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_ZERO_RANGE) != 0
+            && state.seals & linux::F_SEAL_WRITE != 0
+        {
+            bail_libc!(libc::EPERM);
+        }
+        let grow = mode & libc::FALLOC_FL_KEEP_SIZE == 0 && end > attr.size;
+        if grow && state.seals & linux::F_SEAL_GROW != 0 {
+            bail_libc!(libc::EPERM);
+        }
+
+        let pgstart = Addr(offset as u64).round_down().0;
+        let pgend = Addr(end as u64).round_up().unwrap().0;
+        if mode & libc::FALLOC_FL_PUNCH_HOLE != 0 {
+            // Whole pages are released, and the parts of the pages at the edges are zeroed.
+            let inner = MappableRange {
+                start: Addr(offset as u64).round_up().unwrap().0,
+                end: Addr(end as u64).round_down().0,
+            };
+            if inner.start < inner.end {
+                state.data.decommit(inner, ctx)?;
+                state.data.zero(
+                    MappableRange {
+                        start: offset as u64,
+                        end: inner.start,
+                    },
+                    ctx,
+                )?;
+                state.data.zero(
+                    MappableRange {
+                        start: inner.end,
+                        end: end as u64,
+                    },
+                    ctx,
+                )?;
+            } else {
+                state.data.zero(
+                    MappableRange {
+                        start: offset as u64,
+                        end: end as u64,
+                    },
+                    ctx,
+                )?;
+            }
+        } else {
+            state.data.fill(
+                MappableRange {
+                    start: pgstart,
+                    end: pgend,
+                },
+                state.mem_usage,
+                ctx,
+            )?;
+            if mode & libc::FALLOC_FL_ZERO_RANGE != 0 {
+                state.data.zero(
+                    MappableRange {
+                        start: offset as u64,
+                        end: end as u64,
+                    },
+                    ctx,
+                )?;
+            }
+        }
+
+        let now = ctx.now();
+        if grow {
+            attr.size = end;
+        }
+        attr.status_change_time = now;
+        if mode & (libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_ZERO_RANGE) != 0 {
+            attr.modification_time = now;
+        }
         Ok(())
     }
 
@@ -154,6 +265,25 @@ impl InodeOperations for RegularFile {
         err_libc!(libc::ENOTDIR)
     }
 
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        err_libc!(libc::ENOTDIR)
+    }
+
+    fn link(&mut self, _: &Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        err_libc!(libc::ENOTDIR)
+    }
+
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        err_libc!(libc::ENOTDIR)
+    }
+
     fn rename(
         &self,
         parents: RenameUnderParents<&mut Inode>,
@@ -165,12 +295,33 @@ impl InodeOperations for RegularFile {
         super::rename(parents, old_name, new_name, is_replacement, ctx)
     }
 
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.state
+            .borrow()
+            .attr
+            .write()
+            .unwrap()
+            .set_timestamps(ts, ctx.now());
+        Ok(())
+    }
+    fn get_xattr(&self, name: &str) -> SysResult<Vec<u8>> {
+        self.state.borrow().xattrs.get(name)
+    }
+    fn set_xattr(&mut self, name: &str, value: &[u8], flags: i32) -> SysResult<()> {
+        self.state.borrow_mut().xattrs.set(name, value, flags)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        Ok(self.state.borrow().xattrs.list())
+    }
+    fn remove_xattr(&mut self, name: &str) -> SysResult<()> {
+        self.state.borrow_mut().xattrs.remove(name)
+    }
     fn add_link(&self) {
-        self.attr.write().unwrap().links += 1;
+        self.state.borrow().attr.write().unwrap().links += 1;
     }
 
     fn drop_link(&self) {
-        self.attr.write().unwrap().links -= 1;
+        self.state.borrow().attr.write().unwrap().links -= 1;
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -185,12 +336,23 @@ impl InodeOperations for RegularFile {
 impl RegularFile {
     pub fn new_file_in_memory(usage: MemoryKind, attr: UnstableAttr) -> Self {
         let ops = FileRangeSetOperations;
-        Self {
+        let state = FileState {
             attr: RwLock::new(attr),
             mem_usage: usage,
             data: FileRangeSet::new(Box::new(ops)),
             seals: libc::F_SEAL_SEAL,
             mappings: MappingSet::new(Box::new(MappingSetOperations)),
+            xattrs: Xattrs::default(),
+        };
+        Self {
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
+    // new_link returns a RegularFile for a new hard link to this file.
+    pub fn new_link(&self) -> Self {
+        Self {
+            state: Rc::clone(&self.state),
         }
     }
 
@@ -198,14 +360,15 @@ impl RegularFile {
         if src.num_bytes() == 0 {
             return Ok(0);
         }
+        let state = &mut *self.state.borrow_mut();
         let now = ctx.now();
         {
-            let mut attr = self.attr.write().unwrap();
+            let mut attr = state.attr.write().unwrap();
             attr.modification_time = now;
             attr.status_change_time = now;
         }
         src.copy_in_to(&mut FileReadWriter {
-            file: self,
+            file: state,
             offset,
             ctx,
         })
@@ -221,26 +384,186 @@ impl RegularFile {
         if dst.num_bytes() == 0 {
             return Ok(0);
         }
+        let state = &mut *self.state.borrow_mut();
         {
-            let size = self.attr.read().unwrap().size;
+            let size = state.attr.read().unwrap().size;
             if offset >= size {
                 return Err(SysError::eof());
             }
         }
         let ret = dst.copy_out_from(&mut FileReadWriter {
-            file: self,
+            file: state,
             offset,
             ctx,
         });
         if !mflags.no_atime {
-            self.attr.write().unwrap().access_time = ctx.now();
+            state.attr.write().unwrap().access_time = ctx.now();
         }
         ret
     }
 }
 
+// release_if_unreachable frees the pages of the file of dirent, whose last reference the caller
+// holds, if the file is no longer linked anywhere either.
+pub fn release_if_unreachable(dirent: &DirentRef, ctx: &dyn Context) -> SysResult<()> {
+    if Rc::strong_count(dirent) != 1 {
+        return Ok(());
+    }
+    let dirent = dirent.borrow();
+    let file = match dirent.inode().try_inode_operations::<RegularFile>() {
+        Some(f) => f,
+        None => return Ok(()),
+    };
+    if Rc::strong_count(&file.state) != 1 || file.state.borrow().attr.read().unwrap().links != 0 {
+        return Ok(());
+    }
+    let mut state = file.state.borrow_mut();
+    if let Some(seg) = state.data.last_segment() {
+        state.data.decommit(
+            MappableRange {
+                start: 0,
+                end: seg.end(),
+            },
+            ctx,
+        )?;
+        state.data.truncate(0, ctx);
+    }
+    Ok(())
+}
+
+// copy_range copies up to len bytes at src_offset of the file of src to dst_offset of the file of
+// dst, which are both tmpfs files. The pages of the source are mapped and written straight into
+// the destination without bouncing through a buffer, and holes are copied as zeros.
+pub fn copy_range(
+    src: &DirentRef,
+    src_offset: i64,
+    dst: &DirentRef,
+    dst_offset: i64,
+    len: usize,
+    ctx: &dyn Context,
+) -> SysResult<usize> {
+    let (src_state, mflags) = {
+        let src = src.borrow();
+        let inode = src.inode();
+        let iops = inode.inode_operations::<RegularFile>();
+        (Rc::clone(&iops.state), inode.mount_source().flags())
+    };
+    let dst_state = {
+        let dst = dst.borrow();
+        Rc::clone(&dst.inode().inode_operations::<RegularFile>().state)
+    };
+    if Rc::ptr_eq(&src_state, &dst_state) {
+        return copy_range_within(&src_state, src_offset, dst_offset, len, ctx);
+    }
+
+    let src_state = src_state.borrow();
+    let size = src_state.attr.read().unwrap().size;
+    if src_offset >= size || len == 0 {
+        return Ok(0);
+    }
+    let end = read_end_offset(src_offset, min(len, i64::MAX as usize) as i64, size);
+    let now = ctx.now();
+    let dst_state = &mut *dst_state.borrow_mut();
+    {
+        let mut attr = dst_state.attr.write().unwrap();
+        attr.modification_time = now;
+        attr.status_change_time = now;
+    }
+    let mut writer = FileReadWriter {
+        file: dst_state,
+        offset: dst_offset,
+        ctx,
+    };
+
+    let data = &src_state.data;
+    let mut offset = src_offset;
+    while offset < end {
+        let mr = MappableRange {
+            start: offset as u64,
+            end: end as u64,
+        };
+        let (want, n) = match data.find_segment(offset as u64) {
+            Some(seg) => {
+                let fr = data.file_range_of(&seg, seg.range().intersect(&mr));
+                let ims = {
+                    let mut mf = ctx.memory_file_provider().memory_file_write_lock();
+                    mf.map_internal(fr, AccessType::read())?
+                };
+                (ims.num_bytes(), writer.write_from_blocks(ims.as_view())?)
+            }
+            None => {
+                let gap = data.find_gap(offset as u64).unwrap();
+                let want = min(gap.range().intersect(&mr).len(), ZERO_CHUNK_SIZE);
+                let zeros = vec![0; want as usize];
+                let srcs = BlockSeq::from_block(Block::from_slice(&zeros, false));
+                (want, writer.write_from_blocks(srcs.as_view())?)
+            }
+        };
+        offset += n as i64;
+        if (n as u64) < want {
+            break;
+        }
+    }
+    if !mflags.no_atime {
+        src_state.attr.write().unwrap().access_time = now;
+    }
+    Ok((offset - src_offset) as usize)
+}
+
+// copy_range_within implements copy_range between two ranges of the same file, whose pages cannot
+// be borrowed for reading and writing at once and are thus copied through a buffer, a chunk at a
+// time. Like memmove, the chunks are copied back to front when the destination is past the
+// source, so that overlapping ranges aren't read after being written.
+fn copy_range_within(
+    state: &RefCell<FileState>,
+    src_offset: i64,
+    dst_offset: i64,
+    len: usize,
+    ctx: &dyn Context,
+) -> SysResult<usize> {
+    let state = &mut *state.borrow_mut();
+    let size = state.attr.read().unwrap().size;
+    if src_offset >= size || len == 0 {
+        return Ok(0);
+    }
+    let end = read_end_offset(src_offset, min(len, i64::MAX as usize) as i64, size);
+    let total = (end - src_offset) as u64;
+    let chunk = min(total, ZERO_CHUNK_SIZE);
+    let chunks = (total + chunk - 1) / chunk;
+    let backward = dst_offset > src_offset;
+    let now = ctx.now();
+    {
+        let mut attr = state.attr.write().unwrap();
+        attr.modification_time = now;
+        attr.status_change_time = now;
+    }
+    let mut buf = vec![0; chunk as usize];
+    let mut done = 0;
+    for i in 0..chunks {
+        let start = (if backward { chunks - 1 - i } else { i }) * chunk;
+        let want = min(total - start, chunk) as usize;
+        let n =
+            IoSequence::bytes_sequence(&mut buf[..want]).copy_out_from(&mut FileReadWriter {
+                file: state,
+                offset: src_offset + start as i64,
+                ctx,
+            })?;
+        let written =
+            IoSequence::bytes_sequence(&mut buf[..n]).copy_in_to(&mut FileReadWriter {
+                file: state,
+                offset: dst_offset + start as i64,
+                ctx,
+            })?;
+        done += written;
+        if written < want {
+            break;
+        }
+    }
+    Ok(done)
+}
+
 struct FileReadWriter<'a> {
-    file: &'a mut RegularFile,
+    file: &'a mut FileState,
     offset: i64,
     ctx: &'a dyn Context,
 }
@@ -488,6 +811,22 @@ impl FileOperations for RegularFileOperations {
         mask
     }
 
+    // Pages are copied directly when src is a tmpfs file as well.
+    fn copy_range_from(
+        &self,
+        flags: FileFlags,
+        src: &File,
+        src_offset: i64,
+        offset: i64,
+        len: usize,
+        ctx: &dyn Context,
+    ) -> SysResult<usize> {
+        if src.file_operations::<RegularFileOperations>().is_none() {
+            return copy_range_through_buffer(self, flags, src, src_offset, offset, len, ctx);
+        }
+        copy_range(&src.dirent(), src_offset, &self.dirent, offset, len, ctx)
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -502,13 +841,14 @@ mod tests {
     use std::rc::Rc;
 
     use mem::PAGE_SIZE;
+    use time::Time;
 
     use super::*;
     use crate::{
         attr::{InodeType, StableAttr},
         mount::{MountSource, MountSourceFlags},
         tmpfs::TMPFS_DEVICE,
-        Dirent, TestContext,
+        Dirent, SettableFileFlags, TestContext,
     };
 
     fn new_file_inode(ctx: &dyn Context) -> Inode {
@@ -574,4 +914,264 @@ mod tests {
         };
         assert_eq!(want, rbuf);
     }
+
+    #[test]
+    fn new_link() {
+        let ctx = TestContext::init();
+        let f = new_file(&ctx);
+        let mut buf = b"linked".to_vec();
+        let n = f.pwritev(&mut IoSequence::bytes_sequence(&mut buf), 0, &ctx);
+        assert_eq!(n, Ok(buf.len()));
+
+        let inode = {
+            let dirent = f.dirent();
+            let dirent = dirent.borrow();
+            let target = dirent.inode();
+            target.add_link();
+            let iops = target.inode_operations::<RegularFile>().new_link();
+            Inode::new(
+                Box::new(iops),
+                target.mount_source().clone(),
+                target.stable_attr(),
+            )
+        };
+        inode.add_link();
+        assert_eq!(inode.unstable_attr().unwrap().links, 2);
+        assert_eq!(f.unstable_attr().unwrap().links, 2);
+
+        let dirent = Dirent::new(inode, "link".to_string());
+        let linked = dirent
+            .borrow()
+            .inode()
+            .get_file(
+                dirent.clone(),
+                FileFlags {
+                    read: true,
+                    ..FileFlags::default()
+                },
+            )
+            .unwrap();
+        let mut rbuf = vec![0; buf.len()];
+        let n = linked.preadv(&mut IoSequence::bytes_sequence(&mut rbuf), 0, &ctx);
+        assert_eq!(n, Ok(rbuf.len()));
+        assert_eq!(rbuf, buf);
+    }
+
+    #[test]
+    fn copy_range() {
+        let ctx = TestContext::init();
+        let src = new_file(&ctx);
+        let dst = new_file(&ctx);
+        let page = PAGE_SIZE as usize;
+
+        // The source is a pattern, a hole of a page, and the pattern again.
+        let mut pattern: Vec<u8> = (0..page + 10).map(|i| (i % 251) as u8).collect();
+        let n = src.pwritev(&mut IoSequence::bytes_sequence(&mut pattern), 0, &ctx);
+        assert_eq!(n, Ok(pattern.len()));
+        let tail_offset = (2 * page + 10) as i64;
+        let n = src.pwritev(
+            &mut IoSequence::bytes_sequence(&mut pattern),
+            tail_offset,
+            &ctx,
+        );
+        assert_eq!(n, Ok(pattern.len()));
+        let size = tail_offset as usize + pattern.len();
+
+        let n = dst.copy_range_from(&src, Some(5), None, usize::MAX, &ctx);
+        assert_eq!(n, Ok(size - 5));
+        assert_eq!(dst.offset(), (size - 5) as i64);
+        assert_eq!(src.offset(), 0);
+
+        let mut rbuf = vec![1; size - 5];
+        let n = dst.preadv(&mut IoSequence::bytes_sequence(&mut rbuf), 0, &ctx);
+        assert_eq!(n, Ok(rbuf.len()));
+        assert_eq!(rbuf[..page + 5], pattern[5..]);
+        assert!(rbuf[page + 5..2 * page + 5].iter().all(|b| *b == 0));
+        assert_eq!(rbuf[2 * page + 5..], pattern[..]);
+
+        // Copying at EOF copies nothing.
+        assert_eq!(
+            dst.copy_range_from(&src, Some(size as i64), None, 1, &ctx),
+            Ok(0)
+        );
+
+        // A copy within the same file goes through a buffer.
+        let n = src.copy_range_from(&src, None, Some(tail_offset), 10, &ctx);
+        assert_eq!(n, Ok(10));
+        assert_eq!(src.offset(), 10);
+        let mut rbuf = vec![0; 10];
+        let n = src.preadv(
+            &mut IoSequence::bytes_sequence(&mut rbuf),
+            tail_offset,
+            &ctx,
+        );
+        assert_eq!(n, Ok(10));
+        assert_eq!(rbuf[..], pattern[..10]);
+    }
+
+    #[test]
+    fn copy_range_within_overlapping() {
+        let ctx = TestContext::init();
+        let f = new_file(&ctx);
+        let len = ZERO_CHUNK_SIZE as usize + 100;
+        let mut data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let n = f.pwritev(&mut IoSequence::bytes_sequence(&mut data), 0, &ctx);
+        assert_eq!(n, Ok(len));
+
+        // The ranges overlap over more than one chunk, in both directions.
+        let shift = PAGE_SIZE as usize;
+        let n = f.copy_range_from(&f, Some(0), Some(shift as i64), len, &ctx);
+        assert_eq!(n, Ok(len));
+        let mut rbuf = vec![0; len];
+        let n = f.preadv(
+            &mut IoSequence::bytes_sequence(&mut rbuf),
+            shift as i64,
+            &ctx,
+        );
+        assert_eq!(n, Ok(len));
+        assert!(rbuf == data);
+
+        let n = f.copy_range_from(&f, Some(shift as i64), Some(0), len, &ctx);
+        assert_eq!(n, Ok(len));
+        let n = f.preadv(&mut IoSequence::bytes_sequence(&mut rbuf), 0, &ctx);
+        assert_eq!(n, Ok(len));
+        assert!(rbuf == data);
+    }
+
+    #[test]
+    fn set_timestamps() {
+        let ctx = TestContext::init();
+        let mut inode = new_file_inode(&ctx);
+        let before = inode.unstable_attr().unwrap();
+
+        let mtime = Time::from_unix(1_000_000_000, 42);
+        let ts = TimeSpec {
+            access_time: None,
+            modification_time: Some(mtime),
+        };
+        inode.set_timestamps(ts, &ctx).unwrap();
+        let after = inode.unstable_attr().unwrap();
+        assert_eq!(after.modification_time, mtime);
+        assert_eq!(after.access_time, before.access_time);
+        assert!(after.status_change_time >= before.status_change_time);
+    }
+
+    #[test]
+    fn write_seek_read() {
+        let ctx = TestContext::init();
+        let mut f = new_file(&ctx);
+        let page = PAGE_SIZE as usize;
+
+        let mut pattern: Vec<u8> = (0..page + 100).map(|i| (i % 251) as u8).collect();
+        let n = f.writev(&mut IoSequence::bytes_sequence(&mut pattern), &ctx);
+        assert_eq!(n, Ok(pattern.len()));
+        assert_eq!(f.offset(), pattern.len() as i64);
+
+        assert_eq!(f.seek(SeekWhence::Set, 10), Ok(10));
+        let mut rbuf = vec![0; page];
+        let n = f.readv(&mut IoSequence::bytes_sequence(&mut rbuf), &ctx);
+        assert_eq!(n, Ok(rbuf.len()));
+        assert_eq!(rbuf[..], pattern[10..page + 10]);
+
+        // Writing beyond EOF leaves a hole that reads back as zeros.
+        let hole_end = 3 * page as i64;
+        let mut tail = vec![0xff; 8];
+        let n = f.pwritev(&mut IoSequence::bytes_sequence(&mut tail), hole_end, &ctx);
+        assert_eq!(n, Ok(tail.len()));
+        assert_eq!(f.seek(SeekWhence::End, 0), Ok(hole_end + tail.len() as i64));
+
+        let hole_start = pattern.len() as i64;
+        let mut rbuf = vec![1; (hole_end - hole_start) as usize];
+        let n = f.preadv(&mut IoSequence::bytes_sequence(&mut rbuf), hole_start, &ctx);
+        assert_eq!(n, Ok(rbuf.len()));
+        assert!(rbuf.iter().all(|b| *b == 0));
+
+        // Reads crossing EOF are short, and reads at EOF return nothing.
+        let mut rbuf = vec![0; 16];
+        let n = f.preadv(&mut IoSequence::bytes_sequence(&mut rbuf), hole_end, &ctx);
+        assert_eq!(n, Ok(tail.len()));
+        assert_eq!(rbuf[..tail.len()], tail[..]);
+        let n = f.readv(&mut IoSequence::bytes_sequence(&mut rbuf), &ctx);
+        assert_eq!(n, Err(SysError::eof()));
+    }
+
+    #[test]
+    fn append() {
+        let ctx = TestContext::init();
+        let mut f = new_file(&ctx);
+        f.set_flags(SettableFileFlags {
+            append: true,
+            ..SettableFileFlags::default()
+        });
+
+        let mut abuf = b"hello".to_vec();
+        let n = f.writev(&mut IoSequence::bytes_sequence(&mut abuf), &ctx);
+        assert_eq!(n, Ok(abuf.len()));
+
+        // An appending write goes to the end even if the offset was moved back.
+        assert_eq!(f.seek(SeekWhence::Set, 0), Ok(0));
+        let mut bbuf = b" world".to_vec();
+        let n = f.writev(&mut IoSequence::bytes_sequence(&mut bbuf), &ctx);
+        assert_eq!(n, Ok(bbuf.len()));
+        assert_eq!(f.offset(), 11);
+
+        // pwritev ignores the given offset and leaves the file offset alone.
+        let mut cbuf = b"!".to_vec();
+        let n = f.pwritev(&mut IoSequence::bytes_sequence(&mut cbuf), 0, &ctx);
+        assert_eq!(n, Ok(cbuf.len()));
+        assert_eq!(f.offset(), 11);
+
+        let mut rbuf = vec![0; 16];
+        let n = f.preadv(&mut IoSequence::bytes_sequence(&mut rbuf), 0, &ctx);
+        assert_eq!(n, Ok(12));
+        assert_eq!(&rbuf[..12], b"hello world!");
+    }
+
+    #[test]
+    fn allocate() {
+        let ctx = TestContext::init();
+        let f = new_file(&ctx);
+        let allocate = |mode, offset, length| {
+            let dirent = f.dirent();
+            let mut dirent = dirent.borrow_mut();
+            dirent.inode_mut().allocate(mode, offset, length, &ctx)
+        };
+
+        assert_eq!(allocate(0, 0, 10000), Ok(()));
+        assert_eq!(f.unstable_attr().unwrap().size, 10000);
+        assert_eq!(allocate(libc::FALLOC_FL_KEEP_SIZE, 8192, 10000), Ok(()));
+        assert_eq!(f.unstable_attr().unwrap().size, 10000);
+
+        let mut pattern = vec![b'a'; 3 * PAGE_SIZE as usize];
+        let n = f.pwritev(&mut IoSequence::bytes_sequence(&mut pattern), 0, &ctx);
+        assert_eq!(n, Ok(pattern.len()));
+        let read_at = |offset| {
+            let mut rbuf = vec![0xff; 1];
+            f.preadv(&mut IoSequence::bytes_sequence(&mut rbuf), offset, &ctx)
+                .unwrap();
+            rbuf[0]
+        };
+
+        // Punching a hole across pages zeroes the partial pages and releases the whole one.
+        let page = PAGE_SIZE as i64;
+        let punch = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+        assert_eq!(allocate(punch, page - 10, page + 20), Ok(()));
+        assert_eq!(read_at(page - 11), b'a');
+        assert_eq!(read_at(page - 10), 0);
+        assert_eq!(read_at(page + 100), 0);
+        assert_eq!(read_at(2 * page + 9), 0);
+        assert_eq!(read_at(2 * page + 10), b'a');
+        assert_eq!(f.unstable_attr().unwrap().size, 3 * page);
+
+        let zero = libc::FALLOC_FL_ZERO_RANGE;
+        assert_eq!(allocate(zero, 5, 10), Ok(()));
+        assert_eq!(read_at(4), b'a');
+        assert_eq!(read_at(5), 0);
+        assert_eq!(read_at(15), b'a');
+
+        assert_eq!(
+            allocate(libc::FALLOC_FL_COLLAPSE_RANGE, 0, page),
+            Err(SysError::new(libc::EOPNOTSUPP))
+        );
+    }
 }
@@ -0,0 +1,139 @@
+use std::rc::Rc;
+
+use utils::{bail_libc, SysError, SysResult};
+
+use crate::{
+    attr::{FilePermissions, StableAttr, TimeSpec, UnstableAttr},
+    context::Context,
+    fsutils::inode::InodeSimpleAttributes,
+    host::SymlinkFileOperations,
+    inode::Inode,
+    inode_operations::RenameUnderParents,
+    mount::MountSource,
+    xattr::Xattrs,
+    DirentRef, File, FileFlags, InodeOperations,
+};
+
+// Symlink implements InodeOperations for a symbolic link created by the application.
+#[derive(Debug)]
+pub struct Symlink {
+    attr: InodeSimpleAttributes,
+    target: String,
+    xattrs: Xattrs,
+}
+
+impl InodeOperations for Symlink {
+    fn lookup(&mut self, _: &str, _: &dyn Context) -> SysResult<DirentRef> {
+        bail_libc!(libc::ENOTDIR)
+    }
+
+    fn get_file(&self, dirent: DirentRef, flags: FileFlags) -> SysResult<File> {
+        Ok(File::new(flags, Box::new(SymlinkFileOperations { dirent })))
+    }
+
+    fn unstable_attr(&self, msrc: &Rc<MountSource>, sattr: StableAttr) -> SysResult<UnstableAttr> {
+        let mut uattr = self.attr.unstable_attr(msrc, sattr)?;
+        uattr.size = self.target.len() as i64;
+        uattr.usage = uattr.size;
+        Ok(uattr)
+    }
+
+    fn get_link(&self) -> SysResult<DirentRef> {
+        Err(SysError::resolve_via_readlink())
+    }
+
+    fn read_link(&self) -> SysResult<String> {
+        Ok(self.target.clone())
+    }
+
+    fn truncate(&mut self, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::EINVAL)
+    }
+
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENODEV)
+    }
+
+    fn create(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: FileFlags,
+        _: FilePermissions,
+        _: &dyn Context,
+    ) -> SysResult<File> {
+        bail_libc!(libc::ENOTDIR)
+    }
+
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+
+    fn link(&mut self, _: &Inode, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+
+    fn unlink(&mut self, _: &str, _: &dyn Context) -> SysResult<()> {
+        bail_libc!(libc::ENOTDIR)
+    }
+
+    fn rename(
+        &self,
+        parents: RenameUnderParents<&mut Inode>,
+        old_name: &str,
+        new_name: String,
+        is_replacement: bool,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
+        super::rename(parents, old_name, new_name, is_replacement, ctx)
+    }
+
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.attr.set_timestamps(ts, ctx)
+    }
+    fn get_xattr(&self, name: &str) -> SysResult<Vec<u8>> {
+        self.xattrs.get(name)
+    }
+    fn set_xattr(&mut self, name: &str, value: &[u8], flags: i32) -> SysResult<()> {
+        self.xattrs.set(name, value, flags)
+    }
+    fn list_xattr(&self) -> SysResult<Vec<String>> {
+        Ok(self.xattrs.list())
+    }
+    fn remove_xattr(&mut self, name: &str) -> SysResult<()> {
+        self.xattrs.remove(name)
+    }
+    fn add_link(&self) {
+        self.attr.add_link();
+    }
+
+    fn drop_link(&self) {
+        self.attr.drop_link();
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Symlink {
+    pub fn new(attr: InodeSimpleAttributes, target: String) -> Self {
+        Self {
+            attr,
+            target,
+            xattrs: Xattrs::default(),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::HashMap, ffi::CString, path::Path};
+
+use linux::Capability;
+use utils::{bail_libc, err_libc, SysError, SysResult};
+
+use crate::{attr::PermMask, inode::Inode, Context};
+
+const USER_PREFIX: &str = "user.";
+const TRUSTED_PREFIX: &str = "trusted.";
+const SECURITY_PREFIX: &str = "security.";
+
+// Xattrs holds the extended attributes of a file living in memory.
+#[derive(Debug, Default)]
+pub struct Xattrs {
+    attrs: HashMap<String, Vec<u8>>,
+}
+
+impl Xattrs {
+    pub fn get(&self, name: &str) -> SysResult<Vec<u8>> {
+        self.attrs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SysError::new(libc::ENODATA))
+    }
+
+    // set stores value under name. XATTR_CREATE fails if name exists, and XATTR_REPLACE fails if
+    // it does not.
+    pub fn set(&mut self, name: &str, value: &[u8], flags: i32) -> SysResult<()> {
+        let exists = self.attrs.contains_key(name);
+        if flags & libc::XATTR_CREATE != 0 && exists {
+            bail_libc!(libc::EEXIST);
+        }
+        if flags & libc::XATTR_REPLACE != 0 && !exists {
+            bail_libc!(libc::ENODATA);
+        }
+        self.attrs.insert(name.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        self.attrs.keys().cloned().collect()
+    }
+
+    pub fn remove(&mut self, name: &str) -> SysResult<()> {
+        match self.attrs.remove(name) {
+            Some(_) => Ok(()),
+            None => err_libc!(libc::ENODATA),
+        }
+    }
+}
+
+// check_permission checks that the caller may read, or write if write is set, the extended
+// attribute name of inode. user.* attributes follow the permissions of the file and are only
+// allowed on regular files and directories, while trusted.* ones need CAP_SYS_ADMIN. Without a
+// security module, only CAP_SYS_ADMIN may write security.* ones.
+pub fn check_permission(
+    inode: &Inode,
+    name: &str,
+    write: bool,
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    if name.is_empty() || name.len() > linux::XATTR_NAME_MAX {
+        bail_libc!(libc::ERANGE);
+    }
+    let sattr = inode.stable_attr();
+    if name.starts_with(USER_PREFIX) {
+        if !sattr.is_regular() && !sattr.is_directory() {
+            return err_libc!(if write { libc::EPERM } else { libc::ENODATA });
+        }
+        let mask = PermMask {
+            read: !write,
+            write,
+            execute: false,
+        };
+        inode.check_permission(mask, ctx)
+    } else if name.starts_with(TRUSTED_PREFIX) {
+        if !has_cap_sys_admin(ctx) {
+            return err_libc!(if write { libc::EPERM } else { libc::ENODATA });
+        }
+        Ok(())
+    } else if name.starts_with(SECURITY_PREFIX) {
+        if write && !has_cap_sys_admin(ctx) {
+            bail_libc!(libc::EPERM);
+        }
+        Ok(())
+    } else {
+        err_libc!(libc::EOPNOTSUPP)
+    }
+}
+
+// is_listable tells whether name is listed by listxattr(2) to the caller, which does not see
+// trusted.* attributes without CAP_SYS_ADMIN.
+pub fn is_listable(name: &str, ctx: &dyn Context) -> bool {
+    !name.starts_with(TRUSTED_PREFIX) || has_cap_sys_admin(ctx)
+}
+
+fn has_cap_sys_admin(ctx: &dyn Context) -> bool {
+    ctx.credentials()
+        .has_capability(&Capability::cap_sys_admin())
+}
+
+fn to_cstring<T: AsRef<[u8]>>(s: T) -> SysResult<CString> {
+    CString::new(s.as_ref()).map_err(|_| SysError::new(libc::EINVAL))
+}
+
+// get_host reads the extended attribute name of the host file at path, without following a
+// symbolic link.
+pub fn get_host(path: &Path, name: &str) -> SysResult<Vec<u8>> {
+    let path = to_cstring(path.to_str().unwrap())?;
+    let name = to_cstring(name)?;
+    let mut buf = vec![0; linux::XATTR_SIZE_MAX];
+    let n = unsafe {
+        libc::lgetxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+        )
+    };
+    if n < 0 {
+        return Err(SysError::from_io_error(std::io::Error::last_os_error()));
+    }
+    buf.truncate(n as usize);
+    Ok(buf)
+}
+
+// list_host lists the names of the extended attributes of the host file at path, without
+// following a symbolic link.
+pub fn list_host(path: &Path) -> SysResult<Vec<String>> {
+    let path = to_cstring(path.to_str().unwrap())?;
+    let mut buf = vec![0u8; linux::XATTR_LIST_MAX];
+    let n = unsafe {
+        libc::llistxattr(
+            path.as_ptr(),
+            buf.as_mut_ptr() as *mut libc::c_char,
+            buf.len(),
+        )
+    };
+    if n < 0 {
+        return Err(SysError::from_io_error(std::io::Error::last_os_error()));
+    }
+    Ok(buf[..n as usize]
+        .split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_flags() {
+        let mut xattrs = Xattrs::default();
+        assert_eq!(
+            xattrs.set("user.a", b"1", libc::XATTR_REPLACE),
+            Err(SysError::new(libc::ENODATA))
+        );
+        xattrs.set("user.a", b"1", libc::XATTR_CREATE).unwrap();
+        assert_eq!(
+            xattrs.set("user.a", b"2", libc::XATTR_CREATE),
+            Err(SysError::new(libc::EEXIST))
+        );
+        xattrs.set("user.a", b"2", libc::XATTR_REPLACE).unwrap();
+        assert_eq!(xattrs.get("user.a"), Ok(b"2".to_vec()));
+        assert_eq!(xattrs.list(), vec!["user.a".to_string()]);
+
+        xattrs.remove("user.a").unwrap();
+        assert_eq!(xattrs.get("user.a"), Err(SysError::new(libc::ENODATA)));
+        assert_eq!(xattrs.remove("user.a"), Err(SysError::new(libc::ENODATA)));
+    }
+}
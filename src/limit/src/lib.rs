@@ -1,6 +1,7 @@
 use utils::{bail_libc, SysError, SysResult};
 
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Limit {
     pub cur: u64,
     pub max: u64,
@@ -14,9 +15,18 @@ impl Limit {
             max: rlimit64.rlim_max,
         }
     }
+
+    #[inline]
+    pub fn to_libc_rlimit64(self) -> libc::rlimit64 {
+        libc::rlimit64 {
+            rlim_cur: self.cur,
+            rlim_max: self.max,
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LimitSet {
     cpu: Option<Limit>,
     file_size: Option<Limit>,
@@ -86,6 +96,9 @@ pub fn is_valid_resource(resource: u32) -> bool {
 
 pub const INFINITY: u64 = u64::MAX;
 
+// NUM_RESOURCES is the number of RLIMIT_* resources, which are numbered from 0.
+pub const NUM_RESOURCES: usize = 16;
+
 macro_rules! get_field {
     ($fn:ident, $field:ident) => {
         pub fn $fn(&self) -> Limit {
@@ -97,20 +110,20 @@ macro_rules! get_field {
     };
 }
 
+// set_field returns the previous limit as get_field reports it, so that an unset limit is
+// written back as RLIM_INFINITY.
 macro_rules! set_field {
-    ($fn:ident, $field:ident) => {
+    ($fn:ident, $get:ident, $field:ident) => {
         pub fn $fn(&mut self, v: Limit, privileged: bool) -> SysResult<Limit> {
-            let old = self.$field;
-            if let Some(limit) = old {
-                if limit.max < v.max && !privileged {
-                    bail_libc!(libc::EPERM);
-                }
-                if v.cur > v.max {
-                    bail_libc!(libc::EINVAL);
-                }
+            if v.cur > v.max {
+                bail_libc!(libc::EINVAL);
+            }
+            let old = self.$get();
+            if old.max < v.max && !privileged {
+                bail_libc!(libc::EPERM);
             }
             self.$field = Some(v);
-            Ok(old.unwrap_or_else(|| Limit::default()))
+            Ok(old)
         }
     };
 }
@@ -133,22 +146,30 @@ impl LimitSet {
     get_field!(get_real_time_priority, real_time_priority);
     get_field!(get_rtt_time, rtt_time);
 
-    set_field!(set_cpu, cpu);
-    set_field!(set_file_size, file_size);
-    set_field!(set_data, data);
-    set_field!(set_stack, stack);
-    set_field!(set_core, core);
-    set_field!(set_rss, rss);
-    set_field!(set_process_count, process_count);
-    set_field!(set_number_of_files, number_of_files);
-    set_field!(set_memory_locked, memory_locked);
-    set_field!(set_address_space, address_space);
-    set_field!(set_locks, locks);
-    set_field!(set_signals_pending, signals_pending);
-    set_field!(set_message_queue_bytes, message_queue_bytes);
-    set_field!(set_nice, nice);
-    set_field!(set_real_time_priority, real_time_priority);
-    set_field!(set_rtt_time, rtt_time);
+    set_field!(set_cpu, get_cpu, cpu);
+    set_field!(set_file_size, get_file_size, file_size);
+    set_field!(set_data, get_data, data);
+    set_field!(set_stack, get_stack, stack);
+    set_field!(set_core, get_core, core);
+    set_field!(set_rss, get_rss, rss);
+    set_field!(set_process_count, get_process_count, process_count);
+    set_field!(set_number_of_files, get_number_of_files, number_of_files);
+    set_field!(set_memory_locked, get_memory_locked, memory_locked);
+    set_field!(set_address_space, get_address_space, address_space);
+    set_field!(set_locks, get_locks, locks);
+    set_field!(set_signals_pending, get_signals_pending, signals_pending);
+    set_field!(
+        set_message_queue_bytes,
+        get_message_queue_bytes,
+        message_queue_bytes
+    );
+    set_field!(set_nice, get_nice, nice);
+    set_field!(
+        set_real_time_priority,
+        get_real_time_priority,
+        real_time_priority
+    );
+    set_field!(set_rtt_time, get_rtt_time, rtt_time);
 
     pub fn get_resource(&self, resource: u32) -> Limit {
         match resource {
@@ -202,8 +223,132 @@ impl LimitSet {
             _ => bail_libc!(libc::EINVAL),
         }
     }
+
+    // to_rlimit_array returns the limits indexed by RLIMIT_* resource. A limit that was never
+    // set is None rather than RLIM_INFINITY, since get_resource may default it differently.
+    pub fn to_rlimit_array(&self) -> [Option<libc::rlimit64>; NUM_RESOURCES] {
+        let mut arr = [None; NUM_RESOURCES];
+        for (resource, limit) in [
+            (libc::RLIMIT_CPU, self.cpu),
+            (libc::RLIMIT_FSIZE, self.file_size),
+            (libc::RLIMIT_DATA, self.data),
+            (libc::RLIMIT_STACK, self.stack),
+            (libc::RLIMIT_CORE, self.core),
+            (libc::RLIMIT_RSS, self.rss),
+            (libc::RLIMIT_NPROC, self.process_count),
+            (libc::RLIMIT_NOFILE, self.number_of_files),
+            (libc::RLIMIT_MEMLOCK, self.memory_locked),
+            (libc::RLIMIT_AS, self.address_space),
+            (libc::RLIMIT_LOCKS, self.locks),
+            (libc::RLIMIT_SIGPENDING, self.signals_pending),
+            (libc::RLIMIT_MSGQUEUE, self.message_queue_bytes),
+            (libc::RLIMIT_NICE, self.nice),
+            (libc::RLIMIT_RTPRIO, self.real_time_priority),
+            (libc::RLIMIT_RTTIME, self.rtt_time),
+        ] {
+            arr[resource as usize] = limit.map(Limit::to_libc_rlimit64);
+        }
+        arr
+    }
+
+    pub fn from_rlimit_array(arr: &[Option<libc::rlimit64>; NUM_RESOURCES]) -> Self {
+        let get = |resource: u32| {
+            arr[resource as usize]
+                .as_ref()
+                .map(Limit::from_libc_rlimit64)
+        };
+        Self {
+            cpu: get(libc::RLIMIT_CPU),
+            file_size: get(libc::RLIMIT_FSIZE),
+            data: get(libc::RLIMIT_DATA),
+            stack: get(libc::RLIMIT_STACK),
+            core: get(libc::RLIMIT_CORE),
+            rss: get(libc::RLIMIT_RSS),
+            process_count: get(libc::RLIMIT_NPROC),
+            number_of_files: get(libc::RLIMIT_NOFILE),
+            memory_locked: get(libc::RLIMIT_MEMLOCK),
+            address_space: get(libc::RLIMIT_AS),
+            locks: get(libc::RLIMIT_LOCKS),
+            signals_pending: get(libc::RLIMIT_SIGPENDING),
+            message_queue_bytes: get(libc::RLIMIT_MSGQUEUE),
+            nice: get(libc::RLIMIT_NICE),
+            real_time_priority: get(libc::RLIMIT_RTPRIO),
+            rtt_time: get(libc::RLIMIT_RTTIME),
+        }
+    }
 }
 
 pub trait Context {
     fn limits(&self) -> LimitSet;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rlimit_array_round_trip() {
+        let mut limits = LimitSet::default();
+        limits
+            .set_file_size(
+                Limit {
+                    cur: INFINITY,
+                    max: INFINITY,
+                },
+                true,
+            )
+            .unwrap();
+        limits
+            .set_number_of_files(Limit { cur: 64, max: 1024 }, true)
+            .unwrap();
+
+        let arr = limits.to_rlimit_array();
+        assert!(arr[libc::RLIMIT_CPU as usize].is_none());
+        let fsize = arr[libc::RLIMIT_FSIZE as usize].unwrap();
+        assert_eq!((fsize.rlim_cur, fsize.rlim_max), (INFINITY, INFINITY));
+        let stack = arr[libc::RLIMIT_STACK as usize].unwrap();
+        assert_eq!((stack.rlim_cur, stack.rlim_max), (0x800000, INFINITY));
+
+        let restored = LimitSet::from_rlimit_array(&arr);
+        assert_eq!(restored, limits);
+        assert_eq!(restored.cpu, None);
+        assert_eq!(
+            restored.file_size,
+            Some(Limit {
+                cur: INFINITY,
+                max: INFINITY
+            })
+        );
+    }
+
+    #[test]
+    fn set_returns_old_limit() {
+        let mut limits = LimitSet::default();
+        let old = limits
+            .set_number_of_files(Limit { cur: 64, max: 1024 }, false)
+            .unwrap();
+        assert_eq!(
+            old,
+            Limit {
+                cur: INFINITY,
+                max: INFINITY
+            }
+        );
+        let old = limits
+            .set_number_of_files(Limit { cur: 32, max: 1024 }, false)
+            .unwrap();
+        assert_eq!(old, Limit { cur: 64, max: 1024 });
+
+        let err = limits
+            .set_number_of_files(Limit { cur: 64, max: 2048 }, false)
+            .unwrap_err();
+        assert_eq!(err.code(), libc::EPERM);
+        assert!(limits
+            .set_number_of_files(Limit { cur: 64, max: 2048 }, true)
+            .is_ok());
+        let err = limits
+            .set_number_of_files(Limit { cur: 128, max: 64 }, true)
+            .unwrap_err();
+        assert_eq!(err.code(), libc::EINVAL);
+    }
+}
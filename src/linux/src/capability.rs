@@ -17,6 +17,9 @@ impl Capability {
     pub const fn ipc_lock() -> Self {
         Self(14)
     }
+    pub const fn cap_sys_admin() -> Self {
+        Self(21)
+    }
     pub const fn cap_sys_nice() -> Self {
         Self(23)
     }
@@ -3,5 +3,10 @@ pub const RAMFS_MAGIC: u64 = 0x09041934;
 pub const SOCKFS_MAGIC: u64 = 0x534F434B;
 pub const PIPEFS_MAGIC: u64 = 0x50495045;
 pub const TMPFS_MAGIC: u64 = 0x01021994;
+pub const PROC_SUPER_MAGIC: u64 = 0x9fa0;
 
 pub const NAME_MAX: usize = 255;
+
+pub const XATTR_NAME_MAX: usize = 255;
+pub const XATTR_SIZE_MAX: usize = 65536;
+pub const XATTR_LIST_MAX: usize = 65536;
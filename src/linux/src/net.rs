@@ -2,3 +2,57 @@ pub const DEFAULT_KEEPALIVE_SECS: u64 = 7200;
 pub const DEFAULT_ACK_DELAY_MILLI_SECS: u64 = 10;
 pub const IP_DEFAULT_MCAST_TTL: u8 = 1;
 pub const IPV6_DEFAULT_MCAST_HOPS: u8 = 1;
+pub const SOCK_MIN_SNDBUF: usize = 4608;
+pub const SOCK_MIN_RCVBUF: usize = 2304;
+pub const SYSCTL_WMEM_MAX: usize = 212992;
+pub const SYSCTL_RMEM_MAX: usize = 212992;
+
+// The values of tcp_info.tcpi_state.
+pub const TCP_ESTABLISHED: u8 = 1;
+pub const TCP_SYN_SENT: u8 = 2;
+pub const TCP_SYN_RECV: u8 = 3;
+pub const TCP_FIN_WAIT1: u8 = 4;
+pub const TCP_FIN_WAIT2: u8 = 5;
+pub const TCP_TIME_WAIT: u8 = 6;
+pub const TCP_CLOSE: u8 = 7;
+pub const TCP_CLOSE_WAIT: u8 = 8;
+pub const TCP_LAST_ACK: u8 = 9;
+pub const TCP_LISTEN: u8 = 10;
+pub const TCP_CLOSING: u8 = 11;
+
+// TcpInfo is struct tcp_info returned by getsockopt(TCP_INFO), up to tcpi_total_retrans.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TcpInfo {
+    pub state: u8,
+    pub ca_state: u8,
+    pub retransmits: u8,
+    pub probes: u8,
+    pub backoff: u8,
+    pub options: u8,
+    pub snd_rcv_wscale: u8,
+    pub rto: u32,
+    pub ato: u32,
+    pub snd_mss: u32,
+    pub rcv_mss: u32,
+    pub unacked: u32,
+    pub sacked: u32,
+    pub lost: u32,
+    pub retrans: u32,
+    pub fackets: u32,
+    pub last_data_sent: u32,
+    pub last_ack_sent: u32,
+    pub last_data_recv: u32,
+    pub last_ack_recv: u32,
+    pub pmtu: u32,
+    pub rcv_ssthresh: u32,
+    pub rtt: u32,
+    pub rttvar: u32,
+    pub snd_ssthresh: u32,
+    pub snd_cwnd: u32,
+    pub advmss: u32,
+    pub reordering: u32,
+    pub rcv_rtt: u32,
+    pub rcv_space: u32,
+    pub total_retrans: u32,
+}
@@ -29,3 +29,38 @@ pub struct SigAction {
 }
 
 pub const SIG_ACTION_SIZE: usize = std::mem::size_of::<SigAction>();
+
+pub const SIG_DFL: u64 = 0;
+pub const SIG_IGN: u64 = 1;
+
+pub const SA_SIGINFO: u64 = 0x00000004;
+pub const SA_RESTORER: u64 = 0x04000000;
+pub const SA_ONSTACK: u64 = 0x08000000;
+pub const SA_RESTART: u64 = 0x10000000;
+pub const SA_NODEFER: u64 = 0x40000000;
+pub const SA_RESETHAND: u64 = 0x80000000;
+
+pub const SI_KERNEL: i32 = 0x80;
+
+pub const SEGV_MAPERR: i32 = 1;
+pub const SEGV_ACCERR: i32 = 2;
+
+pub const BUS_ADRALN: i32 = 1;
+pub const BUS_ADRERR: i32 = 2;
+pub const BUS_OBJERR: i32 = 3;
+
+impl Signal {
+    pub fn mask(&self) -> SignalSet {
+        1 << (self.0 - 1)
+    }
+}
+
+impl SigAction {
+    pub fn is_default(&self) -> bool {
+        self.handler == SIG_DFL
+    }
+
+    pub fn is_ignored(&self) -> bool {
+        self.handler == SIG_IGN
+    }
+}
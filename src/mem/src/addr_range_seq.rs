@@ -10,7 +10,8 @@ pub struct AddrRangeSeq {
 
 impl AddrRangeSeq {
     pub fn as_view(&self) -> AddrRangeSeqView<'_> {
-        match self.data.len() {
+        // A sequence of a single range keeps it in offset and limit rather than in data.
+        match self.length {
             0 => AddrRangeSeqView::default(),
             _ => AddrRangeSeqView {
                 data: &self.data,
@@ -68,6 +68,23 @@ impl IoSequence {
             .copy_out_from(self.addrs.as_view(), src, &self.opts)
     }
 
+    // zero writes zeros across the whole sequence and returns the number of bytes zeroed. Like
+    // write, it consumes the zeroed bytes, and it stops early at the first short copy.
+    pub fn zero(&mut self) -> SysResult<usize> {
+        let buf = [0u8; PAGE_SIZE as usize];
+        let mut done = 0;
+        while self.num_bytes() > 0 {
+            let want = std::cmp::min(self.num_bytes(), buf.len());
+            let n = self.copy_out(&buf[..want])?;
+            done += n;
+            self.drop_first(n);
+            if n < want {
+                break;
+            }
+        }
+        Ok(done)
+    }
+
     pub fn bytes_sequence(buf: &mut [u8]) -> Self {
         Self {
             io: Rc::new(RefCell::new(BytesIo::new(buf))),
@@ -176,6 +193,44 @@ pub fn copy_string_in(
     bail_libc!(libc::ENAMETOOLONG)
 }
 
+// copy_string_out copies s followed by a terminating NUL to the memory mapped at addr in uio and
+// returns the number of bytes written, including the NUL
+pub fn copy_string_out(
+    uio: &Rc<RefCell<impl io::Io>>,
+    mut addr: Addr,
+    s: &str,
+    max_len: usize,
+    opts: &IoOpts,
+) -> SysResult<usize> {
+    let mut buf = Vec::with_capacity(s.len() + 1);
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    if buf.len() > max_len {
+        bail_libc!(libc::ENAMETOOLONG);
+    }
+    let mut done = 0;
+    while done < buf.len() {
+        let mut write_len = std::cmp::min(buf.len() - done, COPY_STRING_INCREMENT);
+        let mut end = addr
+            .add_length(write_len as u64)
+            .ok_or_else(|| SysError::new(libc::EFAULT))?;
+        if addr.round_down() != end.round_down() {
+            end = end.round_down();
+            write_len = (end.0 - addr.0) as usize;
+        }
+        let n = uio
+            .as_ref()
+            .borrow_mut()
+            .copy_out(addr, &buf[done..done + write_len], opts)?;
+        if n < write_len {
+            bail_libc!(libc::EFAULT);
+        }
+        done += n;
+        addr = end;
+    }
+    Ok(done)
+}
+
 fn copy_out_vec(
     uio: &Rc<RefCell<dyn io::Io>>,
     mut ars: AddrRangeSeqView,
@@ -213,3 +268,56 @@ fn copy_in_vec(
     }
     Ok(done)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_string_out_across_page_boundary() {
+        let mut data = vec![b'x'; 2 * PAGE_SIZE as usize];
+        let uio = Rc::new(RefCell::new(BytesIo::new(&mut data)));
+        let addr = Addr(PAGE_SIZE as u64 - 3);
+        let opts = IoOpts::default();
+        let n = copy_string_out(&uio, addr, "foobar", 16, &opts);
+        assert_eq!(n, Ok(7));
+        let s = copy_string_in(&uio, addr, 16, &opts);
+        assert_eq!(s, Ok("foobar".to_string()));
+        assert_eq!(data[PAGE_SIZE as usize + 4], b'x');
+    }
+
+    #[test]
+    fn copy_string_out_too_long() {
+        let mut data = vec![b'x'; 8];
+        let uio = Rc::new(RefCell::new(BytesIo::new(&mut data)));
+        let res = copy_string_out(&uio, Addr(0), "foobar", 6, &IoOpts::default());
+        assert_eq!(res, Err(SysError::new(libc::ENAMETOOLONG)));
+        assert_eq!(data, b"xxxxxxxx".to_vec());
+    }
+
+    #[test]
+    fn zero_scattered_sequence() {
+        let mut data = vec![b'x'; 2 * PAGE_SIZE as usize];
+        let mut seq = IoSequence {
+            io: Rc::new(RefCell::new(BytesIo::new(&mut data))),
+            addrs: AddrRangeSeq::from_slice(&[
+                AddrRange { start: 2, end: 5 },
+                AddrRange {
+                    start: PAGE_SIZE as u64 - 1,
+                    end: 2 * PAGE_SIZE as u64 - 1,
+                },
+            ]),
+            opts: IoOpts::default(),
+        };
+        assert_eq!(seq.zero(), Ok(PAGE_SIZE as usize + 3));
+        assert_eq!(seq.num_bytes(), 0);
+        drop(seq);
+
+        let zeroed = |i: usize| {
+            (2..5).contains(&i) || (PAGE_SIZE as usize - 1..2 * PAGE_SIZE as usize - 1).contains(&i)
+        };
+        for (i, b) in data.iter().enumerate() {
+            assert_eq!(*b, if zeroed(i) { 0 } else { b'x' }, "byte {}", i);
+        }
+    }
+}
@@ -5,13 +5,27 @@ use smoltcp::{
     phy::TunTapInterface,
     socket::Socket,
     time::Duration,
+    wire::IpAddress,
 };
 
+use utils::SysResult;
+
 pub trait Context: mem::Context {
     fn add_socket(&self, socket: Socket<'static>) -> SocketHandle;
     fn poll_wait(&self, once: bool);
     fn gen_local_port(&self) -> u16;
     fn remove_local_port(&self, p: u16);
+    // bind_local_port records one more socket bound to p for protocol. It returns false without
+    // recording anything if p already was bound or gen_local_port handed it out, unless reuse is
+    // set.
+    fn bind_local_port(&self, protocol: i32, p: u16, reuse: bool) -> bool;
+    // unbind_local_port forgets one of the sockets bound to p for protocol.
+    fn unbind_local_port(&self, protocol: i32, p: u16);
+    // join_multicast_group subscribes the interface to group for one more socket.
+    fn join_multicast_group(&self, group: IpAddress) -> SysResult<()>;
+    // leave_multicast_group drops a subscription made by join_multicast_group. The interface
+    // leaves group once no socket is subscribed to it anymore.
+    fn leave_multicast_group(&self, group: IpAddress);
     fn wait(&self, duration: Option<Duration>);
     fn network_interface_mut(&self) -> RwLockWriteGuard<'_, Interface<'static, TunTapInterface>>;
 
@@ -0,0 +1,141 @@
+use std::io::{Read, Write};
+
+use mem::IoSequence;
+use smoltcp::{
+    iface::SocketHandle,
+    phy::ChecksumCapabilities,
+    socket::IcmpSocket,
+    wire::{
+        Icmpv4Message, Icmpv4Packet, Icmpv4Repr, Icmpv6Message, Icmpv6Packet, Icmpv6Repr,
+        IpAddress, IpEndpoint,
+    },
+};
+use utils::{bail_libc, SysError, SysResult};
+
+use crate::Context;
+
+// Like Linux ping sockets, the guest reads and writes whole ICMP echo messages, and the
+// identifier of outgoing requests is replaced with the one the socket is bound to.
+
+pub fn recv(
+    handle: SocketHandle,
+    dst: &mut IoSequence,
+    peek: bool,
+    non_blocking: bool,
+    ctx: &dyn Context,
+) -> SysResult<(usize, IpEndpoint)> {
+    if peek {
+        // smoltcp's IcmpSocket can't peek at a packet without dequeuing it.
+        bail_libc!(libc::EOPNOTSUPP);
+    }
+    let mut once = true;
+    loop {
+        {
+            let mut iface = ctx.network_interface_mut();
+            let socket = iface.get_socket::<IcmpSocket>(handle);
+            while socket.can_recv() {
+                let (data, addr) = socket.recv().map_err(SysError::from_smoltcp_error)?;
+                if !is_echo_reply(data, addr) {
+                    continue;
+                }
+                let n = dst.write(data).map_err(SysError::from_io_error)?;
+                return Ok((n, IpEndpoint { addr, port: 0 }));
+            }
+        }
+        if non_blocking {
+            bail_libc!(libc::EAGAIN);
+        }
+        ctx.poll_wait(once);
+        once = false;
+    }
+}
+
+pub fn send(
+    handle: SocketHandle,
+    ident: u16,
+    src: &mut IoSequence,
+    non_blocking: bool,
+    dst: IpAddress,
+    ctx: &dyn Context,
+) -> SysResult<usize> {
+    let mut buf = vec![0; src.num_bytes()];
+    let n = src.read(&mut buf).map_err(SysError::from_io_error)?;
+    let buf = &buf[..n];
+
+    let mut once = true;
+    while {
+        let mut iface = ctx.network_interface_mut();
+        let socket = iface.get_socket::<IcmpSocket>(handle);
+        !socket.can_send()
+    } {
+        if non_blocking {
+            bail_libc!(libc::EAGAIN);
+        }
+        ctx.poll_wait(once);
+        once = false;
+    }
+
+    let mut iface = ctx.network_interface_mut();
+    let checksum_caps = ChecksumCapabilities::default();
+    match dst {
+        IpAddress::Ipv4(_) => {
+            let packet = Icmpv4Packet::new_checked(buf).map_err(|_| SysError::new(libc::EINVAL))?;
+            if packet.msg_type() != Icmpv4Message::EchoRequest {
+                bail_libc!(libc::EINVAL);
+            }
+            let repr = Icmpv4Repr::EchoRequest {
+                ident,
+                seq_no: packet.echo_seq_no(),
+                data: packet.data(),
+            };
+            let socket = iface.get_socket::<IcmpSocket>(handle);
+            let out = socket
+                .send(repr.buffer_len(), dst)
+                .map_err(SysError::from_smoltcp_error)?;
+            repr.emit(&mut Icmpv4Packet::new_unchecked(out), &checksum_caps);
+        }
+        IpAddress::Ipv6(_) => {
+            let src_addr = iface
+                .ip_addrs()
+                .iter()
+                .find_map(|cidr| match cidr.address() {
+                    IpAddress::Ipv6(addr) if !addr.is_link_local() => Some(addr),
+                    _ => None,
+                })
+                .ok_or_else(|| SysError::new(libc::EADDRNOTAVAIL))?;
+            let packet = Icmpv6Packet::new_checked(buf).map_err(|_| SysError::new(libc::EINVAL))?;
+            if packet.msg_type() != Icmpv6Message::EchoRequest {
+                bail_libc!(libc::EINVAL);
+            }
+            let repr = Icmpv6Repr::EchoRequest {
+                ident,
+                seq_no: packet.echo_seq_no(),
+                data: packet.payload(),
+            };
+            let socket = iface.get_socket::<IcmpSocket>(handle);
+            let out = socket
+                .send(repr.buffer_len(), dst)
+                .map_err(SysError::from_smoltcp_error)?;
+            repr.emit(
+                &IpAddress::Ipv6(src_addr),
+                &dst,
+                &mut Icmpv6Packet::new_unchecked(out),
+                &checksum_caps,
+            );
+        }
+        _ => bail_libc!(libc::EINVAL),
+    }
+    drop(iface);
+    ctx.poll_wait(false);
+    Ok(n)
+}
+
+fn is_echo_reply(data: &[u8], addr: IpAddress) -> bool {
+    match addr {
+        IpAddress::Ipv4(_) => Icmpv4Packet::new_checked(data)
+            .map_or(false, |p| p.msg_type() == Icmpv4Message::EchoReply),
+        IpAddress::Ipv6(_) => Icmpv6Packet::new_checked(data)
+            .map_or(false, |p| p.msg_type() == Icmpv6Message::EchoReply),
+        _ => false,
+    }
+}
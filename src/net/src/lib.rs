@@ -1,14 +1,19 @@
 #![feature(unix_socket_ancillary_data)]
 
 mod context;
+mod icmp;
 mod tcp;
 mod udp;
+mod unix;
 mod utils;
 
 use std::{
+    cell::{Cell, RefCell},
+    mem::ManuallyDrop,
+    net::Shutdown,
     os::unix::{
         net::{UnixDatagram, UnixStream},
-        prelude::{AsRawFd, FromRawFd, RawFd},
+        prelude::{FromRawFd, IntoRawFd, RawFd},
     },
     time::Duration,
 };
@@ -20,26 +25,63 @@ use mem::{Addr, IoSequence};
 use smoltcp::{
     iface::SocketHandle,
     socket::{
-        AnySocket, IcmpPacketMetadata, IcmpSocket, IcmpSocketBuffer, TcpSocket, TcpSocketBuffer,
-        UdpPacketMetadata, UdpSocket, UdpSocketBuffer,
+        AnySocket, IcmpEndpoint, IcmpPacketMetadata, IcmpSocket, IcmpSocketBuffer, TcpSocket,
+        TcpState, UdpSocket,
     },
     time::Duration as TDuration,
     wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv6Address},
 };
 
+// MAX_LISTEN_BACKLOG caps the number of LISTEN sockets pre-created for a listening Tcp socket.
+// Each of them holds its own 128KiB of buffers, so the backlog given to listen(2) is clamped to
+// [1, MAX_LISTEN_BACKLOG].
+pub const MAX_LISTEN_BACKLOG: usize = 8;
+
 #[derive(Debug)]
 pub enum Socket {
     UnixDatagram(Option<RawFd>),
     UnixStream(Option<RawFd>),
+    // Backed by a host SOCK_SEQPACKET socket, which keeps the boundaries of the messages sent
+    // through it.
+    UnixSeqpacket(Option<RawFd>),
+    // A listening Tcp socket keeps `handle` and every handle in `backlog` in the LISTEN state,
+    // since smoltcp needs a socket per pending connection.
     Tcp {
         handle: SocketHandle,
         local_endpoint: IpEndpoint,
+        listening: bool,
+        backlog: Vec<SocketHandle>,
+        reuse_addr: bool,
+        reuse_port: bool,
+        // Set while a connect(2) outcome has not been reported through SO_ERROR yet.
+        connect_pending: bool,
+        // Set on sockets returned by accept(2), which share the port of their listener.
+        accepted: bool,
+        // The send and receive buffer sizes requested through SO_SNDBUF and SO_RCVBUF.
+        buffer_sizes: (Option<usize>, Option<usize>),
     },
     Udp {
         handle: SocketHandle,
         default_endpoint: Option<IpEndpoint>,
+        // The sender of the last datagram received, reported as the peer while not connected.
+        last_peer: Cell<Option<IpEndpoint>>,
+        reuse_addr: bool,
+        reuse_port: bool,
+        // Set by SO_BROADCAST to allow sending to broadcast addresses.
+        broadcast: bool,
+        // The multicast groups joined through IP_ADD_MEMBERSHIP or IPV6_ADD_MEMBERSHIP.
+        multicast_groups: RefCell<Vec<IpAddress>>,
+        // The send and receive buffer sizes requested through SO_SNDBUF and SO_RCVBUF. They take
+        // effect once no datagram is queued.
+        buffer_sizes: (Option<usize>, Option<usize>),
+    },
+    Icmp {
+        handle: SocketHandle,
+        // The echo identifier set by bind(2).
+        ident: Option<u16>,
     },
-    Icmp(SocketHandle),
+    // A socket whose resources have been released by close.
+    Closed,
 }
 
 impl Socket {
@@ -52,10 +94,14 @@ impl Socket {
                 match stype {
                     libc::SOCK_DGRAM => {
                         let sock = UnixDatagram::unbound().map_err(SysError::from_io_error)?;
-                        Ok(Self::UnixDatagram(Some(sock.as_raw_fd())))
+                        Ok(Self::UnixDatagram(Some(sock.into_raw_fd())))
                     }
                     libc::SOCK_STREAM => Ok(Self::UnixStream(None)),
-                    _ => todo!("unhandled stype for Unix domain socket."),
+                    libc::SOCK_SEQPACKET => Ok(Self::UnixSeqpacket(Some(unix::new_seqpacket()?))),
+                    _ => {
+                        logger::warn!("{}:{} unhandled stype {}", file!(), line!(), stype);
+                        bail_libc!(libc::EINVAL)
+                    }
                 }
             }
             libc::AF_INET | libc::AF_INET6 => {
@@ -64,30 +110,31 @@ impl Socket {
                         if protocol != 0 && protocol != libc::IPPROTO_TCP {
                             bail_libc!(libc::EINVAL);
                         }
-                        let rx_buffer = TcpSocketBuffer::new(vec![0; 65536]);
-                        let tx_buffer = TcpSocketBuffer::new(vec![0; 65536]);
-                        let socket = TcpSocket::new(rx_buffer, tx_buffer);
-                        let handle = ctx.add_socket(socket.upcast());
                         Ok(Self::Tcp {
-                            handle,
+                            handle: tcp::new_socket(ctx),
                             local_endpoint: IpEndpoint::UNSPECIFIED,
+                            listening: false,
+                            backlog: Vec::new(),
+                            reuse_addr: false,
+                            reuse_port: false,
+                            connect_pending: false,
+                            accepted: false,
+                            buffer_sizes: (None, None),
                         })
                     }
                     libc::SOCK_DGRAM => match protocol {
                         0 | libc::IPPROTO_UDP => {
-                            let rx_buffer = UdpSocketBuffer::new(
-                                vec![UdpPacketMetadata::EMPTY],
-                                vec![0; 65536],
-                            );
-                            let tx_buffer = UdpSocketBuffer::new(
-                                vec![UdpPacketMetadata::EMPTY],
-                                vec![0; 65536],
-                            );
-                            let socket = UdpSocket::new(rx_buffer, tx_buffer);
+                            let socket = udp::new_socket(65536, 65536);
                             let handle = ctx.add_socket(socket.upcast());
                             Ok(Self::Udp {
                                 handle,
                                 default_endpoint: None,
+                                last_peer: Cell::new(None),
+                                reuse_addr: false,
+                                reuse_port: false,
+                                broadcast: false,
+                                multicast_groups: RefCell::new(Vec::new()),
+                                buffer_sizes: (None, None),
                             })
                         }
                         // FIXME: should handle this separately..?
@@ -102,7 +149,10 @@ impl Socket {
                             );
                             let socket = IcmpSocket::new(rx_buffer, tx_buffer);
                             let handle = ctx.add_socket(socket.upcast());
-                            Ok(Self::Icmp(handle))
+                            Ok(Self::Icmp {
+                                handle,
+                                ident: None,
+                            })
                         }
                         _ => {
                             logger::warn!(
@@ -133,7 +183,66 @@ impl Socket {
         }
     }
 
-    pub fn connect(&mut self, sock_addr: &[u8], domain: i32, ctx: &dyn Context) -> SysResult<()> {
+    // from_host_fd wraps the host Unix domain socket fd, e.g. one received as SCM_RIGHTS, and
+    // returns it along with its type.
+    pub fn from_host_fd(fd: RawFd) -> SysResult<(Self, i32)> {
+        let stype = unix::socket_type(fd)?;
+        let socket = match stype {
+            libc::SOCK_DGRAM => Self::UnixDatagram(Some(fd)),
+            libc::SOCK_STREAM => Self::UnixStream(Some(fd)),
+            libc::SOCK_SEQPACKET => Self::UnixSeqpacket(Some(fd)),
+            _ => bail_libc!(libc::EINVAL),
+        };
+        Ok((socket, stype))
+    }
+
+    // host_fd returns the host file descriptor backing a Unix domain socket.
+    pub fn host_fd(&self) -> Option<RawFd> {
+        match *self {
+            Self::UnixDatagram(fd) | Self::UnixStream(fd) | Self::UnixSeqpacket(fd) => fd,
+            _ => None,
+        }
+    }
+
+    // new_pair creates a pair of sockets connected to each other. Only Unix domain sockets can
+    // be created in pairs.
+    pub fn new_pair(domain: i32, stype: i32, protocol: i32) -> SysResult<(Self, Self)> {
+        if domain != libc::AF_UNIX {
+            bail_libc!(libc::EOPNOTSUPP);
+        }
+        if protocol != 0 && protocol != libc::AF_UNIX {
+            bail_libc!(libc::EINVAL);
+        }
+        match stype {
+            libc::SOCK_DGRAM => {
+                let (a, b) = unix::new_pair(stype)?;
+                Ok((Self::UnixDatagram(Some(a)), Self::UnixDatagram(Some(b))))
+            }
+            libc::SOCK_STREAM => {
+                let (a, b) = unix::new_pair(stype)?;
+                Ok((Self::UnixStream(Some(a)), Self::UnixStream(Some(b))))
+            }
+            libc::SOCK_SEQPACKET => {
+                let (a, b) = unix::new_pair(stype)?;
+                Ok((Self::UnixSeqpacket(Some(a)), Self::UnixSeqpacket(Some(b))))
+            }
+            _ => {
+                logger::warn!("{}:{} unhandled stype {}", file!(), line!(), stype);
+                bail_libc!(libc::EINVAL)
+            }
+        }
+    }
+
+    // connect connects the socket to sock_addr. A non-blocking TCP socket returns EINPROGRESS
+    // while the handshake is in flight, and its outcome is reported by a later connect or through
+    // SO_ERROR.
+    pub fn connect(
+        &mut self,
+        sock_addr: &[u8],
+        domain: i32,
+        non_blocking: bool,
+        ctx: &dyn Context,
+    ) -> SysResult<()> {
         let (endpoint, dom) = address_and_family(sock_addr)?;
         if dom != domain as u16 {
             logger::warn!("specified domain does not match");
@@ -141,41 +250,69 @@ impl Socket {
         }
         match (self, endpoint) {
             (&mut Self::UnixDatagram(fd), Endpoint::Unix(path)) => {
-                let socket = unsafe { UnixDatagram::from_raw_fd(fd.unwrap()) };
+                let socket = ManuallyDrop::new(unsafe { UnixDatagram::from_raw_fd(fd.unwrap()) });
                 socket.connect(path).map_err(SysError::from_io_error)
             }
             (&mut Self::UnixStream(ref mut fd), Endpoint::Unix(path)) => {
                 let socket = UnixStream::connect(path).map_err(SysError::from_io_error)?;
-                *fd = Some(socket.as_raw_fd());
+                *fd = Some(socket.into_raw_fd());
                 Ok(())
             }
+            (&mut Self::UnixSeqpacket(Some(fd)), Endpoint::Unix(path)) => unix::connect(fd, path),
             (
                 &mut Self::Tcp {
                     handle,
                     ref mut local_endpoint,
+                    ref mut connect_pending,
+                    ..
                 },
                 Endpoint::Ip(remote_endpoint),
             ) => {
+                if *connect_pending {
+                    // A connect(2) repeated after EINPROGRESS reports how the first one went.
+                    ctx.poll_wait(true);
+                    let mut iface = ctx.network_interface_mut();
+                    let state = iface.get_socket::<TcpSocket>(handle).state();
+                    return match tcp::connect_outcome(state) {
+                        None => err_libc!(libc::EALREADY),
+                        Some(res) => {
+                            *connect_pending = false;
+                            res
+                        }
+                    };
+                }
                 {
                     let mut iface = ctx.network_interface_mut();
                     let (socket, cx) = iface.get_socket_and_context::<TcpSocket>(handle);
-                    // FIXME: what if blocking?
-                    if !local_endpoint.is_specified() {
-                        *local_endpoint = IpEndpoint::from(ctx.gen_local_port());
+                    // A bound address and port are kept, and only a missing port is allocated.
+                    if local_endpoint.port == 0 {
+                        local_endpoint.port = ctx.gen_local_port();
+                    }
+                    // smoltcp picks the source address itself only for IpAddress::Unspecified,
+                    // so the any address of either family is passed as that.
+                    let mut local = *local_endpoint;
+                    if local.addr.is_unspecified() {
+                        local.addr = IpAddress::Unspecified;
                     }
                     socket
-                        .connect(cx, remote_endpoint, *local_endpoint)
+                        .connect(cx, remote_endpoint, local)
                         .map_err(SysError::from_smoltcp_error)?;
                 }
+                *connect_pending = true;
+                if non_blocking {
+                    // Send the SYN right away, but leave the handshake to complete later.
+                    ctx.poll_wait(true);
+                    bail_libc!(libc::EINPROGRESS);
+                }
                 loop {
                     ctx.poll_wait(false);
                     let mut iface = ctx.network_interface_mut();
-                    let socket = iface.get_socket::<TcpSocket>(handle);
-                    if socket.is_active() && socket.may_send() && socket.may_recv() {
-                        break;
+                    let state = iface.get_socket::<TcpSocket>(handle).state();
+                    if let Some(res) = tcp::connect_outcome(state) {
+                        *connect_pending = false;
+                        return res;
                     }
                 }
-                Ok(())
             }
             (
                 &mut Self::Udp {
@@ -210,30 +347,85 @@ impl Socket {
             match (self, endpoint) {
                 (&mut Self::UnixDatagram(ref mut fd), Endpoint::Unix(path)) => {
                     let sock = UnixDatagram::bind(path).map_err(SysError::from_io_error)?;
-                    *fd = Some(sock.as_raw_fd());
+                    *fd = Some(sock.into_raw_fd());
                     Ok(())
                 }
                 (&mut Self::UnixStream(ref mut fd), Endpoint::Unix(path)) => {
                     let sock = UnixStream::connect(path).map_err(SysError::from_io_error)?;
-                    *fd = Some(sock.as_raw_fd());
+                    *fd = Some(sock.into_raw_fd());
                     Ok(())
                 }
+                (&mut Self::UnixSeqpacket(Some(fd)), Endpoint::Unix(path)) => unix::bind(fd, path),
                 (
                     &mut Self::Tcp {
                         ref mut local_endpoint,
+                        reuse_addr,
+                        reuse_port,
                         ..
                     },
                     Endpoint::Ip(ip_endpoint),
                 ) => {
+                    // Like linux, a socket is bound at most once, so each binding is released
+                    // exactly once on close.
+                    if local_endpoint.port != 0 {
+                        bail_libc!(libc::EINVAL);
+                    }
+                    bind_port(
+                        libc::IPPROTO_TCP,
+                        ip_endpoint.port,
+                        reuse_addr || reuse_port,
+                        ctx,
+                    )?;
                     *local_endpoint = ip_endpoint;
                     Ok(())
                 }
-                (&mut Self::Udp { handle, .. }, Endpoint::Ip(ip_endpoint)) => {
+                (
+                    &mut Self::Udp {
+                        handle,
+                        reuse_addr,
+                        reuse_port,
+                        ..
+                    },
+                    Endpoint::Ip(ip_endpoint),
+                ) => {
+                    bind_port(
+                        libc::IPPROTO_UDP,
+                        ip_endpoint.port,
+                        reuse_addr || reuse_port,
+                        ctx,
+                    )?;
+                    // Like connect on TCP sockets, the any address of either family is bound as
+                    // IpAddress::Unspecified so that smoltcp picks the source address.
+                    let mut ip_endpoint = ip_endpoint;
+                    if ip_endpoint.addr.is_unspecified() {
+                        ip_endpoint.addr = IpAddress::Unspecified;
+                    }
                     let mut iface = ctx.network_interface_mut();
                     let socket = iface.get_socket::<UdpSocket>(handle);
-                    socket
+                    let res = socket
                         .bind(ip_endpoint)
-                        .map_err(SysError::from_smoltcp_error)
+                        .map_err(SysError::from_smoltcp_error);
+                    drop(iface);
+                    if res.is_err() {
+                        release_port(libc::IPPROTO_UDP, ip_endpoint.port, ctx);
+                    }
+                    res
+                }
+                (
+                    &mut Self::Icmp {
+                        handle,
+                        ref mut ident,
+                    },
+                    Endpoint::Ip(ip_endpoint),
+                ) => {
+                    // Like Linux ping sockets, the port is used as the echo identifier.
+                    let mut iface = ctx.network_interface_mut();
+                    let socket = iface.get_socket::<IcmpSocket>(handle);
+                    socket
+                        .bind(IcmpEndpoint::Ident(ip_endpoint.port))
+                        .map_err(SysError::from_smoltcp_error)?;
+                    *ident = Some(ip_endpoint.port);
+                    Ok(())
                 }
                 _ => {
                     logger::warn!("endpoint type mismatch");
@@ -245,9 +437,35 @@ impl Socket {
 
     pub fn readiness(&self, mask: u64, ctx: &dyn Context) -> u64 {
         match *self {
-            Self::Tcp { handle, .. } => {
+            Self::Tcp {
+                handle,
+                listening: true,
+                ref backlog,
+                ..
+            } => {
+                // A listener is readable once a connection is pending accept.
+                let mut iface = ctx.network_interface_mut();
+                let pending = std::iter::once(handle)
+                    .chain(backlog.iter().copied())
+                    .any(|h| tcp::accept_ready(iface.get_socket::<TcpSocket>(h).state()));
+                if pending {
+                    mask & linux::POLL_READABLE_EVENTS
+                } else {
+                    0
+                }
+            }
+            Self::Tcp {
+                handle,
+                connect_pending,
+                ..
+            } => {
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<TcpSocket>(handle);
+                if connect_pending && socket.state() == TcpState::Closed {
+                    // A refused connect(2) wakes up pollers with an error until SO_ERROR is read.
+                    return (libc::POLLERR | libc::POLLHUP) as u64
+                        | mask & (linux::POLL_READABLE_EVENTS | linux::POLL_WRITABLE_EVENTS);
+                }
                 let mut r = 0;
                 if mask & linux::POLL_READABLE_EVENTS != 0 && socket.may_recv() {
                     r |= linux::POLL_READABLE_EVENTS;
@@ -268,7 +486,7 @@ impl Socket {
                 }
                 r
             }
-            Self::Icmp(handle) => {
+            Self::Icmp { handle, .. } => {
                 let mut r = mask & linux::POLL_WRITABLE_EVENTS;
                 if mask & linux::POLL_READABLE_EVENTS != 0 {
                     let mut iface = ctx.network_interface_mut();
@@ -279,15 +497,19 @@ impl Socket {
                 }
                 r
             }
-            Self::UnixStream(fd) => {
-                get_poll_event_from_fd(fd.expect("FD for UnixStream is not set"), mask)
-            }
-            Self::UnixDatagram(fd) => {
-                get_poll_event_from_fd(fd.expect("FD for UnixDatagram is not set"), mask)
+            Self::UnixStream(Some(fd))
+            | Self::UnixDatagram(Some(fd))
+            | Self::UnixSeqpacket(Some(fd)) => get_poll_event_from_fd(fd, mask),
+            // Like linux, an unconnected stream socket is writable and hung up.
+            Self::UnixStream(None) | Self::UnixDatagram(None) | Self::UnixSeqpacket(None) => {
+                (mask & linux::POLL_WRITABLE_EVENTS) | libc::POLLHUP as u64
             }
+            Self::Closed => 0,
         }
     }
 
+    // send_msg sends src as a single message. On UnixSeqpacket sockets, like datagram ones, the
+    // message is delivered whole, and one that doesn't fit in the send buffer fails with EMSGSIZE.
     pub fn send_msg(
         &self,
         src: &mut IoSequence,
@@ -300,6 +522,9 @@ impl Socket {
             Self::Udp {
                 handle,
                 default_endpoint,
+                broadcast,
+                buffer_sizes,
+                ..
             } => {
                 let ep = match addr_and_family {
                     Some((ep, _family)) => match ep {
@@ -309,20 +534,76 @@ impl Socket {
                     },
                     None => default_endpoint.ok_or_else(|| SysError::new(libc::EINVAL))?,
                 };
-                udp::send(handle, src, non_blocking, ep, ctx)
+                let n = udp::send(handle, src, non_blocking, ep, broadcast, ctx)?;
+                udp::resize_buffers(handle, buffer_sizes.0, buffer_sizes.1, ctx)?;
+                Ok(n)
             }
-            Self::Icmp(_handle) => {
-                todo!("send_msg for ICMP")
+            Self::Icmp { handle, ident } => {
+                let ident = ident.ok_or_else(|| SysError::new(libc::EINVAL))?;
+                let addr = match addr_and_family {
+                    Some((Endpoint::Ip(ep), _)) => ep.addr,
+                    Some((Endpoint::Unix(_), _)) => bail_libc!(libc::EINVAL),
+                    None => bail_libc!(libc::EDESTADDRREQ),
+                };
+                icmp::send(handle, ident, src, non_blocking, addr, ctx)
+            }
+            Self::UnixStream(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                unix::send(fd, src, non_blocking, None)
             }
-            Self::UnixStream(_fd) => {
-                todo!("send_msg for UnixStream")
+            Self::UnixDatagram(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                let path = match addr_and_family {
+                    Some((Endpoint::Unix(path), _)) => Some(path),
+                    Some((Endpoint::Ip(_), _)) => bail_libc!(libc::EINVAL),
+                    None => None,
+                };
+                unix::send(fd, src, non_blocking, path)
             }
-            Self::UnixDatagram(_fd) => {
-                todo!("send_msg for UnixDatagram")
+            Self::UnixSeqpacket(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                if let Some((Endpoint::Ip(_), _)) = addr_and_family {
+                    bail_libc!(libc::EINVAL);
+                }
+                unix::send(fd, src, non_blocking, None)
             }
+            Self::Closed => err_libc!(libc::EBADF),
         }
     }
 
+    // send_msg_with_fds is send_msg for Unix domain sockets, which additionally passes the host
+    // file descriptors fds to the peer as SCM_RIGHTS.
+    pub fn send_msg_with_fds(
+        &self,
+        src: &mut IoSequence,
+        non_blocking: bool,
+        addr_and_family: Option<(Endpoint<'_>, u16)>,
+        fds: &[RawFd],
+    ) -> SysResult<usize> {
+        match *self {
+            Self::UnixStream(fd) | Self::UnixSeqpacket(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                if let Some((Endpoint::Ip(_), _)) = addr_and_family {
+                    bail_libc!(libc::EINVAL);
+                }
+                unix::send_with_fds(fd, src, non_blocking, None, fds)
+            }
+            Self::UnixDatagram(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                let path = match addr_and_family {
+                    Some((Endpoint::Unix(path), _)) => Some(path),
+                    Some((Endpoint::Ip(_), _)) => bail_libc!(libc::EINVAL),
+                    None => None,
+                };
+                unix::send_with_fds(fd, src, non_blocking, path, fds)
+            }
+            Self::Closed => err_libc!(libc::EBADF),
+            _ => err_libc!(libc::EINVAL),
+        }
+    }
+
+    // recv_msg receives from the socket into dst. On UnixSeqpacket sockets, like datagram ones, at
+    // most one message is returned and the part of it that doesn't fit in dst is discarded.
     pub fn recv_msg(
         &self,
         dst: &mut IoSequence,
@@ -333,8 +614,37 @@ impl Socket {
     ) -> SysResult<usize> {
         let (n, endpoint) = match *self {
             Self::Tcp { handle, .. } => tcp::recv(handle, dst, peek, non_blocking, ctx)?,
-            Self::Udp { handle, .. } => udp::recv(handle, dst, peek, non_blocking, ctx)?,
-            _ => todo!("recv_msg"),
+            Self::Udp {
+                handle,
+                ref last_peer,
+                buffer_sizes,
+                ..
+            } => {
+                let (n, endpoint) = udp::recv(handle, dst, peek, non_blocking, ctx)?;
+                udp::resize_buffers(handle, buffer_sizes.0, buffer_sizes.1, ctx)?;
+                last_peer.set(Some(endpoint));
+                (n, endpoint)
+            }
+            Self::UnixStream(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                let (n, _) = unix::recv(fd, dst, peek, non_blocking)?;
+                return Ok(n);
+            }
+            Self::UnixDatagram(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                let (n, addr) = unix::recv(fd, dst, peek, non_blocking)?;
+                if let Some(s) = src_addr_and_len {
+                    copy_out_socket_addr(&addr, s, ctx)?;
+                }
+                return Ok(n);
+            }
+            Self::UnixSeqpacket(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                let (n, _) = unix::recv(fd, dst, peek, non_blocking)?;
+                return Ok(n);
+            }
+            Self::Icmp { handle, .. } => icmp::recv(handle, dst, peek, non_blocking, ctx)?,
+            Self::Closed => bail_libc!(libc::EBADF),
         };
         if let Some(s) = src_addr_and_len {
             self.write_socket_addr(endpoint, s, ctx)?;
@@ -342,13 +652,47 @@ impl Socket {
         Ok(n)
     }
 
+    // recv_msg_with_fds is recv_msg which, on Unix domain sockets, also accepts the file
+    // descriptors passed as SCM_RIGHTS that fit in control_len bytes. It returns the received
+    // host file descriptors and the MSG_TRUNC and MSG_CTRUNC flags along with the number of
+    // bytes read. Peeking never accepts file descriptors.
+    pub fn recv_msg_with_fds(
+        &self,
+        dst: &mut IoSequence,
+        peek: bool,
+        non_blocking: bool,
+        control_len: usize,
+        src_addr_and_len: Option<(Addr, Addr)>,
+        ctx: &dyn Context,
+    ) -> SysResult<(usize, Vec<RawFd>, i32)> {
+        let fd = match *self {
+            Self::UnixStream(fd) | Self::UnixDatagram(fd) | Self::UnixSeqpacket(fd) if !peek => {
+                fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?
+            }
+            _ => {
+                return self
+                    .recv_msg(dst, peek, non_blocking, src_addr_and_len, ctx)
+                    .map(|n| (n, Vec::new(), 0))
+            }
+        };
+        let (n, addr, fds, msg_flags) = unix::recv_with_fds(fd, dst, non_blocking, control_len)?;
+        if let (Self::UnixDatagram(_), Some(s)) = (self, src_addr_and_len) {
+            if let Err(err) = copy_out_socket_addr(&addr, s, ctx) {
+                for fd in fds {
+                    unsafe { libc::close(fd) };
+                }
+                return Err(err);
+            }
+        }
+        Ok((n, fds, msg_flags))
+    }
+
     pub fn write_socket_addr(
         &self,
         endpoint: IpEndpoint,
         addr_and_len: (Addr, Addr),
         ctx: &dyn Context,
     ) -> SysResult<()> {
-        let (addr, len) = addr_and_len;
         match endpoint.addr {
             IpAddress::Ipv4(ipv4) => {
                 let s_addr = {
@@ -367,18 +711,26 @@ impl Socket {
                         std::mem::size_of::<libc::sockaddr_in>(),
                     )
                 };
-                let mut orig_src_len = [0; 4];
-                ctx.copy_in_bytes(len, &mut orig_src_len)?;
-                let orig_src_len = u32::from_le_bytes(orig_src_len) as usize;
-                let src_bytes_len = src_bytes.len();
-                ctx.copy_out_bytes(
-                    addr,
-                    &src_bytes[..std::cmp::min(src_bytes_len, orig_src_len)],
-                )?;
-                ctx.copy_out_bytes(len, &(src_bytes_len as u32).to_le_bytes())?;
-                Ok(())
+                copy_out_socket_addr(src_bytes, addr_and_len, ctx)
+            }
+            IpAddress::Ipv6(ipv6) => {
+                let mut sin6_addr = libc::in6_addr { s6_addr: [0; 16] };
+                sin6_addr.s6_addr.copy_from_slice(ipv6.as_bytes());
+                let sockaddr = libc::sockaddr_in6 {
+                    sin6_family: libc::AF_INET6 as u16,
+                    sin6_port: endpoint.port.swap_bytes(),
+                    sin6_flowinfo: 0,
+                    sin6_addr,
+                    sin6_scope_id: 0,
+                };
+                let src_bytes = unsafe {
+                    std::slice::from_raw_parts(
+                        &sockaddr as *const _ as *const u8,
+                        std::mem::size_of::<libc::sockaddr_in6>(),
+                    )
+                };
+                copy_out_socket_addr(src_bytes, addr_and_len, ctx)
             }
-            IpAddress::Ipv6(_) => todo!("writing out ipv6"),
             _ => {
                 logger::warn!("remote address unspecified?: {:?}", endpoint.addr);
                 bail_libc!(libc::EINVAL)
@@ -386,15 +738,21 @@ impl Socket {
         }
     }
 
+    // FIONBIO never reaches here: the generic ioctl(2) handler turns it into the file's
+    // O_NONBLOCK flag, which callers pass down to send_msg and recv_msg.
     pub fn ioctl(&self, regs: &libc::user_regs_struct, ctx: &dyn Context) -> SysResult<usize> {
         match regs.rsi {
-            libc::TIOCINQ => {
+            // TIOCINQ is an alias of FIONREAD.
+            libc::FIONREAD => {
                 let amount = self.recv_packet_size(ctx)?;
                 let amount = std::cmp::min(amount, i32::MAX as usize) as i32;
                 ctx.copy_out_bytes(Addr(regs.rdx), &amount.to_le_bytes())?;
                 Ok(0)
             }
-            _ => todo!(),
+            _ => {
+                logger::warn!("ioctl request {:#x} is not supported on sockets", regs.rsi);
+                err_libc!(libc::EINVAL)
+            }
         }
     }
 
@@ -410,20 +768,66 @@ impl Socket {
                 let socket = iface.get_socket::<UdpSocket>(handle);
                 match socket.peek() {
                     Ok((n, _)) => Ok(n.len()),
+                    Err(smoltcp::Error::Exhausted) => Ok(0),
                     Err(err) => Err(SysError::from_smoltcp_error(err)),
                 }
             }
-            _ => todo!(),
+            _ => err_libc!(libc::EINVAL),
         }
     }
 
     pub fn set_sock_opt_socket(
-        &self,
+        &mut self,
         name: i32,
         optval: &[u8],
         ctx: &dyn Context,
     ) -> SysResult<()> {
         match *self {
+            Self::Tcp {
+                ref mut reuse_addr,
+                ref mut reuse_port,
+                ..
+            }
+            | Self::Udp {
+                ref mut reuse_addr,
+                ref mut reuse_port,
+                ..
+            } if name == libc::SO_REUSEADDR || name == libc::SO_REUSEPORT => {
+                if optval.len() < 4 {
+                    bail_libc!(libc::EINVAL);
+                }
+                let v = u32::from_le_bytes([optval[0], optval[1], optval[2], optval[3]]);
+                if name == libc::SO_REUSEADDR {
+                    *reuse_addr = v != 0;
+                } else {
+                    *reuse_port = v != 0;
+                }
+                Ok(())
+            }
+            Self::Tcp {
+                handle,
+                ref backlog,
+                ref mut buffer_sizes,
+                ..
+            } if name == libc::SO_SNDBUF || name == libc::SO_RCVBUF => {
+                let (send_size, recv_size) = socket_buffer_sizes(name, optval)?;
+                buffer_sizes.0 = send_size.or(buffer_sizes.0);
+                buffer_sizes.1 = recv_size.or(buffer_sizes.1);
+                for &h in std::iter::once(&handle).chain(backlog.iter()) {
+                    tcp::resize_buffers(h, buffer_sizes.0, buffer_sizes.1, ctx)?;
+                }
+                Ok(())
+            }
+            Self::Udp {
+                handle,
+                ref mut buffer_sizes,
+                ..
+            } if name == libc::SO_SNDBUF || name == libc::SO_RCVBUF => {
+                let (send_size, recv_size) = socket_buffer_sizes(name, optval)?;
+                buffer_sizes.0 = send_size.or(buffer_sizes.0);
+                buffer_sizes.1 = recv_size.or(buffer_sizes.1);
+                udp::resize_buffers(handle, buffer_sizes.0, buffer_sizes.1, ctx)
+            }
             Self::Tcp { handle, .. } => {
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<TcpSocket>(handle);
@@ -450,17 +854,26 @@ impl Socket {
                     }
                 }
             }
+            Self::Udp {
+                ref mut broadcast, ..
+            } if name == libc::SO_BROADCAST => {
+                if optval.len() < 4 {
+                    bail_libc!(libc::EINVAL);
+                }
+                let v = u32::from_le_bytes([optval[0], optval[1], optval[2], optval[3]]);
+                *broadcast = v != 0;
+                Ok(())
+            }
             Self::Udp { .. } => {
                 logger::warn!("Nothing to do for setsockopt on UDP socket for now..");
                 Ok(())
             }
-            Self::Icmp(_) => {
+            Self::Icmp { .. } => {
                 logger::warn!("Nothing to do for setsockopt on ICMP socket for now..");
                 Ok(())
             }
-            Self::UnixStream(fd) => {
-                let fd = fd.expect("File descriptor for UnixStream is not set.");
-                let sock = unsafe { UnixStream::from_raw_fd(fd) };
+            Self::UnixStream(Some(fd)) => {
+                let sock = ManuallyDrop::new(unsafe { UnixStream::from_raw_fd(fd) });
                 match name {
                     libc::SO_PASSCRED => {
                         if optval.len() < 4 {
@@ -469,32 +882,12 @@ impl Socket {
                         let v = u32::from_le_bytes([optval[0], optval[1], optval[2], optval[3]]);
                         sock.set_passcred(v != 0).map_err(SysError::from_io_error)
                     }
-                    libc::SO_RCVTIMEO => {
-                        if optval.len() < std::mem::size_of::<libc::timeval>() {
-                            bail_libc!(libc::EINVAL);
-                        }
-                        let timeval = unsafe { *(optval.as_ptr() as *const libc::timeval) };
-                        if timeval.tv_usec < 0 || timeval.tv_usec >= 1_000_000 {
-                            bail_libc!(libc::EDOM);
-                        }
-                        let d_usec = Duration::from_micros(timeval.tv_usec as u64);
-                        let d_sec = Duration::from_micros(timeval.tv_sec as u64);
-                        sock.set_read_timeout(Some(d_usec + d_sec))
-                            .map_err(SysError::from_io_error)
-                    }
-                    libc::SO_SNDTIMEO => {
-                        if optval.len() < std::mem::size_of::<libc::timeval>() {
-                            bail_libc!(libc::EINVAL);
-                        }
-                        let timeval = unsafe { *(optval.as_ptr() as *const libc::timeval) };
-                        if timeval.tv_usec < 0 || timeval.tv_usec >= 1_000_000 {
-                            bail_libc!(libc::EDOM);
-                        }
-                        let d_usec = Duration::from_micros(timeval.tv_usec as u64);
-                        let d_sec = Duration::from_micros(timeval.tv_sec as u64);
-                        sock.set_write_timeout(Some(d_usec + d_sec))
-                            .map_err(SysError::from_io_error)
-                    }
+                    libc::SO_RCVTIMEO => sock
+                        .set_read_timeout(parse_timeout(optval)?)
+                        .map_err(SysError::from_io_error),
+                    libc::SO_SNDTIMEO => sock
+                        .set_write_timeout(parse_timeout(optval)?)
+                        .map_err(SysError::from_io_error),
                     _ => {
                         logger::warn!(
                             "Socket option {} is not yet implemented for UnixStream. Ignoring for now.",
@@ -504,9 +897,8 @@ impl Socket {
                     }
                 }
             }
-            Self::UnixDatagram(fd) => {
-                let fd = fd.expect("File descriptor for UnixDatagram is not set.");
-                let sock = unsafe { UnixDatagram::from_raw_fd(fd) };
+            Self::UnixDatagram(Some(fd)) => {
+                let sock = ManuallyDrop::new(unsafe { UnixDatagram::from_raw_fd(fd) });
                 match name {
                     libc::SO_PASSCRED => {
                         if optval.len() < 4 {
@@ -515,32 +907,12 @@ impl Socket {
                         let v = u32::from_le_bytes([optval[0], optval[1], optval[2], optval[3]]);
                         sock.set_passcred(v != 0).map_err(SysError::from_io_error)
                     }
-                    libc::SO_RCVTIMEO => {
-                        if optval.len() < std::mem::size_of::<libc::timeval>() {
-                            bail_libc!(libc::EINVAL);
-                        }
-                        let timeval = unsafe { *(optval.as_ptr() as *const libc::timeval) };
-                        if timeval.tv_usec < 0 || timeval.tv_usec >= 1_000_000 {
-                            bail_libc!(libc::EDOM);
-                        }
-                        let d_usec = Duration::from_micros(timeval.tv_usec as u64);
-                        let d_sec = Duration::from_micros(timeval.tv_sec as u64);
-                        sock.set_read_timeout(Some(d_usec + d_sec))
-                            .map_err(SysError::from_io_error)
-                    }
-                    libc::SO_SNDTIMEO => {
-                        if optval.len() < std::mem::size_of::<libc::timeval>() {
-                            bail_libc!(libc::EINVAL);
-                        }
-                        let timeval = unsafe { *(optval.as_ptr() as *const libc::timeval) };
-                        if timeval.tv_usec < 0 || timeval.tv_usec >= 1_000_000 {
-                            bail_libc!(libc::EDOM);
-                        }
-                        let d_usec = Duration::from_micros(timeval.tv_usec as u64);
-                        let d_sec = Duration::from_micros(timeval.tv_sec as u64);
-                        sock.set_write_timeout(Some(d_usec + d_sec))
-                            .map_err(SysError::from_io_error)
-                    }
+                    libc::SO_RCVTIMEO => sock
+                        .set_read_timeout(parse_timeout(optval)?)
+                        .map_err(SysError::from_io_error),
+                    libc::SO_SNDTIMEO => sock
+                        .set_write_timeout(parse_timeout(optval)?)
+                        .map_err(SysError::from_io_error),
                     _ => {
                         logger::warn!(
                             "Socket option {} is not yet implemented for UnixDatagram. Ignoring for now.",
@@ -550,6 +922,16 @@ impl Socket {
                     }
                 }
             }
+            Self::UnixSeqpacket(Some(fd)) => unix::set_sock_opt(fd, name, optval),
+            // A UnixStream gets its host socket on connect(2), so there is nothing to set yet.
+            Self::UnixStream(None) | Self::UnixDatagram(None) | Self::UnixSeqpacket(None) => {
+                logger::warn!(
+                    "Socket option {} on an unconnected Unix socket. Ignoring for now.",
+                    name
+                );
+                Ok(())
+            }
+            Self::Closed => err_libc!(libc::EBADF),
         }
     }
 
@@ -642,7 +1024,16 @@ impl Socket {
                     }
                 }
             }
-            Self::Udp { handle, .. } => {
+            Self::Udp {
+                handle,
+                ref multicast_groups,
+                ..
+            } => {
+                if name == libc::IP_ADD_MEMBERSHIP || name == libc::IP_DROP_MEMBERSHIP {
+                    let group = IpAddress::Ipv4(parse_ip_mreq(optval)?);
+                    let join = name == libc::IP_ADD_MEMBERSHIP;
+                    return set_multicast_membership(multicast_groups, group, join, ctx);
+                }
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<UdpSocket>(handle);
                 match name {
@@ -711,7 +1102,16 @@ impl Socket {
                     }
                 }
             }
-            Self::Udp { handle, .. } => {
+            Self::Udp {
+                handle,
+                ref multicast_groups,
+                ..
+            } => {
+                if name == libc::IPV6_ADD_MEMBERSHIP || name == libc::IPV6_DROP_MEMBERSHIP {
+                    let group = IpAddress::Ipv6(parse_ipv6_mreq(optval)?);
+                    let join = name == libc::IPV6_ADD_MEMBERSHIP;
+                    return set_multicast_membership(multicast_groups, group, join, ctx);
+                }
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<UdpSocket>(handle);
                 match name {
@@ -749,13 +1149,62 @@ impl Socket {
     }
 
     pub fn get_sock_opt_socket(
-        &self,
+        &mut self,
         name: i32,
         optlen: u32,
         ctx: &dyn Context,
     ) -> SysResult<Vec<u8>> {
         match *self {
-            Self::Tcp { handle, .. } => {
+            Self::Tcp {
+                reuse_addr,
+                reuse_port,
+                ..
+            }
+            | Self::Udp {
+                reuse_addr,
+                reuse_port,
+                ..
+            } if name == libc::SO_REUSEADDR || name == libc::SO_REUSEPORT => {
+                if optlen < 4 {
+                    bail_libc!(libc::EINVAL);
+                }
+                let v = if name == libc::SO_REUSEADDR {
+                    reuse_addr
+                } else {
+                    reuse_port
+                };
+                Ok((v as i32).to_le_bytes().to_vec())
+            }
+            // Reading SO_ERROR clears the pending error, as on Linux.
+            Self::Tcp {
+                handle,
+                ref mut connect_pending,
+                ..
+            } if name == libc::SO_ERROR => {
+                if optlen < 4 {
+                    bail_libc!(libc::EINVAL);
+                }
+                if !*connect_pending {
+                    return Ok(0i32.to_le_bytes().to_vec());
+                }
+                ctx.poll_wait(true);
+                let mut iface = ctx.network_interface_mut();
+                let state = iface.get_socket::<TcpSocket>(handle).state();
+                // While the handshake is still in flight there is nothing to report yet.
+                let err = match tcp::connect_outcome(state) {
+                    None => 0,
+                    Some(res) => {
+                        *connect_pending = false;
+                        res.err().map_or(0, |e| e.code())
+                    }
+                };
+                Ok(err.to_le_bytes().to_vec())
+            }
+            Self::Tcp {
+                handle,
+                buffer_sizes,
+                ..
+            } => {
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<TcpSocket>(handle);
                 match name {
@@ -770,14 +1219,16 @@ impl Socket {
                         if optlen < 4 {
                             bail_libc!(libc::EINVAL);
                         }
-                        let size = std::cmp::min(socket.send_capacity(), i32::MAX as usize) as i32;
+                        let size = buffer_sizes.0.unwrap_or_else(|| socket.send_capacity());
+                        let size = std::cmp::min(size, i32::MAX as usize) as i32;
                         Ok(size.to_le_bytes().to_vec())
                     }
                     libc::SO_RCVBUF => {
                         if optlen < 4 {
                             bail_libc!(libc::EINVAL);
                         }
-                        let size = std::cmp::min(socket.recv_capacity(), i32::MAX as usize) as i32;
+                        let size = buffer_sizes.1.unwrap_or_else(|| socket.recv_capacity());
+                        let size = std::cmp::min(size, i32::MAX as usize) as i32;
                         Ok(size.to_le_bytes().to_vec())
                     }
                     _ => {
@@ -789,24 +1240,45 @@ impl Socket {
                     }
                 }
             }
-            Self::Udp { handle, .. } => {
+            // Timeouts aren't supported on Ip sockets yet, so they never have one.
+            Self::Tcp { .. } | Self::Udp { .. }
+                if name == libc::SO_RCVTIMEO || name == libc::SO_SNDTIMEO =>
+            {
+                timeout_bytes(None, optlen)
+            }
+            Self::Udp {
+                handle,
+                broadcast,
+                buffer_sizes,
+                ..
+            } => {
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<UdpSocket>(handle);
                 match name {
+                    libc::SO_BROADCAST => {
+                        if optlen < 4 {
+                            bail_libc!(libc::EINVAL);
+                        }
+                        Ok((broadcast as i32).to_le_bytes().to_vec())
+                    }
                     libc::SO_SNDBUF => {
                         if optlen < 4 {
                             bail_libc!(libc::EINVAL);
                         }
-                        let size =
-                            std::cmp::min(socket.payload_send_capacity(), i32::MAX as usize) as i32;
+                        let size = buffer_sizes
+                            .0
+                            .unwrap_or_else(|| socket.payload_send_capacity());
+                        let size = std::cmp::min(size, i32::MAX as usize) as i32;
                         Ok(size.to_le_bytes().to_vec())
                     }
                     libc::SO_RCVBUF => {
                         if optlen < 4 {
                             bail_libc!(libc::EINVAL);
                         }
-                        let size =
-                            std::cmp::min(socket.payload_recv_capacity(), i32::MAX as usize) as i32;
+                        let size = buffer_sizes
+                            .1
+                            .unwrap_or_else(|| socket.payload_recv_capacity());
+                        let size = std::cmp::min(size, i32::MAX as usize) as i32;
                         Ok(size.to_le_bytes().to_vec())
                     }
                     _ => {
@@ -818,7 +1290,46 @@ impl Socket {
                     }
                 }
             }
-            _ => todo!("get_sock_opt_socket"),
+            Self::UnixStream(Some(fd))
+                if name == libc::SO_RCVTIMEO || name == libc::SO_SNDTIMEO =>
+            {
+                let sock = ManuallyDrop::new(unsafe { UnixStream::from_raw_fd(fd) });
+                let timeout = if name == libc::SO_RCVTIMEO {
+                    sock.read_timeout()
+                } else {
+                    sock.write_timeout()
+                };
+                timeout_bytes(timeout.map_err(SysError::from_io_error)?, optlen)
+            }
+            Self::UnixDatagram(Some(fd))
+                if name == libc::SO_RCVTIMEO || name == libc::SO_SNDTIMEO =>
+            {
+                let sock = ManuallyDrop::new(unsafe { UnixDatagram::from_raw_fd(fd) });
+                let timeout = if name == libc::SO_RCVTIMEO {
+                    sock.read_timeout()
+                } else {
+                    sock.write_timeout()
+                };
+                timeout_bytes(timeout.map_err(SysError::from_io_error)?, optlen)
+            }
+            Self::UnixStream(Some(fd))
+            | Self::UnixDatagram(Some(fd))
+            | Self::UnixSeqpacket(Some(fd)) => unix::get_sock_opt(fd, name, optlen),
+            // A UnixStream gets its host socket on connect(2), and has no timeouts until then.
+            Self::UnixStream(None) if name == libc::SO_RCVTIMEO || name == libc::SO_SNDTIMEO => {
+                timeout_bytes(None, optlen)
+            }
+            Self::Icmp { .. }
+            | Self::UnixStream(None)
+            | Self::UnixDatagram(None)
+            | Self::UnixSeqpacket(None) => {
+                logger::warn!(
+                    "Socket option {} is not yet implemented. Reporting ENOPROTOOPT.",
+                    name
+                );
+                err_libc!(libc::ENOPROTOOPT)
+            }
+            Self::Closed => err_libc!(libc::EBADF),
         }
     }
 
@@ -829,6 +1340,18 @@ impl Socket {
         ctx: &dyn Context,
     ) -> SysResult<Vec<u8>> {
         match *self {
+            Self::Tcp { handle, .. } if name == libc::TCP_INFO => {
+                let info = tcp::info(handle, ctx);
+                let bytes = unsafe {
+                    std::slice::from_raw_parts(
+                        &info as *const _ as *const u8,
+                        std::mem::size_of::<linux::TcpInfo>(),
+                    )
+                };
+                // Like linux, a shorter buffer gets the head of the structure.
+                let n = bytes.len().min(optlen as usize);
+                Ok(bytes[..n].to_vec())
+            }
             Self::Tcp { handle, .. } => {
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<TcpSocket>(handle);
@@ -978,29 +1501,72 @@ impl Socket {
         }
     }
 
-    pub fn local_endpoint(&self, ctx: &dyn Context) -> IpEndpoint {
+    // default_protocol returns the protocol a socket created with protocol 0 ends up using, as
+    // reported by SO_PROTOCOL.
+    pub fn default_protocol(&self) -> i32 {
+        match *self {
+            Self::Tcp { .. } => libc::IPPROTO_TCP,
+            Self::Udp { .. } => libc::IPPROTO_UDP,
+            _ => 0,
+        }
+    }
+
+    pub fn local_endpoint(&self, ctx: &dyn Context) -> SysResult<IpEndpoint> {
         match *self {
-            Self::Tcp { local_endpoint, .. } => local_endpoint,
+            Self::Tcp {
+                handle,
+                local_endpoint,
+                ..
+            } => {
+                // A connected socket reports the source address smoltcp picked for it.
+                let mut iface = ctx.network_interface_mut();
+                let socket = iface.get_socket::<TcpSocket>(handle);
+                if socket.is_active() {
+                    Ok(socket.local_endpoint())
+                } else {
+                    Ok(local_endpoint)
+                }
+            }
             Self::Udp { handle, .. } => {
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<UdpSocket>(handle);
-                socket.endpoint()
+                if socket.is_open() {
+                    Ok(socket.endpoint())
+                } else {
+                    Ok(IpEndpoint::UNSPECIFIED)
+                }
             }
-            _ => todo!("local_endpoint"),
+            // Like linux ping sockets, the echo identifier is reported as the port.
+            Self::Icmp { ident, .. } => Ok(IpEndpoint {
+                addr: IpAddress::Unspecified,
+                port: ident.unwrap_or(0),
+            }),
+            Self::UnixDatagram(_) | Self::UnixStream(_) | Self::UnixSeqpacket(_) => {
+                err_libc!(libc::EOPNOTSUPP)
+            }
+            Self::Closed => err_libc!(libc::EBADF),
         }
     }
 
-    pub fn remote_endpoint(&self, ctx: &dyn Context) -> Option<IpEndpoint> {
+    pub fn remote_endpoint(&self, ctx: &dyn Context) -> SysResult<IpEndpoint> {
         match *self {
             Self::Tcp { handle, .. } => {
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<TcpSocket>(handle);
-                Some(socket.remote_endpoint())
+                Ok(socket.remote_endpoint())
             }
             Self::Udp {
-                default_endpoint, ..
-            } => default_endpoint,
-            _ => todo!("local_endpoint"),
+                default_endpoint,
+                ref last_peer,
+                ..
+            } => default_endpoint
+                .or_else(|| last_peer.get())
+                .ok_or_else(|| SysError::new(libc::ENOTCONN)),
+            Self::Icmp { .. } => err_libc!(libc::ENOTCONN),
+            Self::UnixDatagram(_) | Self::UnixStream(_) | Self::UnixSeqpacket(_) => {
+                err_libc!(libc::EOPNOTSUPP)
+            }
+            Self::Closed => err_libc!(libc::EBADF),
         }
     }
 
@@ -1015,6 +1581,9 @@ impl Socket {
             Self::Udp {
                 handle,
                 default_endpoint,
+                broadcast,
+                buffer_sizes,
+                ..
             } => {
                 let mut iface = ctx.network_interface_mut();
                 let socket = iface.get_socket::<UdpSocket>(handle);
@@ -1023,73 +1592,393 @@ impl Socket {
                 } else {
                     default_endpoint.ok_or_else(|| SysError::new(libc::EINVAL))?
                 };
-                udp::send(handle, src, non_blocking, endpoint, ctx)
+                drop(iface);
+                let n = udp::send(handle, src, non_blocking, endpoint, broadcast, ctx)?;
+                udp::resize_buffers(handle, buffer_sizes.0, buffer_sizes.1, ctx)?;
+                Ok(n)
+            }
+            Self::UnixStream(fd) | Self::UnixDatagram(fd) | Self::UnixSeqpacket(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                unix::send(fd, src, non_blocking, None)
+            }
+            // Ping sockets can't be connected, so a write has no destination.
+            Self::Icmp { .. } => err_libc!(libc::EDESTADDRREQ),
+            Self::Closed => err_libc!(libc::EBADF),
+        }
+    }
+
+    pub fn shutdown(&self, how: i32, ctx: &dyn Context) -> SysResult<()> {
+        let how = match how {
+            libc::SHUT_RD => Shutdown::Read,
+            libc::SHUT_WR => Shutdown::Write,
+            libc::SHUT_RDWR => Shutdown::Both,
+            _ => bail_libc!(libc::EINVAL),
+        };
+        match *self {
+            Self::Tcp { handle, .. } => {
+                {
+                    let mut iface = ctx.network_interface_mut();
+                    let socket = iface.get_socket::<TcpSocket>(handle);
+                    if !socket.is_active() {
+                        bail_libc!(libc::ENOTCONN);
+                    }
+                    // smoltcp can't stop receiving without closing, so SHUT_RD alone is a no-op.
+                    if how != Shutdown::Read {
+                        socket.close();
+                    }
+                }
+                // Send out the FIN right away.
+                ctx.poll_wait(true);
+                Ok(())
             }
-            _ => todo!("write to socket"),
+            Self::Udp {
+                default_endpoint, ..
+            } => match default_endpoint {
+                Some(_) => Ok(()),
+                None => err_libc!(libc::ENOTCONN),
+            },
+            Self::Icmp { .. } => err_libc!(libc::ENOTCONN),
+            Self::UnixStream(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                // Borrow the fd without closing it on drop.
+                let socket = ManuallyDrop::new(unsafe { UnixStream::from_raw_fd(fd) });
+                socket.shutdown(how).map_err(SysError::from_io_error)
+            }
+            Self::UnixDatagram(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                let socket = ManuallyDrop::new(unsafe { UnixDatagram::from_raw_fd(fd) });
+                socket.shutdown(how).map_err(SysError::from_io_error)
+            }
+            Self::UnixSeqpacket(fd) => {
+                let fd = fd.ok_or_else(|| SysError::new(libc::ENOTCONN))?;
+                // UnixStream::shutdown is a plain shutdown(2), which works for any socket type.
+                let socket = ManuallyDrop::new(unsafe { UnixStream::from_raw_fd(fd) });
+                socket.shutdown(how).map_err(SysError::from_io_error)
+            }
+            Self::Closed => err_libc!(libc::EBADF),
         }
     }
 
-    pub fn listen(&mut self, ctx: &dyn Context) -> SysResult<()> {
+    // close releases what the socket holds: smoltcp sockets are removed from the interface along
+    // with the ports they use, and host sockets are closed. Closing a closed socket does nothing.
+    pub fn close(&mut self, ctx: &dyn Context) {
+        match std::mem::replace(self, Self::Closed) {
+            Self::Tcp {
+                handle,
+                local_endpoint,
+                backlog,
+                accepted,
+                ..
+            } => {
+                let handles: Vec<_> = std::iter::once(handle).chain(backlog).collect();
+                {
+                    let mut iface = ctx.network_interface_mut();
+                    for &h in handles.iter() {
+                        iface.get_socket::<TcpSocket>(h).close();
+                    }
+                }
+                // Send out the FIN before the socket goes away. Unlike linux, data still queued
+                // after that is dropped rather than lingering.
+                ctx.poll_wait(true);
+                let mut iface = ctx.network_interface_mut();
+                for h in handles {
+                    iface.remove_socket(h);
+                }
+                drop(iface);
+                if !accepted {
+                    release_port(libc::IPPROTO_TCP, local_endpoint.port, ctx);
+                }
+            }
+            Self::Udp {
+                handle,
+                multicast_groups,
+                ..
+            } => {
+                for group in multicast_groups.into_inner() {
+                    ctx.leave_multicast_group(group);
+                }
+                let mut iface = ctx.network_interface_mut();
+                let socket = iface.get_socket::<UdpSocket>(handle);
+                let port = socket.endpoint().port;
+                socket.close();
+                iface.remove_socket(handle);
+                drop(iface);
+                release_port(libc::IPPROTO_UDP, port, ctx);
+            }
+            Self::Icmp { handle, .. } => {
+                ctx.network_interface_mut().remove_socket(handle);
+            }
+            Self::UnixDatagram(fd) | Self::UnixStream(fd) | Self::UnixSeqpacket(fd) => {
+                if let Some(fd) = fd {
+                    unsafe { libc::close(fd) };
+                }
+            }
+            Self::Closed => (),
+        }
+    }
+
+    pub fn listen(&mut self, backlog: i32, ctx: &dyn Context) -> SysResult<()> {
         match self {
             &mut Self::Tcp {
                 handle,
                 ref mut local_endpoint,
+                ref mut listening,
+                backlog: ref mut pending,
+                buffer_sizes,
+                ..
             } => {
-                if !local_endpoint.is_specified() {
-                    *local_endpoint = IpEndpoint::from(ctx.gen_local_port());
+                // listen(2) on a listening socket keeps its current backlog.
+                if *listening {
+                    return Ok(());
+                }
+                if local_endpoint.port == 0 {
+                    local_endpoint.port = ctx.gen_local_port();
+                }
+                let n = backlog.clamp(1, MAX_LISTEN_BACKLOG as i32) as usize;
+                let handles: Vec<_> = std::iter::once(handle)
+                    .chain((1..n).map(|_| tcp::new_socket(ctx)))
+                    .collect();
+                for &h in handles[1..].iter() {
+                    tcp::resize_buffers(h, buffer_sizes.0, buffer_sizes.1, ctx)?;
                 }
                 let mut iface = ctx.network_interface_mut();
-                let socket = iface.get_socket::<TcpSocket>(handle);
-                socket
-                    .listen(local_endpoint.port)
-                    .map_err(SysError::from_smoltcp_error)
+                for &h in handles.iter() {
+                    let socket = iface.get_socket::<TcpSocket>(h);
+                    socket
+                        .listen(local_endpoint.port)
+                        .map_err(SysError::from_smoltcp_error)?;
+                }
+                *pending = handles[1..].to_vec();
+                *listening = true;
+                Ok(())
             }
             _ => err_libc!(libc::EOPNOTSUPP),
         }
     }
+
+    // accept takes an established connection off a listening socket, blocking until one arrives
+    // unless non_blocking is set. The listening socket that received the connection is replaced
+    // with a new one, so the listener keeps accepting the same number of pending connections.
+    pub fn accept(
+        &mut self,
+        non_blocking: bool,
+        ctx: &dyn Context,
+    ) -> SysResult<(Self, IpEndpoint)> {
+        let (handle, local_endpoint, backlog, buffer_sizes) = match self {
+            Self::Tcp {
+                handle,
+                local_endpoint,
+                listening: true,
+                backlog,
+                buffer_sizes,
+                ..
+            } => (handle, *local_endpoint, backlog, *buffer_sizes),
+            Self::Tcp { .. } => bail_libc!(libc::EINVAL),
+            _ => bail_libc!(libc::EOPNOTSUPP),
+        };
+
+        // Like recv, the interface is polled once before a non-blocking accept gives up, and only
+        // a blocking one waits for activity.
+        let mut once = true;
+        let (accepted, remote_endpoint) = loop {
+            ctx.poll_wait(once);
+            once = false;
+            let mut iface = ctx.network_interface_mut();
+            let established = std::iter::once(*handle)
+                .chain(backlog.iter().copied())
+                .find_map(|h| {
+                    let socket = iface.get_socket::<TcpSocket>(h);
+                    if tcp::accept_ready(socket.state()) {
+                        Some((h, socket.remote_endpoint()))
+                    } else {
+                        None
+                    }
+                });
+            if let Some(established) = established {
+                break established;
+            }
+            if non_blocking {
+                bail_libc!(libc::EWOULDBLOCK);
+            }
+        };
+
+        let replacement = tcp::new_socket(ctx);
+        tcp::resize_buffers(replacement, buffer_sizes.0, buffer_sizes.1, ctx)?;
+        {
+            let mut iface = ctx.network_interface_mut();
+            let socket = iface.get_socket::<TcpSocket>(replacement);
+            socket
+                .listen(local_endpoint.port)
+                .map_err(SysError::from_smoltcp_error)?;
+        }
+        if accepted == *handle {
+            *handle = replacement;
+        } else if let Some(h) = backlog.iter_mut().find(|h| **h == accepted) {
+            *h = replacement;
+        }
+
+        let socket = Self::Tcp {
+            handle: accepted,
+            local_endpoint,
+            listening: false,
+            backlog: Vec::new(),
+            reuse_addr: false,
+            reuse_port: false,
+            connect_pending: false,
+            accepted: true,
+            buffer_sizes,
+        };
+        Ok((socket, remote_endpoint))
+    }
 }
 
-// impl Drop for Socket {
-//     fn drop(&mut self) {
-//         match *self {
-//             Self::Tcp { handle, .. } => {
-//                 let ctx = crate::context::context();
-//                 let mut iface = ctx.network_interface_mut();
-//                 let socket = iface.get_socket::<TcpSocket>(handle);
-//                 let port = socket.remote_endpoint().port;
-//                 ctx.remove_local_port(port);
-//                 socket.abort();
-//                 iface.remove_socket(handle);
-//             }
-//             Self::Udp { handle, .. } => {
-//                 let ctx = crate::context::context();
-//                 let mut iface = ctx.network_interface_mut();
-//                 let socket = iface.get_socket::<UdpSocket>(handle);
-//                 let port = socket.endpoint().port;
-//                 ctx.remove_local_port(port);
-//                 socket.close();
-//                 iface.remove_socket(handle);
-//             }
-//             Self::Icmp(handle) => {
-//                 let ctx = crate::context::context();
-//                 let mut iface = ctx.network_interface_mut();
-//                 iface.remove_socket(handle);
-//             }
-//             Self::UnixDatagram(fd) => {
-//                 if let Some(fd) = fd {
-//                     let socket = unsafe { UnixDatagram::from_raw_fd(fd) };
-//                     socket.shutdown(Shutdown::Both).unwrap();
-//                 }
-//             }
-//             Self::UnixStream(fd) => {
-//                 if let Some(fd) = fd {
-//                     let socket = unsafe { UnixStream::from_raw_fd(fd) };
-//                     socket.shutdown(Shutdown::Both).unwrap();
-//                 }
-//             }
-//         }
-//     }
-// }
+// copy_out_socket_addr writes the socket address to addr, truncated to the buffer length the
+// caller stored at len, and then stores the full length of the address at len.
+fn copy_out_socket_addr(
+    src_bytes: &[u8],
+    addr_and_len: (Addr, Addr),
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    let (addr, len) = addr_and_len;
+    let mut orig_src_len = [0; 4];
+    ctx.copy_in_bytes(len, &mut orig_src_len)?;
+    let orig_src_len = u32::from_le_bytes(orig_src_len) as usize;
+    let src_bytes_len = src_bytes.len();
+    ctx.copy_out_bytes(
+        addr,
+        &src_bytes[..std::cmp::min(src_bytes_len, orig_src_len)],
+    )?;
+    ctx.copy_out_bytes(len, &(src_bytes_len as u32).to_le_bytes())?;
+    Ok(())
+}
+
+// bind_port marks the local port as bound for the protocol, failing with EADDRINUSE if it's
+// already bound unless the socket has SO_REUSEADDR or SO_REUSEPORT set.
+fn bind_port(protocol: i32, port: u16, reuse: bool, ctx: &dyn Context) -> SysResult<()> {
+    if port == 0 {
+        return Ok(());
+    }
+    if !ctx.bind_local_port(protocol, port, reuse) {
+        bail_libc!(libc::EADDRINUSE);
+    }
+    Ok(())
+}
+
+// release_port drops one binding of port, and makes it available again to bind(2) and to
+// ephemeral port allocation once no socket is bound to it.
+fn release_port(protocol: i32, port: u16, ctx: &dyn Context) {
+    if port == 0 {
+        return;
+    }
+    ctx.unbind_local_port(protocol, port);
+    ctx.remove_local_port(port);
+}
+
+// parse_ip_mreq returns the group of the ip_mreq or ip_mreqn given to IP_ADD_MEMBERSHIP and
+// IP_DROP_MEMBERSHIP.
+fn parse_ip_mreq(optval: &[u8]) -> SysResult<Ipv4Address> {
+    if optval.len() < std::mem::size_of::<libc::ip_mreq>() {
+        bail_libc!(libc::EINVAL);
+    }
+    let group = Ipv4Address::from_bytes(&optval[..4]);
+    if !group.is_multicast() {
+        bail_libc!(libc::EINVAL);
+    }
+    Ok(group)
+}
+
+// parse_ipv6_mreq returns the group of the ipv6_mreq given to IPV6_ADD_MEMBERSHIP and
+// IPV6_DROP_MEMBERSHIP.
+fn parse_ipv6_mreq(optval: &[u8]) -> SysResult<Ipv6Address> {
+    if optval.len() < std::mem::size_of::<libc::ipv6_mreq>() {
+        bail_libc!(libc::EINVAL);
+    }
+    let group = Ipv6Address::from_bytes(&optval[..16]);
+    if !group.is_multicast() {
+        bail_libc!(libc::EINVAL);
+    }
+    Ok(group)
+}
+
+// set_multicast_membership makes a Udp socket, whose joined groups are in groups, join or leave
+// group. Like linux, joining twice fails with EADDRINUSE and leaving a group which wasn't joined
+// fails with EADDRNOTAVAIL.
+fn set_multicast_membership(
+    groups: &RefCell<Vec<IpAddress>>,
+    group: IpAddress,
+    join: bool,
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    let mut groups = groups.borrow_mut();
+    match (join, groups.iter().position(|g| *g == group)) {
+        (true, Some(_)) => bail_libc!(libc::EADDRINUSE),
+        (true, None) => {
+            ctx.join_multicast_group(group)?;
+            groups.push(group);
+        }
+        (false, Some(i)) => {
+            groups.remove(i);
+            ctx.leave_multicast_group(group);
+        }
+        (false, None) => bail_libc!(libc::EADDRNOTAVAIL),
+    }
+    Ok(())
+}
+
+// parse_timeout parses the timeval given to SO_RCVTIMEO or SO_SNDTIMEO. A zero timeval disables
+// the timeout.
+fn parse_timeout(optval: &[u8]) -> SysResult<Option<Duration>> {
+    if optval.len() < std::mem::size_of::<libc::timeval>() {
+        bail_libc!(libc::EINVAL);
+    }
+    let timeval = unsafe { std::ptr::read_unaligned(optval.as_ptr() as *const libc::timeval) };
+    if timeval.tv_usec < 0 || timeval.tv_usec >= 1_000_000 {
+        bail_libc!(libc::EDOM);
+    }
+    // Like linux, a negative timeval doesn't block at all.
+    if timeval.tv_sec < 0 {
+        return Ok(Some(Duration::from_micros(1)));
+    }
+    let timeout =
+        Duration::from_secs(timeval.tv_sec as u64) + Duration::from_micros(timeval.tv_usec as u64);
+    Ok(Some(timeout).filter(|t| !t.is_zero()))
+}
+
+// timeout_bytes returns timeout as the timeval reported by SO_RCVTIMEO or SO_SNDTIMEO.
+fn timeout_bytes(timeout: Option<Duration>, optlen: u32) -> SysResult<Vec<u8>> {
+    if (optlen as usize) < std::mem::size_of::<libc::timeval>() {
+        bail_libc!(libc::EINVAL);
+    }
+    let timeout = timeout.unwrap_or_default();
+    let timeval = libc::timeval {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_usec: timeout.subsec_micros() as libc::suseconds_t,
+    };
+    let b = unsafe {
+        std::slice::from_raw_parts(
+            &timeval as *const _ as *const u8,
+            std::mem::size_of::<libc::timeval>(),
+        )
+    };
+    Ok(b.to_vec())
+}
+
+// socket_buffer_sizes parses an SO_SNDBUF or SO_RCVBUF value into (send, receive) buffer sizes,
+// clamped to the range Linux allows. Linux doubles the value to account for bookkeeping
+// overhead; we don't, so getsockopt reports exactly the clamped size.
+fn socket_buffer_sizes(name: i32, optval: &[u8]) -> SysResult<(Option<usize>, Option<usize>)> {
+    if optval.len() < 4 {
+        bail_libc!(libc::EINVAL);
+    }
+    let v = i32::from_le_bytes([optval[0], optval[1], optval[2], optval[3]]).max(0) as usize;
+    if name == libc::SO_SNDBUF {
+        let size = v.clamp(linux::SOCK_MIN_SNDBUF, linux::SYSCTL_WMEM_MAX);
+        Ok((Some(size), None))
+    } else {
+        let size = v.clamp(linux::SOCK_MIN_RCVBUF, linux::SYSCTL_RMEM_MAX);
+        Ok((None, Some(size)))
+    }
+}
 
 #[derive(Debug)]
 pub enum Endpoint<'a> {
@@ -1097,6 +1986,9 @@ pub enum Endpoint<'a> {
     Ip(IpEndpoint),
 }
 
+// SIN6_LEN_RFC2133 is the size of a sockaddr_in6 without its sin6_scope_id field.
+const SIN6_LEN_RFC2133: usize = 24;
+
 pub fn address_and_family(addr: &[u8]) -> SysResult<(Endpoint<'_>, u16)> {
     if addr.len() < 2 {
         bail_libc!(libc::EINVAL);
@@ -1104,6 +1996,9 @@ pub fn address_and_family(addr: &[u8]) -> SysResult<(Endpoint<'_>, u16)> {
     let dom = u16::from_le_bytes([addr[0], addr[1]]);
     match dom as i32 {
         libc::AF_INET => {
+            if addr.len() < std::mem::size_of::<libc::sockaddr_in>() {
+                bail_libc!(libc::EINVAL);
+            }
             let sock_addr = unsafe { std::ptr::read(addr.as_ptr() as *const libc::sockaddr_in) };
             let ipv4 = Ipv4Address::from_bytes(&sock_addr.sin_addr.s_addr.to_le_bytes());
             let ip_endpoint = IpEndpoint {
@@ -1113,7 +2008,14 @@ pub fn address_and_family(addr: &[u8]) -> SysResult<(Endpoint<'_>, u16)> {
             Ok((Endpoint::Ip(ip_endpoint), dom))
         }
         libc::AF_INET6 => {
-            let sock_addr = unsafe { std::ptr::read(addr.as_ptr() as *const libc::sockaddr_in6) };
+            // Like linux, the sin6_scope_id missing from the RFC 2133 layout may be left out.
+            if addr.len() < SIN6_LEN_RFC2133 {
+                bail_libc!(libc::EINVAL);
+            }
+            let mut buf = [0u8; std::mem::size_of::<libc::sockaddr_in6>()];
+            let n = addr.len().min(buf.len());
+            buf[..n].copy_from_slice(&addr[..n]);
+            let sock_addr = unsafe { std::ptr::read(buf.as_ptr() as *const libc::sockaddr_in6) };
             let ipv6 = Ipv6Address::from_bytes(&sock_addr.sin6_addr.s6_addr);
             let ip_endpoint = IpEndpoint {
                 addr: IpAddress::Ipv6(ipv6),
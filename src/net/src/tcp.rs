@@ -1,11 +1,108 @@
 use std::io::{Read, Write};
 
 use mem::IoSequence;
-use smoltcp::{iface::SocketHandle, socket::TcpSocket, wire::IpEndpoint};
+use smoltcp::{
+    iface::SocketHandle,
+    phy::Device,
+    socket::{AnySocket, TcpSocket, TcpSocketBuffer, TcpState},
+    wire::{IpAddress, IpEndpoint},
+};
 use utils::{bail_libc, SysError, SysResult};
 
 use crate::Context;
 
+pub fn new_socket(ctx: &dyn Context) -> SocketHandle {
+    let rx_buffer = TcpSocketBuffer::new(vec![0; 65536]);
+    let tx_buffer = TcpSocketBuffer::new(vec![0; 65536]);
+    let socket = TcpSocket::new(rx_buffer, tx_buffer);
+    ctx.add_socket(socket.upcast())
+}
+
+// resize_buffers replaces the socket behind handle with one whose buffers have the given sizes,
+// keeping the other options. smoltcp can't resize buffers in place, so a listening socket listens
+// again on the same port, and a socket with a connection keeps the buffers it has.
+pub fn resize_buffers(
+    handle: SocketHandle,
+    send_size: Option<usize>,
+    recv_size: Option<usize>,
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    let mut iface = ctx.network_interface_mut();
+    let socket = iface.get_socket::<TcpSocket>(handle);
+    let listen_port = match socket.state() {
+        TcpState::Closed => None,
+        TcpState::Listen => Some(socket.local_endpoint().port),
+        _ => return Ok(()),
+    };
+    let send_size = send_size.unwrap_or_else(|| socket.send_capacity());
+    let recv_size = recv_size.unwrap_or_else(|| socket.recv_capacity());
+    let mut resized = TcpSocket::new(
+        TcpSocketBuffer::new(vec![0; recv_size]),
+        TcpSocketBuffer::new(vec![0; send_size]),
+    );
+    resized.set_keep_alive(socket.keep_alive());
+    resized.set_nagle_enabled(socket.nagle_enabled());
+    resized.set_timeout(socket.timeout());
+    resized.set_hop_limit(socket.hop_limit());
+    if let Some(port) = listen_port {
+        resized.listen(port).map_err(SysError::from_smoltcp_error)?;
+    }
+    *socket = resized;
+    Ok(())
+}
+
+// connect_outcome returns the result of a connect(2) on a socket in state, or None while the
+// handshake is still in flight. A socket that went back to CLOSED was refused by the peer.
+pub fn connect_outcome(state: TcpState) -> Option<SysResult<()>> {
+    match state {
+        TcpState::SynSent | TcpState::SynReceived => None,
+        TcpState::Closed => Some(Err(SysError::new(libc::ECONNREFUSED))),
+        _ => Some(Ok(())),
+    }
+}
+
+// accept_ready reports whether a listening socket in state holds a connection accept(2) can take.
+// A connection the peer already closed its side of is still accepted, like on linux.
+pub fn accept_ready(state: TcpState) -> bool {
+    matches!(state, TcpState::Established | TcpState::CloseWait)
+}
+
+// info fills a tcp_info for getsockopt(TCP_INFO) from the state of the socket behind handle.
+// smoltcp doesn't expose the negotiated MSS, so the MSS is derived from the MTU of the interface,
+// and the fields smoltcp keeps no counterpart of are left zero.
+pub fn info(handle: SocketHandle, ctx: &dyn Context) -> linux::TcpInfo {
+    let mut iface = ctx.network_interface_mut();
+    let ip_mtu = iface.device().capabilities().ip_mtu();
+    let socket = iface.get_socket::<TcpSocket>(handle);
+    let ip_header_len = match socket.local_endpoint().addr {
+        IpAddress::Ipv6(_) => 40,
+        _ => 20,
+    };
+    let mss = ip_mtu.saturating_sub(ip_header_len + 20) as u32;
+    let state = match socket.state() {
+        TcpState::Closed => linux::TCP_CLOSE,
+        TcpState::Listen => linux::TCP_LISTEN,
+        TcpState::SynSent => linux::TCP_SYN_SENT,
+        TcpState::SynReceived => linux::TCP_SYN_RECV,
+        TcpState::Established => linux::TCP_ESTABLISHED,
+        TcpState::FinWait1 => linux::TCP_FIN_WAIT1,
+        TcpState::FinWait2 => linux::TCP_FIN_WAIT2,
+        TcpState::CloseWait => linux::TCP_CLOSE_WAIT,
+        TcpState::Closing => linux::TCP_CLOSING,
+        TcpState::LastAck => linux::TCP_LAST_ACK,
+        TcpState::TimeWait => linux::TCP_TIME_WAIT,
+    };
+    linux::TcpInfo {
+        state,
+        snd_mss: mss,
+        rcv_mss: mss,
+        advmss: mss,
+        pmtu: ip_mtu as u32,
+        rcv_space: socket.recv_capacity() as u32,
+        ..Default::default()
+    }
+}
+
 pub fn recv(
     handle: SocketHandle,
     dst: &mut IoSequence,
@@ -1,11 +1,53 @@
 use std::io::{Read, Write};
 
 use mem::IoSequence;
-use smoltcp::{iface::SocketHandle, socket::UdpSocket, wire::IpEndpoint};
+use smoltcp::{
+    iface::SocketHandle,
+    socket::{UdpPacketMetadata, UdpSocket, UdpSocketBuffer},
+    wire::{IpAddress, IpCidr, IpEndpoint},
+};
 use utils::{bail_libc, SysError, SysResult};
 
 use crate::Context;
 
+pub fn new_socket(send_size: usize, recv_size: usize) -> UdpSocket<'static> {
+    let rx_buffer = UdpSocketBuffer::new(vec![UdpPacketMetadata::EMPTY], vec![0; recv_size]);
+    let tx_buffer = UdpSocketBuffer::new(vec![UdpPacketMetadata::EMPTY], vec![0; send_size]);
+    UdpSocket::new(rx_buffer, tx_buffer)
+}
+
+// resize_buffers replaces the socket behind handle with one whose buffers have the given sizes,
+// keeping its binding. smoltcp can't resize buffers in place, so while a datagram is still queued
+// in either direction the socket is left as is, and the caller tries again once it has drained.
+pub fn resize_buffers(
+    handle: SocketHandle,
+    send_size: Option<usize>,
+    recv_size: Option<usize>,
+    ctx: &dyn Context,
+) -> SysResult<()> {
+    let mut iface = ctx.network_interface_mut();
+    let socket = iface.get_socket::<UdpSocket>(handle);
+    let send_size = send_size.unwrap_or_else(|| socket.payload_send_capacity());
+    let recv_size = recv_size.unwrap_or_else(|| socket.payload_recv_capacity());
+    if send_size == socket.payload_send_capacity() && recv_size == socket.payload_recv_capacity() {
+        return Ok(());
+    }
+    // Each buffer holds a single datagram, so the socket is idle iff nothing can be received
+    // and a datagram can be queued for sending.
+    if socket.can_recv() || !socket.can_send() {
+        return Ok(());
+    }
+    let mut resized = new_socket(send_size, recv_size);
+    if socket.is_open() {
+        resized
+            .bind(socket.endpoint())
+            .map_err(SysError::from_smoltcp_error)?;
+    }
+    resized.set_hop_limit(socket.hop_limit());
+    *socket = resized;
+    Ok(())
+}
+
 pub fn recv(
     handle: SocketHandle,
     dst: &mut IoSequence,
@@ -49,8 +91,13 @@ pub fn send(
     src: &mut IoSequence,
     non_blocking: bool,
     endpoint: IpEndpoint,
+    broadcast: bool,
     ctx: &dyn Context,
 ) -> SysResult<usize> {
+    // Like linux, sending to a broadcast address needs SO_BROADCAST.
+    if !broadcast && is_broadcast(endpoint.addr, ctx) {
+        bail_libc!(libc::EACCES);
+    }
     let start = std::time::Instant::now();
 
     let mut once = true;
@@ -91,3 +138,20 @@ pub fn send(
         };
     }
 }
+
+// is_broadcast reports whether addr is the limited broadcast address or the broadcast address of a
+// subnet the interface is on.
+fn is_broadcast(addr: IpAddress, ctx: &dyn Context) -> bool {
+    let addr = match addr {
+        IpAddress::Ipv4(addr) => addr,
+        _ => return false,
+    };
+    if addr.is_broadcast() {
+        return true;
+    }
+    let iface = ctx.network_interface_mut();
+    iface.ip_addrs().iter().any(|cidr| match cidr {
+        IpCidr::Ipv4(cidr) => cidr.broadcast() == Some(addr),
+        _ => false,
+    })
+}
@@ -0,0 +1,316 @@
+use std::{
+    io::{self, IoSlice, IoSliceMut, Read, Write},
+    mem::ManuallyDrop,
+    os::unix::{
+        net::{AncillaryData, SocketAddr, SocketAncillary, UnixDatagram},
+        prelude::{FromRawFd, RawFd},
+    },
+};
+
+use mem::IoSequence;
+use utils::{bail_libc, SysError, SysResult};
+
+use crate::get_poll_event_from_fd;
+
+pub fn recv(
+    fd: RawFd,
+    dst: &mut IoSequence,
+    peek: bool,
+    non_blocking: bool,
+) -> SysResult<(usize, Vec<u8>)> {
+    let mut flags = 0;
+    if peek {
+        flags |= libc::MSG_PEEK;
+    }
+    if non_blocking {
+        flags |= libc::MSG_DONTWAIT;
+    }
+    let mut buf = vec![0; dst.num_bytes()];
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    let mut addr_len = std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+    let n = loop {
+        let n = unsafe {
+            libc::recvfrom(
+                fd,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                flags,
+                &mut addr as *mut _ as *mut libc::sockaddr,
+                &mut addr_len as *mut _,
+            )
+        };
+        if n >= 0 {
+            break n as usize;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(SysError::from_io_error(err));
+        }
+    };
+    let n = dst.write(&buf[..n]).map_err(SysError::from_io_error)?;
+
+    let addr_bytes = unsafe {
+        std::slice::from_raw_parts(
+            &addr as *const _ as *const u8,
+            std::cmp::min(addr_len as usize, std::mem::size_of::<libc::sockaddr_un>()),
+        )
+    };
+    Ok((n, addr_bytes.to_vec()))
+}
+
+pub fn send(
+    fd: RawFd,
+    src: &mut IoSequence,
+    non_blocking: bool,
+    path: Option<&str>,
+) -> SysResult<usize> {
+    let mut flags = libc::MSG_NOSIGNAL;
+    if non_blocking {
+        flags |= libc::MSG_DONTWAIT;
+    }
+    let mut buf = vec![0; src.num_bytes()];
+    let n = src.read(&mut buf).map_err(SysError::from_io_error)?;
+    let (addr, addr_len) = match path {
+        Some(path) => {
+            let (addr, addr_len) = sockaddr_un(path)?;
+            (Some(addr), addr_len)
+        }
+        None => (None, 0),
+    };
+    loop {
+        let r = unsafe {
+            libc::sendto(
+                fd,
+                buf.as_ptr() as *const libc::c_void,
+                n,
+                flags,
+                addr.as_ref()
+                    .map_or(std::ptr::null(), |a| a as *const _ as *const libc::sockaddr),
+                addr_len,
+            )
+        };
+        if r >= 0 {
+            return Ok(r as usize);
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(SysError::from_io_error(err));
+        }
+    }
+}
+
+// send_with_fds is send for a socket which additionally passes the host file descriptors fds to
+// the peer as a SCM_RIGHTS control message.
+pub fn send_with_fds(
+    fd: RawFd,
+    src: &mut IoSequence,
+    non_blocking: bool,
+    path: Option<&str>,
+    fds: &[RawFd],
+) -> SysResult<usize> {
+    // std's ancillary API has no way to pass MSG_DONTWAIT.
+    if non_blocking && get_poll_event_from_fd(fd, libc::POLLOUT as u64) == 0 {
+        bail_libc!(libc::EWOULDBLOCK);
+    }
+    let mut buf = vec![0; src.num_bytes()];
+    let n = src.read(&mut buf).map_err(SysError::from_io_error)?;
+    let mut control = vec![0; unsafe { libc::CMSG_SPACE((fds.len() * 4) as u32) } as usize];
+    let mut ancillary = SocketAncillary::new(&mut control);
+    if !ancillary.add_fds(fds) {
+        bail_libc!(libc::EINVAL);
+    }
+    // sendmsg(2) is the same for every socket type, so UnixDatagram works for all of them.
+    let socket = ManuallyDrop::new(unsafe { UnixDatagram::from_raw_fd(fd) });
+    let bufs = [IoSlice::new(&buf[..n])];
+    loop {
+        let r = match path {
+            Some(path) => socket.send_vectored_with_ancillary_to(&bufs, &mut ancillary, path),
+            None => socket.send_vectored_with_ancillary(&bufs, &mut ancillary),
+        };
+        match r {
+            Ok(n) => return Ok(n),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(SysError::from_io_error(err)),
+        }
+    }
+}
+
+// recv_with_fds is recv for a socket which additionally accepts the file descriptors passed as
+// SCM_RIGHTS, as many as fit in control_len bytes of control messages. Along with the number of
+// bytes read and the source address, it returns the received host file descriptors and the
+// MSG_TRUNC and MSG_CTRUNC flags of the message.
+pub fn recv_with_fds(
+    fd: RawFd,
+    dst: &mut IoSequence,
+    non_blocking: bool,
+    control_len: usize,
+) -> SysResult<(usize, Vec<u8>, Vec<RawFd>, i32)> {
+    if non_blocking && get_poll_event_from_fd(fd, libc::POLLIN as u64) == 0 {
+        bail_libc!(libc::EWOULDBLOCK);
+    }
+    let mut buf = vec![0; dst.num_bytes()];
+    let mut control = vec![0; control_len];
+    let mut ancillary = SocketAncillary::new(&mut control);
+    let socket = ManuallyDrop::new(unsafe { UnixDatagram::from_raw_fd(fd) });
+    let (n, truncated, addr) = loop {
+        match socket
+            .recv_vectored_with_ancillary_from(&mut [IoSliceMut::new(&mut buf)], &mut ancillary)
+        {
+            Ok(r) => break r,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(SysError::from_io_error(err)),
+        }
+    };
+
+    let mut fds = Vec::new();
+    for msg in ancillary.messages() {
+        if let Ok(AncillaryData::ScmRights(rights)) = msg {
+            fds.extend(rights);
+        }
+    }
+    let mut msg_flags = 0;
+    if truncated {
+        msg_flags |= libc::MSG_TRUNC;
+    }
+    if ancillary.truncated() {
+        msg_flags |= libc::MSG_CTRUNC;
+    }
+
+    let n = match dst.write(&buf[..n]) {
+        Ok(n) => n,
+        Err(err) => {
+            for fd in fds {
+                unsafe { libc::close(fd) };
+            }
+            return Err(SysError::from_io_error(err));
+        }
+    };
+    Ok((n, socket_addr_bytes(&addr), fds, msg_flags))
+}
+
+// socket_type returns the SO_TYPE of the host socket fd.
+pub fn socket_type(fd: RawFd) -> SysResult<i32> {
+    let mut stype: i32 = 0;
+    let mut len = std::mem::size_of::<i32>() as libc::socklen_t;
+    let r = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_TYPE,
+            &mut stype as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if r < 0 {
+        return Err(SysError::from_io_error(io::Error::last_os_error()));
+    }
+    Ok(stype)
+}
+
+// new_seqpacket creates a host SOCK_SEQPACKET socket, for which std has no wrapper.
+pub fn new_seqpacket() -> SysResult<RawFd> {
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(SysError::from_io_error(io::Error::last_os_error()));
+    }
+    Ok(fd)
+}
+
+// new_pair creates a pair of connected host sockets of type stype.
+pub fn new_pair(stype: i32) -> SysResult<(RawFd, RawFd)> {
+    let mut fds = [0; 2];
+    let r = unsafe {
+        libc::socketpair(
+            libc::AF_UNIX,
+            stype | libc::SOCK_CLOEXEC,
+            0,
+            fds.as_mut_ptr(),
+        )
+    };
+    if r < 0 {
+        return Err(SysError::from_io_error(io::Error::last_os_error()));
+    }
+    Ok((fds[0], fds[1]))
+}
+
+pub fn bind(fd: RawFd, path: &str) -> SysResult<()> {
+    let (addr, addr_len) = sockaddr_un(path)?;
+    let r = unsafe { libc::bind(fd, &addr as *const _ as *const libc::sockaddr, addr_len) };
+    if r < 0 {
+        return Err(SysError::from_io_error(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+pub fn connect(fd: RawFd, path: &str) -> SysResult<()> {
+    let (addr, addr_len) = sockaddr_un(path)?;
+    let r = unsafe { libc::connect(fd, &addr as *const _ as *const libc::sockaddr, addr_len) };
+    if r < 0 {
+        return Err(SysError::from_io_error(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+// set_sock_opt sets the SOL_SOCKET option name of the host socket fd to optval as is.
+pub fn set_sock_opt(fd: RawFd, name: i32, optval: &[u8]) -> SysResult<()> {
+    let r = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            name,
+            optval.as_ptr() as *const libc::c_void,
+            optval.len() as libc::socklen_t,
+        )
+    };
+    if r < 0 {
+        return Err(SysError::from_io_error(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+// get_sock_opt returns the SOL_SOCKET option name of the host socket fd as is, truncated to optlen
+// bytes.
+pub fn get_sock_opt(fd: RawFd, name: i32, optlen: u32) -> SysResult<Vec<u8>> {
+    let mut optval = vec![0u8; optlen as usize];
+    let mut len = optlen as libc::socklen_t;
+    let r = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            name,
+            optval.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if r < 0 {
+        return Err(SysError::from_io_error(io::Error::last_os_error()));
+    }
+    optval.truncate(len as usize);
+    Ok(optval)
+}
+
+fn sockaddr_un(path: &str) -> SysResult<(libc::sockaddr_un, libc::socklen_t)> {
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    let bytes = path.as_bytes();
+    if bytes.len() >= addr.sun_path.len() {
+        bail_libc!(libc::EINVAL);
+    }
+    for (d, s) in addr.sun_path.iter_mut().zip(bytes) {
+        *d = *s as libc::c_char;
+    }
+    let len = std::mem::size_of::<libc::sa_family_t>() + bytes.len() + 1;
+    Ok((addr, len as libc::socklen_t))
+}
+
+// socket_addr_bytes returns addr as a sockaddr_un, which only holds the family if the address is
+// not a pathname.
+fn socket_addr_bytes(addr: &SocketAddr) -> Vec<u8> {
+    let path = addr.as_pathname().and_then(|p| p.to_str());
+    match path.map(sockaddr_un) {
+        Some(Ok((addr, addr_len))) => unsafe {
+            std::slice::from_raw_parts(&addr as *const _ as *const u8, addr_len as usize).to_vec()
+        },
+        _ => (libc::AF_UNIX as libc::sa_family_t).to_le_bytes().to_vec(),
+    }
+}
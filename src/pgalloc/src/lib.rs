@@ -2,6 +2,8 @@ mod context;
 
 pub use context::Context;
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fs::File as StdFile;
 use std::io;
 use std::os::unix::io::AsRawFd;
@@ -59,6 +61,52 @@ pub struct MemoryFileOpts {
     delayed_eviction: DelayedEviction,
     use_host_memcg_pressure: bool,
     manual_zeroing: bool,
+    growth_policy: GrowthPolicy,
+}
+
+impl MemoryFileOpts {
+    pub fn with_growth_policy(mut self, growth_policy: GrowthPolicy) -> Self {
+        self.growth_policy = growth_policy;
+        self
+    }
+}
+
+// GrowthPolicy decides how far a top-down allocation that doesn't fit grows the file. The file
+// is always sized in whole chunks, so Exact only differs from ChunkAligned in where the new
+// range is placed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrowthPolicy {
+    // Double the file size until the allocation fits.
+    #[default]
+    Double,
+    // Round the size the allocation needs up to CHUNK_SIZE.
+    ChunkAligned,
+    // Grow the file by exactly what the allocation needs.
+    Exact,
+}
+
+impl GrowthPolicy {
+    // grow returns the size a file of file_size bytes grows to so that it holds needed bytes.
+    fn grow(self, file_size: i64, needed: u64) -> Option<i64> {
+        let needed = i64::try_from(needed).ok()?;
+        match self {
+            Self::Double => {
+                let mut file_size = file_size;
+                loop {
+                    file_size = if file_size == 0 {
+                        CHUNK_SIZE
+                    } else {
+                        file_size.checked_mul(2)?
+                    };
+                    if file_size >= needed {
+                        return Some(file_size);
+                    }
+                }
+            }
+            Self::ChunkAligned => Some(needed.checked_add(CHUNK_MASK)? & !CHUNK_MASK),
+            Self::Exact => Some(needed),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -275,6 +323,32 @@ impl MemoryFile {
         Ok(())
     }
 
+    // resident_pages reports, for each page of fr, whether the host has it resident.
+    fn resident_pages(&mut self, fr: &FileRange) -> SysResult<Vec<bool>> {
+        let page = PAGE_SIZE as usize;
+        let mut resident = Vec::with_capacity(fr.len() as usize / page);
+        let mut res = Ok(());
+        self.for_each_mapping_slice(fr, |bs| {
+            if res.is_err() {
+                return;
+            }
+            let mut vec = vec![0u8; bs.len() / page];
+            let ret = unsafe {
+                libc::mincore(
+                    bs.as_mut_ptr() as *mut libc::c_void,
+                    bs.len(),
+                    vec.as_mut_ptr(),
+                )
+            };
+            if ret < 0 {
+                res = Err(SysError::from_io_error(io::Error::last_os_error()));
+                return;
+            }
+            resident.extend(vec.iter().map(|v| v & 1 != 0));
+        })?;
+        res.map(|_| resident)
+    }
+
     fn find_available_range(
         &self,
         length: u64,
@@ -283,10 +357,54 @@ impl MemoryFile {
     ) -> Option<FileRange> {
         match dir {
             Direction::BottomUp => find_available_range_bottom_up(&self.usage, length, alignment),
-            Direction::TopDown => {
-                find_available_range_top_down(&self.usage, self.file_size, length, alignment)
+            Direction::TopDown => find_available_range_top_down(
+                &self.usage,
+                self.file_size,
+                length,
+                alignment,
+                self.opts.growth_policy,
+            ),
+        }
+    }
+
+    // decommit releases the backing store of the page-aligned range fr. The range stays allocated
+    // and reads back as zeros afterwards.
+    pub fn decommit(&mut self, fr: FileRange) -> SysResult<()> {
+        let page = PAGE_SIZE as u64;
+        if !fr.is_well_formed()
+            || fr.is_empty()
+            || fr.start % page != 0
+            || fr.end % page != 0
+            || fr.end > self.file_size as u64
+        {
+            bail_libc!(libc::EINVAL);
+        }
+        let ret = unsafe {
+            libc::fallocate(
+                self.file.as_raw_fd(),
+                libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                fr.start as i64,
+                fr.len() as i64,
+            )
+        };
+        if ret < 0 {
+            return Err(SysError::from_io_error(io::Error::last_os_error()));
+        }
+
+        let mut seg_maybe = self.usage.lower_bound_segment(fr.start);
+        while let Some(seg) = seg_maybe {
+            if seg.start() >= fr.end {
+                break;
             }
+            let seg = self.usage.isolate(&seg, fr);
+            self.usage.value_mut(&seg).known_committed = false;
+            seg_maybe = self.usage.next_segment_of_seg(&seg);
         }
+        self.usage.merge_range(Range {
+            start: fr.start.saturating_sub(1),
+            end: fr.end + 1,
+        });
+        Ok(())
     }
 
     pub fn should_cache_evictable(&self) -> bool {
@@ -298,6 +416,70 @@ impl MemoryFile {
         Ok((stat.st_blocks as u64) * 512)
     }
 
+    // update_usage refreshes known_committed for every allocated page from the host's view of
+    // which pages of the file are resident. It returns the change in committed bytes, which is
+    // negative if the host reclaimed pages.
+    pub fn update_usage(&mut self) -> SysResult<i64> {
+        let page = PAGE_SIZE as u64;
+        let segs: Vec<(FileRange, bool)> = self
+            .usage
+            .segments()
+            .map(|(r, v)| (r, v.known_committed))
+            .collect();
+
+        let mut delta = 0i64;
+        let mut changes = Vec::new();
+        for (fr, committed) in segs {
+            let resident = self.resident_pages(&fr)?;
+            let mut i = 0;
+            while i < resident.len() {
+                let mut j = i + 1;
+                while j < resident.len() && resident[j] == resident[i] {
+                    j += 1;
+                }
+                if resident[i] != committed {
+                    let r = FileRange {
+                        start: fr.start + i as u64 * page,
+                        end: std::cmp::min(fr.start + j as u64 * page, fr.end),
+                    };
+                    if resident[i] {
+                        delta += r.len() as i64;
+                    } else {
+                        delta -= r.len() as i64;
+                    }
+                    changes.push((r, resident[i]));
+                }
+                i = j;
+            }
+        }
+
+        for (r, committed) in changes {
+            let seg = self.usage.find_segment(r.start).unwrap();
+            let seg = self.usage.isolate(&seg, r);
+            self.usage.value_mut(&seg).known_committed = committed;
+        }
+        if let (Some(first), Some(last)) = (self.usage.first_segment(), self.usage.last_segment()) {
+            self.usage.merge_range(Range {
+                start: first.start(),
+                end: last.end(),
+            });
+        }
+        Ok(delta)
+    }
+
+    // usage_by_kind returns the number of allocated bytes attributed to each memory kind. If
+    // committed_only is set, only pages known to be committed are counted.
+    pub fn usage_by_kind(&self, committed_only: bool) -> HashMap<MemoryKind, u64> {
+        let mut usage = HashMap::new();
+        for (r, info) in self.usage.segments() {
+            if committed_only && !info.known_committed {
+                continue;
+            }
+            *usage.entry(info.kind).or_insert(0) += r.len();
+        }
+        usage
+    }
+
     pub fn total_size(&self) -> u64 {
         self.file_size as u64
     }
@@ -360,9 +542,10 @@ fn find_available_range_bottom_up(
 
 fn find_available_range_top_down(
     usage: &UsageSet,
-    mut file_size: i64,
+    file_size: i64,
     length: u64,
     alignment: u64,
+    growth_policy: GrowthPolicy,
 ) -> Option<FileRange> {
     let alignment_mask = alignment - 1;
     let last_gap = usage.last_gap().unwrap();
@@ -388,27 +571,12 @@ fn find_available_range_top_down(
 
     let min = last_gap.start();
     let min = (min + alignment_mask) & !alignment_mask;
-    min.checked_add(length)?;
-
-    loop {
-        let new_file_size = if file_size == 0 {
-            CHUNK_SIZE
-        } else {
-            file_size.checked_mul(2)?
-        };
-        file_size = new_file_size;
-        if (file_size as u64) < length {
-            continue;
-        }
-        let unaligned_start = file_size as u64 - length;
-        let start = unaligned_start & !alignment_mask;
-        if start >= min {
-            return Some(FileRange {
-                start,
-                end: start + length,
-            });
-        }
-    }
+    let file_size = growth_policy.grow(file_size, min.checked_add(length)?)?;
+    let start = (file_size as u64 - length) & !alignment_mask;
+    Some(FileRange {
+        start,
+        end: start + length,
+    })
 }
 
 #[cfg(test)]
@@ -920,4 +1088,267 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn find_unallocated_range_growth() {
+        const CHUNK: u64 = CHUNK_SIZE as u64;
+        let full_chunk = || UsageSegmentDataSlices {
+            start: vec![0],
+            end: vec![CHUNK],
+            values: vec![UsageInfo {
+                kind: MemoryKind::System,
+                known_committed: false,
+            }],
+        };
+        // (growth policy, length, start of the range, file size after allocation)
+        for (growth_policy, length, want_start, want_file_size) in [
+            (GrowthPolicy::Double, PAGE, 2 * CHUNK - PAGE, 2 * CHUNK),
+            (
+                GrowthPolicy::ChunkAligned,
+                PAGE,
+                2 * CHUNK - PAGE,
+                2 * CHUNK,
+            ),
+            (GrowthPolicy::Exact, PAGE, CHUNK, 2 * CHUNK),
+            (GrowthPolicy::Double, 2 * CHUNK, 2 * CHUNK, 4 * CHUNK),
+            (GrowthPolicy::ChunkAligned, 2 * CHUNK, CHUNK, 3 * CHUNK),
+            (GrowthPolicy::Exact, 2 * CHUNK, CHUNK, 3 * CHUNK),
+            (
+                GrowthPolicy::Double,
+                2 * CHUNK + PAGE,
+                2 * CHUNK - PAGE,
+                4 * CHUNK,
+            ),
+            (
+                GrowthPolicy::ChunkAligned,
+                2 * CHUNK + PAGE,
+                2 * CHUNK - PAGE,
+                4 * CHUNK,
+            ),
+            (GrowthPolicy::Exact, 2 * CHUNK + PAGE, CHUNK, 4 * CHUNK),
+        ] {
+            let dummy_file = StdFile::open("/tmp").unwrap();
+            let mut mf = MemoryFile {
+                file: Box::new(dummy_file),
+                file_size: CHUNK as i64,
+                mappings: Vec::new(),
+                usage: UsageSet::new(Box::new(UsageInfoSetOperations)),
+                opts: MemoryFileOpts::default().with_growth_policy(growth_policy),
+            };
+            assert!(mf.usage.import_sorted_slices(&full_chunk()).is_ok());
+
+            let fr = mf
+                .find_available_range(length, PAGE, Direction::TopDown)
+                .unwrap();
+            assert_eq!(fr.start, want_start, "{:?} {}", growth_policy, length);
+            assert_eq!(
+                fr.end,
+                want_start + length,
+                "{:?} {}",
+                growth_policy,
+                length
+            );
+            let file_size = (fr.end + CHUNK_MASK as u64) & !(CHUNK_MASK as u64);
+            assert_eq!(file_size, want_file_size, "{:?} {}", growth_policy, length);
+        }
+    }
+
+    fn new_memory_file(name: &str) -> MemoryFile {
+        let path = std::env::temp_dir().join(format!("pgalloc-{}-{}", name, std::process::id()));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        MemoryFile::new(file, MemoryFileOpts::default()).unwrap()
+    }
+
+    #[test]
+    fn usage_by_kind() {
+        let mut mf = new_memory_file("usage_by_kind");
+        let usage = UsageSegmentDataSlices {
+            start: vec![0, PAGE, 3 * PAGE, 6 * PAGE],
+            end: vec![PAGE, 3 * PAGE, 6 * PAGE, 7 * PAGE],
+            values: vec![
+                UsageInfo {
+                    kind: MemoryKind::System,
+                    known_committed: true,
+                },
+                UsageInfo {
+                    kind: MemoryKind::Anonymous,
+                    known_committed: false,
+                },
+                UsageInfo {
+                    kind: MemoryKind::Anonymous,
+                    known_committed: true,
+                },
+                UsageInfo {
+                    kind: MemoryKind::PageCache,
+                    known_committed: false,
+                },
+            ],
+        };
+        mf.usage.import_sorted_slices(&usage).unwrap();
+
+        let all = mf.usage_by_kind(false);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[&MemoryKind::System], PAGE);
+        assert_eq!(all[&MemoryKind::Anonymous], 5 * PAGE);
+        assert_eq!(all[&MemoryKind::PageCache], PAGE);
+
+        let committed = mf.usage_by_kind(true);
+        assert_eq!(committed.len(), 2);
+        assert_eq!(committed[&MemoryKind::System], PAGE);
+        assert_eq!(committed[&MemoryKind::Anonymous], 3 * PAGE);
+    }
+
+    #[test]
+    fn update_usage() {
+        use std::os::unix::fs::FileExt;
+
+        let mut mf = new_memory_file("update_usage");
+        let fr = mf
+            .allocate(
+                4 * PAGE,
+                AllocOpts {
+                    kind: MemoryKind::Anonymous,
+                    dir: Direction::BottomUp,
+                },
+            )
+            .unwrap();
+        // Only the pages that were written to have backing store.
+        mf.file
+            .write_all_at(&[1; PAGE_SIZE as usize], fr.start)
+            .unwrap();
+        mf.file
+            .write_all_at(&[1; PAGE_SIZE as usize], fr.start + 2 * PAGE)
+            .unwrap();
+
+        assert_eq!(mf.update_usage().unwrap(), 2 * PAGE as i64);
+        let segs: Vec<_> = mf
+            .usage
+            .segments()
+            .map(|(r, v)| (r.start, v.known_committed))
+            .collect();
+        assert_eq!(
+            segs,
+            vec![
+                (fr.start, true),
+                (fr.start + PAGE, false),
+                (fr.start + 2 * PAGE, true),
+                (fr.start + 3 * PAGE, false),
+            ]
+        );
+        assert_eq!(mf.update_usage().unwrap(), 0);
+
+        // Reclaiming a page behind the usage set's back shows up as a negative delta.
+        let ret = unsafe {
+            libc::fallocate(
+                mf.file.as_raw_fd(),
+                libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                fr.start as i64,
+                PAGE as i64,
+            )
+        };
+        assert_eq!(ret, 0);
+        assert_eq!(mf.update_usage().unwrap(), -(PAGE as i64));
+        let segs: Vec<_> = mf
+            .usage
+            .segments()
+            .map(|(r, v)| (r.start, v.known_committed))
+            .collect();
+        assert_eq!(
+            segs,
+            vec![
+                (fr.start, false),
+                (fr.start + 2 * PAGE, true),
+                (fr.start + 3 * PAGE, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn decommit() {
+        use std::os::unix::fs::FileExt;
+
+        let mut mf = new_memory_file("decommit");
+        let fr = mf
+            .allocate(
+                4 * PAGE,
+                AllocOpts {
+                    kind: MemoryKind::Anonymous,
+                    dir: Direction::BottomUp,
+                },
+            )
+            .unwrap();
+        mf.file
+            .write_all_at(&[1; 4 * PAGE_SIZE as usize], fr.start)
+            .unwrap();
+        for (_, v) in mf.usage.segments_mut() {
+            v.known_committed = true;
+        }
+
+        let hole = FileRange {
+            start: fr.start + PAGE,
+            end: fr.start + 3 * PAGE,
+        };
+        mf.decommit(hole).unwrap();
+
+        let mut buf = [0xff; PAGE_SIZE as usize];
+        mf.file.read_exact_at(&mut buf, hole.start).unwrap();
+        assert!(buf.iter().all(|&b| b == 0));
+        mf.file.read_exact_at(&mut buf, fr.start).unwrap();
+        assert!(buf.iter().all(|&b| b == 1));
+
+        let segs: Vec<_> = mf
+            .usage
+            .segments()
+            .map(|(r, v)| (r, v.known_committed))
+            .collect();
+        assert_eq!(
+            segs,
+            vec![
+                (
+                    Range {
+                        start: fr.start,
+                        end: hole.start
+                    },
+                    true
+                ),
+                (hole, false),
+                (
+                    Range {
+                        start: hole.end,
+                        end: fr.end
+                    },
+                    true
+                ),
+            ]
+        );
+
+        // Decommitting the rest merges everything back into one segment.
+        mf.decommit(fr).unwrap();
+        let segs: Vec<_> = mf
+            .usage
+            .segments()
+            .map(|(r, v)| (r, v.known_committed))
+            .collect();
+        assert_eq!(segs, vec![(fr, false)]);
+
+        for bad in [
+            FileRange {
+                start: fr.start + 1,
+                end: fr.end,
+            },
+            FileRange {
+                start: fr.start,
+                end: mf.total_size() + PAGE,
+            },
+        ] {
+            assert_eq!(mf.decommit(bad).unwrap_err().code(), libc::EINVAL);
+        }
+    }
 }
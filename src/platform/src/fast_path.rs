@@ -0,0 +1,485 @@
+// The fast path lets the syscalls in an allowlist reach sentinel through seccomp user
+// notification instead of a PTRACE_SYSEMU stop. A notification is a single round trip between
+// the tracee and sentinel, whereas a ptrace stop also costs PTRACE_GETREGS, PTRACE_SETREGS and a
+// resume. The other syscalls are stopped with SECCOMP_RET_TRACE, which sentinel handles just like
+// a PTRACE_SYSEMU stop, so the tracee is resumed with PTRACE_CONT in this mode.
+//
+// A notified tracee stays blocked in the kernel rather than in a ptrace stop, so only syscalls
+// that neither inject syscalls into the tracee nor touch its registers may take the fast path.
+
+use std::{
+    os::unix::io::{AsRawFd, RawFd},
+    time::Duration,
+};
+
+use nix::{
+    errno::Errno,
+    sys::{
+        ptrace,
+        signal::{SigSet, Signal},
+        signalfd::{SfdFlags, SignalFd},
+        time::TimeSpec,
+        wait::{waitpid, WaitPidFlag, WaitStatus},
+    },
+    unistd::Pid,
+};
+use utils::{SysError, SysResult};
+
+use crate::{create_syscall_regs, Context};
+
+const BPF_LD: u16 = 0x00;
+const BPF_W: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_JMP: u16 = 0x05;
+const BPF_JEQ: u16 = 0x10;
+const BPF_K: u16 = 0x00;
+const BPF_RET: u16 = 0x06;
+
+const AUDIT_ARCH_X86_64: u32 = 0xc000_003e;
+
+// Offsets in struct seccomp_data.
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+
+const SECCOMP_SET_MODE_FILTER: u64 = 1;
+const SECCOMP_FILTER_FLAG_NEW_LISTENER: u64 = 1 << 3;
+const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc0_0000;
+const SECCOMP_IOCTL_NOTIF_RECV: u64 = 0xc050_2100;
+const SECCOMP_IOCTL_NOTIF_SEND: u64 = 0xc018_2101;
+
+// Just a safety net in case SIGCHLD is consumed by another thread.
+const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+const SYSCALL_SET_WORDS: usize = 8;
+
+// SyscallSet is a set of x86_64 syscall numbers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyscallSet([u64; SYSCALL_SET_WORDS]);
+
+impl SyscallSet {
+    pub const MAX: i64 = (SYSCALL_SET_WORDS * 64) as i64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // insert adds nr to the set, and returns false if nr can't be a member.
+    pub fn insert(&mut self, nr: i64) -> bool {
+        if !(0..Self::MAX).contains(&nr) {
+            return false;
+        }
+        self.0[nr as usize / 64] |= 1 << (nr % 64);
+        true
+    }
+
+    pub fn contains(&self, nr: i64) -> bool {
+        (0..Self::MAX).contains(&nr) && self.0[nr as usize / 64] & (1 << (nr % 64)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|w| *w == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        (0..Self::MAX).filter(move |nr| self.contains(*nr))
+    }
+}
+
+fn stmt(code: u16, k: u32) -> libc::sock_filter {
+    libc::sock_filter {
+        code,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter { code, jt, jf, k }
+}
+
+// Filter is the BPF program installed on the tracee. It has to be built before the tracee is
+// cloned, since the tracee reads it from its own copy of sentinel's heap.
+#[derive(Debug)]
+pub struct Filter {
+    // _insns owns the instructions prog.filter points to. It's never read, but can't be built at
+    // install time, since the tracee would not have a copy of it.
+    _insns: Vec<libc::sock_filter>,
+    prog: Box<libc::sock_fprog>,
+}
+
+impl Filter {
+    // new builds a filter notifying the syscalls in syscalls and tracing the others. The syscalls
+    // sentinel injects through the stub are traced as well rather than allowed by their address,
+    // since the guest can jump to the stub too. They are run on the PTRACE_EVENT_SECCOMP stop only
+    // while sentinel is injecting one.
+    pub fn new(syscalls: &SyscallSet) -> Self {
+        let mut insns = vec![
+            stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_ARCH),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH_X86_64, 1, 0),
+            stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_TRACE),
+            stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_NR),
+        ];
+        for nr in syscalls.iter() {
+            insns.push(jump(BPF_JMP | BPF_JEQ | BPF_K, nr as u32, 0, 1));
+            insns.push(stmt(BPF_RET | BPF_K, SECCOMP_RET_USER_NOTIF));
+        }
+        insns.push(stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_TRACE));
+        let prog = Box::new(libc::sock_fprog {
+            len: insns.len() as u16,
+            filter: insns.as_ptr() as *mut libc::sock_filter,
+        });
+        Self {
+            _insns: insns,
+            prog,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct SeccompData {
+    nr: i32,
+    arch: u32,
+    instruction_pointer: u64,
+    args: [u64; 6],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct SeccompNotif {
+    id: u64,
+    pid: u32,
+    flags: u32,
+    data: SeccompData,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct SeccompNotifResp {
+    id: u64,
+    val: i64,
+    error: i32,
+    flags: u32,
+}
+
+// Notification is a syscall of the tracee waiting for a response from sentinel.
+#[derive(Clone, Copy, Debug)]
+pub struct Notification {
+    id: u64,
+    data: SeccompData,
+}
+
+impl Notification {
+    pub fn sysno(&self) -> i64 {
+        self.data.nr as i64
+    }
+
+    // regs returns base with the syscall filled in, as if the tracee were stopped on it.
+    pub fn regs(&self, base: libc::user_regs_struct) -> libc::user_regs_struct {
+        let mut regs = create_syscall_regs(base, -libc::ENOSYS as u64, &self.data.args);
+        regs.orig_rax = self.data.nr as u64;
+        regs.rip = self.data.instruction_pointer;
+        regs
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Notification(Notification),
+    Status(WaitStatus),
+}
+
+// FastPath waits for both the notifications and the ptrace stops of a tracee.
+#[derive(Debug)]
+pub struct FastPath {
+    listener: RawFd,
+    sigchld: SignalFd,
+}
+
+impl FastPath {
+    // install loads filter into the tracee, which must be stopped in the stub, and returns the
+    // listener. The tracee also needs PTRACE_O_TRACESECCOMP, otherwise the traced syscalls fail
+    // with ENOSYS. SIGCHLD is blocked in the calling thread to be read from a signalfd.
+    pub fn install(filter: &Filter, ctx: &dyn Context) -> SysResult<Self> {
+        inject_syscall(
+            libc::SYS_prctl,
+            &[libc::PR_SET_NO_NEW_PRIVS as u64, 1, 0, 0, 0],
+            ctx,
+        )?;
+        // The tracee shares the file table with sentinel, so the listener is valid here as well.
+        let listener = inject_syscall(
+            libc::SYS_seccomp,
+            &[
+                SECCOMP_SET_MODE_FILTER,
+                SECCOMP_FILTER_FLAG_NEW_LISTENER,
+                &*filter.prog as *const _ as u64,
+            ],
+            ctx,
+        )? as RawFd;
+
+        let mut mask = SigSet::empty();
+        mask.add(Signal::SIGCHLD);
+        mask.thread_block().map_err(|e| SysError::new(e as i32))?;
+        let sigchld = SignalFd::with_flags(&mask, SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC)
+            .map_err(|e| SysError::new(e as i32))?;
+        Ok(Self { listener, sigchld })
+    }
+
+    // wait returns the next notification or change of state of pid, whichever comes first.
+    pub fn wait(&mut self, pid: Pid) -> nix::Result<Event> {
+        let flags = WaitPidFlag::__WALL | WaitPidFlag::WUNTRACED | WaitPidFlag::WNOHANG;
+        loop {
+            match waitpid(pid, Some(flags))? {
+                WaitStatus::StillAlive => (),
+                status => return Ok(Event::Status(status)),
+            }
+            let mut fds = [
+                libc::pollfd {
+                    fd: self.listener,
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: self.sigchld.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            let timeout = TimeSpec::from_duration(WAIT_INTERVAL);
+            // SIGCHLD must stay blocked while waiting, so the mask is left as is.
+            let ret = unsafe {
+                libc::ppoll(
+                    fds.as_mut_ptr(),
+                    fds.len() as libc::nfds_t,
+                    timeout.as_ref(),
+                    std::ptr::null(),
+                )
+            };
+            match Errno::result(ret) {
+                Ok(_) | Err(Errno::EINTR) => (),
+                Err(e) => return Err(e),
+            }
+            while let Ok(Some(_)) = self.sigchld.read_signal() {}
+            if fds[0].revents & libc::POLLIN != 0 {
+                let mut req = SeccompNotif::default();
+                let ret =
+                    unsafe { libc::ioctl(self.listener, SECCOMP_IOCTL_NOTIF_RECV as _, &mut req) };
+                match Errno::result(ret) {
+                    Ok(_) => {
+                        return Ok(Event::Notification(Notification {
+                            id: req.id,
+                            data: req.data,
+                        }))
+                    }
+                    // The syscall was interrupted before it was received.
+                    Err(Errno::ENOENT) | Err(Errno::EINTR) => (),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    // respond completes the syscall of n with result.
+    pub fn respond(&self, n: &Notification, result: SysResult<usize>) -> nix::Result<()> {
+        let mut resp = SeccompNotifResp {
+            id: n.id,
+            ..Default::default()
+        };
+        match result {
+            Ok(val) => resp.val = val as i64,
+            Err(err) => resp.error = -err.code(),
+        }
+        let ret = unsafe { libc::ioctl(self.listener, SECCOMP_IOCTL_NOTIF_SEND as _, &resp) };
+        match Errno::result(ret) {
+            // The tracee was killed while the syscall was being handled.
+            Ok(_) | Err(Errno::ENOENT) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for FastPath {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.listener) };
+    }
+}
+
+// inject_syscall executes the syscall from the stub of the stopped tracee and returns its result.
+fn inject_syscall(sysno: i64, args: &[u64], ctx: &dyn Context) -> SysResult<u64> {
+    let pid = ctx.tid();
+    let regs = create_syscall_regs(ctx.task_init_regs(), sysno as u64, args);
+    ctx.ptrace_set_regs(regs).expect("PTRACE_SETREGS failed");
+    loop {
+        ptrace::cont(pid, None).expect("PTRACE_CONT failed");
+        match waitpid(pid, None).expect("wait failed") {
+            WaitStatus::Stopped(_, Signal::SIGSTOP) => continue,
+            // Resuming from the seccomp stop runs the syscall being injected.
+            WaitStatus::PtraceEvent(_, Signal::SIGTRAP, libc::PTRACE_EVENT_SECCOMP) => continue,
+            WaitStatus::Stopped(_, Signal::SIGTRAP) => break,
+            e => panic!("unhandled event {:?}", e),
+        }
+    }
+    let ret = ptrace::getregs(pid).expect("PTRACE_GETREGS failed").rax as i64;
+    if (-4095..0).contains(&ret) {
+        return Err(SysError::new(-ret as i32));
+    }
+    Ok(ret as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use nix::sys::signal::{self, SigmaskHow};
+
+    use crate::STUB_START;
+
+    use super::*;
+
+    #[test]
+    fn syscall_set() {
+        let mut set = SyscallSet::new();
+        assert!(set.is_empty());
+        assert!(set.insert(libc::SYS_getpid));
+        assert!(set.insert(0));
+        assert!(set.insert(SyscallSet::MAX - 1));
+        assert!(!set.insert(SyscallSet::MAX));
+        assert!(!set.insert(-1));
+        assert!(set.contains(libc::SYS_getpid));
+        assert!(!set.contains(libc::SYS_getppid));
+        assert!(!set.contains(-1));
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![0, libc::SYS_getpid, SyscallSet::MAX - 1]
+        );
+    }
+
+    #[test]
+    fn filter_layout() {
+        let mut set = SyscallSet::new();
+        set.insert(libc::SYS_getpid);
+        set.insert(libc::SYS_gettid);
+        let filter = Filter::new(&set);
+        let insns = &filter._insns;
+        assert_eq!(insns.len(), 9);
+        assert_eq!(insns[4].k, libc::SYS_getpid as u32);
+        assert_eq!(insns[5].k, SECCOMP_RET_USER_NOTIF);
+        assert_eq!(insns[6].k, libc::SYS_gettid as u32);
+        assert_eq!(insns[8].k, libc::SECCOMP_RET_TRACE);
+        // Nothing is allowed without a stop, wherever the syscall comes from.
+        assert!(insns.iter().all(|i| i.k != libc::SECCOMP_RET_ALLOW));
+    }
+
+    struct TestContext {
+        pid: Pid,
+        init_regs: libc::user_regs_struct,
+    }
+
+    impl Context for TestContext {
+        fn tid(&self) -> Pid {
+            self.pid
+        }
+        fn task_init_regs(&self) -> libc::user_regs_struct {
+            self.init_regs
+        }
+        fn ptrace_set_regs(&self, regs: libc::user_regs_struct) -> nix::Result<()> {
+            ptrace::setregs(self.pid, regs)
+        }
+    }
+
+    const ROUNDS: usize = 1000;
+    const FAKE_PID: i64 = 4242;
+
+    // workload runs in the tracee after the filter is installed.
+    extern "C" fn workload() -> ! {
+        let mut ok = true;
+        for _ in 0..ROUNDS {
+            ok &= unsafe { libc::syscall(libc::SYS_getpid) } == FAKE_PID;
+            ok &= unsafe { libc::syscall(libc::SYS_getppid) } > 0;
+        }
+        unsafe { libc::syscall(libc::SYS_exit_group, if ok { 0 } else { 1 }) };
+        unreachable!()
+    }
+
+    // Counts the round trips between the tracee and the tracer for ROUNDS fast syscalls and
+    // ROUNDS traced ones. Requires permission to ptrace a child, so run with
+    // `cargo test -- --ignored --nocapture` to see the timings as well.
+    #[test]
+    #[ignore]
+    fn fast_path_round_trips() {
+        crate::stub_init().ok();
+        let mut set = SyscallSet::new();
+        set.insert(libc::SYS_getpid);
+        let filter = Filter::new(&set);
+        let mut stack = vec![0u8; 1 << 16];
+        let stack_top = (stack.as_mut_ptr() as u64 + stack.len() as u64) & !0xf;
+
+        let pid =
+            unsafe { libc::syscall(libc::SYS_clone, libc::SIGCHLD | libc::CLONE_FILES, 0, 0) };
+        if pid == 0 {
+            let stub_addr = *STUB_START.lock().unwrap();
+            let stub: extern "C" fn() = unsafe { std::mem::transmute(stub_addr as *mut u8) };
+            stub();
+            unreachable!();
+        }
+        let pid = Pid::from_raw(pid as i32);
+        assert!(matches!(
+            waitpid(pid, Some(WaitPidFlag::WUNTRACED)),
+            Ok(WaitStatus::Stopped(_, Signal::SIGSTOP))
+        ));
+        ptrace::attach(pid).unwrap();
+        assert!(matches!(
+            waitpid(pid, None),
+            Ok(WaitStatus::Stopped(_, Signal::SIGSTOP))
+        ));
+        ptrace::setoptions(
+            pid,
+            ptrace::Options::PTRACE_O_EXITKILL | ptrace::Options::PTRACE_O_TRACESECCOMP,
+        )
+        .unwrap();
+        let mut init_regs = ptrace::getregs(pid).unwrap();
+        init_regs.rip -= 2;
+        let ctx = TestContext { pid, init_regs };
+        let mut fast_path = FastPath::install(&filter, &ctx).unwrap();
+
+        let mut regs = init_regs;
+        regs.rip = workload as *const () as u64;
+        regs.rsp = stack_top - 8;
+        ptrace::setregs(pid, regs).unwrap();
+        ptrace::cont(pid, None).unwrap();
+
+        let (mut notifications, mut stops) = (0, 0);
+        let start = Instant::now();
+        let code = loop {
+            match fast_path.wait(pid).unwrap() {
+                Event::Notification(n) => {
+                    assert_eq!(n.sysno(), libc::SYS_getpid);
+                    notifications += 1;
+                    fast_path.respond(&n, Ok(FAKE_PID as usize)).unwrap();
+                }
+                Event::Status(WaitStatus::PtraceEvent(_, Signal::SIGTRAP, ev))
+                    if ev == libc::PTRACE_EVENT_SECCOMP =>
+                {
+                    stops += 1;
+                    ptrace::cont(pid, None).unwrap();
+                }
+                Event::Status(WaitStatus::Exited(_, code)) => break code,
+                e => panic!("unexpected event {:?}", e),
+            }
+        };
+        let elapsed = start.elapsed();
+        let mut mask = SigSet::empty();
+        mask.add(Signal::SIGCHLD);
+        signal::pthread_sigmask(SigmaskHow::SIG_UNBLOCK, Some(&mask), None).unwrap();
+        println!(
+            "{} notifications and {} ptrace stops in {:?}",
+            notifications, stops, elapsed
+        );
+        assert_eq!(code, 0);
+        assert_eq!(notifications, ROUNDS);
+        // getppid and exit_group
+        assert_eq!(stops, ROUNDS + 1);
+        drop(stack);
+    }
+}
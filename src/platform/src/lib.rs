@@ -1,17 +1,27 @@
 mod context;
+mod fast_path;
 
 pub use context::Context;
+pub use fast_path::{Event, FastPath, Filter, Notification, SyscallSet};
 
-use std::sync::Mutex;
+use std::{
+    io::{IoSlice, IoSliceMut},
+    sync::Mutex,
+};
 
 use mem::{AccessType, Addr, PAGE_SIZE};
-use nix::sys::{
-    ptrace,
-    signal::Signal,
-    wait::{waitpid, WaitStatus},
+use nix::{
+    errno::Errno,
+    sys::{
+        ptrace::{self, AddressType},
+        signal::Signal,
+        uio::{process_vm_readv, process_vm_writev, RemoteIoVec},
+        wait::{waitpid, WaitStatus},
+    },
+    unistd::Pid,
 };
 use once_cell::sync::{Lazy, OnceCell};
-use utils::{FileRange, SysResult};
+use utils::{FileRange, SysError, SysResult};
 
 const STUB_INIT_ADDRESS: u64 = 0x7fffffff0000;
 const MAX_USER_ADDRESS: u64 = 0x7ffffffff000; // largest possible user address
@@ -22,9 +32,18 @@ static STUB_END: OnceCell<u64> = OnceCell::new();
 #[derive(Clone, Copy, Debug)]
 pub enum Platform {
     Ptrace,
+    // PtraceFastPath services the syscalls in the set through seccomp user notification instead
+    // of stopping the tracee with PTRACE_SYSEMU.
+    PtraceFastPath(SyscallSet),
 }
 
 impl Platform {
+    pub fn fast_syscalls(&self) -> Option<&SyscallSet> {
+        match self {
+            Platform::Ptrace => None,
+            Platform::PtraceFastPath(syscalls) => Some(syscalls),
+        }
+    }
     pub fn supports_address_space_io(&self) -> bool {
         false
     }
@@ -160,6 +179,9 @@ impl PtraceAddressSpace {
             ptrace::cont(pid, None).expect("PTRACE_CONT failed");
             match waitpid(pid, None).expect("wait failed") {
                 WaitStatus::Stopped(_, sig) if sig == Signal::SIGSTOP => continue,
+                // With the fast path filter installed, the injected syscall stops here first, and
+                // resuming runs it.
+                WaitStatus::PtraceEvent(_, Signal::SIGTRAP, libc::PTRACE_EVENT_SECCOMP) => continue,
                 WaitStatus::Stopped(_, sig) if sig == Signal::SIGTRAP => break,
                 e => panic!("unhandled event {:?}", e),
             }
@@ -181,16 +203,168 @@ impl PtraceAddressSpace {
             ptrace::cont(pid, None).expect("PTRACE_CONT failed");
             match waitpid(pid, None).expect("wait failed") {
                 WaitStatus::Stopped(_, sig) if sig == Signal::SIGSTOP => continue,
+                // With the fast path filter installed, the injected syscall stops here first, and
+                // resuming runs it.
+                WaitStatus::PtraceEvent(_, Signal::SIGTRAP, libc::PTRACE_EVENT_SECCOMP) => continue,
                 WaitStatus::Stopped(_, sig) if sig == Signal::SIGTRAP => break,
                 e => logger::debug!("received signal {:?}", e),
             }
         }
     }
-    pub fn copy_in(&self, _: Addr, _: &mut [u8]) -> SysResult<usize> {
-        unreachable!();
+
+    // copy_in reads dst.len() bytes at addr in the tracee and returns the number of bytes read.
+    // process_vm_readv fails with EFAULT if the range starts in an unmapped or unreadable page,
+    // in which case PTRACE_PEEKDATA is tried word by word instead.
+    pub fn copy_in(&self, addr: Addr, dst: &mut [u8], ctx: &dyn Context) -> SysResult<usize> {
+        if dst.is_empty() {
+            return Ok(0);
+        }
+        let pid = ctx.tid();
+        let remote = [RemoteIoVec {
+            base: addr.0 as usize,
+            len: dst.len(),
+        }];
+        match process_vm_readv(pid, &mut [IoSliceMut::new(dst)], &remote) {
+            Ok(n) => Ok(n),
+            Err(Errno::EFAULT) => peek_data(pid, addr, dst),
+            Err(e) => Err(SysError::new(e as i32)),
+        }
     }
-    pub fn copy_out(&self, _: Addr, _: &[u8]) -> SysResult<usize> {
-        unreachable!();
+
+    // copy_out writes src at addr in the tracee and returns the number of bytes written.
+    // process_vm_writev honors page protections unlike PTRACE_POKEDATA, so the latter is used as
+    // a fallback on EFAULT.
+    pub fn copy_out(&self, addr: Addr, src: &[u8], ctx: &dyn Context) -> SysResult<usize> {
+        if src.is_empty() {
+            return Ok(0);
+        }
+        let pid = ctx.tid();
+        let remote = [RemoteIoVec {
+            base: addr.0 as usize,
+            len: src.len(),
+        }];
+        match process_vm_writev(pid, &[IoSlice::new(src)], &remote) {
+            Ok(n) => Ok(n),
+            Err(Errno::EFAULT) => poke_data(pid, addr, src),
+            Err(e) => Err(SysError::new(e as i32)),
+        }
     }
+
     pub fn release(&self) {}
 }
+
+const WORD_SIZE: u64 = std::mem::size_of::<libc::c_long>() as u64;
+
+// peek_data copies dst.len() bytes at addr in the tracee to dst with PTRACE_PEEKDATA, stopping at
+// the first word that can't be read.
+fn peek_data(pid: Pid, addr: Addr, dst: &mut [u8]) -> SysResult<usize> {
+    let mut done = 0;
+    while done < dst.len() {
+        let cur = addr.0 + done as u64;
+        let word_addr = cur & !(WORD_SIZE - 1);
+        let offset = (cur - word_addr) as usize;
+        let word = match ptrace::read(pid, word_addr as AddressType) {
+            Ok(word) => word.to_ne_bytes(),
+            Err(_) if done > 0 => break,
+            Err(e) => return Err(SysError::new(e as i32)),
+        };
+        let n = std::cmp::min(WORD_SIZE as usize - offset, dst.len() - done);
+        dst[done..done + n].copy_from_slice(&word[offset..offset + n]);
+        done += n;
+    }
+    Ok(done)
+}
+
+// poke_data copies src to addr in the tracee with PTRACE_POKEDATA, stopping at the first word that
+// can't be written. Words only partially covered by src are read first to preserve the rest.
+fn poke_data(pid: Pid, addr: Addr, src: &[u8]) -> SysResult<usize> {
+    let mut done = 0;
+    while done < src.len() {
+        let cur = addr.0 + done as u64;
+        let word_addr = cur & !(WORD_SIZE - 1);
+        let offset = (cur - word_addr) as usize;
+        let n = std::cmp::min(WORD_SIZE as usize - offset, src.len() - done);
+        let mut word = if n < WORD_SIZE as usize {
+            match ptrace::read(pid, word_addr as AddressType) {
+                Ok(word) => word.to_ne_bytes(),
+                Err(_) if done > 0 => break,
+                Err(e) => return Err(SysError::new(e as i32)),
+            }
+        } else {
+            [0; WORD_SIZE as usize]
+        };
+        word[offset..offset + n].copy_from_slice(&src[done..done + n]);
+        let data = libc::c_long::from_ne_bytes(word) as *mut libc::c_void;
+        match unsafe { ptrace::write(pid, word_addr as AddressType, data) } {
+            Ok(()) => done += n,
+            Err(_) if done > 0 => break,
+            Err(e) => return Err(SysError::new(e as i32)),
+        }
+    }
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::{
+        sys::signal,
+        unistd::{fork, ForkResult},
+    };
+
+    use super::*;
+
+    struct TestContext {
+        pid: Pid,
+    }
+
+    impl Context for TestContext {
+        fn tid(&self) -> Pid {
+            self.pid
+        }
+        fn task_init_regs(&self) -> libc::user_regs_struct {
+            unimplemented!()
+        }
+        fn ptrace_set_regs(&self, _: libc::user_regs_struct) -> nix::Result<()> {
+            unimplemented!()
+        }
+    }
+
+    // Requires permission to ptrace a child, so run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn copy_in_out_child_memory() {
+        let mut buf = *b"hello from the tracee";
+        let addr = Addr(buf.as_mut_ptr() as u64);
+        let pid = match unsafe { fork() }.unwrap() {
+            ForkResult::Child => {
+                ptrace::traceme().unwrap();
+                signal::raise(Signal::SIGSTOP).unwrap();
+                unsafe { libc::_exit(0) };
+            }
+            ForkResult::Parent { child } => child,
+        };
+        assert!(matches!(
+            waitpid(pid, None),
+            Ok(WaitStatus::Stopped(_, Signal::SIGSTOP))
+        ));
+        let ctx = TestContext { pid };
+        let address_space = PtraceAddressSpace;
+
+        let mut dst = [0; 21];
+        assert_eq!(address_space.copy_in(addr, &mut dst, &ctx), Ok(21));
+        assert_eq!(&dst, b"hello from the tracee");
+        assert_eq!(peek_data(pid, Addr(addr.0 + 3), &mut dst[..5]), Ok(5));
+        assert_eq!(&dst[..5], b"lo fr");
+
+        assert_eq!(
+            address_space.copy_out(Addr(addr.0 + 6), b"FROM", &ctx),
+            Ok(4)
+        );
+        assert_eq!(poke_data(pid, Addr(addr.0 + 15), b"TR"), Ok(2));
+        assert_eq!(address_space.copy_in(addr, &mut dst, &ctx), Ok(21));
+        assert_eq!(&dst, b"hello FROM the TRacee");
+
+        ptrace::cont(pid, None).unwrap();
+        waitpid(pid, None).unwrap();
+    }
+}
@@ -13,38 +13,38 @@ use std::fmt::Display;
 
 // BPF Instruction classes.
 // See /usr/include/linux/bpf_common.h .
-const BPF_LD: u16 = 0x00;
-const BPF_ALU: u16 = 0x04;
-const BPF_JMP: u16 = 0x05;
-const BPF_RET: u16 = 0x06;
+pub(crate) const BPF_LD: u16 = 0x00;
+pub(crate) const BPF_ALU: u16 = 0x04;
+pub(crate) const BPF_JMP: u16 = 0x05;
+pub(crate) const BPF_RET: u16 = 0x06;
 
 // BPF ld/ldx fields.
 // See /usr/include/linux/bpf_common.h .
-const BPF_W: u16 = 0x00;
-const BPF_ABS: u16 = 0x20;
+pub(crate) const BPF_W: u16 = 0x00;
+pub(crate) const BPF_ABS: u16 = 0x20;
 
 // BPF alu fields.
 // See /usr/include/linux/bpf_common.h .
-const BPF_AND: u16 = 0x50;
+pub(crate) const BPF_AND: u16 = 0x50;
 
 // BPF jmp fields.
 // See /usr/include/linux/bpf_common.h .
-const BPF_JA: u16 = 0x00;
-const BPF_JEQ: u16 = 0x10;
-const BPF_JGT: u16 = 0x20;
-const BPF_JGE: u16 = 0x30;
-const BPF_K: u16 = 0x00;
+pub(crate) const BPF_JA: u16 = 0x00;
+pub(crate) const BPF_JEQ: u16 = 0x10;
+pub(crate) const BPF_JGT: u16 = 0x20;
+pub(crate) const BPF_JGE: u16 = 0x30;
+pub(crate) const BPF_K: u16 = 0x00;
 
 // Return codes for BPF programs.
 // See /usr/include/linux/seccomp.h .
-const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
-const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
-const SECCOMP_RET_KILL_THREAD: u32 = 0x0000_0000;
+pub(crate) const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+pub(crate) const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+pub(crate) const SECCOMP_RET_KILL_THREAD: u32 = 0x0000_0000;
 const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
-const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
-const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
-const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
-const SECCOMP_RET_MASK: u32 = 0x0000_ffff;
+pub(crate) const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+pub(crate) const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
+pub(crate) const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
+pub(crate) const SECCOMP_RET_MASK: u32 = 0x0000_ffff;
 
 // Architecture identifier.
 // See /usr/include/linux/audit.h .
@@ -96,7 +96,7 @@ impl<'de> Deserialize<'de> for Comment {
 
 /// Seccomp errors.
 #[derive(Debug, PartialEq)]
-pub(crate) enum Error {
+pub enum Error {
     /// Attempting to add an empty vector of rules to the rule chain of a syscall.
     EmptyRulesVector,
     /// Filter exceeds the maximum number of instructions that a BPF program can have.
@@ -132,7 +132,7 @@ type Result<T> = std::result::Result<T, Error>;
 /// Supported target architectures.
 #[allow(non_camel_case_types)]
 #[derive(Debug, PartialEq, Clone, Copy)]
-pub(crate) enum TargetArch {
+pub enum TargetArch {
     /// x86_64 arch
     x86_64,
     /// aarch64 arch
@@ -141,7 +141,7 @@ pub(crate) enum TargetArch {
 
 /// Errors related to target arch.
 #[derive(Debug, PartialEq)]
-pub(crate) enum TargetArchError {
+pub enum TargetArchError {
     /// Invalid string.
     InvalidString(String),
 }
@@ -158,7 +158,7 @@ impl Display for TargetArchError {
 
 impl TargetArch {
     /// Get the arch audit value.
-    fn get_audit_value(self) -> u32 {
+    pub(crate) fn get_audit_value(self) -> u32 {
         match self {
             TargetArch::x86_64 => AUDIT_ARCH_X86_64,
             TargetArch::aarch64 => AUDIT_ARCH_AARCH64,
@@ -194,7 +194,7 @@ impl From<TargetArch> for &str {
 /// Comparison to perform when matching a condition.
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
-pub(crate) enum SeccompCmpOp {
+pub enum SeccompCmpOp {
     /// Argument value is equal to the specified value.
     Eq,
     /// Argument value is greater than or equal to the specified value.
@@ -214,7 +214,7 @@ pub(crate) enum SeccompCmpOp {
 /// Seccomp argument value length.
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
-pub(crate) enum SeccompCmpArgLen {
+pub enum SeccompCmpArgLen {
     /// Argument value length is 4 bytes.
     Dword,
     /// Argument value length is 8 bytes.
@@ -224,7 +224,7 @@ pub(crate) enum SeccompCmpArgLen {
 /// Condition that syscall must match in order to satisfy a rule.
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 #[serde(deny_unknown_fields)]
-pub(crate) struct SeccompCondition {
+pub struct SeccompCondition {
     /// Index of the argument that is to be compared.
     #[serde(rename = "index")]
     arg_number: u8,
@@ -244,7 +244,7 @@ pub(crate) struct SeccompCondition {
 /// Actions that `seccomp` can apply to process calling a syscall.
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub(crate) enum SeccompAction {
+pub enum SeccompAction {
     /// Allows syscall.
     Allow,
     /// Returns from syscall with specified error number.
@@ -267,7 +267,7 @@ pub(crate) enum SeccompAction {
 /// The action of the first rule that matches will be applied to the calling process.
 /// If no rule matches the default action is applied.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct SeccompRule {
+pub struct SeccompRule {
     /// Conditions of rule that need to match in order for the rule to get matched.
     conditions: Vec<SeccompCondition>,
     /// Action applied to calling process if rule gets matched.
@@ -275,11 +275,11 @@ pub(crate) struct SeccompRule {
 }
 
 /// Type that associates the syscall number to its SeccompRules.
-pub(crate) type SeccompRuleMap = BTreeMap<i64, Vec<SeccompRule>>;
+pub type SeccompRuleMap = BTreeMap<i64, Vec<SeccompRule>>;
 
 /// Filter containing rules assigned to syscall numbers.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct SeccompFilter {
+pub struct SeccompFilter {
     /// Map of syscall numbers and corresponding rule chains.
     rules: SeccompRuleMap,
     /// Default action to apply to syscall numbers that do not exist in the hash map.
@@ -289,6 +289,31 @@ pub(crate) struct SeccompFilter {
 }
 
 impl SeccompCondition {
+    /// Creates a new `SeccompCondition`.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg_number` - The index of the argument that is to be compared.
+    /// * `arg_len` - The length of the argument value that is to be compared.
+    /// * `operator` - The comparison to perform.
+    /// * `value` - The value that will be compared with the argument value.
+    pub fn new(
+        arg_number: u8,
+        arg_len: SeccompCmpArgLen,
+        operator: SeccompCmpOp,
+        value: u64,
+    ) -> Result<Self> {
+        let instance = Self {
+            arg_number,
+            arg_len,
+            operator,
+            value,
+            comment: None,
+        };
+
+        instance.validate().map(|_| Ok(instance))?
+    }
+
     /// Validates the SeccompCondition data
     pub fn validate(&self) -> Result<()> {
         // Checks that the given argument number is valid.
@@ -894,26 +919,6 @@ mod tests {
         (syscall_number, rules)
     }
 
-    impl SeccompCondition {
-        // Creates a new `SeccompCondition`.
-        pub fn new(
-            arg_number: u8,
-            arg_len: SeccompCmpArgLen,
-            operator: SeccompCmpOp,
-            value: u64,
-        ) -> Result<Self> {
-            let instance = Self {
-                arg_number,
-                arg_len,
-                operator,
-                value,
-                comment: None,
-            };
-
-            instance.validate().map(|_| Ok(instance))?
-        }
-    }
-
     // The type of the `req` parameter is different for the `musl` library. This will enable
     // successful build for other non-musl libraries.
     #[cfg(target_env = "musl")]
@@ -0,0 +1,346 @@
+//! This module evaluates BPF programs the way the kernel does for a seccomp filter. It is meant
+//! for the syscalls which never reach the seccomp of the host, e.g. the ones that a tracer
+//! emulates through `PTRACE_SYSEMU`.
+
+use crate::backend::{
+    SeccompAction, TargetArch, BPF_ABS, BPF_ALU, BPF_AND, BPF_JA, BPF_JEQ, BPF_JGE, BPF_JGT,
+    BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W, SECCOMP_RET_ALLOW, SECCOMP_RET_ERRNO,
+    SECCOMP_RET_KILL_THREAD, SECCOMP_RET_LOG, SECCOMP_RET_MASK, SECCOMP_RET_TRACE,
+    SECCOMP_RET_TRAP,
+};
+use crate::BpfProgramRef;
+
+// BPF Instruction classes which are not emitted by the backend.
+// See /usr/include/linux/bpf_common.h .
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_MISC: u16 = 0x07;
+
+// BPF ld/ldx fields.
+// See /usr/include/linux/bpf_common.h .
+const BPF_IMM: u16 = 0x00;
+const BPF_LEN: u16 = 0x80;
+const BPF_MEM: u16 = 0x60;
+
+// BPF alu fields.
+// See /usr/include/linux/bpf_common.h .
+const BPF_ADD: u16 = 0x00;
+const BPF_SUB: u16 = 0x10;
+const BPF_MUL: u16 = 0x20;
+const BPF_DIV: u16 = 0x30;
+const BPF_OR: u16 = 0x40;
+const BPF_LSH: u16 = 0x60;
+const BPF_RSH: u16 = 0x70;
+const BPF_NEG: u16 = 0x80;
+const BPF_MOD: u16 = 0x90;
+const BPF_XOR: u16 = 0xa0;
+
+// BPF jmp fields.
+// See /usr/include/linux/bpf_common.h .
+const BPF_JSET: u16 = 0x40;
+const BPF_X: u16 = 0x08;
+
+// BPF ret and misc fields.
+// See /usr/include/linux/bpf_common.h and /usr/include/linux/filter.h .
+const BPF_A: u16 = 0x10;
+const BPF_TAX: u16 = 0x00;
+const BPF_TXA: u16 = 0x80;
+
+// The number of words in the scratch memory of a BPF program.
+const BPF_MEMWORDS: usize = 16;
+
+// The part of the return value of a seccomp filter that holds the action.
+// See /usr/include/linux/seccomp.h .
+const SECCOMP_RET_ACTION_FULL: u32 = 0xffff_0000;
+
+/// Data examined by a seccomp filter when a syscall is made.
+/// See `struct seccomp_data` in /usr/include/linux/seccomp.h .
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SeccompData {
+    /// The syscall number.
+    pub nr: i32,
+    /// The `AUDIT_ARCH_*` value of the calling convention.
+    pub arch: u32,
+    /// The instruction pointer at the time of the syscall.
+    pub instruction_pointer: u64,
+    /// The syscall arguments.
+    pub args: [u64; 6],
+}
+
+impl SeccompData {
+    // The size of `struct seccomp_data` in bytes.
+    const SIZE: u32 = 64;
+
+    /// Creates the data of a syscall made with the calling convention of `arch`.
+    ///
+    /// # Arguments
+    ///
+    /// * `nr` - The syscall number.
+    /// * `arch` - The architecture of the caller.
+    /// * `instruction_pointer` - The instruction pointer at the time of the syscall.
+    /// * `args` - The syscall arguments.
+    pub fn new(nr: i32, arch: TargetArch, instruction_pointer: u64, args: [u64; 6]) -> Self {
+        Self {
+            nr,
+            arch: arch.get_audit_value(),
+            instruction_pointer,
+            args,
+        }
+    }
+
+    // Loads the 32 bit word at `offset`, as if the structure was laid out in little endian
+    // order. Returns `None` for unaligned or out of bound accesses.
+    fn load_word(&self, offset: u32) -> Option<u32> {
+        if offset % 4 != 0 || offset >= Self::SIZE {
+            return None;
+        }
+        let word = match offset {
+            0 => self.nr as u32,
+            4 => self.arch,
+            8 => self.instruction_pointer as u32,
+            12 => (self.instruction_pointer >> 32) as u32,
+            _ => {
+                let arg = self.args[(offset as usize - 16) / 8];
+                if offset % 8 == 0 {
+                    arg as u32
+                } else {
+                    (arg >> 32) as u32
+                }
+            }
+        };
+        Some(word)
+    }
+}
+
+impl From<u32> for SeccompAction {
+    /// Decodes the return value of a seccomp filter.
+    ///
+    /// Unknown actions are treated as [`SeccompAction::KillProcess`], just as the kernel does.
+    ///
+    /// [`SeccompAction::KillProcess`]: enum.SeccompAction.html#variant.KillProcess
+    fn from(ret: u32) -> Self {
+        let data = ret & SECCOMP_RET_MASK;
+        match ret & SECCOMP_RET_ACTION_FULL {
+            SECCOMP_RET_ALLOW => SeccompAction::Allow,
+            SECCOMP_RET_ERRNO => SeccompAction::Errno(data),
+            SECCOMP_RET_KILL_THREAD => SeccompAction::KillThread,
+            SECCOMP_RET_LOG => SeccompAction::Log,
+            SECCOMP_RET_TRACE => SeccompAction::Trace(data),
+            SECCOMP_RET_TRAP => SeccompAction::Trap,
+            _ => SeccompAction::KillProcess,
+        }
+    }
+}
+
+/// Runs a seccomp BPF program against the data of a syscall and returns the resulting action.
+///
+/// A program which would be rejected by the kernel, e.g. because it reads out of bounds or
+/// contains an unknown instruction, results in [`SeccompAction::KillProcess`].
+///
+/// # Arguments
+///
+/// * `program` - The BPF program to be run.
+/// * `data` - The data of the syscall that is examined.
+///
+/// [`SeccompAction::KillProcess`]: enum.SeccompAction.html#variant.KillProcess
+pub fn evaluate_filter(program: BpfProgramRef, data: &SeccompData) -> SeccompAction {
+    run(program, data).map_or(SeccompAction::KillProcess, SeccompAction::from)
+}
+
+// Interprets the BPF program and returns the value it returns.
+fn run(program: BpfProgramRef, data: &SeccompData) -> Option<u32> {
+    let mut a: u32 = 0;
+    let mut x: u32 = 0;
+    let mut mem = [0u32; BPF_MEMWORDS];
+    let mut pc = 0usize;
+
+    loop {
+        let insn = program.get(pc)?;
+        pc += 1;
+        let k = insn.k;
+        let code = insn.code;
+
+        match code & 0x07 {
+            BPF_LD => {
+                a = match code & 0xf8 {
+                    c if c == BPF_W + BPF_ABS => data.load_word(k)?,
+                    c if c == BPF_W + BPF_LEN => SeccompData::SIZE,
+                    BPF_IMM => k,
+                    BPF_MEM => *mem.get(k as usize)?,
+                    _ => return None,
+                }
+            }
+            BPF_LDX => {
+                x = match code & 0xf8 {
+                    c if c == BPF_W + BPF_LEN => SeccompData::SIZE,
+                    BPF_IMM => k,
+                    BPF_MEM => *mem.get(k as usize)?,
+                    _ => return None,
+                }
+            }
+            BPF_ST => *mem.get_mut(k as usize)? = a,
+            BPF_STX => *mem.get_mut(k as usize)? = x,
+            BPF_ALU => {
+                let operand = if code & BPF_X != 0 { x } else { k };
+                a = match code & 0xf0 {
+                    BPF_ADD => a.wrapping_add(operand),
+                    BPF_SUB => a.wrapping_sub(operand),
+                    BPF_MUL => a.wrapping_mul(operand),
+                    BPF_DIV => a.checked_div(operand)?,
+                    BPF_MOD => a.checked_rem(operand)?,
+                    BPF_OR => a | operand,
+                    BPF_AND => a & operand,
+                    BPF_XOR => a ^ operand,
+                    BPF_LSH => a.checked_shl(operand)?,
+                    BPF_RSH => a.checked_shr(operand)?,
+                    BPF_NEG => a.wrapping_neg(),
+                    _ => return None,
+                }
+            }
+            BPF_JMP => {
+                let operand = if code & BPF_X != 0 { x } else { k };
+                let taken = match code & 0xf0 {
+                    BPF_JA => {
+                        pc = pc.checked_add(k as usize)?;
+                        continue;
+                    }
+                    BPF_JEQ => a == operand,
+                    BPF_JGT => a > operand,
+                    BPF_JGE => a >= operand,
+                    BPF_JSET => a & operand != 0,
+                    _ => return None,
+                };
+                pc += usize::from(if taken { insn.jt } else { insn.jf });
+            }
+            BPF_RET => {
+                return match code & 0x18 {
+                    BPF_K => Some(k),
+                    BPF_A => Some(a),
+                    _ => None,
+                }
+            }
+            BPF_MISC => match code & 0xf8 {
+                BPF_TAX => x = a,
+                BPF_TXA => a = x,
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{
+        SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter, SeccompRule,
+    };
+    use crate::common::BpfProgram;
+    use std::convert::TryInto;
+
+    fn data(nr: i32, args: [u64; 6]) -> SeccompData {
+        SeccompData::new(nr, TargetArch::x86_64, 0, args)
+    }
+
+    #[test]
+    fn test_evaluate_filter() {
+        let rules = vec![
+            (
+                libc::SYS_read,
+                vec![SeccompRule::new(vec![], SeccompAction::Allow)],
+            ),
+            (
+                libc::SYS_ioctl,
+                vec![
+                    SeccompRule::new(
+                        vec![SeccompCondition::new(
+                            1,
+                            SeccompCmpArgLen::Qword,
+                            SeccompCmpOp::Eq,
+                            0x1_0000_0002,
+                        )
+                        .unwrap()],
+                        SeccompAction::Allow,
+                    ),
+                    SeccompRule::new(
+                        vec![SeccompCondition::new(
+                            2,
+                            SeccompCmpArgLen::Dword,
+                            SeccompCmpOp::MaskedEq(0xf0),
+                            0x30,
+                        )
+                        .unwrap()],
+                        SeccompAction::KillThread,
+                    ),
+                ],
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let program: BpfProgram =
+            SeccompFilter::new(rules, SeccompAction::Errno(libc::EPERM as u32), "x86_64")
+                .unwrap()
+                .try_into()
+                .unwrap();
+
+        let cases = vec![
+            (data(libc::SYS_read as i32, [0; 6]), SeccompAction::Allow),
+            (
+                data(libc::SYS_write as i32, [0; 6]),
+                SeccompAction::Errno(libc::EPERM as u32),
+            ),
+            (
+                data(libc::SYS_ioctl as i32, [0, 0x1_0000_0002, 0, 0, 0, 0]),
+                SeccompAction::Allow,
+            ),
+            (
+                data(libc::SYS_ioctl as i32, [0, 0x2, 0, 0, 0, 0]),
+                SeccompAction::Errno(libc::EPERM as u32),
+            ),
+            (
+                data(libc::SYS_ioctl as i32, [0, 0, 0x135, 0, 0, 0]),
+                SeccompAction::KillThread,
+            ),
+        ];
+        for (data, action) in cases {
+            assert_eq!(evaluate_filter(&program, &data), action);
+        }
+
+        // The architecture is checked before anything else.
+        let mut foreign = data(libc::SYS_read as i32, [0; 6]);
+        foreign.arch = TargetArch::aarch64.get_audit_value();
+        assert_eq!(
+            evaluate_filter(&program, &foreign),
+            SeccompAction::KillProcess
+        );
+    }
+
+    #[test]
+    fn test_evaluate_invalid_program() {
+        // Out of bound load.
+        let program = vec![
+            crate::common::sock_filter {
+                code: BPF_LD + BPF_W + BPF_ABS,
+                jt: 0,
+                jf: 0,
+                k: SeccompData::SIZE,
+            },
+            crate::common::sock_filter {
+                code: BPF_RET + BPF_K,
+                jt: 0,
+                jf: 0,
+                k: SECCOMP_RET_ALLOW,
+            },
+        ];
+        assert_eq!(
+            evaluate_filter(&program, &data(0, [0; 6])),
+            SeccompAction::KillProcess
+        );
+        // Running off the end of the program.
+        assert_eq!(
+            evaluate_filter(&program[1..1], &data(0, [0; 6])),
+            SeccompAction::KillProcess
+        );
+    }
+}
@@ -5,7 +5,10 @@
 //! The library crate that defines common helper functions that are generally used in
 //! conjunction with seccompiler-bin.
 
+mod backend;
 mod common;
+mod interpreter;
+mod syscall_table;
 
 use bincode::Error as BincodeError;
 use bincode::{DefaultOptions, Options};
@@ -16,7 +19,13 @@ use std::io::Read;
 use std::sync::Arc;
 
 // Re-export the data types needed for calling the helper functions.
+pub use backend::{
+    Error as FilterError, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition,
+    SeccompFilter, SeccompRule, SeccompRuleMap, TargetArch, TargetArchError,
+};
 pub use common::{sock_filter, BpfProgram};
+pub use interpreter::{evaluate_filter, SeccompData};
+pub use syscall_table::SyscallTable;
 
 /// Type that associates a thread category to a BPF program.
 pub type BpfThreadMap = HashMap<String, Arc<BpfProgram>>;
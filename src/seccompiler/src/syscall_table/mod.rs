@@ -9,7 +9,7 @@ use std::collections::HashMap;
 
 /// Creates and owns a mapping from the arch-specific syscall name to the right number.
 #[derive(Debug)]
-pub(crate) struct SyscallTable {
+pub struct SyscallTable {
     map: HashMap<String, i64>,
     arch: TargetArch,
 }
@@ -18,6 +18,7 @@ pub(crate) struct SyscallTable {
 const MAP_CAPACITY: usize = 351;
 
 impl SyscallTable {
+    /// Creates the syscall table of the given architecture.
     pub fn new(arch: TargetArch) -> Self {
         let mut instance = Self {
             arch,
@@ -102,6 +102,53 @@ pub enum SegOrGap<K: num::Integer + num::Bounded + Copy> {
     Gap(Gap<K>),
 }
 
+// OverlapError is returned when a segment can't be inserted because it overlaps an existing one.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct OverlapError<K> {
+    pub range: Range<K>,
+    pub conflict: Range<K>,
+}
+
+impl<K: std::fmt::Debug> std::fmt::Display for OverlapError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "new segment {:?} overlaps existing segment {:?}",
+            self.range, self.conflict
+        )
+    }
+}
+
+impl<K: std::fmt::Debug> std::error::Error for OverlapError<K> {}
+
+// SegError describes why a segment couldn't be added to a Set.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SegError<K> {
+    Overlap { existing: Range<K> },
+    InvalidRange,
+}
+
+impl<K: std::fmt::Debug> std::fmt::Display for SegError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Overlap { existing } => {
+                write!(f, "new segment overlaps existing segment {:?}", existing)
+            }
+            Self::InvalidRange => write!(f, "invalid segment range"),
+        }
+    }
+}
+
+impl<K: std::fmt::Debug> std::error::Error for SegError<K> {}
+
+impl<K> From<OverlapError<K>> for SegError<K> {
+    fn from(err: OverlapError<K>) -> Self {
+        Self::Overlap {
+            existing: err.conflict,
+        }
+    }
+}
+
 pub trait SetOperations {
     type K;
     type V;
@@ -147,6 +194,15 @@ impl<
         }
     }
 
+    // clone_with_ops returns a copy of the set that uses the given operations. The operations
+    // must behave exactly like the ones of this set, or the copy may merge and split differently.
+    pub fn clone_with_ops(&self, operations: Box<dyn SetOperations<K = K, V = V>>) -> Self {
+        Self {
+            map: self.map.clone(),
+            operations,
+        }
+    }
+
     pub fn inner_map(&self) -> &BTreeMap<Range<K>, V> {
         &self.map
     }
@@ -162,16 +218,25 @@ impl<
     }
 
     pub fn span(&self) -> K {
+        self.span_with(|r, _| r.len())
+    }
+
+    pub fn span_range(&self, r: Range<K>) -> K {
+        self.span_range_with(r, |r, _| r.len())
+    }
+
+    // span_with returns the sum of f over all segments.
+    pub fn span_with<F: Fn(Range<K>, &V) -> K>(&self, f: F) -> K {
         let mut sz = K::zero();
-        let mut maybe_seg = self.first_segment();
-        while let Some(seg) = maybe_seg {
-            sz += seg.range.len();
-            maybe_seg = self.next_segment_of_seg(&seg);
+        for (r, v) in self.map.iter() {
+            sz += f(*r, v);
         }
         sz
     }
 
-    pub fn span_range(&self, r: Range<K>) -> K {
+    // span_range_with returns the sum of f over all segments intersecting r, where each
+    // segment's range is clipped to r before f is called.
+    pub fn span_range_with<F: Fn(Range<K>, &V) -> K>(&self, r: Range<K>, f: F) -> K {
         if r.end < r.start {
             panic!("invalid range: {:?}", r);
         }
@@ -182,12 +247,55 @@ impl<
         let mut maybe_seg = self.lower_bound_segment(r.start);
         while maybe_seg.map_or(false, |s| s.start() < r.end) {
             let seg = maybe_seg.unwrap();
-            sz += seg.range.intersect(&r).len();
+            sz += f(seg.range.intersect(&r), self.value(&seg));
             maybe_seg = self.next_segment_of_seg(&seg);
         }
         sz
     }
 
+    // coverage returns how many keys of r belong to a segment, which is the same as span_range.
+    pub fn coverage(&self, r: Range<K>) -> K {
+        self.span_range(r)
+    }
+
+    // fully_covered returns whether every key of r belongs to a segment. Unlike comparing
+    // coverage with the length of r, it stops at the first gap.
+    pub fn fully_covered(&self, r: Range<K>) -> bool {
+        if r.end < r.start {
+            panic!("invalid range: {:?}", r);
+        }
+        let mut covered_to = r.start;
+        let mut maybe_seg = self.find_segment(r.start);
+        while covered_to < r.end {
+            match maybe_seg {
+                Some(seg) if seg.start() <= covered_to => {
+                    covered_to = seg.end();
+                    maybe_seg = self.next_segment_of_seg(&seg);
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    // segments returns an iterator over the range and value of every segment in ascending order.
+    pub fn segments(&self) -> impl Iterator<Item = (Range<K>, &V)> + '_ {
+        self.map.iter().map(|(k, v)| (*k, v))
+    }
+
+    pub fn segments_mut(&mut self) -> impl Iterator<Item = (Range<K>, &mut V)> + '_ {
+        self.map.iter_mut().map(|(k, v)| (*k, v))
+    }
+
+    // gaps returns an iterator over the non-empty gaps in ascending order.
+    pub fn gaps(&self) -> Gaps<'_, K, V> {
+        let next = self.first_gap().or_else(|| {
+            self.first_segment()
+                .and_then(|seg| self.next_gap_of_seg(&seg))
+        });
+        Gaps { set: self, next }
+    }
+
     pub fn last_segment(&self) -> Option<Seg<K>> {
         let mut back = self.map.iter().rev();
         let last_range = back.next().map(|(k, _)| *k)?;
@@ -386,6 +494,24 @@ impl<
         }
     }
 
+    // find_overlapping returns an iterator over every segment intersecting r in ascending order.
+    pub fn find_overlapping(&self, r: Range<K>) -> impl Iterator<Item = Seg<K>> + '_ {
+        let first = if r.start < r.end {
+            self.lower_bound_segment(r.start)
+        } else {
+            None
+        };
+        std::iter::successors(first, move |seg| self.next_segment_of_seg(seg))
+            .take_while(move |seg| seg.start() < r.end)
+    }
+
+    // iter_range returns an iterator over the range and value of every segment intersecting r in
+    // ascending order, where each range is clipped to r.
+    pub fn iter_range(&self, r: Range<K>) -> impl Iterator<Item = (Range<K>, &V)> + '_ {
+        self.find_overlapping(r)
+            .map(move |seg| (seg.range().intersect(&r), self.value(&seg)))
+    }
+
     pub fn upper_bound_segment(&self, max: K) -> Option<Seg<K>> {
         match self.find_segment(max) {
             Some(seg) => Some(seg),
@@ -432,22 +558,65 @@ impl<
         None
     }
 
+    // find_overlap returns the range of an existing segment overlapping the given range.
+    fn find_overlap(&self, range: Range<K>) -> Option<Range<K>> {
+        let key = Range {
+            start: range.start,
+            end: range.start,
+        };
+        let prev = self.map.range(..key).next_back().map(|(k, _)| *k);
+        if let Some(prev) = prev.filter(|p| p.end > range.start) {
+            return Some(prev);
+        }
+        let next = self.map.range(key..).next().map(|(k, _)| *k);
+        next.filter(|n| n.start < range.end)
+    }
+
     pub fn add(&mut self, range: Range<K>, val: V) -> bool {
-        let start = range.start;
-        let end = range.end;
-        if end <= start {
-            panic!("invalid segment range: ({}, {})", start, end);
+        match self.add_checked(range, val) {
+            Ok(_) => true,
+            Err(SegError::InvalidRange) => {
+                panic!("invalid segment range: ({}, {})", range.start, range.end)
+            }
+            Err(_) => false,
         }
-        let gap = match self.find_gap(start) {
-            Some(gap) => gap,
-            None => return false,
-        };
-        if end > gap.end() {
-            false
-        } else {
+    }
+
+    // add_checked is the same as add, except that it reports why the segment couldn't be added
+    // instead of returning false or panicking.
+    pub fn add_checked(&mut self, range: Range<K>, val: V) -> Result<Seg<K>, SegError<K>> {
+        if range.end <= range.start {
+            return Err(SegError::InvalidRange);
+        }
+        Ok(self.try_insert(range, val)?)
+    }
+
+    // extend_merging adds segments given in any order, merging them with each other and with
+    // the existing segments where the operations permit. All the segments are validated before
+    // any of them is inserted, so the set is left untouched on error.
+    pub fn extend_merging<I: IntoIterator<Item = (Range<K>, V)>>(
+        &mut self,
+        items: I,
+    ) -> Result<(), SegError<K>> {
+        let mut items: Vec<_> = items.into_iter().collect();
+        items.sort_by_key(|(range, _)| range.start);
+        let mut prev: Option<Range<K>> = None;
+        for (range, _) in &items {
+            if range.end <= range.start {
+                return Err(SegError::InvalidRange);
+            }
+            if let Some(prev) = prev.filter(|p| p.end > range.start) {
+                return Err(SegError::Overlap { existing: prev });
+            }
+            if let Some(existing) = self.find_overlap(*range) {
+                return Err(SegError::Overlap { existing });
+            }
+            prev = Some(*range);
+        }
+        for (range, val) in items {
             self.insert(range, val);
-            true
         }
+        Ok(())
     }
 
     pub fn add_without_merging(&mut self, range: Range<K>, val: V) -> bool {
@@ -456,15 +625,25 @@ impl<
         if end <= start {
             panic!("invalid segment range: ({}, {})", start, end);
         }
-        let gap = match self.find_gap(start) {
-            Some(gap) => gap,
-            None => return false,
-        };
-        if end > gap.end() {
-            false
-        } else {
-            self.insert_without_merging(&gap, range, val);
-            true
+        if self.find_overlap(range).is_some() {
+            return false;
+        }
+        let gap = self
+            .find_gap(start)
+            .expect("non-overlapping range should start in a gap");
+        self.insert_without_merging(&gap, range, val);
+        true
+    }
+
+    // try_insert is the same as insert, except that it returns an error describing the
+    // conflicting segment instead of panicking when the range overlaps an existing segment.
+    pub fn try_insert(&mut self, range: Range<K>, val: V) -> Result<Seg<K>, OverlapError<K>> {
+        if range.end < range.start {
+            panic!("invalid segment range: ({}, {})", range.start, range.end);
+        }
+        match self.find_overlap(range) {
+            Some(conflict) => Err(OverlapError { range, conflict }),
+            None => Ok(self.insert(range, val)),
         }
     }
 
@@ -649,6 +828,42 @@ impl<
         )
     }
 
+    // remove_range removes all segments intersecting the given range and returns them in
+    // ascending order. Segments straddling either end of the range are split first, so only
+    // the parts inside the range are removed.
+    pub fn remove_range(&mut self, r: Range<K>) -> Vec<(Range<K>, V)> {
+        if r.end < r.start {
+            panic!("invalid range: {:?}", r);
+        }
+        if r.start == r.end {
+            return Vec::new();
+        }
+        for split in [r.start, r.end] {
+            if let Some(seg) = self.find_segment(split) {
+                if seg.range.can_split_at(split) {
+                    self.split_unchecked(&seg, split);
+                }
+            }
+        }
+        let keys: Vec<_> = self
+            .map
+            .range(
+                Range {
+                    start: r.start,
+                    end: r.start,
+                }..,
+            )
+            .take_while(|(k, _)| k.start < r.end)
+            .map(|(k, _)| *k)
+            .collect();
+        keys.into_iter()
+            .map(|k| {
+                let v = self.map.remove(&k).unwrap();
+                (k, v)
+            })
+            .collect()
+    }
+
     pub fn next_non_empty(&self, seg: &Seg<K>) -> Option<SegOrGap<K>> {
         if let Some(gap) = self.next_gap_of_seg(seg) {
             let start = gap.range.start.unwrap();
@@ -946,6 +1161,37 @@ impl<
         seg
     }
 
+    // get returns the value of the segment containing key, or None if key is in a gap.
+    pub fn get(&self, key: K) -> Option<&V> {
+        let k = Range {
+            start: key,
+            end: key,
+        };
+        self.map
+            .range(..=k)
+            .next_back()
+            .filter(|(r, _)| r.end > key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, key: K) -> Option<&mut V> {
+        let k = Range {
+            start: key,
+            end: key,
+        };
+        self.map
+            .range_mut(..=k)
+            .next_back()
+            .filter(|(r, _)| r.end > key)
+            .map(|(_, v)| v)
+    }
+
+    // update applies f to the value of the segment containing key in place, and returns false
+    // without calling f if key is in a gap.
+    pub fn update<F: FnOnce(&mut V)>(&mut self, key: K, f: F) -> bool {
+        self.get_mut(key).map(f).is_some()
+    }
+
     pub fn value(&self, seg: &Seg<K>) -> &V {
         self.map.get(&seg.range).unwrap()
     }
@@ -954,10 +1200,10 @@ impl<
         self.map.get_mut(&seg.range).unwrap()
     }
 
-    pub fn apply_contiguous(
+    pub fn apply_contiguous<F: FnMut(&mut BTreeMap<Range<K>, V>, Seg<K>)>(
         &mut self,
         r: Range<K>,
-        f: fn(&mut BTreeMap<Range<K>, V>, Seg<K>),
+        mut f: F,
     ) -> Option<Gap<K>> {
         let mut seg = match self.find_segment(r.start) {
             Some(s) => s,
@@ -1057,6 +1303,37 @@ impl<
     }
 }
 
+pub struct Gaps<'a, K: num::Integer + num::Bounded + Copy, V> {
+    set: &'a Set<K, V>,
+    next: Option<Gap<K>>,
+}
+
+impl<
+        'a,
+        K: num::Integer
+            + num::Bounded
+            + num::ToPrimitive
+            + std::ops::AddAssign
+            + std::fmt::Display
+            + std::fmt::Debug
+            + Copy
+            + Clone,
+        V: std::cmp::PartialEq + Clone,
+    > Iterator for Gaps<'a, K, V>
+{
+    type Item = Gap<K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let gap = self.next.take()?;
+            self.next = self.set.next_gap_of_gap(&gap);
+            if !gap.is_empty() {
+                return Some(gap);
+            }
+        }
+    }
+}
+
 pub struct SegmentDataSlices<K, V> {
     pub start: Vec<K>,
     pub end: Vec<K>,
@@ -1412,6 +1689,375 @@ mod tests {
         }
     }
 
+    #[test]
+    fn try_insert_overlap() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        let existing = Range {
+            start: 100,
+            end: 200,
+        };
+        assert!(s.try_insert(existing, 0).is_ok());
+
+        let overlapping = [
+            Range {
+                start: 50,
+                end: 150,
+            },
+            Range {
+                start: 150,
+                end: 250,
+            },
+            Range {
+                start: 120,
+                end: 130,
+            },
+            Range {
+                start: 50,
+                end: 250,
+            },
+        ];
+        for r in overlapping {
+            assert_eq!(
+                s.try_insert(r, 1),
+                Err(OverlapError {
+                    range: r,
+                    conflict: existing,
+                })
+            );
+        }
+        assert_eq!(s.count_segments(), 1);
+
+        let adjacent = Range {
+            start: 200,
+            end: 300,
+        };
+        let seg = s.try_insert(adjacent, 1).unwrap();
+        assert_eq!(seg.range(), adjacent);
+        assert_eq!(s.count_segments(), 2);
+    }
+
+    #[test]
+    fn add_checked_errors() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        let existing = Range {
+            start: 100,
+            end: 200,
+        };
+        let seg = s.add_checked(existing, 0).unwrap();
+        assert_eq!(seg.range(), existing);
+
+        assert_eq!(
+            s.add_checked(
+                Range {
+                    start: 150,
+                    end: 250
+                },
+                1
+            ),
+            Err(SegError::Overlap { existing })
+        );
+        assert_eq!(
+            s.add_checked(
+                Range {
+                    start: 300,
+                    end: 300
+                },
+                1
+            ),
+            Err(SegError::InvalidRange)
+        );
+        assert_eq!(
+            s.add_checked(
+                Range {
+                    start: 300,
+                    end: 200
+                },
+                1
+            ),
+            Err(SegError::InvalidRange)
+        );
+        assert_eq!(s.count_segments(), 1);
+
+        // Adjacent segments with the same value are merged just like add.
+        let seg = s
+            .add_checked(
+                Range {
+                    start: 200,
+                    end: 300,
+                },
+                0,
+            )
+            .unwrap();
+        assert_eq!(
+            seg.range(),
+            Range {
+                start: 100,
+                end: 300
+            }
+        );
+        assert_eq!(s.count_segments(), 1);
+    }
+
+    #[test]
+    fn extend_merging() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        assert!(s.add_without_merging(Range { start: 20, end: 30 }, 1));
+
+        let items = vec![
+            (Range { start: 40, end: 50 }, 2),
+            (Range { start: 10, end: 20 }, 1),
+            (Range { start: 30, end: 40 }, 2),
+            (Range { start: 60, end: 70 }, 3),
+        ];
+        s.extend_merging(items).unwrap();
+        let got: Vec<_> = s.segments().map(|(r, v)| (r.start, r.end, *v)).collect();
+        assert_eq!(got, vec![(10, 30, 1), (30, 50, 2), (60, 70, 3)]);
+
+        // Nothing is inserted when any of the segments is rejected.
+        let items = vec![
+            (Range { start: 80, end: 90 }, 4),
+            (Range { start: 65, end: 75 }, 4),
+        ];
+        assert_eq!(
+            s.extend_merging(items),
+            Err(SegError::Overlap {
+                existing: Range { start: 60, end: 70 }
+            })
+        );
+        let items = vec![
+            (Range { start: 85, end: 95 }, 4),
+            (Range { start: 80, end: 90 }, 4),
+        ];
+        assert_eq!(
+            s.extend_merging(items),
+            Err(SegError::Overlap {
+                existing: Range { start: 80, end: 90 }
+            })
+        );
+        let items = vec![
+            (Range { start: 80, end: 90 }, 4),
+            (Range { start: 95, end: 95 }, 4),
+        ];
+        assert_eq!(s.extend_merging(items), Err(SegError::InvalidRange));
+        assert_eq!(s.count_segments(), 3);
+    }
+
+    #[test]
+    fn remove_range() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(0, 10, 0), (20, 30, 20), (30, 40, 30), (50, 60, 50)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+
+        assert!(s.remove_range(Range { start: 25, end: 25 }).is_empty());
+        assert_eq!(s.count_segments(), 4);
+
+        // [20, 30) and [50, 60) are split by Ops::split rather than removed whole.
+        let removed = s.remove_range(Range { start: 25, end: 55 });
+        let removed: Vec<_> = removed.iter().map(|(r, v)| (r.start, r.end, *v)).collect();
+        assert_eq!(removed, vec![(25, 30, 25), (30, 40, 30), (50, 55, 50)]);
+
+        let left: Vec<_> = s.segments().map(|(r, v)| (r.start, r.end, *v)).collect();
+        assert_eq!(left, vec![(0, 10, 0), (20, 25, 20), (55, 60, 55)]);
+
+        // A range within a gap removes nothing.
+        assert!(s.remove_range(Range { start: 10, end: 20 }).is_empty());
+        assert_eq!(s.count_segments(), 3);
+    }
+
+    #[test]
+    fn clone_with_ops() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(0, 10, 0), (20, 30, 20)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+
+        let mut c = s.clone_with_ops(Box::new(Ops {}));
+        assert_eq!(c.inner_map(), s.inner_map());
+
+        assert!(c.add(Range { start: 40, end: 50 }, 40));
+        c.remove_range(Range { start: 0, end: 5 });
+        for (_, v) in c.segments_mut() {
+            *v += 1;
+        }
+
+        let orig: Vec<_> = s.segments().map(|(r, v)| (r.start, r.end, *v)).collect();
+        assert_eq!(orig, vec![(0, 10, 0), (20, 30, 20)]);
+        let copy: Vec<_> = c.segments().map(|(r, v)| (r.start, r.end, *v)).collect();
+        assert_eq!(copy, vec![(5, 10, 6), (20, 30, 21), (40, 50, 41)]);
+    }
+
+    #[test]
+    fn apply_contiguous_closure() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(0, 10, 0), (10, 20, 10), (30, 40, 30)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+
+        let mut visited = Vec::new();
+        let gap = s.apply_contiguous(Range { start: 5, end: 35 }, |map, seg| {
+            *map.get_mut(&seg.range()).unwrap() += 1;
+            visited.push((seg.start(), seg.end()));
+        });
+        assert_eq!(gap.map(|g| (g.start(), g.end())), Some((20, 30)));
+        assert_eq!(visited, vec![(5, 10), (10, 20)]);
+
+        let vals: Vec<_> = s.segments().map(|(r, v)| (r.start, r.end, *v)).collect();
+        assert_eq!(
+            vals,
+            vec![(0, 5, 0), (5, 10, 6), (10, 20, 11), (30, 40, 30)]
+        );
+
+        let mut total = 0;
+        let gap = s.apply_contiguous(Range { start: 0, end: 20 }, |map, seg| {
+            total += map[&seg.range()];
+        });
+        assert!(gap.is_none());
+        assert_eq!(total, 17);
+    }
+
+    #[test]
+    fn span_with() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(0, 10, 1), (20, 30, 0), (30, 40, 2)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+
+        assert_eq!(s.span(), 30);
+        assert_eq!(s.span_with(|r, v| r.len() * *v as u64), 30);
+        assert_eq!(s.span_range(Range { start: 5, end: 35 }), 20);
+        assert_eq!(
+            s.span_range_with(Range { start: 5, end: 35 }, |r, v| r.len() * *v as u64),
+            15
+        );
+        assert_eq!(s.span_range_with(Range { start: 10, end: 20 }, |_, _| 1), 0);
+        assert_eq!(s.span_range_with(Range { start: 5, end: 5 }, |_, _| 1), 0);
+    }
+
+    #[test]
+    fn fully_covered() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(0, 10, 1), (10, 20, 2), (30, 40, 3)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+
+        assert!(s.fully_covered(Range { start: 0, end: 20 }));
+        assert!(s.fully_covered(Range { start: 5, end: 15 }));
+        assert!(s.fully_covered(Range { start: 30, end: 40 }));
+        assert!(s.fully_covered(Range { start: 25, end: 25 }));
+        assert!(!s.fully_covered(Range { start: 5, end: 35 }));
+        assert!(!s.fully_covered(Range { start: 15, end: 21 }));
+        assert!(!s.fully_covered(Range { start: 25, end: 35 }));
+        assert!(!s.fully_covered(Range { start: 35, end: 41 }));
+
+        assert_eq!(s.coverage(Range { start: 5, end: 35 }), 20);
+        assert_eq!(s.coverage(Range { start: 0, end: 20 }), 20);
+        assert_eq!(s.coverage(Range { start: 20, end: 30 }), 0);
+    }
+
+    #[test]
+    fn get() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(10, 20, 10), (20, 30, 20), (40, 50, 40)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+
+        assert_eq!(s.get(0), None);
+        assert_eq!(s.get(10), Some(&10));
+        assert_eq!(s.get(19), Some(&10));
+        assert_eq!(s.get(20), Some(&20));
+        assert_eq!(s.get(30), None);
+        assert_eq!(s.get(49), Some(&40));
+        assert_eq!(s.get(50), None);
+
+        *s.get_mut(25).unwrap() = 25;
+        assert_eq!(s.get(20), Some(&25));
+        assert!(s.get_mut(35).is_none());
+    }
+
+    #[test]
+    fn update() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(10, 20, 10), (20, 30, 20)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+
+        assert!(s.update(29, |v| *v += 5));
+        assert_eq!(s.get(20), Some(&25));
+        assert_eq!(s.get(10), Some(&10));
+        let mut called = false;
+        assert!(!s.update(30, |_| called = true));
+        assert!(!called);
+        assert_eq!(s.segments().count(), 2);
+    }
+
+    #[test]
+    fn find_overlapping() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(10, 20, 1), (20, 30, 2), (40, 50, 3)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+        let overlapping = |start, end| -> Vec<_> {
+            s.find_overlapping(Range { start, end })
+                .map(|seg| (seg.start(), seg.end()))
+                .collect()
+        };
+
+        assert_eq!(overlapping(15, 16), vec![(10, 20)]);
+        assert_eq!(overlapping(15, 45), vec![(10, 20), (20, 30), (40, 50)]);
+        assert_eq!(overlapping(0, 25), vec![(10, 20), (20, 30)]);
+        assert_eq!(overlapping(29, 41), vec![(20, 30), (40, 50)]);
+        assert_eq!(overlapping(30, 40), vec![]);
+        assert_eq!(overlapping(0, 10), vec![]);
+        assert_eq!(overlapping(50, 100), vec![]);
+        assert_eq!(overlapping(15, 15), vec![]);
+    }
+
+    #[test]
+    fn iter_range() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(10, 20, 1), (20, 30, 2), (40, 50, 3)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+        let clipped = |start, end| -> Vec<_> {
+            s.iter_range(Range { start, end })
+                .map(|(r, v)| (r.start, r.end, *v))
+                .collect()
+        };
+
+        // A segment covering the whole range yields the range itself.
+        assert_eq!(clipped(12, 18), vec![(12, 18, 1)]);
+        assert_eq!(clipped(15, 45), vec![(15, 20, 1), (20, 30, 2), (40, 45, 3)]);
+        assert_eq!(clipped(0, 25), vec![(10, 20, 1), (20, 25, 2)]);
+        assert_eq!(clipped(30, 40), vec![]);
+        assert_eq!(clipped(15, 15), vec![]);
+    }
+
+    #[test]
+    fn iterate_segments_and_gaps() {
+        let mut s: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        for (start, end, v) in [(0, 10, 1), (10, 20, 2), (30, 40, 3)] {
+            assert!(s.add_without_merging(Range { start, end }, v));
+        }
+
+        let segs: Vec<_> = s.segments().map(|(r, v)| (r.start, r.end, *v)).collect();
+        assert_eq!(segs, vec![(0, 10, 1), (10, 20, 2), (30, 40, 3)]);
+
+        for (_, v) in s.segments_mut().filter(|(r, _)| r.start >= 10) {
+            *v *= 10;
+        }
+        let vals: Vec<_> = s.segments().map(|(_, v)| *v).collect();
+        assert_eq!(vals, vec![1, 20, 30]);
+
+        let gaps: Vec<_> = s.gaps().map(|g| (g.start(), g.end())).collect();
+        assert_eq!(gaps, vec![(20, 30), (40, u64::MAX)]);
+
+        let empty: Set<u64, i32> = Set::new(Box::new(Ops {}));
+        assert_eq!(empty.segments().count(), 0);
+        let gaps: Vec<_> = empty.gaps().map(|g| (g.start(), g.end())).collect();
+        assert_eq!(gaps, vec![(0, u64::MAX)]);
+    }
+
     #[test]
     fn merge_split() {
         #[derive(Default)]
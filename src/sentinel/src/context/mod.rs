@@ -4,7 +4,10 @@ use std::{
     os::unix::prelude::{AsRawFd, RawFd},
     path::PathBuf,
     rc::Rc,
-    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
 };
 
 use anyhow::Context as AnyhowContext;
@@ -24,17 +27,18 @@ use fs::{
     host,
     inode::Inode,
     mount::{MountNamespace, MountSource, MountSourceFlags},
+    proc::SelfFile,
     setup_fs, Dirent, DirentRef, DockerImageInfo, FsContext,
 };
 use limit::LimitSet;
 use nix::{sys::ptrace, unistd::Pid};
 use once_cell::sync::OnceCell;
 use platform::Platform;
-use time::{Clock, HostClock, Time, Context as TimeContext};
+use time::{Clock, HostClock, MonotonicClock, Time, Context as TimeContext};
 use usage::memory::init_memory_accounting;
 
 use crate::{
-    kernel::{task::Task, Kernel},
+    kernel::{procfs, seccomp, task::Task, Kernel},
     mm::MemoryManager,
 };
 
@@ -44,6 +48,7 @@ pub struct Context {
     kernel: Kernel,
     tid: Option<Pid>,
     task: RwLock<Task>,
+    regs_clobbered: AtomicBool,
     fs_context: Option<FsContext>,
     platform: Platform,
     real_time_clock: Option<HostClock>,
@@ -53,6 +58,10 @@ pub struct Context {
     network_interface: RwLock<Interface<'static, TunTapInterface>>,
     network_device_fd: RawFd,
     used_ports: RwLock<HashSet<u16>>,
+    // The number of sockets bound to each (protocol, port).
+    bound_ports: RwLock<HashMap<(i32, u16), usize>>,
+    // The number of sockets subscribed to each multicast group.
+    multicast_groups: RwLock<HashMap<IpAddress, usize>>,
 }
 
 impl std::fmt::Debug for Context {
@@ -90,7 +99,7 @@ pub fn init_context(
 
     let mounts = {
         let flags = MountSourceFlags::default();
-        let msrc = Rc::new(MountSource::new(flags));
+        let msrc = Rc::new(MountSource::new_host(flags, "/"));
         let stable_attr =
             StableAttr::from_path("/").expect("failed to retrieve StableAttr from fd");
         let dir = host::Dir::new("/", &now);
@@ -123,16 +132,25 @@ pub fn init_context(
         .routes(routes)
         .hardware_addr(ethernet_addr.into())
         .neighbor_cache(neighbor_cache)
+        .ipv4_multicast_groups(BTreeMap::new())
         .finalize();
     let network_interface = RwLock::new(iface);
 
-    let task = RwLock::new(Task::new(mounts.clone()).expect("failed to initialize task"));
+    let mut task = Task::new(mounts.clone()).expect("failed to initialize task");
+    let seccomp = spec.linux().as_ref().and_then(|l| l.seccomp().as_ref());
+    if let Some(seccomp) = seccomp {
+        let filter = seccomp::compile_filter(seccomp)
+            .with_context(|| "failed to compile the seccomp profile")?;
+        task.set_seccomp_filter(filter);
+    }
+    let task = RwLock::new(task);
     let ctx = Context {
         limits,
         credentials,
         kernel,
         tid: None,
         task,
+        regs_clobbered: AtomicBool::new(false),
         fs_context: None,
         platform,
         real_time_clock: None,
@@ -142,6 +160,8 @@ pub fn init_context(
         network_interface,
         network_device_fd,
         used_ports: RwLock::new(HashSet::new()),
+        bound_ports: RwLock::new(HashMap::new()),
+        multicast_groups: RwLock::new(HashMap::new()),
     };
     CONTEXT
         .set(RwLock::new(ctx))
@@ -198,7 +218,7 @@ pub fn init_for_test() {
         return;
     }
     let creds = Credentials::new_anonymous();
-    let kernel = Kernel::load();
+    let kernel = Kernel::load(Platform::Ptrace, &Default::default());
     init_context(
         RwLock::new(LimitSet::default()),
         creds,
@@ -211,6 +231,38 @@ pub fn init_for_test() {
     .expect("failed to initialize the context");
 }
 
+// TestLimits restores the limits that set_limits_for_test replaced once it's dropped.
+#[cfg(test)]
+pub struct TestLimits {
+    old: LimitSet,
+    _lock: std::sync::MutexGuard<'static, ()>,
+}
+
+// set_limits_for_test replaces the limits of the context until the returned TestLimits is
+// dropped. Tests running in parallel share the context, so they take turns.
+#[cfg(test)]
+pub fn set_limits_for_test(limits: LimitSet) -> TestLimits {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut ctx = context_mut();
+    let old = *ctx.limits_mut();
+    ctx.set_limits(limits);
+    TestLimits { old, _lock: lock }
+}
+
+#[cfg(test)]
+impl Drop for TestLimits {
+    fn drop(&mut self) {
+        // A test that failed while holding the context mustn't keep the others from running.
+        let mut ctx = CONTEXT
+            .get()
+            .expect("Context is not set")
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        ctx.set_limits(self.old);
+    }
+}
+
 pub fn now() -> Time {
     match CONTEXT.get() {
         Some(c) => c.read().unwrap().now(),
@@ -237,6 +289,10 @@ impl time::Context for Context {
     fn now(&self) -> Time {
         self.real_time_clock().now()
     }
+
+    fn monotonic_now(&self) -> Time {
+        MonotonicClock.now()
+    }
 }
 
 impl limit::Context for Context {
@@ -279,12 +335,78 @@ impl net::Context for Context {
         }
     }
 
+    // remove_local_port keeps p reserved as long as a socket of another protocol is bound to it.
     fn remove_local_port(&self, p: u16) {
+        let bound_ports = self.bound_ports.read().unwrap();
+        if bound_ports.keys().any(|(_, q)| *q == p) {
+            return;
+        }
         if !self.used_ports.write().unwrap().remove(&p) {
             logger::info!("removing unused port");
         }
     }
 
+    // Bound ports are reserved as well, so that gen_local_port never hands them out. A port it
+    // has already handed out can't be bound in turn, unless reuse is set.
+    fn bind_local_port(&self, protocol: i32, p: u16, reuse: bool) -> bool {
+        let mut bound_ports = self.bound_ports.write().unwrap();
+        let mut used_ports = self.used_ports.write().unwrap();
+        let in_use = bound_ports.contains_key(&(protocol, p))
+            || used_ports.contains(&p) && !bound_ports.keys().any(|(_, q)| *q == p);
+        if in_use && !reuse {
+            return false;
+        }
+        used_ports.insert(p);
+        *bound_ports.entry((protocol, p)).or_insert(0) += 1;
+        true
+    }
+
+    fn unbind_local_port(&self, protocol: i32, p: u16) {
+        let mut bound_ports = self.bound_ports.write().unwrap();
+        let count = match bound_ports.get_mut(&(protocol, p)) {
+            Some(count) => count,
+            None => return,
+        };
+        *count -= 1;
+        if *count == 0 {
+            bound_ports.remove(&(protocol, p));
+        }
+    }
+
+    // smoltcp only speaks IGMP, so IPv6 groups are counted without subscribing the interface.
+    fn join_multicast_group(&self, group: IpAddress) -> utils::SysResult<()> {
+        let mut groups = self.multicast_groups.write().unwrap();
+        let count = groups.get(&group).copied().unwrap_or(0);
+        if count == 0 && matches!(group, IpAddress::Ipv4(_)) {
+            let mut iface = self.network_interface_mut();
+            if let Err(err) = iface.join_multicast_group(group, Instant::now()) {
+                logger::warn!("failed to join multicast group {}: {:?}", group, err);
+                return Err(utils::SysError::new(libc::ENOBUFS));
+            }
+        }
+        groups.insert(group, count + 1);
+        Ok(())
+    }
+
+    fn leave_multicast_group(&self, group: IpAddress) {
+        let mut groups = self.multicast_groups.write().unwrap();
+        let count = match groups.get_mut(&group) {
+            Some(count) => count,
+            None => return,
+        };
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        groups.remove(&group);
+        if matches!(group, IpAddress::Ipv4(_)) {
+            let mut iface = self.network_interface_mut();
+            if let Err(err) = iface.leave_multicast_group(group, Instant::now()) {
+                logger::warn!("failed to leave multicast group {}: {:?}", group, err);
+            }
+        }
+    }
+
     fn poll_wait(&self, once: bool) {
         let mut iface = self.network_interface_mut();
         while !match iface.poll(Instant::now()) {
@@ -401,6 +523,15 @@ impl fs::Context for Context {
         let mut task = self.task_mut();
         task.new_fd_from(fd, file, flags)
     }
+
+    fn proc_self_file(&self, file: SelfFile) -> Vec<u8> {
+        let s = match file {
+            SelfFile::Maps => procfs::maps(self),
+            SelfFile::Stat => procfs::stat(self),
+            SelfFile::Status => procfs::status(self),
+        };
+        s.into_bytes()
+    }
 }
 
 impl platform::Context for Context {
@@ -412,6 +543,9 @@ impl platform::Context for Context {
     }
     fn ptrace_set_regs(&self, regs: libc::user_regs_struct) -> nix::Result<()> {
         let pid = self.tid();
+        // The tracee no longer holds the task's registers, which have to be restored before
+        // resuming it. The task may be locked by the caller, so just record it here.
+        self.regs_clobbered.store(true, Ordering::SeqCst);
         ptrace::setregs(pid, regs)
     }
 }
@@ -472,6 +606,15 @@ impl Context {
             .expect("failed to acquire write lock from context.task")
     }
 
+    // store_task_regs writes the task's registers back to the tracee if they are out of sync.
+    pub fn store_task_regs(&self) -> nix::Result<()> {
+        let mut task = self.task_mut();
+        if self.regs_clobbered.swap(false, Ordering::SeqCst) {
+            task.invalidate_regs();
+        }
+        task.store_regs(self.tid())
+    }
+
     #[inline]
     pub fn kernel(&self) -> &Kernel {
         &self.kernel
@@ -2,7 +2,7 @@ use std::{cell::RefCell, collections::VecDeque, hash::Hash, rc::Rc, sync::RwLock
 
 use fs::{inode::Inode, Dirent, DirentRef, FileFlags, FileOperations, ReaddirError};
 use time::Context;
-use utils::{bail_libc, SysError, SysResult};
+use utils::{bail_libc, err_libc, SysError, SysResult};
 
 use crate::context;
 
@@ -27,32 +27,65 @@ impl PartialEq for FileIdentifier {
 
 impl Eq for FileIdentifier {}
 
-#[derive(Debug, Clone)]
+// PollEntry is a file registered in an EventPoll.
+#[derive(Debug)]
 struct PollEntry {
-    file: Rc<RefCell<fs::File>>,
+    id: FileIdentifier,
+    // mask is the set of events the entry is interested in, which always includes EPOLLERR and
+    // EPOLLHUP. A one-shot entry which has been reported has an empty mask until it is re-armed.
     mask: u64,
+    user_data: u64,
+    edge_triggered: bool,
+    one_shot: bool,
+    // last_ready is the readiness seen by the last check, used to find the transitions from not
+    // ready to ready of an edge-triggered entry.
+    last_ready: u64,
 }
 
-impl Hash for PollEntry {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        Rc::as_ptr(&self.file).hash(state);
-        self.mask.hash(state);
+impl PollEntry {
+    fn new(id: FileIdentifier, events: u32, user_data: u64) -> Self {
+        let mut entry = Self {
+            id,
+            mask: 0,
+            user_data,
+            edge_triggered: false,
+            one_shot: false,
+            last_ready: 0,
+        };
+        entry.set_events(events, user_data);
+        entry
     }
-}
 
-impl PartialEq for PollEntry {
-    fn eq(&self, other: &Self) -> bool {
-        Rc::as_ptr(&self.file) == Rc::as_ptr(&other.file) && self.mask == other.mask
+    fn set_events(&mut self, events: u32, user_data: u64) {
+        self.mask = (events & !EP_PRIVATE_BITS) as u64 | (libc::EPOLLERR | libc::EPOLLHUP) as u64;
+        self.user_data = user_data;
+        self.edge_triggered = events & libc::EPOLLET as u32 != 0;
+        self.one_shot = events & libc::EPOLLONESHOT as u32 != 0;
+        self.last_ready = 0;
+    }
+
+    // ready returns the events the entry reports now, along with its current readiness.
+    fn ready(&self, ctx: &dyn fs::Context) -> (u64, u64) {
+        if self.mask == 0 {
+            return (0, 0);
+        }
+        let ready = self.id.file.borrow().readiness(self.mask, ctx) & self.mask;
+        if self.edge_triggered && ready & !self.last_ready == 0 {
+            (0, ready)
+        } else {
+            (ready, ready)
+        }
     }
 }
 
-impl Eq for PollEntry {}
+// EP_PRIVATE_BITS are the flags in epoll_event.events which are not events.
+const EP_PRIVATE_BITS: u32 =
+    (libc::EPOLLWAKEUP | libc::EPOLLONESHOT | libc::EPOLLET | libc::EPOLLEXCLUSIVE) as u32;
 
 #[derive(Debug)]
 pub struct EventPoll {
     dirent: DirentRef,
-    ready_queue: RwLock<VecDeque<PollEntry>>,
-    waiting_queue: RwLock<VecDeque<PollEntry>>,
+    entries: RwLock<VecDeque<PollEntry>>,
 }
 
 impl FileOperations for EventPoll {
@@ -123,20 +156,95 @@ impl FileOperations for EventPoll {
 
 impl EventPoll {
     fn events_available(&self) -> bool {
-        let q: VecDeque<PollEntry> = self.ready_queue.read().unwrap().clone();
-        let mut ready_queue = self.ready_queue.write().unwrap();
-        let mut waiting_queue = self.waiting_queue.write().unwrap();
-        for (i, e) in q.iter().enumerate() {
-            let f = e.file.borrow();
-            let ctx = &*context::context();
-            let ready = f.readiness(e.mask, ctx);
-            if ready != 0 {
-                return true;
+        let ctx = &*context::context();
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .any(|e| e.ready(ctx).0 != 0)
+    }
+
+    // add_entry registers fd referring to file with the events and user data of an epoll_event.
+    pub fn add_entry(
+        &self,
+        file: Rc<RefCell<fs::File>>,
+        fd: i32,
+        events: u32,
+        user_data: u64,
+    ) -> SysResult<()> {
+        let id = FileIdentifier { file, fd };
+        let mut entries = self.entries.write().unwrap();
+        if entries.iter().any(|e| e.id == id) {
+            bail_libc!(libc::EEXIST);
+        }
+        entries.push_back(PollEntry::new(id, events, user_data));
+        Ok(())
+    }
+
+    // update_entry replaces the events and user data of a registered fd, re-arming it if it is a
+    // disabled one-shot entry.
+    pub fn update_entry(
+        &self,
+        file: Rc<RefCell<fs::File>>,
+        fd: i32,
+        events: u32,
+        user_data: u64,
+    ) -> SysResult<()> {
+        let id = FileIdentifier { file, fd };
+        let mut entries = self.entries.write().unwrap();
+        match entries.iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                entry.set_events(events, user_data);
+                Ok(())
+            }
+            None => err_libc!(libc::ENOENT),
+        }
+    }
+
+    pub fn remove_entry(&self, file: Rc<RefCell<fs::File>>, fd: i32) -> SysResult<()> {
+        let id = FileIdentifier { file, fd };
+        let mut entries = self.entries.write().unwrap();
+        match entries.iter().position(|e| e.id == id) {
+            Some(i) => {
+                entries.remove(i);
+                Ok(())
             }
-            ready_queue.remove(i);
-            waiting_queue.push_back(e.clone());
+            None => err_libc!(libc::ENOENT),
+        }
+    }
+
+    // read_events returns up to max events of the registered files. The reported entries are
+    // moved to the back so that the other ready entries are reported first next time.
+    pub fn read_events(&self, max: usize) -> Vec<libc::epoll_event> {
+        let ctx = &*context::context();
+        let mut entries = self.entries.write().unwrap();
+        // The file is gone once the entry holds the last reference to it.
+        entries.retain(|e| Rc::strong_count(&e.id.file) > 1);
+        let mut events = Vec::new();
+        let mut reported = Vec::new();
+        for (i, e) in entries.iter_mut().enumerate() {
+            if events.len() >= max {
+                break;
+            }
+            let (ready, last_ready) = e.ready(ctx);
+            e.last_ready = last_ready;
+            if ready == 0 {
+                continue;
+            }
+            events.push(libc::epoll_event {
+                events: ready as u32,
+                u64: e.user_data,
+            });
+            if e.one_shot {
+                e.mask = 0;
+            }
+            reported.push(i);
+        }
+        for (n, i) in reported.into_iter().enumerate() {
+            let entry = entries.remove(i - n).unwrap();
+            entries.push_back(entry);
         }
-        false
+        events
     }
 }
 
@@ -148,8 +256,7 @@ pub fn new_event_poll() -> fs::File {
         FileFlags::default(),
         Box::new(EventPoll {
             dirent,
-            ready_queue: RwLock::new(VecDeque::new()),
-            waiting_queue: RwLock::new(VecDeque::new()),
+            entries: RwLock::new(VecDeque::new()),
         }),
     )
 }
@@ -1,8 +1,13 @@
+use std::cell::Cell;
+
 use fs::{inode::Inode, FileFlags, FileOperations};
 use time::Time;
-use utils::{err_libc, SysError, SysResult};
+use utils::{bail_libc, err_libc, SysError, SysResult};
+
+// The largest value an eventfd counter can hold.
+const MAX_COUNTER: u64 = u64::MAX - 1;
 
-pub fn new_eventfd<F: Fn() -> Time>(timer: F) -> fs::File {
+pub fn new_eventfd<F: Fn() -> Time>(timer: F, init_val: u64, semaphore: bool) -> fs::File {
     let inode = Inode::new_anon(timer);
     let dirent = fs::Dirent::new(inode, "anon_inode:[eventfd]".to_string());
     fs::File::new(
@@ -11,13 +16,22 @@ pub fn new_eventfd<F: Fn() -> Time>(timer: F) -> fs::File {
             write: true,
             ..FileFlags::default()
         },
-        Box::new(EventFileOperations { dirent }),
+        Box::new(EventFileOperations {
+            dirent,
+            counter: Cell::new(init_val),
+            semaphore,
+        }),
     )
 }
 
+// Since the guest is a single task, nobody could ever wake up a read or write that blocks. So
+// they fail with EAGAIN whether or not the file is non-blocking, just as pipes do.
 #[derive(Debug)]
 pub struct EventFileOperations {
     dirent: fs::DirentRef,
+    counter: Cell<u64>,
+    // Set by EFD_SEMAPHORE, with which a read decrements the counter by one instead of draining it.
+    semaphore: bool,
 }
 
 impl FileOperations for EventFileOperations {
@@ -27,20 +41,50 @@ impl FileOperations for EventFileOperations {
     fn read(
         &self,
         _: fs::FileFlags,
-        _: &mut mem::IoSequence,
+        dst: &mut mem::IoSequence,
         _: i64,
         _: &dyn fs::Context,
     ) -> SysResult<usize> {
-        todo!()
+        if dst.num_bytes() < std::mem::size_of::<u64>() {
+            bail_libc!(libc::EINVAL);
+        }
+        let counter = self.counter.get();
+        if counter == 0 {
+            bail_libc!(libc::EAGAIN);
+        }
+        let val = if self.semaphore { 1 } else { counter };
+        let n = dst.copy_out(&val.to_ne_bytes())?;
+        if n < std::mem::size_of::<u64>() {
+            bail_libc!(libc::EFAULT);
+        }
+        self.counter.set(counter - val);
+        Ok(n)
     }
     fn write(
         &self,
         _: fs::FileFlags,
-        _: &mut mem::IoSequence,
+        src: &mut mem::IoSequence,
         _: i64,
         _: &dyn fs::Context,
     ) -> SysResult<usize> {
-        todo!()
+        let mut b = [0; std::mem::size_of::<u64>()];
+        if src.num_bytes() < b.len() {
+            bail_libc!(libc::EINVAL);
+        }
+        let n = src.copy_in(&mut b)?;
+        if n < b.len() {
+            bail_libc!(libc::EFAULT);
+        }
+        let val = u64::from_ne_bytes(b);
+        if val == u64::MAX {
+            bail_libc!(libc::EINVAL);
+        }
+        let counter = self.counter.get();
+        if val > MAX_COUNTER - counter {
+            bail_libc!(libc::EAGAIN);
+        }
+        self.counter.set(counter + val);
+        Ok(n)
     }
     fn configure_mmap(&mut self, _: &mut memmap::mmap_opts::MmapOpts) -> SysResult<()> {
         err_libc!(libc::ENODEV)
@@ -71,8 +115,16 @@ impl FileOperations for EventFileOperations {
     ) -> fs::ReaddirResult<i64> {
         Err(fs::ReaddirError::new(0, libc::ENOTDIR))
     }
-    fn readiness(&self, _: u64, _: &dyn fs::Context) -> u64 {
-        todo!()
+    fn readiness(&self, mask: u64, _: &dyn fs::Context) -> u64 {
+        let counter = self.counter.get();
+        let mut r = 0;
+        if counter > 0 {
+            r |= linux::POLL_READABLE_EVENTS;
+        }
+        if counter < MAX_COUNTER {
+            r |= linux::POLL_WRITABLE_EVENTS;
+        }
+        mask & r
     }
     fn as_any(&self) -> &dyn std::any::Any {
         self
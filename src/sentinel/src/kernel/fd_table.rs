@@ -180,7 +180,6 @@ impl FdTable {
         Ok(fds)
     }
 
-    #[cfg(test)]
     pub fn new_fd_at(
         &mut self,
         fd: i32,
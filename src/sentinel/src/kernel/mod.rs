@@ -2,6 +2,8 @@ pub mod epoll;
 pub mod eventfd;
 pub mod fd_table;
 pub mod pipe;
+pub mod procfs;
+pub mod seccomp;
 pub mod task;
 mod task_image;
 mod uts_namespace;
@@ -22,6 +24,7 @@ use mem::{
 };
 use pgalloc::{AllocOpts, Direction, MemoryFile, MemoryFileOpts, MemoryFileProvider};
 use platform::Platform;
+use sentinel_oci::UnameConfig;
 use usage::MemoryKind;
 use utils::mem::create_mem_fd;
 
@@ -104,7 +107,7 @@ impl Kernel {
         &self.version
     }
 
-    pub fn load() -> Self {
+    pub fn load(platform: Platform, uname: &UnameConfig) -> Self {
         let memfile_name = "sentinel-context-memory";
         let memfd = create_mem_fd(memfile_name, 0)
             .unwrap_or_else(|e| panic!("error creating application memory file: {:?}", e));
@@ -114,10 +117,10 @@ impl Kernel {
         let vdso = Vdso::prepare(&mut memory_file).expect("failed to load vdso");
 
         Self {
-            platform: Platform::Ptrace,
+            platform,
             memory_file: Rc::new(RwLock::new(memory_file)),
             vdso,
-            version: KernelVersion::init(),
+            version: KernelVersion::new(uname),
         }
     }
 }
@@ -127,15 +130,19 @@ pub struct KernelVersion {
     pub sysname: String,
     pub release: String,
     pub version: String,
+    pub machine: String,
 }
 
 impl KernelVersion {
-    fn init() -> Self {
+    // new returns the version given in uname, or the default for the fields not given.
+    fn new(uname: &UnameConfig) -> Self {
         // These strings are just copied from gVisor.
+        let default = |s: &Option<String>, d: &str| s.clone().unwrap_or_else(|| d.to_string());
         Self {
-            sysname: "Linux".to_string(),
-            release: "4.4.0".to_string(),
+            sysname: default(&uname.sysname, "Linux"),
+            release: default(&uname.release, "4.4.0"),
             version: "#1 SMP Sun Jan 10 15:06:54 PST 2016".to_string(),
+            machine: default(&uname.machine, "x86_64"),
         }
     }
 }
@@ -1,6 +1,7 @@
 use std::{
     any::Any,
     cell::RefCell,
+    collections::VecDeque,
     rc::Rc,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -10,7 +11,7 @@ use std::{
 
 use dev::Device;
 use fs::{
-    attr::{FilePermissions, InodeType, PermMask, StableAttr, UnstableAttr},
+    attr::{FilePermissions, InodeType, PermMask, StableAttr, TimeSpec, UnstableAttr},
     dentry::DentrySerializer,
     fsutils::inode::InodeSimpleAttributes,
     mount::MountSource,
@@ -39,6 +40,9 @@ struct Pipe {
     max: usize,
     has_reader: AtomicBool,
     has_writer: AtomicBool,
+    // packets holds the length of each buffered packet when the pipe was
+    // created with O_DIRECT, and is None for a byte-stream pipe.
+    packets: Option<VecDeque<usize>>,
 }
 
 const MIN_PIPE_SIZE: usize = PAGE_SIZE as usize;
@@ -47,7 +51,7 @@ const ATOMIC_IO_BYTES: usize = 4096;
 pub const DEFAULT_PIPE_SIZE: usize = 16 * PAGE_SIZE as usize;
 
 impl Pipe {
-    fn new(max: usize) -> Self {
+    fn new(max: usize, packet: bool) -> Self {
         let max = std::cmp::min(max, MAX_PIPE_SIZE);
         let max = std::cmp::max(max, MIN_PIPE_SIZE);
         Pipe {
@@ -58,6 +62,7 @@ impl Pipe {
             max,
             has_reader: AtomicBool::new(false),
             has_writer: AtomicBool::new(false),
+            packets: if packet { Some(VecDeque::new()) } else { None },
         }
     }
 
@@ -111,6 +116,16 @@ impl Pipe {
             .take_first64(count as u64);
         let done = f(bs)?;
         self.size += done;
+        // In packet mode, each write is split into packets of at most
+        // ATOMIC_IO_BYTES bytes which are never merged with each other.
+        if let Some(packets) = self.packets.as_mut() {
+            let mut left = done;
+            while left > 0 {
+                let n = std::cmp::min(left, ATOMIC_IO_BYTES);
+                packets.push_back(n);
+                left -= n;
+            }
+        }
         if done < count {
             Ok(done)
         } else if short {
@@ -126,9 +141,19 @@ impl Pipe {
         f: F,
         remove: bool,
     ) -> SysResult<usize> {
+        // In packet mode a read returns at most one packet, and the part of
+        // the packet which does not fit in the buffer is discarded.
+        let packet = self.packets.as_ref().and_then(|p| p.front().copied());
+        let count = packet.map_or(count, |len| std::cmp::min(count, len));
         let n = self.peek(count, f)?;
         if n > 0 && remove {
-            self.consume(n);
+            match packet {
+                Some(len) => {
+                    self.consume(len);
+                    self.packets.as_mut().unwrap().pop_front();
+                }
+                None => self.consume(n),
+            }
         }
         Ok(n)
     }
@@ -175,26 +200,41 @@ static PIPE_DEVICE: Lazy<Arc<Mutex<Device>>> = Lazy::new(dev::Device::new_anonym
 pub struct PipeRef {
     pipe: Rc<RefCell<Pipe>>,
     dirent: Option<DirentRef>,
+    // read and write tell which ends of the pipe the file refers to.
+    read: bool,
+    write: bool,
 }
 
 impl PipeRef {
-    pub fn new(max: usize) -> Self {
-        let pipe = Rc::new(RefCell::new(Pipe::new(max)));
-        Self { pipe, dirent: None }
+    // new creates a pipe. If packet is true, the pipe works in packet mode
+    // (O_DIRECT) instead of as a byte stream.
+    pub fn new(max: usize, packet: bool) -> Self {
+        let pipe = Rc::new(RefCell::new(Pipe::new(max, packet)));
+        Self {
+            pipe,
+            dirent: None,
+            read: false,
+            write: false,
+        }
     }
 
     fn open(&self, mut flags: FileFlags) -> fs::File {
         flags.non_seekable = true;
+        let pipe_ref = Self {
+            read: flags.read,
+            write: flags.write,
+            ..self.clone()
+        };
         if flags.read && flags.write {
             self.pipe.borrow().has_reader.store(true, Ordering::SeqCst);
             self.pipe.borrow().has_writer.store(true, Ordering::SeqCst);
-            fs::File::new(flags, Box::new(self.clone()))
+            fs::File::new(flags, Box::new(pipe_ref))
         } else if flags.read {
             self.pipe.borrow().has_reader.store(true, Ordering::SeqCst);
-            fs::File::new(flags, Box::new(self.clone()))
+            fs::File::new(flags, Box::new(pipe_ref))
         } else if flags.write {
             self.pipe.borrow().has_writer.store(true, Ordering::SeqCst);
-            fs::File::new(flags, Box::new(self.clone()))
+            fs::File::new(flags, Box::new(pipe_ref))
         } else {
             panic!("invalid pipe flags")
         }
@@ -236,8 +276,10 @@ impl PipeRef {
             read: true,
             ..FileFlags::default()
         });
+        // Like Linux, only the write end reports O_DIRECT.
         let w = self.open(FileFlags {
             write: true,
+            direct: self.pipe.borrow().packets.is_some(),
             ..FileFlags::default()
         });
         (r, w)
@@ -326,8 +368,16 @@ impl FileOperations for PipeRef {
     ) -> ReaddirResult<i64> {
         Err(ReaddirError::new(0, libc::ENOTDIR))
     }
-    fn readiness(&self, _: u64, _: &dyn fs::Context) -> u64 {
-        todo!()
+    fn readiness(&self, mask: u64, _: &dyn fs::Context) -> u64 {
+        let pipe = self.pipe.borrow();
+        let mut r = 0;
+        if self.read && pipe.size > 0 {
+            r |= linux::POLL_READABLE_EVENTS;
+        }
+        if self.write && pipe.size < pipe.max {
+            r |= linux::POLL_WRITABLE_EVENTS;
+        }
+        mask & r
     }
     fn as_any(&self) -> &dyn Any {
         self
@@ -361,6 +411,9 @@ impl InodeOperations for PipeInodeOperations {
     fn truncate(&mut self, _: i64, _: &dyn fs::Context) -> SysResult<()> {
         Ok(())
     }
+    fn allocate(&mut self, _: i32, _: i64, _: i64, _: &dyn fs::Context) -> SysResult<()> {
+        bail_libc!(libc::ESPIPE)
+    }
     fn create(
         &mut self,
         _: UnstableAttr,
@@ -372,6 +425,16 @@ impl InodeOperations for PipeInodeOperations {
     ) -> SysResult<fs::File> {
         err_libc!(libc::ENOTDIR)
     }
+    fn create_link(
+        &mut self,
+        _: UnstableAttr,
+        _: Rc<MountSource>,
+        _: &str,
+        _: &str,
+        _: &dyn Context,
+    ) -> SysResult<()> {
+        err_libc!(libc::ENOTDIR)
+    }
     fn rename(
         &self,
         _: RenameUnderParents<&mut fs::inode::Inode>,
@@ -382,6 +445,9 @@ impl InodeOperations for PipeInodeOperations {
     ) -> SysResult<()> {
         err_libc!(libc::EINVAL)
     }
+    fn set_timestamps(&mut self, ts: TimeSpec, ctx: &dyn Context) -> SysResult<()> {
+        self.simple_attrs.set_timestamps(ts, ctx)
+    }
     fn add_link(&self) {
         self.simple_attrs.add_link()
     }
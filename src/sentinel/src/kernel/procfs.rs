@@ -0,0 +1,132 @@
+use auth::Context as AuthContext;
+use limit::Context as LimitContext;
+use platform::Context as PlatformContext;
+
+use crate::context::Context;
+
+const TASK_COMM_LEN: usize = 16;
+
+// comm returns the name of the task as seen in /proc/self/{stat,status}, which is the file name
+// of the executable truncated just like Linux does.
+fn comm(ctx: &Context) -> String {
+    let name = ctx
+        .executable_path()
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    name.chars().take(TASK_COMM_LEN - 1).collect()
+}
+
+// maps generates /proc/self/maps.
+pub fn maps(ctx: &Context) -> String {
+    ctx.memory_manager().borrow().maps()
+}
+
+// status generates /proc/self/status from the credentials, the limits and the memory usage of
+// the task. Sentinel runs a single thread as the init of the sandbox.
+pub fn status(ctx: &Context) -> String {
+    let creds = ctx.credentials();
+    let ns = &creds.user_namespace;
+    let uid = |kuid| ns.map_from_kuid(kuid).or_overflow().0;
+    let gid = |kgid| ns.map_from_kgid(kgid).or_overflow().0;
+    let groups = creds
+        .extra_kgids
+        .iter()
+        .map(|g| format!("{} ", gid(g)))
+        .collect::<String>();
+    let (vm_size, vm_rss, vm_data) = {
+        let mm = ctx.memory_manager();
+        let mm = mm.borrow();
+        (
+            mm.usage_address_space(),
+            mm.resident_set_size(),
+            mm.data_address_space(),
+        )
+    };
+    let seccomp = if ctx.task().has_seccomp_filter() {
+        2
+    } else {
+        0
+    };
+    let pid = ctx.tid().as_raw();
+
+    let mut s = String::new();
+    s.push_str(&format!("Name:\t{}\n", comm(ctx)));
+    s.push_str(&format!("Umask:\t{:04o}\n", fs::Context::umask(ctx)));
+    s.push_str("State:\tR (running)\n");
+    s.push_str(&format!("Tgid:\t{}\n", pid));
+    s.push_str(&format!("Pid:\t{}\n", pid));
+    s.push_str("PPid:\t0\n");
+    s.push_str("TracerPid:\t0\n");
+    s.push_str(&format!(
+        "Uid:\t{}\t{}\t{}\t{}\n",
+        uid(&creds.real_kuid),
+        uid(&creds.effective_kuid),
+        uid(&creds.saved_kuid),
+        uid(&creds.effective_kuid),
+    ));
+    s.push_str(&format!(
+        "Gid:\t{}\t{}\t{}\t{}\n",
+        gid(&creds.real_kgid),
+        gid(&creds.effective_kgid),
+        gid(&creds.saved_kgid),
+        gid(&creds.effective_kgid),
+    ));
+    s.push_str(&format!("Groups:\t{}\n", groups));
+    s.push_str(&format!("VmSize:\t{:>8} kB\n", vm_size >> 10));
+    s.push_str(&format!("VmRSS:\t{:>8} kB\n", vm_rss >> 10));
+    s.push_str(&format!("VmData:\t{:>8} kB\n", vm_data >> 10));
+    s.push_str("Threads:\t1\n");
+    s.push_str(&format!(
+        "SigQ:\t0/{}\n",
+        ctx.limits().get_signals_pending().cur
+    ));
+    s.push_str(&format!("CapInh:\t{:016x}\n", creds.inheritable_caps.0));
+    s.push_str(&format!("CapPrm:\t{:016x}\n", creds.permitted_caps.0));
+    s.push_str(&format!("CapEff:\t{:016x}\n", creds.effective_caps.0));
+    s.push_str(&format!("CapBnd:\t{:016x}\n", creds.bounding_caps.0));
+    s.push_str(&format!("Seccomp:\t{}\n", seccomp));
+    s
+}
+
+// stat generates /proc/self/stat. Fields sentinel does not keep track of, e.g. the CPU times and
+// the page fault counts, are zero.
+pub fn stat(ctx: &Context) -> String {
+    let pid = ctx.tid().as_raw();
+    let (vsize, rss) = {
+        let mm = ctx.memory_manager();
+        let mm = mm.borrow();
+        (mm.usage_address_space(), mm.resident_set_size())
+    };
+    let rsslim = ctx.limits().get_rss().cur;
+
+    // pid (comm) state ppid pgrp session tty_nr tpgid flags
+    let mut fields = vec![
+        pid.to_string(),
+        format!("({})", comm(ctx)),
+        "R".to_string(),
+        "0".to_string(),
+        pid.to_string(),
+        pid.to_string(),
+        "0".to_string(),
+        "-1".to_string(),
+        "0".to_string(),
+    ];
+    // minflt cminflt majflt cmajflt utime stime cutime cstime
+    fields.extend(std::iter::repeat("0".to_string()).take(8));
+    // priority nice num_threads itrealvalue starttime vsize rss rsslim
+    fields.extend_from_slice(&[
+        "20".to_string(),
+        "0".to_string(),
+        "1".to_string(),
+        "0".to_string(),
+        "0".to_string(),
+        vsize.to_string(),
+        (rss / mem::PAGE_SIZE as u64).to_string(),
+        rsslim.to_string(),
+    ]);
+    // startcode ... exit_code, where exit_signal is SIGCHLD.
+    fields.extend(std::iter::repeat("0".to_string()).take(27));
+    fields[37] = libc::SIGCHLD.to_string();
+    format!("{}\n", fields.join(" "))
+}
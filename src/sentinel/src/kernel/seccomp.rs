@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+use anyhow::{anyhow, bail};
+use oci_spec::runtime::{
+    Arch, LinuxSeccomp, LinuxSeccompAction, LinuxSeccompArg, LinuxSeccompOperator,
+};
+use seccompiler::{
+    BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+    SeccompRule, SeccompRuleMap, SyscallTable, TargetArch,
+};
+
+// Seccomp filters of the guest are evaluated by sentinel itself, because PTRACE_SYSEMU reports
+// the guest's syscalls before the seccomp of the host examines them. Hence a filter installed
+// on the guest thread would never see them. The filter of sentinel itself ("vmm") is unaffected.
+
+// compile_filter translates the seccomp profile of the OCI spec into a BPF program for the guest.
+pub fn compile_filter(seccomp: &LinuxSeccomp) -> anyhow::Result<BpfProgram> {
+    if let Some(archs) = seccomp.architectures() {
+        if !archs.is_empty()
+            && !archs
+                .iter()
+                .any(|a| matches!(a, Arch::ScmpArchNative | Arch::ScmpArchX86_64))
+        {
+            bail!("seccomp profile does not cover x86_64: {:?}", archs);
+        }
+    }
+
+    let default_action = seccomp_action(seccomp.default_action(), None)?;
+    let table = SyscallTable::new(TargetArch::x86_64);
+    let mut chains: BTreeMap<i64, Vec<(Vec<SeccompCondition>, SeccompAction)>> = BTreeMap::new();
+    for syscall in seccomp.syscalls().iter().flatten() {
+        let action = seccomp_action(syscall.action(), syscall.errno_ret())?;
+        if action == default_action {
+            continue;
+        }
+        let conditions = syscall
+            .args()
+            .iter()
+            .flatten()
+            .map(seccomp_condition)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for name in syscall.names() {
+            // Just like libseccomp, names unknown to this architecture are ignored.
+            let nr = match table.get_syscall_nr(name) {
+                Some(nr) => nr,
+                None => {
+                    logger::warn!("ignoring unknown syscall {} in seccomp profile", name);
+                    continue;
+                }
+            };
+            let chain = chains.entry(nr).or_default();
+            // Rules following an unconditional one are never reached.
+            if !chain.iter().any(|(conds, _)| conds.is_empty()) {
+                chain.push((conditions.clone(), action.clone()));
+            }
+        }
+    }
+
+    let rules: SeccompRuleMap = chains
+        .into_iter()
+        .map(|(nr, chain)| {
+            let len = chain.len();
+            let rules = chain
+                .into_iter()
+                .map(|(mut conditions, action)| {
+                    // seccompiler does not allow an unconditional rule next to conditional ones,
+                    // so it is written as a condition that always holds.
+                    if conditions.is_empty() && len > 1 {
+                        conditions.push(always_true()?);
+                    }
+                    Ok(SeccompRule::new(conditions, action))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok((nr, rules))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    SeccompFilter::new(rules, default_action, TargetArch::x86_64.into())
+        .and_then(|filter| filter.try_into())
+        .map_err(|e| anyhow!("failed to compile seccomp profile: {}", e))
+}
+
+fn seccomp_action(
+    action: LinuxSeccompAction,
+    errno_ret: Option<u32>,
+) -> anyhow::Result<SeccompAction> {
+    match action {
+        LinuxSeccompAction::ScmpActAllow => Ok(SeccompAction::Allow),
+        LinuxSeccompAction::ScmpActErrno => Ok(SeccompAction::Errno(
+            errno_ret.unwrap_or(libc::EPERM as u32),
+        )),
+        LinuxSeccompAction::ScmpActKill => Ok(SeccompAction::KillThread),
+        LinuxSeccompAction::ScmpActKillProcess => Ok(SeccompAction::KillProcess),
+        action => bail!(
+            "unsupported seccomp action {:?}: only SCMP_ACT_ALLOW, SCMP_ACT_ERRNO, SCMP_ACT_KILL \
+             and SCMP_ACT_KILL_PROCESS are supported",
+            action
+        ),
+    }
+}
+
+fn seccomp_condition(arg: &LinuxSeccompArg) -> anyhow::Result<SeccompCondition> {
+    let (operator, value) = match arg.op() {
+        LinuxSeccompOperator::ScmpCmpNe => (SeccompCmpOp::Ne, arg.value()),
+        LinuxSeccompOperator::ScmpCmpLt => (SeccompCmpOp::Lt, arg.value()),
+        LinuxSeccompOperator::ScmpCmpLe => (SeccompCmpOp::Le, arg.value()),
+        LinuxSeccompOperator::ScmpCmpEq => (SeccompCmpOp::Eq, arg.value()),
+        LinuxSeccompOperator::ScmpCmpGe => (SeccompCmpOp::Ge, arg.value()),
+        LinuxSeccompOperator::ScmpCmpGt => (SeccompCmpOp::Gt, arg.value()),
+        // For SCMP_CMP_MASKED_EQ, value is the mask and valueTwo is the expected value.
+        LinuxSeccompOperator::ScmpCmpMaskedEq => (
+            SeccompCmpOp::MaskedEq(arg.value()),
+            arg.value_two().unwrap_or(0),
+        ),
+    };
+    let index = arg
+        .index()
+        .try_into()
+        .map_err(|_| anyhow!("invalid seccomp argument index {}", arg.index()))?;
+    SeccompCondition::new(index, SeccompCmpArgLen::Qword, operator, value)
+        .map_err(|e| anyhow!("invalid seccomp argument: {}", e))
+}
+
+fn always_true() -> anyhow::Result<SeccompCondition> {
+    SeccompCondition::new(0, SeccompCmpArgLen::Qword, SeccompCmpOp::MaskedEq(0), 0)
+        .map_err(|e| anyhow!("invalid seccomp argument: {}", e))
+}
+
+// check returns the action that the guest's seccomp filter takes on the syscall held in regs.
+pub fn check(filter: &BpfProgram, regs: &libc::user_regs_struct) -> SeccompAction {
+    let data = seccompiler::SeccompData::new(
+        regs.orig_rax as i32,
+        TargetArch::x86_64,
+        regs.rip,
+        [regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9],
+    );
+    seccompiler::evaluate_filter(filter, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use oci_spec::runtime::{LinuxSeccompArgBuilder, LinuxSeccompBuilder, LinuxSyscallBuilder};
+
+    use super::*;
+
+    fn regs(nr: i64, arg0: u64) -> libc::user_regs_struct {
+        let mut regs = utils::init_libc_regs();
+        regs.orig_rax = nr as u64;
+        regs.rdi = arg0;
+        regs
+    }
+
+    #[test]
+    fn filter_from_spec() {
+        let seccomp = LinuxSeccompBuilder::default()
+            .default_action(LinuxSeccompAction::ScmpActErrno)
+            .architectures(vec![Arch::ScmpArchX86_64])
+            .syscalls(vec![
+                LinuxSyscallBuilder::default()
+                    .names(vec!["read".to_string(), "no_such_syscall".to_string()])
+                    .action(LinuxSeccompAction::ScmpActAllow)
+                    .build()
+                    .unwrap(),
+                LinuxSyscallBuilder::default()
+                    .names(vec!["personality".to_string()])
+                    .action(LinuxSeccompAction::ScmpActAllow)
+                    .args(vec![LinuxSeccompArgBuilder::default()
+                        .index(0usize)
+                        .value(8u64)
+                        .op(LinuxSeccompOperator::ScmpCmpEq)
+                        .build()
+                        .unwrap()])
+                    .build()
+                    .unwrap(),
+                LinuxSyscallBuilder::default()
+                    .names(vec!["personality".to_string()])
+                    .action(LinuxSeccompAction::ScmpActErrno)
+                    .errno_ret(libc::ENOSYS as u32)
+                    .build()
+                    .unwrap(),
+                LinuxSyscallBuilder::default()
+                    .names(vec!["getpid".to_string()])
+                    .action(LinuxSeccompAction::ScmpActKill)
+                    .build()
+                    .unwrap(),
+            ])
+            .build()
+            .unwrap();
+        let filter = compile_filter(&seccomp).unwrap();
+
+        let cases = vec![
+            (libc::SYS_read, 0, SeccompAction::Allow),
+            (libc::SYS_write, 0, SeccompAction::Errno(libc::EPERM as u32)),
+            (libc::SYS_personality, 8, SeccompAction::Allow),
+            (
+                libc::SYS_personality,
+                0,
+                SeccompAction::Errno(libc::ENOSYS as u32),
+            ),
+            (libc::SYS_getpid, 0, SeccompAction::KillThread),
+        ];
+        for (nr, arg0, action) in cases {
+            assert_eq!(check(&filter, &regs(nr, arg0)), action);
+        }
+    }
+
+    #[test]
+    fn unsupported_action() {
+        let seccomp = LinuxSeccompBuilder::default()
+            .default_action(LinuxSeccompAction::ScmpActTrace)
+            .build()
+            .unwrap();
+        assert!(compile_filter(&seccomp).is_err());
+    }
+}
@@ -3,28 +3,30 @@ use std::{
     collections::{HashMap, HashSet},
     path::Path,
     rc::Rc,
-    sync::atomic::{AtomicU64, Ordering},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
 };
 use utils::{bail_libc, SysError, SysResult};
 
 use arch::{
-    signal::{SignalStack, SIGNAL_STACK_FLAG_DISABLE, SIGNAL_STACK_FLAG_ON_STACK},
-    ArchContext, CPUID_INSTRUCTION,
+    signal::{SignalInfo, SignalStack, SIGNAL_STACK_FLAG_DISABLE, SIGNAL_STACK_FLAG_ON_STACK},
+    ArchContext, Stack, CPUID_INSTRUCTION,
 };
-use fs::{mount::MountNamespace, FdFlags, File};
-use mem::{copy_string_in, io::Io, Addr, AddrRangeSeq, IoOpts, IoSequence};
+use fs::{lock::LockOwner, mount::MountNamespace, socket::SocketFile, tmpfs, FdFlags, File};
+use mem::{copy_string_in, copy_string_out, io::Io, Addr, AddrRangeSeq, IoOpts, IoSequence};
 use nix::sys::ptrace;
 use platform::{Context, PtraceAddressSpace};
+use seccompiler::{BpfProgram, SeccompAction};
 
 use crate::{context, mm::MemoryManager};
 
 use super::{
     fd_table::FdTable,
+    seccomp,
     task_image::{MemoryManagerState, TaskImage},
     UtsNameSpace,
 };
 
-const MAX_RW_COUNT: u64 = Addr(i32::MAX as u64).round_down().0;
+pub const MAX_RW_COUNT: u64 = Addr(i32::MAX as u64).round_down().0;
 static IOVEC_SIZE: usize = std::mem::size_of::<libc::iovec>();
 
 #[derive(Debug)]
@@ -45,13 +47,22 @@ pub struct Task {
     signal_stack: SignalStack,
     clear_tid: Addr,
     arch_context: Option<ArchContext>,
+    // regs_dirty is set when the registers held in arch_context differ from the ones of the
+    // tracee, either because they have been modified or because the tracee's registers have been
+    // clobbered by the platform.
+    regs_dirty: AtomicBool,
     init_regs: libc::user_regs_struct,
     uts_namespace: UtsNameSpace,
     cpu_mask: Vec<u8>,
     parent_death_signal: linux::Signal,
+    // personality is inherited from sentinel, e.g. ADDR_NO_RANDOMIZE under `setarch -R`.
+    personality: u32,
     next_timerid: i32,
     timers: HashSet<i32>, //FIXME: properly implement timer instead of just holding the id
     signal_handlers: HashMap<linux::Signal, linux::SigAction>,
+    // seccomp_filter is the filter given in the OCI spec, which every syscall of the task is
+    // checked against before being performed.
+    seccomp_filter: Option<BpfProgram>,
 }
 
 unsafe impl Send for Task {}
@@ -76,13 +87,16 @@ impl Task {
             signal_stack: SignalStack::default(),
             clear_tid: Addr(0),
             arch_context: None,
+            regs_dirty: AtomicBool::new(false),
             init_regs: utils::init_libc_regs(),
             uts_namespace: UtsNameSpace::new("sentinel".to_string(), "sentinel".to_string()),
             cpu_mask,
             parent_death_signal: linux::Signal(0),
+            personality: unsafe { libc::personality(0xffffffff) } as u32,
             next_timerid: 0,
             timers: HashSet::new(),
             signal_handlers: HashMap::new(),
+            seccomp_filter: None,
         })
     }
 
@@ -94,8 +108,14 @@ impl Task {
         extra_auxv: &HashMap<u64, Addr>,
     ) -> anyhow::Result<ArchContext> {
         self.fd_table.set_stdio_files();
-        self.image
-            .load(executable_path, argv, envv, extra_auxv, &self.mounts)
+        self.image.load(
+            executable_path,
+            argv,
+            envv,
+            extra_auxv,
+            &self.mounts,
+            self.personality,
+        )
     }
 
     pub fn set_address_space(&self, address_space: PtraceAddressSpace) {
@@ -125,15 +145,56 @@ impl Task {
 
     #[inline]
     pub fn set_regs(&mut self, regs: libc::user_regs_struct) {
+        self.arch_context_mut().regs = regs;
+    }
+
+    #[inline]
+    pub fn set_arch_context(&mut self, arch_context: ArchContext) {
+        self.arch_context = Some(arch_context);
+        self.invalidate_regs();
+    }
+
+    // invalidate_regs marks the tracee's registers as out of sync with the cached ones, so that the
+    // cached ones are written back before the tracee is resumed.
+    #[inline]
+    pub fn invalidate_regs(&self) {
+        self.regs_dirty.store(true, Ordering::SeqCst);
+    }
+
+    // load_regs fetches the registers of the stopped tracee into the cache. This should be called
+    // once per stop, and the cached registers should be used afterwards.
+    pub fn load_regs(&mut self, pid: nix::unistd::Pid) -> nix::Result<libc::user_regs_struct> {
+        let regs = ptrace::getregs(pid)?;
         self.arch_context
             .as_mut()
             .expect("ArchContext is not set yet")
             .regs = regs;
+        self.regs_dirty.store(false, Ordering::SeqCst);
+        Ok(regs)
     }
 
-    #[inline]
-    pub fn set_arch_context(&mut self, arch_context: ArchContext) {
-        self.arch_context = Some(arch_context);
+    // store_regs writes the cached registers back to the tracee if they have been modified since
+    // they were loaded or last stored.
+    pub fn store_regs(&mut self, pid: nix::unistd::Pid) -> nix::Result<()> {
+        self.store_regs_with(|regs| ptrace::setregs(pid, regs))
+    }
+
+    fn store_regs_with<F>(&mut self, set_regs: F) -> nix::Result<()>
+    where
+        F: FnOnce(libc::user_regs_struct) -> nix::Result<()>,
+    {
+        if !self.regs_dirty.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let mut regs = self.regs();
+        self.reset_sysemu_regs(&mut regs);
+        set_regs(regs)?;
+        self.arch_context
+            .as_mut()
+            .expect("ArchContext is not set yet")
+            .regs = regs;
+        self.regs_dirty.store(false, Ordering::SeqCst);
+        Ok(())
     }
 
     pub fn grab_init_regs(&mut self) {
@@ -155,17 +216,7 @@ impl Task {
         &self.cpu_mask
     }
 
-    pub fn ptrace_get_regs(
-        &self,
-        regs: &mut libc::user_regs_struct,
-        pid: nix::unistd::Pid,
-    ) -> nix::Result<()> {
-        *regs = ptrace::getregs(pid)?;
-        // logger::trace!("PTRACE_GETREGS: {:?}", *regs);
-        Ok(())
-    }
-
-    pub fn reset_sysemu_regs(&mut self, regs: &mut libc::user_regs_struct) {
+    pub fn reset_sysemu_regs(&self, regs: &mut libc::user_regs_struct) {
         regs.cs = self.init_regs.cs;
         regs.ss = self.init_regs.ss;
         regs.ds = self.init_regs.ds;
@@ -175,6 +226,7 @@ impl Task {
     }
 
     pub fn arch_context_mut(&mut self) -> &mut ArchContext {
+        self.invalidate_regs();
         self.arch_context.as_mut().expect("ArchContext is not set")
     }
 
@@ -346,11 +398,32 @@ impl Task {
         &self.mounts
     }
 
+    pub fn set_seccomp_filter(&mut self, filter: BpfProgram) {
+        self.seccomp_filter = Some(filter);
+    }
+
+    pub fn has_seccomp_filter(&self) -> bool {
+        self.seccomp_filter.is_some()
+    }
+
+    // seccomp_action returns the action the seccomp filter takes on the syscall held in regs.
+    pub fn seccomp_action(&self, regs: &libc::user_regs_struct) -> SeccompAction {
+        match self.seccomp_filter {
+            Some(ref filter) => seccomp::check(filter, regs),
+            None => SeccompAction::Allow,
+        }
+    }
+
     #[inline]
     pub fn uts_namespace(&self) -> &UtsNameSpace {
         &self.uts_namespace
     }
 
+    #[inline]
+    pub fn uts_namespace_mut(&mut self) -> &mut UtsNameSpace {
+        &mut self.uts_namespace
+    }
+
     pub fn new_fd_from(
         &mut self,
         fd: i32,
@@ -360,6 +433,39 @@ impl Task {
         self.fd_table.new_fds(fd, &[file], flags).map(|fds| fds[0])
     }
 
+    // close_fd removes fd from the descriptor table and releases the file it referred to.
+    pub fn close_fd(&mut self, fd: i32) -> SysResult<()> {
+        let file = self
+            .fd_table
+            .remove(fd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        release_file(file)
+    }
+
+    // dup_to implements dup2(2) and dup3(2): the file at oldfd is installed at newfd, closing
+    // whatever newfd referred to before. Only O_CLOEXEC is meaningful in flags.
+    pub fn dup_to(&mut self, oldfd: i32, newfd: i32, flags: i32) -> SysResult<i32> {
+        let file = self
+            .get_file(oldfd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        if oldfd == newfd {
+            return Ok(newfd);
+        }
+
+        let displaced = self.get_file(newfd);
+        let fd_flags = FdFlags {
+            close_on_exec: flags & libc::O_CLOEXEC != 0,
+        };
+        self.fd_table
+            .new_fd_at(newfd, &file, fd_flags)
+            .map_err(|_| SysError::new(libc::EBADF))?;
+        if let Some(displaced) = displaced {
+            // Like Linux, errors from closing the old file are not reported by dup2(2).
+            let _ = release_file(displaced);
+        }
+        Ok(newfd)
+    }
+
     #[inline]
     pub fn fd_table(&self) -> &FdTable {
         &self.fd_table
@@ -389,6 +495,18 @@ impl Task {
         )
     }
 
+    pub fn copy_out_string(&self, addr: Addr, s: &str, max_len: usize) -> SysResult<usize> {
+        copy_string_out(
+            self.memory_manager(),
+            addr,
+            s,
+            max_len,
+            &IoOpts {
+                ignore_permissions: false,
+            },
+        )
+    }
+
     pub fn copy_out_bytes(&self, addr: Addr, src: &[u8]) -> SysResult<usize> {
         let opts = IoOpts {
             ignore_permissions: false,
@@ -430,6 +548,81 @@ impl Task {
         Ok(old_act)
     }
 
+    // force_signal delivers a synchronous signal such as one caused by a fault. Like linux's
+    // force_sig_info, such a signal cannot be ignored or blocked, so false is returned when the task
+    // has no handler to run and should be terminated instead.
+    pub fn force_signal(&mut self, info: &SignalInfo) -> bool {
+        let sig = linux::Signal(info.signo);
+        let act = self.signal_handlers.get(&sig).copied().unwrap_or_default();
+        if act.is_default() || act.is_ignored() || self.signal_mask() & sig.mask() != 0 {
+            return false;
+        }
+        match self.deliver_signal_to_handler(sig, act, info) {
+            Ok(()) => true,
+            Err(err) => {
+                logger::warn!("failed to set up the signal frame for {:?}: {:?}", sig, err);
+                false
+            }
+        }
+    }
+
+    fn deliver_signal_to_handler(
+        &mut self,
+        sig: linux::Signal,
+        act: linux::SigAction,
+        info: &SignalInfo,
+    ) -> SysResult<()> {
+        let alt = self.signal_stack();
+        let sp = if act.flags & linux::SA_ONSTACK != 0
+            && alt.is_enabled()
+            && alt.flags & SIGNAL_STACK_FLAG_ON_STACK == 0
+        {
+            alt.top()
+        } else {
+            Addr(self.regs().rsp)
+        };
+        let mut st = Stack::new(sp);
+        let mask = self.signal_mask();
+        let mm = self.memory_manager().clone();
+        let pid = context::context().tid().as_raw();
+        self.arch_context_mut().grab_fp_regs(pid)?;
+        self.arch_context_mut().signal_setup(
+            &mut st,
+            &act,
+            info,
+            &alt,
+            mask,
+            &mut *mm.borrow_mut(),
+        )?;
+
+        let mut new_mask = mask | act.mask;
+        if act.flags & linux::SA_NODEFER == 0 {
+            new_mask |= sig.mask();
+        }
+        self.set_signal_mask(new_mask);
+        if act.flags & linux::SA_RESETHAND != 0 {
+            self.signal_handlers
+                .insert(sig, linux::SigAction::default());
+        }
+        Ok(())
+    }
+
+    // signal_return restores the state saved when the current signal handler was invoked.
+    pub fn signal_return(&mut self) -> SysResult<()> {
+        let st = Stack::new(Addr(self.regs().rsp));
+        let mm = self.memory_manager().clone();
+        let (mask, alt) = self
+            .arch_context_mut()
+            .signal_restore(&st, &mut *mm.borrow_mut())?;
+        let pid = context::context().tid().as_raw();
+        self.arch_context_mut().restore_fp_regs(pid)?;
+        let unblockable = linux::Signal(libc::SIGKILL).mask() | linux::Signal(libc::SIGSTOP).mask();
+        self.set_signal_mask(mask & !unblockable);
+        // Like linux, failing to restore the alternate signal stack is not an error.
+        self.set_signal_stack(alt);
+        Ok(())
+    }
+
     pub fn iovecs_io_sequence(
         &self,
         addr: Addr,
@@ -457,6 +650,16 @@ impl Task {
         self.parent_death_signal = signal;
     }
 
+    #[inline]
+    pub fn personality(&self) -> u32 {
+        self.personality
+    }
+
+    #[inline]
+    pub fn set_personality(&mut self, personality: u32) {
+        self.personality = personality;
+    }
+
     pub fn create_timer(&mut self) -> i32 {
         let ret = self.next_timerid;
         self.timers.insert(ret);
@@ -469,6 +672,44 @@ impl Task {
     }
 }
 
+// release_file releases a file that has just been removed from the descriptor table. The file is
+// only flushed and closed once no other descriptor refers to it.
+fn release_file(file: Rc<RefCell<File>>) -> SysResult<()> {
+    // Closing any descriptor of a file releases the record locks the process holds on it.
+    file.borrow()
+        .dirent()
+        .borrow_mut()
+        .inode_mut()
+        .record_locks_mut()
+        .release(context::context().tid().as_raw() as LockOwner);
+
+    // Other descriptors from dup(2) may still refer to the file.
+    if Rc::strong_count(&file) == 1 {
+        file.borrow()
+            .flush()
+            .expect("flush returned error in current implementation?");
+        file.borrow().close()?;
+        let owner = Rc::as_ptr(&file) as LockOwner;
+        file.borrow()
+            .dirent()
+            .borrow_mut()
+            .inode_mut()
+            .locks_mut()
+            .unlock(owner);
+        if let Some(socket) = file.borrow_mut().file_operations_mut::<SocketFile>() {
+            socket.close_socket(&*context::context());
+        }
+        // The file may have been unlinked while it was open.
+        if let Some(regular) = file
+            .borrow()
+            .file_operations::<tmpfs::RegularFileOperations>()
+        {
+            tmpfs::release_if_unreachable(&regular.dirent, &*context::context())?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::context;
@@ -495,10 +736,7 @@ mod tests {
             limit_set
         };
 
-        {
-            let mut ctx = context::context_mut();
-            ctx.set_limits(limit_set);
-        }
+        let _limits = context::set_limits_for_test(limit_set);
 
         let mut fd_table = FdTable::init();
 
@@ -607,6 +845,58 @@ mod tests {
         });
     }
 
+    #[test]
+    fn regs_stored_once_per_stop() {
+        context::init_for_test();
+        let mut task = {
+            let ctx = context::context();
+            let mounts = ctx.task().mount_namespace().clone();
+            Task::new(mounts).unwrap()
+        };
+        task.set_arch_context(ArchContext::new());
+
+        let mut stored = Vec::new();
+        task.store_regs_with(|regs| {
+            stored.push(regs);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(stored.len(), 1);
+
+        // Nothing has changed since the last write back.
+        task.store_regs_with(|regs| {
+            stored.push(regs);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(stored.len(), 1);
+
+        // Multiple modifications within a stop are coalesced into a single write back.
+        let mut regs = task.regs();
+        regs.orig_rax = libc::SYS_getpid as u64;
+        task.set_regs(regs);
+        regs.rax = 42;
+        task.set_regs(regs);
+        task.store_regs_with(|regs| {
+            stored.push(regs);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[1].rax, 42);
+        assert_eq!(stored[1].orig_rax, libc::SYS_getpid as u64);
+
+        // The registers have to be restored once the platform has clobbered them.
+        task.invalidate_regs();
+        task.store_regs_with(|regs| {
+            stored.push(regs);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored[2].rax, 42);
+    }
+
     #[test]
     fn descriptor_flags() {
         run_test(|fd_table, file| {
@@ -32,9 +32,10 @@ impl TaskImage {
         envv: &HashMap<String, String>,
         extra_auxv: &HashMap<u64, Addr>,
         mount: &MountNamespace,
+        personality: u32,
     ) -> anyhow::Result<ArchContext> {
         let mut mm = MemoryManager::new();
-        let mut loader = Loader::new(&mut mm, argv, envv, mount);
+        let mut loader = Loader::new(&mut mm, argv, envv, mount, personality);
         let arch_context = loader.load(executable_path, extra_auxv)?;
         self.memory_manager = MemoryManagerState::Loaded(Rc::new(RefCell::new(mm)));
         Ok(arch_context)
@@ -12,11 +12,20 @@ impl UtsNameSpace {
         }
     }
 
-    pub fn host_name(&self) -> &String {
-        &self.host_name
+    // host_name returns the hostname of the UTS namespace sentinel runs in, which the runtime has
+    // set to the one of the container. host_name given on creation is only a fallback.
+    pub fn host_name(&self) -> String {
+        nix::unistd::gethostname()
+            .ok()
+            .and_then(|h| h.into_string().ok())
+            .unwrap_or_else(|| self.host_name.clone())
     }
 
     pub fn domain_name(&self) -> &String {
         &self.domain_name
     }
+
+    pub fn set_domain_name(&mut self, domain_name: String) {
+        self.domain_name = domain_name;
+    }
 }
@@ -10,7 +10,11 @@ use std::{
     collections::{BTreeMap, HashMap},
     ffi::CString,
     io::{Read, Write},
-    os::unix::net::{UnixListener, UnixStream},
+    os::unix::{
+        fs::OpenOptionsExt,
+        io::AsRawFd,
+        net::{UnixListener, UnixStream},
+    },
     path::{Path, PathBuf},
     rc::Rc,
     sync::RwLock,
@@ -18,13 +22,14 @@ use std::{
 };
 
 use anyhow::{anyhow, bail, Context as AnyhowCtx};
+use arch::signal::SignalInfo;
 use auth::{
     credentials::Credentials,
     id::{Kgid, Kuid},
     user_namespace::UserNamespace,
 };
 use capabilities::{drop_privileges, set_capabilities};
-use kernel::Kernel;
+use kernel::{task::ExitStatus, Kernel};
 use limit::LimitSet;
 use mem::{AccessType, Addr};
 use nix::{
@@ -37,10 +42,11 @@ use nix::{
     unistd::{self, Gid, Pid, Uid},
 };
 use oci_spec::runtime::{LinuxNamespaceType, Spec};
-use platform::{stub_init, Context, STUB_START};
-use seccompiler::deserialize_binary;
+use platform::{stub_init, Event, FastPath, Filter, Platform, SyscallSet, STUB_START};
+use seccompiler::{deserialize_binary, SeccompAction, SyscallTable, TargetArch};
 use sentinel_oci::{ContainerStatus, SentinelConfig, SentinelNamespaces};
-use utils::SysError;
+use serde::{Deserialize, Serialize};
+use utils::{SysError, SysErrorKind};
 
 // This byte limit is passed to `bincode` to guard against a potential memory
 // allocation DOS caused by binary filters that are too large.
@@ -48,6 +54,13 @@ use utils::SysError;
 // filter is 4096 instructions and Firecracker has a finite number of threads.
 const DESERIALIZATION_BYTES_LIMIT: Option<u64> = Some(100_000);
 
+// FAST_SYSCALLS_ANNOTATION lists the syscalls, separated by commas, which are serviced through
+// seccomp user notification instead of PTRACE_SYSEMU stops.
+const FAST_SYSCALLS_ANNOTATION: &str = "dev.sentinel.fast-syscalls";
+
+// NOTIFY_TIMEOUT is how long NotifySender waits for the listener to come up by default.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 pub struct NotifyListener {
     socket: UnixListener,
@@ -58,19 +71,8 @@ impl NotifyListener {
         if !socket_path.as_ref().is_absolute() {
             bail!("socket path {:?} is not absolute", socket_path.as_ref());
         }
-        let cwd = std::env::current_dir().context("failed to retrieve cwd")?;
-        let parent = socket_path
-            .as_ref()
-            .parent()
-            .context("no parent directory?")?;
-        std::env::set_current_dir(parent).context("failed to set cwd")?;
-        let socket_name = socket_path
-            .as_ref()
-            .file_name()
-            .context("failed to retrieve file name of socket")?;
-        let socket = UnixListener::bind(socket_name)
+        let socket = with_short_socket_path(socket_path.as_ref(), UnixListener::bind)
             .with_context(|| format!("failed to bind {:?}", socket_path.as_ref()))?;
-        std::env::set_current_dir(cwd).context("failed to set working directory back")?;
         Ok(Self { socket })
     }
 
@@ -88,6 +90,22 @@ impl NotifyListener {
         }
     }
 
+    // wait_for_stats waits for the end notification of a sandbox and returns the resource usage
+    // it carries.
+    pub fn wait_for_stats(&self) -> anyhow::Result<SandboxStats> {
+        match self.socket.accept() {
+            Ok((mut socket, _)) => {
+                let mut response = String::new();
+                socket
+                    .read_to_string(&mut response)
+                    .context("failed to read content of socket into string")?;
+                logger::debug!("NotifyListener received {}", response);
+                serde_json::from_str(&response).context("failed to parse sandbox stats")
+            }
+            Err(err) => bail!("NotifyListener accept failed: {}", err),
+        }
+    }
+
     fn wait_for_pid(&self) -> anyhow::Result<i32> {
         match self.socket.accept() {
             Ok((mut socket, _)) => {
@@ -115,26 +133,74 @@ impl NotifySender {
     }
 
     pub fn notify(&self, b: &[u8]) -> anyhow::Result<()> {
-        let cwd = std::env::current_dir().context("failed to retrieve cwd")?;
-        let parent = self.path.parent().context("no parent directory?")?;
-        std::env::set_current_dir(parent)
-            .with_context(|| format!("failed to set cwd to {:?}", parent))?;
-        let mut stream = UnixStream::connect(&self.path.file_name().unwrap())?;
+        self.notify_with_timeout(b, NOTIFY_TIMEOUT)
+    }
+
+    // notify_with_timeout sends b to the listener, retrying the connection until timeout passes
+    // in case the listener has not bound its socket yet.
+    pub fn notify_with_timeout(&self, b: &[u8], timeout: Duration) -> anyhow::Result<()> {
+        let mut stream = self.connect(timeout)?;
         stream.write_all(b)?;
-        std::env::set_current_dir(cwd)?;
         Ok(())
     }
 
     fn send_pid(&self, pid: i32) -> anyhow::Result<()> {
-        let cwd = std::env::current_dir().context("failed to retrieve cwd")?;
-        let parent = self.path.parent().context("no parent directory?")?;
-        std::env::set_current_dir(parent).context("failed to set cwd")?;
-        let mut stream = UnixStream::connect(&self.path.file_name().unwrap())?;
+        let mut stream = self.connect(NOTIFY_TIMEOUT)?;
         stream.write_all(&pid.to_le_bytes())?;
         logger::debug!("pid notify done");
-        std::env::set_current_dir(cwd)?;
         Ok(())
     }
+
+    // connect connects to the listener, backing off between attempts while the socket doesn't
+    // exist or nobody listens on it yet.
+    fn connect(&self, timeout: Duration) -> anyhow::Result<UnixStream> {
+        let deadline = std::time::Instant::now() + timeout;
+        let mut backoff = Duration::from_millis(1);
+        loop {
+            let err = match with_short_socket_path(&self.path, UnixStream::connect) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => err,
+            };
+            let retryable = matches!(
+                err.downcast_ref::<std::io::Error>().map(|e| e.kind()),
+                Some(std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused)
+            );
+            if !retryable {
+                return Err(err.context(format!("failed to connect to {:?}", self.path)));
+            }
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                bail!(
+                    "timed out after {:?} waiting for a listener on {:?}: {}",
+                    timeout,
+                    self.path,
+                    err
+                );
+            }
+            std::thread::sleep(backoff.min(deadline - now));
+            backoff = (backoff * 2).min(Duration::from_millis(100));
+        }
+    }
+}
+
+// with_short_socket_path calls f with a path naming the unix socket at path which fits in a
+// sockaddr_un however long path is. The parent directory is reached through an O_PATH fd under
+// /proc/self/fd rather than by changing the working directory, which other threads share.
+fn with_short_socket_path<T>(
+    path: &Path,
+    f: impl FnOnce(PathBuf) -> std::io::Result<T>,
+) -> anyhow::Result<T> {
+    let parent = path.parent().context("no parent directory?")?;
+    let name = path
+        .file_name()
+        .context("failed to retrieve file name of socket")?;
+    let dir = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_PATH | libc::O_DIRECTORY)
+        .open(parent)
+        .with_context(|| format!("failed to open {:?}", parent))?;
+    let short_path = PathBuf::from(format!("/proc/self/fd/{}", dir.as_raw_fd())).join(name);
+    Ok(f(short_path)?)
 }
 
 pub fn spawn_sandbox(
@@ -294,14 +360,25 @@ fn create_sandbox(
             std::process::exit(0);
         }
         pid if pid > 0 => {
+            // This is the pid spawn_sandbox returns, which oci_create persists. It's only set here
+            // for the state the hooks below see.
+            config.state.set_pid(Some(pid));
             pid_sender.send_pid(pid)?;
             config
                 .run_create_runtime_hooks()
                 .with_context(|| "StartContainer hooks")?;
             waitpid(Pid::from_raw(pid), None).with_context(|| "failed to wait")?;
             let cgroup_path = get_cgroup_path(linux.cgroups_path(), config.state.container_id());
+            // The cgroup has to be read before cleanup_sandbox removes it.
+            let stats = read_sandbox_stats(&cgroup_path, config.state.container_id())
+                .unwrap_or_else(|e| {
+                    logger::warn!("failed to read sandbox stats: {:?}", e);
+                    SandboxStats::default()
+                });
+            logger::info!("sandbox stats: {:?}", stats);
+            let stats = serde_json::to_vec(&stats).context("failed to serialize sandbox stats")?;
             end_notifier
-                .notify(b"sandbox execution ended!")
+                .notify(&stats)
                 .with_context(|| "failed to notify the end of execution")?;
             logger::debug!("cleaning up pid: {}", pid);
             cleanup_sandbox(config, &cgroup_path, dir).with_context(|| "cleanup failed")?;
@@ -317,6 +394,11 @@ fn run_sandbox() -> anyhow::Result<()> {
     let mut syscall_counter = 0usize;
 
     stub_init().with_context(|| "Failed to stub_init")?;
+    // The tracee reads the filter from its copy of our memory, so it has to exist before clone.
+    let fast_path_filter = {
+        let ctx = context::context();
+        ctx.platform().fast_syscalls().map(Filter::new)
+    };
 
     let pid =
         unsafe { libc::syscall(libc::SYS_clone, libc::SIGCHLD | libc::CLONE_FILES, 0, 0) } as i32;
@@ -354,13 +436,17 @@ fn run_sandbox() -> anyhow::Result<()> {
                 task.grab_init_regs();
             }
 
-            ptrace::setoptions(
-                pid,
-                ptrace::Options::PTRACE_O_EXITKILL
-                    | ptrace::Options::PTRACE_O_TRACEEXEC
-                    | ptrace::Options::PTRACE_O_TRACESYSGOOD,
-            )
-            .expect("PTRACE_SETOPTIONS failed");
+            let mut options = ptrace::Options::PTRACE_O_EXITKILL
+                | ptrace::Options::PTRACE_O_TRACEEXEC
+                | ptrace::Options::PTRACE_O_TRACESYSGOOD;
+            if fast_path_filter.is_some() {
+                options |= ptrace::Options::PTRACE_O_TRACESECCOMP;
+            }
+            ptrace::setoptions(pid, options).expect("PTRACE_SETOPTIONS failed");
+            let mut fast_path = fast_path_filter.as_ref().map(|filter| {
+                let ctx = &*context::context();
+                FastPath::install(filter, ctx).expect("failed to install the fast path")
+            });
 
             let arch_context = {
                 let ctx = context::context();
@@ -383,6 +469,9 @@ fn run_sandbox() -> anyhow::Result<()> {
                 let ctx = context::context();
                 let mut task = ctx.task_mut();
                 task.set_arch_context(arch_context);
+                task.arch_context_mut()
+                    .grab_fp_regs(pid.as_raw())
+                    .expect("PTRACE_GETFPREGS failed");
             }
 
             logger::info!("applying seccomp filters..");
@@ -391,66 +480,142 @@ fn run_sandbox() -> anyhow::Result<()> {
 
             let mut last_segv_addr = None;
             let mut last_segv_ip = None;
-            loop {
+            'sandbox: loop {
                 {
-                    let ctx = &*context::context();
-                    let mut task = ctx.task_mut();
-                    let mut regs = task.regs();
-                    task.reset_sysemu_regs(&mut regs);
-                    ctx.ptrace_set_regs(regs).expect("PTRACE_SETREGS failed");
+                    let ctx = context::context();
+                    ctx.store_task_regs().expect("PTRACE_SETREGS failed");
                 }
-                ptrace::sysemu(pid, None).expect("PTRACE_SYSEMU failed");
-                match waitpid(pid, Some(WaitPidFlag::__WALL | WaitPidFlag::WUNTRACED))
-                    .expect("wait failed")
-                {
-                    WaitStatus::PtraceSyscall(_) => {
-                        let mut regs = utils::init_libc_regs();
-                        {
+                let status = match fast_path.as_mut() {
+                    Some(fast_path) => {
+                        ptrace::cont(pid, None).expect("PTRACE_CONT failed");
+                        loop {
+                            let notification = match fast_path.wait(pid).expect("wait failed") {
+                                Event::Status(status) => break status,
+                                Event::Notification(notification) => notification,
+                            };
+                            let mut regs = {
+                                let ctx = context::context();
+                                let task = ctx.task();
+                                notification.regs(task.regs())
+                            };
+                            let seccomp_action = {
+                                let ctx = context::context();
+                                let task = ctx.task();
+                                task.seccomp_action(&regs)
+                            };
+                            syscall_counter += 1;
+                            let res = match seccomp_action {
+                                SeccompAction::Allow => perform_syscall(
+                                    &mut regs,
+                                    syscall_counter,
+                                    &mut syscall_latencies,
+                                ),
+                                SeccompAction::Errno(errno) => {
+                                    logger::info!("syscall {} denied by seccomp", regs.orig_rax);
+                                    Err(SysError::new(errno as i32))
+                                }
+                                action => {
+                                    logger::warn!(
+                                        "task killed by seccomp ({:?}) at syscall {}",
+                                        action,
+                                        regs.orig_rax
+                                    );
+                                    let ctx = context::context();
+                                    let mut task = ctx.task_mut();
+                                    task.set_exit_status(ExitStatus {
+                                        code: 0,
+                                        sig_no: libc::SIGSYS,
+                                    });
+                                    break 'sandbox;
+                                }
+                            };
+                            fast_path
+                                .respond(&notification, res)
+                                .expect("SECCOMP_IOCTL_NOTIF_SEND failed");
+                        }
+                    }
+                    None => {
+                        ptrace::sysemu(pid, None).expect("PTRACE_SYSEMU failed");
+                        waitpid(pid, Some(WaitPidFlag::__WALL | WaitPidFlag::WUNTRACED))
+                            .expect("wait failed")
+                    }
+                };
+                // Unlike PTRACE_SYSEMU, a SECCOMP_RET_TRACE stop runs the syscall on the host
+                // unless its number is replaced with -1.
+                let skip_syscall = matches!(
+                    status,
+                    WaitStatus::PtraceEvent(_, Signal::SIGTRAP, libc::PTRACE_EVENT_SECCOMP)
+                );
+                match status {
+                    WaitStatus::PtraceSyscall(_)
+                    | WaitStatus::PtraceEvent(_, Signal::SIGTRAP, libc::PTRACE_EVENT_SECCOMP) => {
+                        let mut regs = {
+                            let ctx = context::context();
+                            let mut task = ctx.task_mut();
+                            task.load_regs(pid).expect("PTRACE_GETREGS failed")
+                        };
+                        let seccomp_action = {
                             let ctx = context::context();
                             let task = ctx.task();
-                            let pid = ctx.tid();
-                            task.ptrace_get_regs(&mut regs, pid)
-                                .expect("PTRACE_GETREGS failed");
+                            task.seccomp_action(&regs)
+                        };
+                        match seccomp_action {
+                            SeccompAction::Allow => (),
+                            SeccompAction::Errno(errno) => {
+                                logger::info!("syscall {} denied by seccomp", regs.orig_rax);
+                                regs.rax = -(errno as i64) as u64;
+                                if skip_syscall {
+                                    regs.orig_rax = u64::MAX;
+                                }
+                                let ctx = context::context();
+                                let mut task = ctx.task_mut();
+                                task.set_regs(regs);
+                                continue;
+                            }
+                            action => {
+                                logger::warn!(
+                                    "task killed by seccomp ({:?}) at syscall {}",
+                                    action,
+                                    regs.orig_rax
+                                );
+                                let ctx = context::context();
+                                let mut task = ctx.task_mut();
+                                task.set_exit_status(ExitStatus {
+                                    code: 0,
+                                    sig_no: libc::SIGSYS,
+                                });
+                                break;
+                            }
                         }
-                        let start = std::time::Instant::now();
                         let should_exit = syscalls::should_exit(regs.orig_rax as i64);
                         syscall_counter += 1;
-                        regs.rax = match syscalls::perform(&mut regs, syscall_counter) {
-                            Ok(n) => {
-                                let elapsed = start.elapsed();
-                                logger::info!(
-                                    "success: {:#x} ({}) (Elapsed: {:?})\n",
-                                    n,
-                                    n,
-                                    elapsed
-                                );
-                                syscall_latencies
-                                    .insert(elapsed, (syscall_counter, regs.orig_rax as usize));
-                                n as u64
-                            }
-                            Err(err) => {
-                                logger::info!("failed: {} (Elapsed: {:?})\n", err, start.elapsed());
-                                -err.code() as u64
-                            }
+                        regs.rax = match perform_syscall(
+                            &mut regs,
+                            syscall_counter,
+                            &mut syscall_latencies,
+                        ) {
+                            Ok(n) => n as u64,
+                            Err(err) => -err.code() as u64,
                         };
                         if should_exit {
                             logger::info!("task exiting");
                             break;
                         }
+                        if skip_syscall {
+                            regs.orig_rax = u64::MAX;
+                        }
                         {
                             let ctx = context::context();
                             let mut task = ctx.task_mut();
                             task.set_regs(regs);
-                            ctx.ptrace_set_regs(regs).expect("PTRACE_SETREGS failed");
                         }
                     }
                     WaitStatus::Stopped(_, sig) => match sig {
                         Signal::SIGSEGV => {
                             let ctx = context::context();
                             {
-                                let regs = ptrace::getregs(pid).expect("PTRACE_GETREGS failed");
                                 let mut task = ctx.task_mut();
-                                task.set_regs(regs);
+                                task.load_regs(pid).expect("PTRACE_GETREGS failed");
                                 if task.handle_possible_cpuid_instruction() {
                                     continue;
                                 }
@@ -473,30 +638,52 @@ fn run_sandbox() -> anyhow::Result<()> {
                             last_segv_addr = Some(addr);
                             last_segv_ip = Some(rip);
 
-                            let mm = ctx.memory_manager();
-                            let mut mm = mm.borrow_mut();
-                            match mm.handle_user_fault(addr, at) {
+                            let res = {
+                                let mm = ctx.memory_manager();
+                                let mut mm = mm.borrow_mut();
+                                mm.handle_user_fault(addr, at)
+                            };
+                            match res {
                                 Ok(()) => {
                                     logger::info!("handled user fault: {:?} {}", at, addr);
                                     continue;
                                 }
                                 Err(e) => {
-                                    logger::warn!(
-                                        "handle_user_fault at {} failed (ignoring for now) {:?}: {:?}",
+                                    logger::info!(
+                                        "handle_user_fault at {} failed {:?}: {:?}",
                                         addr,
                                         sig_info,
                                         e
                                     );
-                                    let regs = ptrace::getregs(pid).expect("PTRACE_GETREGS failed");
+                                    let info = fault_signal_info(addr, &e);
+                                    let mut task = ctx.task_mut();
+                                    if task.force_signal(&info) {
+                                        logger::info!(
+                                            "delivered signal {} to the task",
+                                            info.signo
+                                        );
+                                        last_segv_addr = None;
+                                        last_segv_ip = None;
+                                        continue;
+                                    }
                                     logger::warn!(
-                                        "handle_user_fault failed and regs are: {:?}",
-                                        regs
+                                        "task killed by signal {} and regs are: {:?}",
+                                        info.signo,
+                                        task.regs()
                                     );
+                                    task.set_exit_status(ExitStatus {
+                                        code: 0,
+                                        sig_no: info.signo,
+                                    });
                                     break;
                                 }
                             }
                         }
-                        Signal::SIGTRAP => continue,
+                        Signal::SIGTRAP => {
+                            let ctx = context::context();
+                            ctx.task().invalidate_regs();
+                            continue;
+                        }
                         e => bail!("unhandled signal at {}:{}: {:?}", file!(), line!(), e),
                     },
                     WaitStatus::Exited(_, _) => {
@@ -538,6 +725,85 @@ fn run_sandbox() -> anyhow::Result<()> {
     Ok(())
 }
 
+// perform_syscall performs the syscall in regs and records its latency.
+fn perform_syscall(
+    regs: &mut libc::user_regs_struct,
+    counter: usize,
+    latencies: &mut BTreeMap<Duration, (usize, usize)>,
+) -> syscalls::Result {
+    let start = std::time::Instant::now();
+    let res = syscalls::perform(regs, counter);
+    let elapsed = start.elapsed();
+    match res {
+        Ok(n) => {
+            logger::info!("success: {:#x} ({}) (Elapsed: {:?})\n", n, n, elapsed);
+            latencies.insert(elapsed, (counter, regs.orig_rax as usize));
+        }
+        Err(ref err) => logger::info!("failed: {} (Elapsed: {:?})\n", err, elapsed),
+    }
+    res
+}
+
+// fault_signal_info builds the siginfo for a fault at addr which could not be resolved.
+fn fault_signal_info(addr: Addr, err: &SysError) -> SignalInfo {
+    let mut info = match (err.kind(), err.code()) {
+        (SysErrorKind::BusError, _) => SignalInfo::new(libc::SIGBUS, linux::BUS_ADRERR),
+        (_, libc::EPERM) => SignalInfo::new(libc::SIGSEGV, linux::SEGV_ACCERR),
+        _ => SignalInfo::new(libc::SIGSEGV, linux::SEGV_MAPERR),
+    };
+    info.set_addr(addr);
+    info
+}
+
+// SandboxStats is the resource usage of a sandbox, read from its cgroup when it exits and sent
+// with the end notification.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxStats {
+    // The memory charged to the cgroup at exit and at most, in bytes. The peak is unknown on
+    // cgroup v2 kernels without memory.peak.
+    pub memory_current: u64,
+    pub memory_peak: Option<u64>,
+    // The CPU time spent by the cgroup, in microseconds.
+    pub cpu_usage_usec: u64,
+    pub cpu_user_usec: u64,
+    pub cpu_system_usec: u64,
+}
+
+// read_sandbox_stats queries the cgroup manager of the sandbox for its resource usage.
+fn read_sandbox_stats(cgroups_path: &Path, container_id: &str) -> anyhow::Result<SandboxStats> {
+    let cgroup_manager =
+        libcgroups::common::create_cgroup_manager(cgroups_path, false, container_id)?;
+    let stats = cgroup_manager
+        .stats()
+        .context("failed to read cgroup stats")?;
+    let unified = matches!(
+        libcgroups::common::get_cgroup_setup()?,
+        libcgroups::common::CgroupSetup::Unified
+    );
+    // cgroup v1 accounts CPU time in nanoseconds, while cgroup v2 does in microseconds.
+    let to_usec = |t: u64| if unified { t } else { t / 1000 };
+    let memory_peak = if stats.memory.memory.max_usage > 0 {
+        Some(stats.memory.memory.max_usage)
+    } else if unified {
+        // The cgroup v2 manager doesn't report memory.peak, so it's read from cgroupfs directly.
+        let path = Path::new(libcgroups::common::DEFAULT_CGROUP_ROOT)
+            .join(cgroups_path.strip_prefix("/").unwrap_or(cgroups_path))
+            .join("memory.peak");
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+    } else {
+        None
+    };
+    Ok(SandboxStats {
+        memory_current: stats.memory.memory.usage,
+        memory_peak,
+        cpu_usage_usec: to_usec(stats.cpu.usage.usage_total),
+        cpu_user_usec: to_usec(stats.cpu.usage.usage_user),
+        cpu_system_usec: to_usec(stats.cpu.usage.usage_kernel),
+    })
+}
+
 fn cleanup_sandbox<P: AsRef<Path>>(
     config: &mut SentinelConfig,
     cgroups_path: &PathBuf,
@@ -581,7 +847,7 @@ fn build_context(spec: &Spec, config: &SentinelConfig, namespace: &SentinelNames
         None,
         Rc::new(UserNamespace::new_root()),
     );
-    let kernel = Kernel::load();
+    let kernel = Kernel::load(platform_from_spec(spec), &config.uname);
     context::init_context(
         RwLock::new(LimitSet::default()),
         creds,
@@ -594,6 +860,31 @@ fn build_context(spec: &Spec, config: &SentinelConfig, namespace: &SentinelNames
     .expect("failed to initialize the context");
 }
 
+// platform_from_spec enables the fast path for the syscalls listed in FAST_SYSCALLS_ANNOTATION.
+fn platform_from_spec(spec: &Spec) -> Platform {
+    let names = match spec
+        .annotations()
+        .as_ref()
+        .and_then(|a| a.get(FAST_SYSCALLS_ANNOTATION))
+    {
+        Some(names) => names,
+        None => return Platform::Ptrace,
+    };
+    let table = SyscallTable::new(TargetArch::x86_64);
+    let mut fast_syscalls = SyscallSet::new();
+    for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match table.get_syscall_nr(name) {
+            Some(nr) if syscalls::supports_fast_path(nr) && fast_syscalls.insert(nr) => (),
+            _ => logger::warn!("ignoring syscall {} unfit for the fast path", name),
+        }
+    }
+    if fast_syscalls.is_empty() {
+        Platform::Ptrace
+    } else {
+        Platform::PtraceFastPath(fast_syscalls)
+    }
+}
+
 fn apply_rest_namespaces(ns: &SentinelNamespaces, spec: &Spec) -> anyhow::Result<()> {
     ns.apply(|t| t != LinuxNamespaceType::User && t != LinuxNamespaceType::Pid)
         .with_context(|| "failed to apply namespaces")?;
@@ -652,3 +943,76 @@ fn get_output(fd: i32) -> anyhow::Result<String> {
         Err(err) => Err(anyhow::anyhow!("error occurred: {:?}", err)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_long_socket_path() {
+        let root = std::env::temp_dir().join(format!("sentinel-notify-{}", std::process::id()));
+        // The directory alone is longer than sun_path.
+        let dir = root.join("d".repeat(120));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let cwd = std::env::current_dir().unwrap();
+
+        let listener = NotifyListener::new(&path).unwrap();
+        assert_eq!(std::env::current_dir().unwrap(), cwd);
+        let sender = NotifySender::new(&path);
+        let handle = std::thread::spawn(move || sender.send_pid(42).unwrap());
+        assert_eq!(listener.wait_for_pid().unwrap(), 42);
+        handle.join().unwrap();
+        assert_eq!(std::env::current_dir().unwrap(), cwd);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn notify_before_listener_binds() {
+        let dir = std::env::temp_dir().join(format!("sentinel-notify-late-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+
+        let sender = NotifySender::new(&path);
+        let handle = std::thread::spawn(move || {
+            sender.notify_with_timeout(b"ready", Duration::from_secs(10))
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        let listener = NotifyListener::new(&path).unwrap();
+        listener.wait().unwrap();
+        handle.join().unwrap().unwrap();
+
+        // Without a listener the sender gives up once the timeout passes.
+        std::fs::remove_file(&path).unwrap();
+        let err = NotifySender::new(&path)
+            .notify_with_timeout(b"ready", Duration::from_millis(50))
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn end_notification_carries_stats() {
+        let dir = std::env::temp_dir().join(format!("sentinel-notify-end-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("end.sock");
+        let listener = NotifyListener::new(&path).unwrap();
+
+        let stats = SandboxStats {
+            memory_current: 4096,
+            memory_peak: Some(1 << 20),
+            cpu_usage_usec: 300,
+            cpu_user_usec: 200,
+            cpu_system_usec: 100,
+        };
+        let bytes = serde_json::to_vec(&stats).unwrap();
+        let sender = NotifySender::new(&path);
+        let handle = std::thread::spawn(move || sender.notify(&bytes).unwrap());
+        assert_eq!(listener.wait_for_stats().unwrap(), stats);
+        handle.join().unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -12,7 +12,7 @@ use goblin::{
     elf::{Elf, ProgramHeader},
     elf64::{
         header::ELFMAG,
-        program_header::{PF_X, PT_INTERP, PT_LOAD},
+        program_header::{PF_X, PT_GNU_STACK, PT_INTERP, PT_LOAD},
     },
 };
 use mem::{io::Io, AccessType, Addr, IoOpts, IoSequence, PAGE_SIZE};
@@ -26,12 +26,23 @@ const MAX_LOADER_ATTEMPS: u32 = 6;
 
 static INTERPRETER_MAGIC: &[u8; 2] = b"#!";
 
+// BASELINE_HWCAP is the x86-64 baseline (FPU, TSC, CX8, CMOV, MMX, FXSR, SSE and SSE2), which is
+// reported as AT_HWCAP if the host's value can't be retrieved.
+const BASELINE_HWCAP: u64 = 1 | 1 << 4 | 1 << 8 | 1 << 15 | 1 << 23 | 1 << 24 | 1 << 25 | 1 << 26;
+
+// LoadedElf describes an ELF mapped into memory. ET_EXEC binaries are mapped at their link-time
+// addresses, so load_offset is zero. ET_DYN ones are relocated by load_offset, and are either a
+// dynamically linked PIE (interpreter is set), a static-PIE (no interpreter) or an interpreter
+// itself. entry and start already include load_offset.
 #[derive(Debug)]
 struct LoadedElf {
     entry: u64,
     start: Addr,
     end: Addr,
+    load_offset: Addr,
     interpreter: Option<String>,
+    // executable_stack is whether PT_GNU_STACK asks for an executable stack.
+    executable_stack: bool,
     phdr_addr: Addr,
     phdr_size: u16,
     phdr_num: u16,
@@ -45,6 +56,7 @@ pub struct Loader<'a> {
     argv: Vec<String>,
     envv: &'a HashMap<String, String>,
     mount: &'a MountNamespace,
+    personality: u32,
 }
 
 impl<'a> Loader<'a> {
@@ -53,6 +65,7 @@ impl<'a> Loader<'a> {
         argv: Vec<String>,
         envv: &'a HashMap<String, String>,
         mount: &'a MountNamespace,
+        personality: u32,
     ) -> Self {
         let root = Rc::downgrade(mount.root());
         // TODO: setting working directory to root for now.
@@ -64,6 +77,7 @@ impl<'a> Loader<'a> {
             argv,
             envv,
             mount,
+            personality,
         }
     }
 
@@ -91,7 +105,7 @@ impl<'a> Loader<'a> {
         self.mm.brk_setup(e);
 
         // allocate our stack
-        let mut stack = self.alloc_stack()?;
+        let mut stack = self.alloc_stack(loaded.executable_stack)?;
         stack.push(
             StackVal::Bytes(executable_path.as_ref().to_str().unwrap().as_bytes()),
             self.mm,
@@ -130,6 +144,7 @@ impl<'a> Loader<'a> {
         auxv.insert(libc::AT_RANDOM, Addr(random));
         auxv.insert(libc::AT_PAGESZ, Addr(PAGE_SIZE as u64));
         auxv.insert(linux::AT_SYSINFO_EHDR, vdso_addr);
+        insert_hwcap(&mut auxv, |typ| unsafe { libc::getauxval(typ) });
         auxv.extend(extra_auxv);
 
         let stack_layout = stack.load(&self.argv, self.envv, &auxv, self.mm)?;
@@ -194,7 +209,8 @@ impl<'a> Loader<'a> {
                 auxv.insert(libc::AT_BASE, i.start);
             }
             None => {
-                auxv.insert(libc::AT_BASE, Addr(0));
+                // A static-PIE relocates itself, so it is given its own base here.
+                auxv.insert(libc::AT_BASE, bin.load_offset);
             }
         }
         bin.auxv = auxv;
@@ -208,7 +224,7 @@ impl<'a> Loader<'a> {
 
     fn load_initial_elf(&mut self, file: &mut fs::File) -> SysResult<(LoadedElf, ArchContext)> {
         let arch_context = ArchContext::new();
-        let layout = self.mm.set_mmap_layout()?;
+        let layout = self.mm.set_mmap_layout(self.personality)?;
         let elf = self.load_parsed_elf(file, layout.pie_load_address())?;
         Ok((elf, arch_context))
     }
@@ -227,6 +243,7 @@ impl<'a> Loader<'a> {
 
         let mut start = None;
         let mut end = Addr(0);
+        let mut executable_stack = false;
         for prog_hdr in &elf.program_headers {
             let p_type = prog_hdr.p_type;
             if p_type == PT_LOAD {
@@ -247,6 +264,8 @@ impl<'a> Loader<'a> {
                 if elf.interpreter.is_none() {
                     panic!("PT_INTERP path is empty");
                 }
+            } else if p_type == PT_GNU_STACK {
+                executable_stack = prog_hdr.p_flags & PF_X != 0;
             }
         }
         let mut start = start.unwrap();
@@ -298,7 +317,9 @@ impl<'a> Loader<'a> {
             entry,
             start,
             end,
+            load_offset: offset,
             interpreter: elf.interpreter.map(|s| s.to_string()),
+            executable_stack,
             phdr_addr,
             phdr_size: elf.header.e_phentsize,
             phdr_num: elf.header.e_phnum,
@@ -386,7 +407,8 @@ impl<'a> Loader<'a> {
         let inode = dirent_ref.inode();
         let sattr = inode.stable_attr();
         if sattr.is_symlink() {
-            panic!("trying to load a symlink (should call find_link() in the future)");
+            // find_inode resolves symlinks, so one is left only when the traversal limit was hit.
+            bail_libc!(libc::ELOOP);
         }
         let perms = PermMask {
             read: true,
@@ -410,8 +432,8 @@ impl<'a> Loader<'a> {
         )
     }
 
-    fn alloc_stack(&mut self) -> SysResult<Stack> {
-        let ar = self.mm.map_stack()?;
+    fn alloc_stack(&mut self, executable: bool) -> SysResult<Stack> {
+        let ar = self.mm.map_stack(executable)?;
         Ok(Stack::new(Addr(ar.end)))
     }
 
@@ -543,3 +565,40 @@ impl<'a> Loader<'a> {
         Ok(interp.to_string())
     }
 }
+
+// insert_hwcap adds AT_HWCAP and AT_HWCAP2 to auxv with the values getauxval reports for the host.
+// glibc relies on them to pick its optimized routines.
+fn insert_hwcap<F: Fn(u64) -> u64>(auxv: &mut HashMap<u64, Addr>, getauxval: F) {
+    let hwcap = match getauxval(libc::AT_HWCAP) {
+        0 => BASELINE_HWCAP,
+        v => v,
+    };
+    auxv.insert(libc::AT_HWCAP, Addr(hwcap));
+    auxv.insert(libc::AT_HWCAP2, Addr(getauxval(libc::AT_HWCAP2)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_hwcap_from_host() {
+        let mut auxv = HashMap::new();
+        insert_hwcap(&mut auxv, |typ| unsafe { libc::getauxval(typ) });
+        let hwcap = auxv.get(&libc::AT_HWCAP).copied();
+        assert_eq!(
+            hwcap,
+            Some(Addr(unsafe { libc::getauxval(libc::AT_HWCAP) }))
+        );
+        assert_ne!(hwcap, Some(Addr(0)));
+        assert!(auxv.contains_key(&libc::AT_HWCAP2));
+    }
+
+    #[test]
+    fn insert_hwcap_fallback() {
+        let mut auxv = HashMap::new();
+        insert_hwcap(&mut auxv, |_| 0);
+        assert_eq!(auxv.get(&libc::AT_HWCAP), Some(&Addr(BASELINE_HWCAP)));
+        assert_eq!(auxv.get(&libc::AT_HWCAP2), Some(&Addr(0)));
+    }
+}
@@ -22,7 +22,6 @@ use memmap::{
 };
 use pgalloc::{AllocOpts, Direction, MemoryFile, MemoryFileProvider};
 use platform::PtraceAddressSpace;
-use rand::Rng;
 use segment::{Gap, Seg, SegOrGap, Set, SetOperations};
 use usage::MemoryKind;
 use utils::{bail_libc, err_libc, FileRange, Range, SysError, SysResult};
@@ -207,6 +206,7 @@ pub struct MemoryManager {
     pmas: PmaSet,
     vmas: VmaSet,
     brk: AddrRange,
+    stack: AddrRange,
     usage_address_space: u64,
     locked_as: u64,
     data_address_space: u64,
@@ -232,6 +232,7 @@ impl MemoryManager {
             vmas: VmaSet::new(Box::new(vma_ops)),
             usage_address_space: 0,
             brk: AddrRange::default(),
+            stack: AddrRange::default(),
             locked_as: 0,
             data_address_space: 0,
             cur_rss: 0,
@@ -263,13 +264,16 @@ impl MemoryManager {
         self.argv.end = a.0;
     }
 
-    pub fn set_mmap_layout(&mut self) -> SysResult<MmapLayout> {
+    // set_mmap_layout chooses the layout of a new image, which is only randomized if personality
+    // lacks ADDR_NO_RANDOMIZE.
+    pub fn set_mmap_layout(&mut self, personality: u32) -> SysResult<MmapLayout> {
         let ctx = &*context::context();
         let platform = ctx.platform();
         let layout = MmapLayout::new(
             platform.min_user_address(),
             platform.max_user_address(),
             &ctx.limits(),
+            personality & libc::ADDR_NO_RANDOMIZE as u32 == 0,
         )?;
         self.layout = layout;
         Ok(layout)
@@ -1153,6 +1157,9 @@ impl MemoryManager {
             .round_down()
             .to_range(PAGE_SIZE as u64)
             .ok_or_else(|| SysError::new(libc::EFAULT))?;
+        if self.vmas.find_segment(ar.start).is_none() {
+            self.grow_down(ar.start)?;
+        }
         let (vseg, _, res) = self.get_vmas(ar, at, false);
         res?;
         let (pseg, _, res) = self.get_pmas(vseg.unwrap(), ar, at);
@@ -1160,6 +1167,44 @@ impl MemoryManager {
         self.map_address_space(pseg.unwrap(), ar, false)
     }
 
+    // grow_down extends the grows-down vma above the unmapped page at addr down to it, the way
+    // linux grows stacks. The page has to lie in the guard region below the vma, and the grown vma
+    // has to fit in RLIMIT_STACK; otherwise the fault fails with EFAULT.
+    fn grow_down(&mut self, addr: u64) -> SysResult<()> {
+        let vseg = self
+            .vmas
+            .find_gap(addr)
+            .and_then(|vgap| self.vmas.next_segment_of_gap(&vgap))
+            .ok_or_else(|| SysError::new(libc::EFAULT))?;
+        let vma = self.vmas.value(&vseg).clone();
+        if !vma.grows_down || vseg.start() - addr > GUARD_BYTES as u64 {
+            bail_libc!(libc::EFAULT);
+        }
+        let ar = AddrRange {
+            start: addr,
+            end: vseg.start(),
+        };
+        {
+            let ctx = context::context();
+            let limits = ctx.limits();
+            if vseg.end() - ar.start > limits.get_stack().cur
+                || self.usage_address_space + ar.len() > limits.get_address_space().cur
+            {
+                bail_libc!(libc::EFAULT);
+            }
+        }
+
+        self.usage_address_space += ar.len();
+        if vma.mlock_mode != MLockMode::None_ {
+            self.locked_as += ar.len();
+        }
+        if self.stack.start == vseg.start() {
+            self.stack.start = ar.start;
+        }
+        self.vmas.insert(ar, vma);
+        Ok(())
+    }
+
     pub fn mmap(&mut self, mut opts: MmapOpts) -> SysResult<Addr> {
         if opts.length == 0 {
             bail_libc!(libc::EINVAL);
@@ -1237,8 +1282,9 @@ impl MemoryManager {
             bail_libc!(libc::EINVAL);
         }
 
-        let old_size_addr = Addr(old_size).round_up().expect("should handle None");
-        let old_size = old_size_addr.0;
+        // like linux, an old_size which rounds up past the end of the address space wraps to 0,
+        // which is a valid size.
+        let old_size = Addr(old_size).round_up().unwrap_or(Addr(0)).0;
         let new_size = match Addr(new_size).round_up() {
             Some(Addr(0)) | None => bail_libc!(libc::EINVAL),
             Some(addr) => addr,
@@ -1398,9 +1444,9 @@ impl MemoryManager {
             if is_private_data {
                 self.data_address_space += new_ar.len();
             }
-            if lock_mode == MLockMode::None_ {
+            if lock_mode != MLockMode::None_ {
                 self.locked_as += new_ar.len();
-                if lock_mode != MLockMode::Eager {
+                if lock_mode == MLockMode::Eager {
                     self.populate_vma(&vseg, new_ar, true);
                 }
             }
@@ -1545,6 +1591,210 @@ impl MemoryManager {
         }
     }
 
+    // mlock sets the mlock mode of the vmas covering [addr, addr+length). Locking fails with ENOMEM
+    // if it would take the locked address space over RLIMIT_MEMLOCK, and eagerly locked pages are
+    // committed right away.
+    pub fn mlock(&mut self, addr: Addr, length: u64, mode: MLockMode) -> SysResult<()> {
+        let la = length
+            .checked_add(addr.page_offset())
+            .and_then(|l| Addr(l).round_up())
+            .ok_or_else(|| SysError::new(libc::EINVAL))?;
+        let ar = addr
+            .round_down()
+            .to_range(la.0)
+            .ok_or_else(|| SysError::new(libc::EINVAL))?;
+
+        if mode != MLockMode::None_ {
+            self.check_mlock_limit(self.locked_as + ar.len() - self.mlocked_bytes_range(ar))?;
+        }
+
+        let mut unmapped = false;
+        let mut vseg_maybe = self.vmas.find_segment(ar.start);
+        loop {
+            let vseg = match vseg_maybe {
+                Some(vseg) => self.vmas.isolate(&vseg, ar),
+                None => {
+                    unmapped = true;
+                    break;
+                }
+            };
+            self.set_mlock_mode(&vseg, mode);
+            if ar.end <= vseg.end() {
+                break;
+            }
+            vseg_maybe = match self.vmas.next_non_empty(&vseg) {
+                Some(SegOrGap::Segment(s)) => Some(s),
+                Some(SegOrGap::Gap(_)) | None => None,
+            };
+        }
+        self.vmas.merge_range(ar);
+        self.vmas.merge_adjacant(ar);
+        if unmapped {
+            bail_libc!(libc::ENOMEM);
+        }
+
+        if mode == MLockMode::Eager {
+            let mut vseg_maybe = self.vmas.find_segment(ar.start);
+            while let Some(vseg) = vseg_maybe.filter(|v| v.start() < ar.end) {
+                // linux fails to fault in the pages of inaccessible mappings with EFAULT, which
+                // mlock(2) reports as ENOMEM.
+                if !self.vmas.value(&vseg).effective_perms.any() {
+                    bail_libc!(libc::ENOMEM);
+                }
+                self.populate_vma(&vseg, vseg.range().intersect(&ar), true);
+                vseg_maybe = self.vmas.next_segment_of_seg(&vseg);
+            }
+        }
+        Ok(())
+    }
+
+    // mlockall sets the mlock mode of every current mapping if current is set, and of every future
+    // one if future is set.
+    pub fn mlockall(&mut self, current: bool, future: bool, mode: MLockMode) -> SysResult<()> {
+        if !current && !future {
+            bail_libc!(libc::EINVAL);
+        }
+        if current {
+            if mode != MLockMode::None_ {
+                self.check_mlock_limit(self.usage_address_space)?;
+            }
+            let mut vseg_maybe = self.vmas.first_segment();
+            while let Some(vseg) = vseg_maybe {
+                self.set_mlock_mode(&vseg, mode);
+                vseg_maybe = self.vmas.next_segment_of_seg(&vseg);
+            }
+        }
+        if future {
+            self.def_mlock_mode = mode;
+        }
+        if current && mode == MLockMode::Eager {
+            // like linux, faulting in the pages is best effort here.
+            let mut vseg_maybe = self.vmas.first_segment();
+            while let Some(vseg) = vseg_maybe {
+                self.populate_vma(&vseg, vseg.range(), true);
+                vseg_maybe = self.vmas.next_segment_of_seg(&vseg);
+            }
+        }
+        Ok(())
+    }
+
+    // check_mlock_limit fails unless the task may lock locked bytes of its address space, which is
+    // always the case with CAP_IPC_LOCK.
+    fn check_mlock_limit(&self, locked: u64) -> SysResult<()> {
+        let ctx = context::context();
+        let creds = ctx.credentials();
+        let root = UserNamespace::get_root(&creds.user_namespace);
+        if creds.has_capability_in(&linux::Capability::ipc_lock(), root) {
+            return Ok(());
+        }
+        let mlock_limit = ctx.limits().get_memory_locked().cur;
+        if mlock_limit == 0 {
+            bail_libc!(libc::EPERM);
+        }
+        if locked > mlock_limit {
+            bail_libc!(libc::ENOMEM);
+        }
+        Ok(())
+    }
+
+    fn set_mlock_mode(&mut self, vseg: &Seg<u64>, mode: MLockMode) {
+        let length = vseg.range().len();
+        let vma = self.vmas.value_mut(vseg);
+        let prev_mode = vma.mlock_mode;
+        vma.mlock_mode = mode;
+        if mode != MLockMode::None_ && prev_mode == MLockMode::None_ {
+            self.locked_as += length;
+        } else if mode == MLockMode::None_ && prev_mode != MLockMode::None_ {
+            self.locked_as -= length;
+        }
+    }
+
+    // mapped_range returns the page-aligned range [addr, addr+length), failing with EINVAL if addr
+    // is unaligned and with ENOMEM if any page in the range is not mapped.
+    pub fn mapped_range(&self, addr: Addr, length: u64) -> SysResult<AddrRange> {
+        if addr.round_down() != addr {
+            bail_libc!(libc::EINVAL);
+        }
+        let rlength = Addr(length)
+            .round_up()
+            .ok_or_else(|| SysError::new(libc::ENOMEM))?;
+        let ar = addr
+            .to_range(rlength.0)
+            .ok_or_else(|| SysError::new(libc::ENOMEM))?;
+
+        let mut mapped_end = ar.start;
+        for vseg in self.vmas.find_overlapping(ar) {
+            if vseg.start() > mapped_end {
+                bail_libc!(libc::ENOMEM);
+            }
+            mapped_end = vseg.end();
+        }
+        if mapped_end < ar.end {
+            bail_libc!(libc::ENOMEM);
+        }
+        Ok(ar)
+    }
+
+    // returns one byte per page in [addr, addr+length), with bit 0 set if the page
+    // is already backed by a pma.
+    pub fn mincore(&self, addr: Addr, length: u64) -> SysResult<Vec<u8>> {
+        let ar = self.mapped_range(addr, length)?;
+        let mut vec = vec![0; (ar.len() / PAGE_SIZE as u64) as usize];
+        for pseg in self.pmas.find_overlapping(ar) {
+            let pma_ar = pseg.range().intersect(&ar);
+            for page in (pma_ar.start..pma_ar.end).step_by(PAGE_SIZE as usize) {
+                vec[((page - ar.start) / PAGE_SIZE as u64) as usize] = 1;
+            }
+        }
+        Ok(vec)
+    }
+
+    // decommit implements the semantics of madvise(MADV_DONTNEED). Pages of private anonymous
+    // mappings are released in place and read back as zeros; any other pma is dropped so that the
+    // next access faults it in again from its mappable.
+    pub fn decommit(&mut self, addr: Addr, length: u64) -> SysResult<()> {
+        let ar = self.mapped_range(addr, length)?;
+        let vsegs = self.vmas.find_overlapping(ar).collect::<Vec<_>>();
+        if vsegs
+            .iter()
+            .any(|vseg| self.vmas.value(vseg).mlock_mode != MLockMode::None_)
+        {
+            bail_libc!(libc::EINVAL);
+        }
+
+        let mut did_unmap_as = false;
+        for vseg in vsegs {
+            let vseg_ar = vseg.range().intersect(&ar);
+            let anonymous = self.vmas.value(&vseg).mappable.upgrade().is_none();
+            let mut cur = vseg_ar.start;
+            while let Some(pseg) = self.pmas.lower_bound_segment(cur) {
+                if pseg.start() >= vseg_ar.end {
+                    break;
+                }
+                let pseg = self.pmas.isolate(&pseg, vseg_ar);
+                cur = pseg.end();
+                if anonymous
+                    && self.pmas.value(&pseg).private
+                    && !self.pma_copy_on_write(&vseg, &pseg)
+                {
+                    let fr = self.file_range(pseg);
+                    let ctx = context::context();
+                    let mut mf = ctx.kernel().memory_file_write_lock();
+                    if mf.decommit(fr).is_ok() {
+                        continue;
+                    }
+                }
+                if !did_unmap_as {
+                    self.unmap_address_space(ar);
+                    did_unmap_as = true;
+                }
+                self.remove_rss(pseg.range());
+                self.pmas.remove(pseg.range());
+            }
+        }
+        Ok(())
+    }
+
     pub fn brk_setup(&mut self, addr: Addr) {
         if !self.brk.is_empty() {
             self.unmap(self.brk);
@@ -1882,7 +2132,15 @@ impl MemoryManager {
         if self.address_space.is_none() {
             return;
         }
-        let (pseg, _, res) = self.get_pmas(*vseg, ar, AccessType::no_access());
+        // like linux, committing a private writable mapping breaks copy-on-write up front, so
+        // that the first write to it doesn't fault.
+        let vma = self.vmas.value(vseg);
+        let at = if precommit && vma.private && vma.effective_perms.write {
+            AccessType::write()
+        } else {
+            AccessType::no_access()
+        };
+        let (pseg, _, res) = self.get_pmas(*vseg, ar, at);
         if res.is_err() {
             return;
         }
@@ -1997,7 +2255,8 @@ impl MemoryManager {
         self.unmap_address_space(old_ar);
     }
 
-    pub fn map_stack(&mut self) -> SysResult<AddrRange> {
+    // map_stack maps the initial stack, which is executable only if the executable asks for it.
+    pub fn map_stack(&mut self, executable: bool) -> SysResult<AddrRange> {
         const MAX_STACK_SIZE: u64 = 128 << 20;
         let stack_size = {
             let ctx = context::context();
@@ -2014,10 +2273,7 @@ impl MemoryManager {
             None => Addr(linux::DEFAULT_STACK_SOFT_LIMIT),
         };
 
-        let stack_end = {
-            let mut rng = rand::thread_rng();
-            self.layout.max_addr - Addr(rng.gen_range(0..self.layout.max_stack_rand)).round_down()
-        };
+        let stack_end = self.layout.max_addr - self.layout.stack_rand();
         if stack_end < sz {
             bail_libc!(libc::ENOMEM);
         }
@@ -2025,12 +2281,17 @@ impl MemoryManager {
         let ret = self.create_vma(&MmapOpts {
             length: sz.0,
             addr: stack_start,
-            perms: AccessType::read_write(),
+            perms: if executable {
+                AccessType::any_access()
+            } else {
+                AccessType::read_write()
+            },
             max_perms: AccessType::any_access(),
             private: true,
             grows_down: true,
             ..MmapOpts::default()
         })?;
+        self.stack = ret.1;
         Ok(ret.1)
     }
 
@@ -2058,6 +2319,60 @@ impl MemoryManager {
         self.vmas.print_keys();
         logger::debug!("done");
     }
+
+    pub fn usage_address_space(&self) -> u64 {
+        self.usage_address_space
+    }
+
+    pub fn data_address_space(&self) -> u64 {
+        self.data_address_space
+    }
+
+    pub fn resident_set_size(&self) -> u64 {
+        self.cur_rss
+    }
+
+    // maps returns the contents of /proc/self/maps. The mappings do not remember the files they
+    // come from, so only the stack and the heap are named.
+    pub fn maps(&self) -> String {
+        let mut s = String::new();
+        let mut maybe_seg = self.vmas.first_segment();
+        while let Some(seg) = maybe_seg {
+            let vma = self.vmas.value(&seg);
+            let r = seg.range();
+            let off = if vma.mappable.upgrade().is_some() {
+                vma.off
+            } else {
+                0
+            };
+            let line = format!(
+                "{:08x}-{:08x} {}{}{}{} {:08x} 00:00 0 ",
+                r.start,
+                r.end,
+                if vma.real_perms.read { 'r' } else { '-' },
+                if vma.real_perms.write { 'w' } else { '-' },
+                if vma.real_perms.execute { 'x' } else { '-' },
+                if vma.private { 'p' } else { 's' },
+                off,
+            );
+            let name = if !r.intersect(&self.stack).is_empty() {
+                "[stack]"
+            } else if !r.intersect(&self.brk).is_empty() {
+                "[heap]"
+            } else {
+                ""
+            };
+            if name.is_empty() {
+                s.push_str(&line);
+            } else {
+                // Like Linux, names start at the same column on every line.
+                s.push_str(&format!("{:<73}{}", line, name));
+            }
+            s.push('\n');
+            maybe_seg = self.vmas.next_segment_of_seg(&seg);
+        }
+        s
+    }
 }
 
 impl mem::io::Io for MemoryManager {
@@ -2344,6 +2659,139 @@ mod tests {
         Rc::new(RefCell::new(mm))
     }
 
+    #[test]
+    fn map_stack_perms() {
+        let mm = memory_manager();
+        let mut mm = mm.as_ref().borrow_mut();
+
+        let ar = mm.map_stack(false).expect("error occurred in map_stack");
+        let vma = mm.vmas.get(ar.start).unwrap();
+        assert!(vma.real_perms.write && !vma.real_perms.execute);
+        mm.munmap(Addr(ar.start), ar.len())
+            .expect("error occurred in munmap");
+
+        let ar = mm.map_stack(true).expect("error occurred in map_stack");
+        let vma = mm.vmas.get(ar.start).unwrap();
+        assert!(vma.real_perms.write && vma.real_perms.execute);
+    }
+
+    #[test]
+    fn maps_names_stack() {
+        let mm = memory_manager();
+        let mut mm = mm.as_ref().borrow_mut();
+
+        let ar = mm.map_stack(false).expect("error occurred in map_stack");
+        let maps = mm.maps();
+        let line = maps
+            .lines()
+            .find(|l| l.ends_with("[stack]"))
+            .expect("no stack line in maps");
+        let prefix = format!("{:08x}-{:08x} rw-p 00000000 00:00 0 ", ar.start, ar.end);
+        assert!(line.starts_with(&prefix));
+        assert_eq!(line.find("[stack]"), Some(73));
+    }
+
+    #[test]
+    fn mmap_layout_follows_personality() {
+        let mm = memory_manager();
+        let mut mm = mm.as_ref().borrow_mut();
+        let set_personality = |persona: u32| {
+            let ctx = context::context();
+            let mut task = ctx.task_mut();
+            let old = task.personality();
+            task.set_personality(persona);
+            old
+        };
+        let mut new_layout = || {
+            let personality = {
+                let ctx = context::context();
+                let task = ctx.task();
+                task.personality()
+            };
+            let layout = mm.set_mmap_layout(personality).unwrap();
+            (
+                layout.bottom_up_base,
+                layout.top_down_base,
+                layout.pie_load_address(),
+                layout.stack_rand(),
+            )
+        };
+
+        let old = set_personality(libc::ADDR_NO_RANDOMIZE as u32);
+        assert_eq!(new_layout(), new_layout());
+        set_personality(0);
+        assert_ne!(new_layout(), new_layout());
+        set_personality(old);
+    }
+
+    #[test]
+    fn mincore_anonymous() {
+        let mm = memory_manager();
+        let mut mm = mm.as_ref().borrow_mut();
+
+        let length = 2 * PAGE_SIZE as u64;
+        let addr = mm
+            .mmap(MmapOpts {
+                length,
+                private: true,
+                perms: AccessType::read_write(),
+                max_perms: AccessType::any_access(),
+                ..MmapOpts::default()
+            })
+            .expect("error occurred in mmap");
+
+        assert_eq!(mm.mincore(addr, length), Ok(vec![0, 0]));
+        assert_eq!(mm.mincore(addr, 1), Ok(vec![0]));
+        assert_eq!(
+            mm.mincore(Addr(addr.0 + 1), 1),
+            Err(SysError::new(libc::EINVAL))
+        );
+
+        let n = mm.copy_out(addr, &[1], &IoOpts::default());
+        assert_eq!(n, Ok(1));
+        assert_eq!(mm.mincore(addr, length), Ok(vec![1, 1]));
+
+        mm.munmap(Addr(addr.0 + PAGE_SIZE as u64), PAGE_SIZE as u64)
+            .expect("error occurred in munmap");
+        assert_eq!(mm.mincore(addr, length), Err(SysError::new(libc::ENOMEM)));
+    }
+
+    #[test]
+    fn decommit_anonymous() {
+        let mm = memory_manager();
+        let mut mm = mm.as_ref().borrow_mut();
+
+        let length = 2 * PAGE_SIZE as u64;
+        let addr = mm
+            .mmap(MmapOpts {
+                length,
+                private: true,
+                perms: AccessType::read_write(),
+                max_perms: AccessType::any_access(),
+                ..MmapOpts::default()
+            })
+            .expect("error occurred in mmap");
+
+        let b = vec![0xaa; length as usize];
+        let n = mm.copy_out(addr, &b, &IoOpts::default());
+        assert_eq!(n, Ok(length as usize));
+
+        mm.decommit(addr, PAGE_SIZE as u64)
+            .expect("error occurred in decommit");
+        let mut b = vec![0; length as usize];
+        let n = mm.copy_in(addr, &mut b, &IoOpts::default());
+        assert_eq!(n, Ok(length as usize));
+        assert!(b[..PAGE_SIZE as usize].iter().all(|&v| v == 0));
+        assert!(b[PAGE_SIZE as usize..].iter().all(|&v| v == 0xaa));
+
+        assert_eq!(
+            mm.decommit(Addr(addr.0 + 1), 1),
+            Err(SysError::new(libc::EINVAL))
+        );
+        mm.munmap(addr, length).expect("error occurred in munmap");
+        assert_eq!(mm.decommit(addr, length), Err(SysError::new(libc::ENOMEM)));
+    }
+
     #[test]
     fn usage_address_space_updates() {
         let mm = memory_manager();
@@ -2414,10 +2862,7 @@ mod tests {
         let mut limit_set = LimitSet::default();
         limit_set.set_data(Limit::default(), true).unwrap();
 
-        {
-            let mut ctx = context::context_mut();
-            ctx.set_limits(limit_set);
-        }
+        let _limits = context::set_limits_for_test(limit_set);
 
         let mut mm = mm.as_ref().borrow_mut();
         let old_brk = mm.brk(Addr(0));
@@ -2425,6 +2870,262 @@ mod tests {
         assert_eq!(old_brk, new_brk);
     }
 
+    #[test]
+    fn brk_grow_and_shrink() {
+        let mm = memory_manager();
+
+        let page = PAGE_SIZE as u64;
+        let mut limit_set = LimitSet::default();
+        limit_set
+            .set_data(
+                Limit {
+                    cur: 4 * page,
+                    max: 4 * page,
+                },
+                true,
+            )
+            .unwrap();
+        let _limits = context::set_limits_for_test(limit_set);
+
+        let mut mm = mm.as_ref().borrow_mut();
+        let start = Addr(0x1000_0000);
+        mm.brk_setup(start);
+        assert_eq!(mm.brk(Addr(0)), start);
+
+        // The break itself needn't be page aligned, but the whole last page is mapped.
+        let grown = Addr(start.0 + page + 1);
+        assert_eq!(mm.brk(grown), grown);
+        let n = mm.copy_out(Addr(start.0 + 2 * page - 1), &[1], &IoOpts::default());
+        assert_eq!(n, Ok(1));
+
+        // Growing past RLIMIT_DATA leaves the break unchanged.
+        assert_eq!(mm.brk(Addr(start.0 + 4 * page + 1)), grown);
+
+        // Shrinking unmaps the pages which are no longer covered by the break.
+        assert_eq!(mm.brk(start), start);
+        let res = mm.copy_out(start, &[1], &IoOpts::default());
+        assert_eq!(res, Err(SysError::new(libc::EFAULT)));
+    }
+
+    #[test]
+    fn grow_down_below_guard() {
+        let mm = memory_manager();
+
+        let page = PAGE_SIZE as u64;
+        let mut limit_set = LimitSet::default();
+        limit_set
+            .set_stack(
+                Limit {
+                    cur: 4 * page,
+                    max: 4 * page,
+                },
+                true,
+            )
+            .unwrap();
+        let _limits = context::set_limits_for_test(limit_set);
+
+        let mut mm = mm.as_ref().borrow_mut();
+        let addr = mm
+            .mmap(MmapOpts {
+                length: page,
+                addr: Addr(0x1000_0000),
+                fixed: true,
+                private: true,
+                grows_down: true,
+                perms: AccessType::read_write(),
+                max_perms: AccessType::any_access(),
+                ..MmapOpts::default()
+            })
+            .expect("error occurred in mmap");
+        let usage = mm.usage_address_space;
+
+        mm.grow_down(addr.0 - 2 * page).unwrap();
+        let vseg = mm.vmas.find_segment(addr.0 - 2 * page).unwrap();
+        assert_eq!(
+            vseg.range(),
+            AddrRange {
+                start: addr.0 - 2 * page,
+                end: addr.0 + page,
+            }
+        );
+        assert_eq!(mm.usage_address_space, usage + 2 * page);
+        let n = mm.copy_out(Addr(addr.0 - 2 * page), &[1], &IoOpts::default());
+        assert_eq!(n, Ok(1));
+
+        // The grown vma may not exceed RLIMIT_STACK.
+        assert_eq!(
+            mm.grow_down(addr.0 - 4 * page),
+            Err(SysError::new(libc::EFAULT))
+        );
+        mm.grow_down(addr.0 - 3 * page).unwrap();
+
+        // Only the pages right below grows-down vmas extend them.
+        let addr = mm
+            .mmap(MmapOpts {
+                length: page,
+                addr: Addr(0x2000_0000),
+                fixed: true,
+                private: true,
+                perms: AccessType::read_write(),
+                max_perms: AccessType::any_access(),
+                ..MmapOpts::default()
+            })
+            .expect("error occurred in mmap");
+        assert_eq!(
+            mm.grow_down(addr.0 - page),
+            Err(SysError::new(libc::EFAULT))
+        );
+    }
+
+    #[test]
+    fn mlock_memory_locked_limit() {
+        let mm = memory_manager();
+
+        let page = PAGE_SIZE as u64;
+        let mut limit_set = LimitSet::default();
+        limit_set
+            .set_memory_locked(
+                Limit {
+                    cur: 2 * page,
+                    max: 2 * page,
+                },
+                true,
+            )
+            .unwrap();
+        let _limits = context::set_limits_for_test(limit_set);
+
+        let mut mm = mm.as_ref().borrow_mut();
+        let addr = mm
+            .mmap(MmapOpts {
+                length: 4 * page,
+                private: true,
+                perms: AccessType::read_write(),
+                max_perms: AccessType::any_access(),
+                ..MmapOpts::default()
+            })
+            .expect("error occurred in mmap");
+
+        mm.mlock(addr, 2 * page, MLockMode::Eager).unwrap();
+        assert_eq!(mm.locked_as, 2 * page);
+        assert_eq!(
+            mm.mlock(addr, 3 * page, MLockMode::Eager),
+            Err(SysError::new(libc::ENOMEM))
+        );
+        assert_eq!(mm.locked_as, 2 * page);
+
+        // Unaligned ranges cover every page they touch.
+        mm.mlock(Addr(addr.0 + page - 1), 2, MLockMode::None_)
+            .unwrap();
+        assert_eq!(mm.locked_as, 0);
+        mm.mlock(Addr(addr.0 + 3 * page), page, MLockMode::Lazy)
+            .unwrap();
+        assert_eq!(mm.locked_as, page);
+        assert_eq!(
+            mm.mlock(Addr(addr.0 + 4 * page), page, MLockMode::Eager),
+            Err(SysError::new(libc::ENOMEM))
+        );
+
+        assert_eq!(
+            mm.mlockall(true, false, MLockMode::Eager),
+            Err(SysError::new(libc::ENOMEM))
+        );
+        // Future mappings count against the limit as well.
+        mm.mlockall(false, true, MLockMode::Eager).unwrap();
+        let res = mm.mmap(MmapOpts {
+            length: 2 * page,
+            private: true,
+            perms: AccessType::read_write(),
+            max_perms: AccessType::any_access(),
+            ..MmapOpts::default()
+        });
+        assert_eq!(res, Err(SysError::new(libc::EAGAIN)));
+
+        mm.mlockall(true, true, MLockMode::None_).unwrap();
+        assert_eq!(mm.locked_as, 0);
+        assert_eq!(mm.def_mlock_mode, MLockMode::None_);
+    }
+
+    #[test]
+    fn mremap_grow_and_move() {
+        let mm = memory_manager();
+
+        let page = PAGE_SIZE as u64;
+        let mut mm = mm.as_ref().borrow_mut();
+        let mm = &mut *mm;
+        let mmap = |mm: &mut MemoryManager, addr: u64| {
+            mm.mmap(MmapOpts {
+                length: page,
+                addr: Addr(addr),
+                fixed: true,
+                private: true,
+                perms: AccessType::read_write(),
+                max_perms: AccessType::any_access(),
+                ..MmapOpts::default()
+            })
+            .expect("error occurred in mmap")
+        };
+        let addr = mmap(mm, 0x1000_0000);
+        let next = mmap(mm, 0x1000_0000 + page);
+        let n = mm.copy_out(addr, &[1], &IoOpts::default());
+        assert_eq!(n, Ok(1));
+
+        let no_move = MremapOpts {
+            mov: MremapMoveMode::No,
+            new_addr: Addr(0),
+        };
+        assert_eq!(
+            mm.mremap(addr, page, 2 * page, &no_move),
+            Err(SysError::new(libc::ENOMEM))
+        );
+
+        // Without room to grow in place, the mapping moves along with its contents.
+        let moved = mm
+            .mremap(
+                addr,
+                page,
+                2 * page,
+                &MremapOpts {
+                    mov: MremapMoveMode::May,
+                    new_addr: Addr(0),
+                },
+            )
+            .expect("error occurred in mremap");
+        assert_ne!(moved, addr);
+        assert!(mm.vmas.find_segment(addr.0).is_none());
+        let mut b = vec![0];
+        let n = mm.copy_in(moved, &mut b, &IoOpts::default());
+        assert_eq!((n, b[0]), (Ok(1), 1));
+
+        // A fixed destination replaces whatever is mapped there, and shrinking drops the tail.
+        let fixed = mm
+            .mremap(
+                moved,
+                2 * page,
+                page,
+                &MremapOpts {
+                    mov: MremapMoveMode::Must,
+                    new_addr: next,
+                },
+            )
+            .expect("error occurred in mremap");
+        assert_eq!(fixed, next);
+        assert!(mm.vmas.find_segment(moved.0).is_none());
+        assert!(mm.vmas.find_segment(moved.0 + page).is_none());
+        let n = mm.copy_in(next, &mut b, &IoOpts::default());
+        assert_eq!((n, b[0]), (Ok(1), 1));
+
+        // With the following range free again, growing in place succeeds.
+        mmap(mm, addr.0);
+        assert_eq!(
+            mm.mremap(addr, page, 2 * page, &no_move),
+            Err(SysError::new(libc::ENOMEM))
+        );
+        mm.munmap(next, page).expect("error occurred in munmap");
+        assert_eq!(mm.mremap(addr, page, 2 * page, &no_move), Ok(addr));
+        assert_eq!(mm.mremap(addr, 2 * page, page, &no_move), Ok(addr));
+        assert!(mm.vmas.find_segment(addr.0 + page).is_none());
+    }
+
     #[test]
     fn io_after_unmap() {
         let mm = memory_manager();
@@ -2451,6 +3152,50 @@ mod tests {
         assert_eq!(res, Err(SysError::new(libc::EFAULT)));
     }
 
+    #[test]
+    fn mprotect_max_perms() {
+        let mm = memory_manager();
+
+        let page = PAGE_SIZE as u64;
+        let mut mm = mm.as_ref().borrow_mut();
+        let addr = mm
+            .mmap(MmapOpts {
+                length: 3 * page,
+                private: true,
+                perms: AccessType::read(),
+                max_perms: AccessType::read(),
+                ..MmapOpts::default()
+            })
+            .expect("error occurred in mmap");
+
+        assert_eq!(
+            mm.mprotect(addr, page, AccessType::from_prot(libc::PROT_EXEC), false),
+            Err(SysError::new(libc::EACCES))
+        );
+        assert_eq!(
+            mm.mprotect(addr, 3 * page, AccessType::read_write(), false),
+            Err(SysError::new(libc::EACCES))
+        );
+        assert_eq!(mm.vmas.get(addr.0).unwrap().real_perms, AccessType::read());
+
+        // Only the protected pages change, so the vma is split around them.
+        mm.mprotect(Addr(addr.0 + page), page, AccessType::no_access(), false)
+            .expect("error occurred in mprotect");
+        let vseg = mm.vmas.find_segment(addr.0 + page).unwrap();
+        assert_eq!(
+            vseg.range(),
+            AddrRange {
+                start: addr.0 + page,
+                end: addr.0 + 2 * page,
+            }
+        );
+        assert_eq!(mm.vmas.value(&vseg).real_perms, AccessType::no_access());
+        assert_eq!(
+            mm.vmas.get(addr.0 + 2 * page).unwrap().real_perms,
+            AccessType::read()
+        );
+    }
+
     #[test]
     fn io_after_mprotect() {
         let mm = memory_manager();
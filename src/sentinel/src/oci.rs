@@ -60,6 +60,7 @@ pub fn oci_main() -> anyhow::Result<()> {
                 .arg(&init_arg),
         )
         .subcommand(Command::new("delete").arg(&id_arg))
+        .subcommand(Command::new("state").arg(&id_arg))
         .get_matches();
 
     let state_dir = matches.value_of("r").unwrap();
@@ -68,6 +69,7 @@ pub fn oci_main() -> anyhow::Result<()> {
         Some(("start", start)) => oci_start(start, state_dir),
         Some(("run", run)) => oci_run(run, state_dir),
         Some(("delete", delete)) => oci_delete(delete, state_dir),
+        Some(("state", state)) => oci_state(state, state_dir),
         e => anyhow::bail!("unknown subcommand for Sentinel {:?}", e),
     }
 }
@@ -115,6 +117,7 @@ fn oci_create(matches: &ArgMatches, state_dir: &str) -> anyhow::Result<()> {
     )
     .context("failed to spawn container process")?;
     config.state.set_pid(Some(container_pid));
+    config.save(&dir)?;
 
     if let Some(pid_file) = matches.value_of("p") {
         let mut f = std::fs::File::create(pid_file)?;
@@ -130,6 +133,11 @@ fn oci_create(matches: &ArgMatches, state_dir: &str) -> anyhow::Result<()> {
     listener
         .wait()
         .with_context(|| "failed to wait for namespace initialization")?;
+    // Like runc, prestart hooks run once the namespaces are set up, before the createRuntime ones,
+    // and a failing one aborts the creation.
+    config
+        .run_prestart_hooks()
+        .with_context(|| "failed to execute prestart hooks")?;
     config.state.set_status(ContainerStatus::Created);
     config
         .run_create_runtime_hooks()
@@ -143,11 +151,7 @@ fn oci_create(matches: &ArgMatches, state_dir: &str) -> anyhow::Result<()> {
 fn oci_start(matches: &ArgMatches, state_dir: &str) -> anyhow::Result<()> {
     let id = matches.value_of("id").unwrap();
     let dir = format!("{}/{}", state_dir, id);
-    let config = SentinelConfig::load(&dir)?;
-
-    config
-        .run_prestart_hooks()
-        .with_context(|| "failed to execute prestart hooks")?;
+    let mut config = SentinelConfig::load(&dir)?;
 
     std::env::set_current_dir(&dir)
         .with_context(|| format!("failed to set current directory to {}", dir))?;
@@ -156,13 +160,23 @@ fn oci_start(matches: &ArgMatches, state_dir: &str) -> anyhow::Result<()> {
     notify
         .notify(b"start container!")
         .context("failed to notify start")?;
+    // The sandbox removes the state directory as soon as the container exits, which may already
+    // have happened, so failing to save the running state isn't fatal.
+    config.state.set_status(ContainerStatus::Running);
+    if let Err(e) = config.save(&dir) {
+        logger::warn!("failed to save the running state: {:?}", e);
+    }
+    // Per the OCI spec, failures of poststart hooks are only logged.
     if let Err(e) = config.run_poststart_hooks() {
         logger::warn!("failed to execute post start hooks: {:?}", e);
     }
 
     let end_listener =
         NotifyListener::new(format!("{}/end.sock", dir)).with_context(|| "end with notifier")?;
-    end_listener.wait().with_context(|| "failed to wait")?;
+    let stats = end_listener
+        .wait_for_stats()
+        .with_context(|| "failed to wait")?;
+    logger::info!("container {} exited: {:?}", id, stats);
 
     Ok(())
 }
@@ -200,3 +214,17 @@ fn oci_delete(matches: &ArgMatches, state_dir: &str) -> anyhow::Result<()> {
     std::fs::remove_dir_all(&dir).with_context(|| "failed to remove all directories")?;
     Ok(())
 }
+
+// oci_state prints the OCI state of the container as JSON. A container whose process is gone is
+// reported as stopped.
+fn oci_state(matches: &ArgMatches, state_dir: &str) -> anyhow::Result<()> {
+    let id = matches.value_of("id").unwrap();
+    let dir = format!("{}/{}", state_dir, id);
+    let mut config =
+        SentinelConfig::load(&dir).with_context(|| format!("container {} does not exist", id))?;
+    config.state.refresh_status();
+    let state = serde_json::to_string_pretty(&config.state)
+        .with_context(|| "failed to encode container state")?;
+    println!("{}", state);
+    Ok(())
+}
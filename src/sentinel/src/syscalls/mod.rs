@@ -8,9 +8,11 @@ mod sys_identity;
 mod sys_lseek;
 mod sys_mempolicy;
 mod sys_mmap;
+mod sys_personality;
 mod sys_pipe;
 mod sys_poll;
 mod sys_prctl;
+mod sys_process_vm;
 mod sys_random;
 mod sys_read;
 mod sys_rlimit;
@@ -25,6 +27,7 @@ mod sys_time;
 mod sys_timer;
 mod sys_utsname;
 mod sys_write;
+mod sys_xattr;
 
 use utils::SysError;
 
@@ -58,32 +61,47 @@ pub fn perform(regs: &mut libc::user_regs_struct, counter: usize) -> Result {
         libc::SYS_brk /* 12 */ => sys_mmap::brk(regs),
         libc::SYS_rt_sigaction /* 13 */ => sys_signal::rt_sigaction(regs),
         libc::SYS_rt_sigprocmask /* 14 */ => sys_signal::rt_sigprocmask(regs),
+        libc::SYS_rt_sigreturn /* 15 */ => sys_signal::rt_sigreturn(regs),
         libc::SYS_ioctl /* 16 */ => sys_file::ioctl(regs),
         libc::SYS_pread64 /* 17 */ => sys_read::pread64(regs),
         libc::SYS_writev /* 20 */ => sys_write::writev(regs),
         libc::SYS_access /* 21 */ => sys_file::access(regs),
         libc::SYS_pipe /* 22 */ => sys_pipe::pipe(regs),
         libc::SYS_mremap /* 25 */ => sys_mmap::mremap(regs),
+        libc::SYS_mincore /* 27 */ => sys_mmap::mincore(regs),
+        libc::SYS_madvise /* 28 */ => sys_mmap::madvise(regs),
         libc::SYS_dup /* 32 */ => sys_file::dup(regs),
+        libc::SYS_dup2 /* 33 */ => sys_file::dup2(regs),
+        libc::SYS_nanosleep /* 35 */ => sys_time::nanosleep(regs),
         libc::SYS_getpid /* 39 */ => sys_thread::getpid(regs),
+        libc::SYS_sendfile /* 40 */ => sys_write::sendfile(regs),
         libc::SYS_socket /* 41 */ => sys_socket::socket(regs),
         libc::SYS_connect /* 42 */ => sys_socket::connect(regs),
         libc::SYS_accept /* 43 */ => sys_socket::accept(regs),
         libc::SYS_sendto /* 44 */ => sys_socket::sendto(regs),
         libc::SYS_recvfrom /* 45 */ => sys_socket::recvfrom(regs),
+        libc::SYS_sendmsg /* 46 */ => sys_socket::sendmsg(regs),
+        libc::SYS_recvmsg /* 47 */ => sys_socket::recvmsg(regs),
+        libc::SYS_shutdown /* 48 */ => sys_socket::shutdown(regs),
         libc::SYS_bind /* 49 */ => sys_socket::bind(regs),
         libc::SYS_listen /* 50 */ => sys_socket::listen(regs),
         libc::SYS_getsockname /* 51 */ => sys_socket::getsockname(regs),
         libc::SYS_getpeername /* 52 */ => sys_socket::getpeername(regs),
+        libc::SYS_socketpair /* 53 */ => sys_socket::socketpair(regs),
         libc::SYS_setsockopt /* 54 */ => sys_socket::setsockopt(regs),
         libc::SYS_getsockopt /* 55 */ => sys_socket::getsockopt(regs),
         libc::SYS_exit /* 60 */ => sys_thread::exit(regs),
         libc::SYS_uname /* 63 */ => sys_utsname::uname(regs),
         libc::SYS_fcntl /* 72 */ => sys_file::fcntl(regs),
+        libc::SYS_flock /* 73 */ => sys_file::flock(regs),
         libc::SYS_getdents /* 78 */ => sys_getdents::getdents(regs),
         libc::SYS_getcwd /* 79 */ => sys_fscontext::getcwd(regs),
         libc::SYS_chdir /* 80 */ => sys_fscontext::chdir(regs),
+        libc::SYS_fchdir /* 81 */ => sys_fscontext::fchdir(regs),
         libc::SYS_rename /* 82 */ => sys_file::rename(regs),
+        libc::SYS_link /* 86 */ => sys_file::link(regs),
+        libc::SYS_unlink /* 87 */ => sys_file::unlink(regs),
+        libc::SYS_symlink /* 88 */ => sys_file::symlink(regs),
         libc::SYS_readlink /* 89 */ => sys_file::readlink(regs),
         libc::SYS_sysinfo /* 99 */ => sys_sysinfo::sysinfo(regs),
         libc::SYS_getuid /* 102 */ => sys_identity::getuid(regs),
@@ -91,9 +109,30 @@ pub fn perform(regs: &mut libc::user_regs_struct, counter: usize) -> Result {
         libc::SYS_geteuid /* 107 */ => sys_identity::geteuid(regs),
         libc::SYS_getegid /* 108 */ => sys_identity::getegid(regs),
         libc::SYS_sigaltstack /* 131 */ => sys_signal::sigaltstack(regs),
+        libc::SYS_personality /* 135 */ => sys_personality::personality(regs),
+        libc::SYS_statfs /* 137 */ => sys_stat::statfs(regs),
+        libc::SYS_fstatfs /* 138 */ => sys_stat::fstatfs(regs),
+        libc::SYS_mlock /* 149 */ => sys_mmap::mlock(regs),
+        libc::SYS_munlock /* 150 */ => sys_mmap::munlock(regs),
+        libc::SYS_mlockall /* 151 */ => sys_mmap::mlockall(regs),
+        libc::SYS_munlockall /* 152 */ => sys_mmap::munlockall(regs),
         libc::SYS_prctl /* 157 */ => sys_prctl::prctl(regs),
         libc::SYS_arch_prctl /* 158 */ => sys_thread_local_storage::arch_prctl(regs),
+        libc::SYS_sethostname /* 170 */ => sys_utsname::sethostname(regs),
+        libc::SYS_setdomainname /* 171 */ => sys_utsname::setdomainname(regs),
         libc::SYS_gettid /* 186 */ => sys_thread::gettid(regs),
+        libc::SYS_setxattr /* 188 */ => sys_xattr::setxattr(regs),
+        libc::SYS_lsetxattr /* 189 */ => sys_xattr::lsetxattr(regs),
+        libc::SYS_fsetxattr /* 190 */ => sys_xattr::fsetxattr(regs),
+        libc::SYS_getxattr /* 191 */ => sys_xattr::getxattr(regs),
+        libc::SYS_lgetxattr /* 192 */ => sys_xattr::lgetxattr(regs),
+        libc::SYS_fgetxattr /* 193 */ => sys_xattr::fgetxattr(regs),
+        libc::SYS_listxattr /* 194 */ => sys_xattr::listxattr(regs),
+        libc::SYS_llistxattr /* 195 */ => sys_xattr::llistxattr(regs),
+        libc::SYS_flistxattr /* 196 */ => sys_xattr::flistxattr(regs),
+        libc::SYS_removexattr /* 197 */ => sys_xattr::removexattr(regs),
+        libc::SYS_lremovexattr /* 198 */ => sys_xattr::lremovexattr(regs),
+        libc::SYS_fremovexattr /* 199 */ => sys_xattr::fremovexattr(regs),
         libc::SYS_futex /* 202 */ => sys_futex::futex(regs),
         libc::SYS_sched_getaffinity /* 204 */ => sys_thread::sched_getaffinity(regs),
         libc::SYS_getdents64 /* 217 */ => sys_getdents::getdents64(regs),
@@ -103,20 +142,35 @@ pub fn perform(regs: &mut libc::user_regs_struct, counter: usize) -> Result {
         libc::SYS_clock_gettime /* 228 */ => sys_time::clock_gettime(regs),
         libc::SYS_clock_nanosleep /* 230 */ => sys_time::clock_nanosleep(regs),
         libc::SYS_exit_group /* 231 */ => sys_thread::exit_group(regs),
+        libc::SYS_epoll_wait /* 232 */ => sys_epoll::epoll_wait(regs),
+        libc::SYS_epoll_ctl /* 233 */ => sys_epoll::epoll_ctl(regs),
         libc::SYS_tgkill /* 234 */ => sys_signal::tgkill(regs),
         libc::SYS_mbind /* 237 */ => sys_mempolicy::mbind(regs),
         libc::SYS_openat /* 257 */ => sys_file::openat(regs),
         libc::SYS_newfstatat /* 262 */ => sys_stat::fstatat(regs),
+        libc::SYS_unlinkat /* 263 */ => sys_file::unlinkat(regs),
         libc::SYS_renameat /* 264 */ => sys_file::renameat(regs),
+        libc::SYS_linkat /* 265 */ => sys_file::linkat(regs),
+        libc::SYS_symlinkat /* 266 */ => sys_file::symlinkat(regs),
+        libc::SYS_ppoll /* 271 */ => sys_poll::ppoll(regs),
         libc::SYS_set_robust_list /* 273 */ => sys_futex::set_robust_list(regs),
+        libc::SYS_utimensat /* 280 */ => sys_stat::utimensat(regs),
+        libc::SYS_epoll_pwait /* 281 */ => sys_epoll::epoll_pwait(regs),
         libc::SYS_eventfd /* 284 */ => sys_eventfd::eventfd(*regs),
+        libc::SYS_fallocate /* 285 */ => sys_file::fallocate(regs),
         libc::SYS_accept4 /* 288 */ => sys_socket::accept4(regs),
         libc::SYS_eventfd2 /* 290 */ => sys_eventfd::eventfd2(regs),
+        libc::SYS_dup3 /* 292 */ => sys_file::dup3(regs),
         libc::SYS_epoll_create1 /* 291 */ => sys_epoll::epoll_create1(regs),
         libc::SYS_pipe2 /* 293 */ => sys_pipe::pipe2(regs),
         libc::SYS_prlimit64 /* 302 */ => sys_rlimit::prlimit64(regs),
         libc::SYS_sendmmsg /* 307 */ => sys_socket::sendmmsg(regs),
+        libc::SYS_process_vm_readv /* 310 */ => sys_process_vm::process_vm_readv(regs),
+        libc::SYS_process_vm_writev /* 311 */ => sys_process_vm::process_vm_writev(regs),
+        libc::SYS_renameat2 /* 316 */ => sys_file::renameat2(regs),
         libc::SYS_getrandom /* 318 */ => sys_random::getrandom(regs),
+        libc::SYS_mlock2 /* 325 */ => sys_mmap::mlock2(regs),
+        libc::SYS_copy_file_range /* 326 */ => sys_write::copy_file_range(regs),
         libc::SYS_rseq /* 334 */ => sys_rseq::rseq(regs),
         _ => {
             logger::info!("stdout: {:?}", crate::get_stdout());
@@ -129,3 +183,40 @@ pub fn perform(regs: &mut libc::user_regs_struct, counter: usize) -> Result {
 pub fn should_exit(syscallno: i64) -> bool {
     matches!(syscallno, libc::SYS_exit | libc::SYS_exit_group)
 }
+
+// supports_fast_path tells whether the syscall can be performed while the tracee is blocked on a
+// seccomp notification rather than stopped by ptrace, i.e. whether it leaves the mappings, the
+// registers and the signal state of the tracee alone.
+pub fn supports_fast_path(syscallno: i64) -> bool {
+    !should_exit(syscallno)
+        && !matches!(
+            syscallno,
+            libc::SYS_mmap
+                | libc::SYS_mprotect
+                | libc::SYS_munmap
+                | libc::SYS_brk
+                | libc::SYS_mremap
+                | libc::SYS_madvise
+                | libc::SYS_mlock
+                | libc::SYS_mlock2
+                | libc::SYS_mlockall
+                | libc::SYS_shmat
+                | libc::SYS_shmdt
+                | libc::SYS_rt_sigreturn
+                | libc::SYS_rt_sigsuspend
+                | libc::SYS_sigaltstack
+                | libc::SYS_pause
+                | libc::SYS_kill
+                | libc::SYS_tkill
+                | libc::SYS_tgkill
+                | libc::SYS_rt_sigqueueinfo
+                | libc::SYS_rt_tgsigqueueinfo
+                | libc::SYS_arch_prctl
+                | libc::SYS_clone
+                | libc::SYS_clone3
+                | libc::SYS_fork
+                | libc::SYS_vfork
+                | libc::SYS_execve
+                | libc::SYS_execveat
+        )
+}
@@ -1,9 +1,17 @@
 use std::{cell::RefCell, rc::Rc};
 
+use mem::Addr;
+use net::Context as NetContext;
+use time::Time;
 use utils::{bail_libc, SysError, SysResult};
 
+use super::sys_poll::wait_ready;
 use crate::{context, kernel::epoll};
 
+const EPOLL_EVENT_SIZE: usize = 12;
+// EP_MAX_EVENTS is the maximum number of events epoll_wait can return at once.
+const EP_MAX_EVENTS: usize = i32::MAX as usize / EPOLL_EVENT_SIZE;
+
 // epoll_create1 implements linux syscall epoll_create1(2)
 pub fn epoll_create1(regs: &libc::user_regs_struct) -> super::Result {
     let flags = regs.rdi as i32;
@@ -20,3 +28,139 @@ fn create_epoll(close_on_exec: bool) -> SysResult<i32> {
     let mut task = ctx.task_mut();
     task.new_fd_from(0, &file, fs::FdFlags { close_on_exec })
 }
+
+// epoll_ctl implements linux syscall epoll_ctl(2)
+pub fn epoll_ctl(regs: &libc::user_regs_struct) -> super::Result {
+    let epfd = regs.rdi as i32;
+    let op = regs.rsi as i32;
+    let fd = regs.rdx as i32;
+    let event_addr = Addr(regs.r10);
+
+    let event = match op {
+        libc::EPOLL_CTL_ADD | libc::EPOLL_CTL_MOD => Some(copy_in_event(event_addr)?),
+        _ => None,
+    };
+    let ctx = context::context();
+    let (epoll_file, file) = {
+        let mut task = ctx.task_mut();
+        match (task.get_file(epfd), task.get_file(fd)) {
+            (Some(epoll_file), Some(file)) => (epoll_file, file),
+            _ => bail_libc!(libc::EBADF),
+        }
+    };
+    // Regular files and directories are always ready, so they can't be waited for.
+    let sattr = file.borrow().dirent().borrow().stable_attr();
+    if sattr.is_regular() || sattr.is_directory() {
+        bail_libc!(libc::EPERM);
+    }
+    if Rc::ptr_eq(&epoll_file, &file) {
+        bail_libc!(libc::EINVAL);
+    }
+    let epoll_file = epoll_file.borrow();
+    let ep = match epoll_file.file_operations::<epoll::EventPoll>() {
+        Some(ep) => ep,
+        None => bail_libc!(libc::EINVAL),
+    };
+    match (op, event) {
+        (libc::EPOLL_CTL_ADD, Some((events, user_data))) => {
+            ep.add_entry(file, fd, events, user_data)?
+        }
+        (libc::EPOLL_CTL_MOD, Some((events, user_data))) => {
+            // EPOLLEXCLUSIVE can only be set when adding an fd.
+            if events & libc::EPOLLEXCLUSIVE as u32 != 0 {
+                bail_libc!(libc::EINVAL);
+            }
+            ep.update_entry(file, fd, events, user_data)?
+        }
+        (libc::EPOLL_CTL_DEL, _) => ep.remove_entry(file, fd)?,
+        _ => bail_libc!(libc::EINVAL),
+    }
+    Ok(0)
+}
+
+// epoll_wait implements linux syscall epoll_wait(2)
+pub fn epoll_wait(regs: &libc::user_regs_struct) -> super::Result {
+    let epfd = regs.rdi as i32;
+    let events_addr = Addr(regs.rsi);
+    let max_events = regs.rdx as i32;
+    let timeout = regs.r10 as i32;
+    wait_impl(epfd, events_addr, max_events, timeout)
+}
+
+// epoll_pwait implements linux syscall epoll_pwait(2)
+pub fn epoll_pwait(regs: &libc::user_regs_struct) -> super::Result {
+    let epfd = regs.rdi as i32;
+    let events_addr = Addr(regs.rsi);
+    let max_events = regs.rdx as i32;
+    let timeout = regs.r10 as i32;
+    let mask_addr = Addr(regs.r8);
+    let mask_size = regs.r9 as i32;
+
+    if mask_addr.0 == 0 {
+        return wait_impl(epfd, events_addr, max_events, timeout);
+    }
+    let ctx = context::context();
+    let old_mask = {
+        let task = ctx.task();
+        let mask = task.copy_in_sig_set(mask_addr, mask_size)?;
+        let old_mask = task.signal_mask();
+        task.set_signal_mask(mask);
+        old_mask
+    };
+    // The given mask is only in effect while waiting.
+    let res = wait_impl(epfd, events_addr, max_events, timeout);
+    ctx.task().set_signal_mask(old_mask);
+    res
+}
+
+fn wait_impl(epfd: i32, events_addr: Addr, max_events: i32, timeout: i32) -> super::Result {
+    if max_events <= 0 || max_events as usize > EP_MAX_EVENTS {
+        bail_libc!(libc::EINVAL);
+    }
+    let epoll_file = {
+        let ctx = context::context();
+        let mut task = ctx.task_mut();
+        task.get_file(epfd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?
+    };
+    let epoll_file = epoll_file.borrow();
+    let ep = match epoll_file.file_operations::<epoll::EventPoll>() {
+        Some(ep) => ep,
+        None => bail_libc!(libc::EINVAL),
+    };
+    let timeout = if timeout >= 0 {
+        Some(Time::from_unix(0, timeout as i64 * 1_000_000))
+    } else {
+        None
+    };
+
+    let mut events = Vec::new();
+    let n = wait_ready(timeout, || {
+        context::context().poll_wait(true);
+        events = ep.read_events(max_events as usize);
+        Ok(events.len())
+    })?;
+    if n > 0 {
+        let mut bytes = Vec::with_capacity(n * EPOLL_EVENT_SIZE);
+        for e in &events {
+            let (events, data) = (e.events, e.u64);
+            bytes.extend_from_slice(&events.to_le_bytes());
+            bytes.extend_from_slice(&data.to_le_bytes());
+        }
+        let ctx = context::context();
+        ctx.task().copy_out_bytes(events_addr, &bytes)?;
+    }
+    Ok(n)
+}
+
+// copy_in_event reads the events and user data of an epoll_event, which is packed on x86_64.
+fn copy_in_event(addr: Addr) -> SysResult<(u32, u64)> {
+    let ctx = context::context();
+    let task = ctx.task();
+    let mut buf = [0; EPOLL_EVENT_SIZE];
+    task.copy_in_bytes(addr, &mut buf)?;
+    let events = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let mut data = [0; 8];
+    data.copy_from_slice(&buf[4..]);
+    Ok((events, u64::from_le_bytes(data)))
+}
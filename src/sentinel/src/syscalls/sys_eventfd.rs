@@ -2,6 +2,7 @@ use std::{cell::RefCell, rc::Rc};
 
 use fs::{FdFlags, SettableFileFlags};
 use time::Context;
+use utils::{bail_libc, SysError};
 
 use crate::{context, kernel::eventfd::new_eventfd};
 
@@ -13,9 +14,17 @@ pub fn eventfd(mut regs: libc::user_regs_struct) -> super::Result {
 
 // eventfd2 implements linux syscall eventfd2
 pub fn eventfd2(regs: &libc::user_regs_struct) -> super::Result {
+    let init_val = regs.rdi as u32;
     let flags = regs.rsi as i32;
+    if flags & !(libc::EFD_CLOEXEC | libc::EFD_NONBLOCK | libc::EFD_SEMAPHORE) != 0 {
+        bail_libc!(libc::EINVAL);
+    }
     let ctx = context::context();
-    let mut event = new_eventfd(&|| ctx.now());
+    let mut event = new_eventfd(
+        &|| ctx.now(),
+        init_val as u64,
+        flags & libc::EFD_SEMAPHORE != 0,
+    );
     event.set_flags(SettableFileFlags {
         non_blocking: flags & libc::EFD_NONBLOCK != 0,
         ..SettableFileFlags::default()
@@ -2,14 +2,17 @@ use std::{cell::RefCell, path::Component, rc::Rc};
 
 use auth::{capability_set::CapabilitySet, id::Uid, Context as AuthContext};
 use fs::{
-    attr::{FilePermissions, PermMask},
-    Context, DirentRef, FdFlags, FileFlags,
+    attr::{FilePermissions, InodeType, PermMask},
+    lock::{LockKind, LockOwner, LOCK_EOF},
+    Context, DirentRef, FdFlags, File, FileFlags,
 };
+use limit::Context as LimitContext;
 use mem::Addr;
+use platform::Context as PlatformContext;
 
 use crate::context;
 
-use utils::{bail_libc, SysError, SysErrorKind, SysResult};
+use utils::{bail_libc, err_libc, Range, SysError, SysErrorKind, SysResult};
 
 // open implements linux syscall open(2)
 pub fn open(regs: &libc::user_regs_struct) -> super::Result {
@@ -363,16 +366,7 @@ pub fn close(regs: &libc::user_regs_struct) -> super::Result {
     let fd = regs.rdi as i32;
     let ctx = context::context();
     let mut task = ctx.task_mut();
-    let f = task
-        .fd_table_mut()
-        .remove(fd)
-        .ok_or_else(|| SysError::new(libc::EBADF))?;
-
-    f.borrow()
-        .flush()
-        .expect("flush returned error in current implementation?");
-    f.borrow().close()?;
-
+    task.close_fd(fd)?;
     Ok(0)
 }
 
@@ -445,6 +439,58 @@ pub fn readlink(regs: &libc::user_regs_struct) -> super::Result {
     readlink_at(libc::AT_FDCWD, addr, buf_addr, size)
 }
 
+// symlink implements linux syscall symlink(2)
+pub fn symlink(regs: &libc::user_regs_struct) -> super::Result {
+    let target_addr = Addr(regs.rdi);
+    let link_addr = Addr(regs.rsi);
+    symlink_at(target_addr, libc::AT_FDCWD, link_addr).map(|()| 0)
+}
+
+// symlinkat implements linux syscall symlinkat(2)
+pub fn symlinkat(regs: &libc::user_regs_struct) -> super::Result {
+    let target_addr = Addr(regs.rdi);
+    let dir_fd = regs.rsi as i32;
+    let link_addr = Addr(regs.rdx);
+    symlink_at(target_addr, dir_fd, link_addr).map(|()| 0)
+}
+
+fn symlink_at(target_addr: Addr, dir_fd: i32, link_addr: Addr) -> SysResult<()> {
+    let target = {
+        let ctx = context::context();
+        let mut task = ctx.task_mut();
+        task.copy_in_string(target_addr, libc::PATH_MAX as usize)?
+    };
+    if target.is_empty() {
+        bail_libc!(libc::ENOENT);
+    }
+    let (path, is_dir) = copy_in_path(link_addr, false)?;
+    if is_dir {
+        bail_libc!(libc::ENOENT);
+    }
+
+    file_op_at(dir_fd, &path, |root, parent, name, _| {
+        let ctx = &*context::context();
+        {
+            let parent = parent.borrow();
+            if !parent.stable_attr().is_directory() {
+                bail_libc!(libc::ENOTDIR);
+            }
+            parent.inode().check_permission(
+                PermMask {
+                    read: false,
+                    write: true,
+                    execute: true,
+                },
+                ctx,
+            )?;
+        }
+        let parent_ptr = parent.clone();
+        parent
+            .borrow_mut()
+            .create_link(root, name, &target, parent_ptr, ctx)
+    })
+}
+
 fn readlink_at(dir_fd: i32, addr: Addr, buf_addr: Addr, size: u32) -> SysResult<usize> {
     let (path, is_dir) = copy_in_path(addr, false)?;
     if is_dir {
@@ -481,6 +527,60 @@ fn readlink_at(dir_fd: i32, addr: Addr, buf_addr: Addr, size: u32) -> SysResult<
     Ok(copied)
 }
 
+// fallocate implements linux syscall fallocate(2)
+pub fn fallocate(regs: &libc::user_regs_struct) -> super::Result {
+    let fd = regs.rdi as i32;
+    let mode = regs.rsi as i32;
+    let offset = regs.rdx as i64;
+    let length = regs.r10 as i64;
+
+    if offset < 0 || length <= 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    if mode & !FALLOC_FL_SUPPORTED_MASK != 0 {
+        bail_libc!(libc::EOPNOTSUPP);
+    }
+    // A hole can't be zeroed at the same time, and punching it must keep the size.
+    if mode & libc::FALLOC_FL_PUNCH_HOLE != 0
+        && (mode & libc::FALLOC_FL_ZERO_RANGE != 0 || mode & libc::FALLOC_FL_KEEP_SIZE == 0)
+    {
+        bail_libc!(libc::EOPNOTSUPP);
+    }
+
+    let ctx = context::context();
+    let file = ctx
+        .task_mut()
+        .get_file(fd)
+        .ok_or_else(|| SysError::new(libc::EBADF))?;
+    let file = file.borrow();
+    if !file.flags().write {
+        bail_libc!(libc::EBADF);
+    }
+    let dirent = file.dirent();
+    let mut dirent = dirent.borrow_mut();
+    let inode = dirent.inode_mut();
+    let sattr = inode.stable_attr();
+    if sattr.typ == InodeType::Pipe {
+        bail_libc!(libc::ESPIPE);
+    }
+    if !sattr.is_regular() && !sattr.is_directory() {
+        bail_libc!(libc::ENODEV);
+    }
+    if offset.checked_add(length).is_none() {
+        bail_libc!(libc::EFBIG);
+    }
+    inode.allocate(mode, offset, length, &*ctx).map(|()| 0)
+}
+
+const FALLOC_FL_SUPPORTED_MASK: i32 = libc::FALLOC_FL_KEEP_SIZE
+    | libc::FALLOC_FL_PUNCH_HOLE
+    | FALLOC_FL_NO_HIDE_STALE
+    | libc::FALLOC_FL_COLLAPSE_RANGE
+    | libc::FALLOC_FL_ZERO_RANGE
+    | libc::FALLOC_FL_INSERT_RANGE
+    | libc::FALLOC_FL_UNSHARE_RANGE;
+const FALLOC_FL_NO_HIDE_STALE: i32 = 0x04;
+
 // fcntl implements linux syscall fcntl(2)
 pub fn fcntl(regs: &libc::user_regs_struct) -> super::Result {
     let fd = regs.rdi as i32;
@@ -517,6 +617,10 @@ pub fn fcntl(regs: &libc::user_regs_struct) -> super::Result {
         }
         libc::F_DUPFD | libc::F_DUPFD_CLOEXEC => {
             let from = regs.rdx as i32;
+            let lim = ctx.limits().get_number_of_files();
+            if from < 0 || (lim.cur != limit::INFINITY && from as u64 >= lim.cur) {
+                bail_libc!(libc::EINVAL);
+            }
             let mut task = ctx.task_mut();
             task.new_fd_from(
                 from,
@@ -527,7 +631,156 @@ pub fn fcntl(regs: &libc::user_regs_struct) -> super::Result {
             )
             .map(|fd| fd as usize)
         }
-        _ => todo!("the flag {} is yet to be implemented in fcntl(2)", cmd),
+        libc::F_GETLK | libc::F_SETLK | libc::F_SETLKW => {
+            record_lock(&file.borrow(), cmd, Addr(regs.rdx), &*ctx)
+        }
+        _ => {
+            logger::warn!("command {} is not implemented in fcntl(2)", cmd);
+            Err(SysError::new(libc::EINVAL))
+        }
+    }
+}
+
+// record_lock implements the F_GETLK, F_SETLK and F_SETLKW commands of fcntl(2). Record locks
+// belong to the process rather than to the open file, so the locks of a task never conflict with
+// each other, and closing any descriptor of the file releases all of them.
+fn record_lock(file: &File, cmd: i32, addr: Addr, ctx: &context::Context) -> super::Result {
+    let mut flock = copy_in_flock(addr)?;
+    let kind = match flock.l_type as i32 {
+        libc::F_RDLCK => Some(LockKind::Read),
+        libc::F_WRLCK => Some(LockKind::Write),
+        libc::F_UNLCK => None,
+        _ => bail_libc!(libc::EINVAL),
+    };
+    let range = lock_range(file, &flock)?;
+    let owner = ctx.tid().as_raw() as LockOwner;
+    let dirent = file.dirent();
+    let mut dirent = dirent.borrow_mut();
+    let locks = dirent.inode_mut().record_locks_mut();
+
+    if cmd == libc::F_GETLK {
+        let kind = kind.ok_or_else(|| SysError::new(libc::EINVAL))?;
+        match locks.test(owner, kind, range) {
+            Some((r, kind, owner)) => {
+                flock.l_type = match kind {
+                    LockKind::Read => libc::F_RDLCK,
+                    LockKind::Write => libc::F_WRLCK,
+                } as i16;
+                flock.l_whence = libc::SEEK_SET as i16;
+                flock.l_start = r.start as i64;
+                flock.l_len = if r.end == LOCK_EOF { 0 } else { r.len() as i64 };
+                flock.l_pid = owner as i32;
+            }
+            None => flock.l_type = libc::F_UNLCK as i16,
+        }
+        return copy_out_flock(addr, &flock).map(|()| 0);
+    }
+
+    let kind = match kind {
+        Some(kind) => kind,
+        None => {
+            locks.unlock(owner, range);
+            return Ok(0);
+        }
+    };
+    let flags = file.flags();
+    if (kind == LockKind::Read && !flags.read) || (kind == LockKind::Write && !flags.write) {
+        bail_libc!(libc::EBADF);
+    }
+    match locks.lock(owner, kind, range) {
+        Ok(()) => Ok(0),
+        Err(err) if cmd == libc::F_SETLK => Err(err),
+        Err(_) => {
+            // Only this task could release the lock, so waiting for it would never end.
+            logger::warn!("F_SETLKW would block forever on a lock held by the same task");
+            err_libc!(libc::EDEADLK)
+        }
+    }
+}
+
+// lock_range returns the bytes of file covered by flock, where a zero l_len reaches past the end
+// of the file and a negative one covers the bytes before l_start.
+fn lock_range(file: &File, flock: &libc::flock) -> SysResult<Range<u64>> {
+    let base = match flock.l_whence as i32 {
+        libc::SEEK_SET => 0,
+        libc::SEEK_CUR => file.offset(),
+        libc::SEEK_END => file.get_file_size()? as i64,
+        _ => bail_libc!(libc::EINVAL),
+    };
+    let start = base
+        .checked_add(flock.l_start)
+        .ok_or_else(|| SysError::new(libc::EOVERFLOW))?;
+    if start < 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    let (start, end) = match flock.l_len {
+        0 => (start as u64, LOCK_EOF),
+        len if len > 0 => {
+            let end = start
+                .checked_add(len)
+                .ok_or_else(|| SysError::new(libc::EOVERFLOW))?;
+            (start as u64, end as u64)
+        }
+        len => {
+            let begin = start + len;
+            if begin < 0 {
+                bail_libc!(libc::EINVAL);
+            }
+            (begin as u64, start as u64)
+        }
+    };
+    Ok(Range { start, end })
+}
+
+fn copy_in_flock(addr: Addr) -> SysResult<libc::flock> {
+    let mut b = [0; std::mem::size_of::<libc::flock>()];
+    context::context().task().copy_in_bytes(addr, &mut b)?;
+    Ok(unsafe { std::ptr::read_unaligned(b.as_ptr() as *const libc::flock) })
+}
+
+fn copy_out_flock(addr: Addr, flock: &libc::flock) -> SysResult<()> {
+    let b = unsafe {
+        std::slice::from_raw_parts(
+            flock as *const _ as *const u8,
+            std::mem::size_of::<libc::flock>(),
+        )
+    };
+    context::context().task().copy_out_bytes(addr, b)?;
+    Ok(())
+}
+
+// flock implements linux syscall flock(2). The lock belongs to the open file description, so
+// duplicated descriptors share it while another open(2) of the same file conflicts with it.
+pub fn flock(regs: &libc::user_regs_struct) -> super::Result {
+    let fd = regs.rdi as i32;
+    let operation = regs.rsi as i32;
+
+    let ctx = context::context();
+    let file = ctx
+        .task_mut()
+        .get_file(fd)
+        .ok_or_else(|| SysError::new(libc::EBADF))?;
+    let owner = Rc::as_ptr(&file) as LockOwner;
+    let dirent = file.borrow().dirent();
+    let mut dirent = dirent.borrow_mut();
+    let locks = dirent.inode_mut().locks_mut();
+
+    let non_blocking = operation & libc::LOCK_NB != 0;
+    match operation & !libc::LOCK_NB {
+        libc::LOCK_UN => {
+            locks.unlock(owner);
+            Ok(0)
+        }
+        op @ (libc::LOCK_SH | libc::LOCK_EX) => match locks.lock(owner, op == libc::LOCK_EX) {
+            Ok(()) => Ok(0),
+            Err(err) if non_blocking => Err(err),
+            Err(_) => {
+                // Only this task could release the lock, so waiting for it would never end.
+                logger::warn!("flock(2) would block forever on a lock held by the same task");
+                err_libc!(libc::EDEADLK)
+            }
+        },
+        _ => err_libc!(libc::EINVAL),
     }
 }
 
@@ -535,7 +788,14 @@ pub fn fcntl(regs: &libc::user_regs_struct) -> super::Result {
 pub fn rename(regs: &libc::user_regs_struct) -> super::Result {
     let old_path_addr = Addr(regs.rdi);
     let new_path_addr = Addr(regs.rsi);
-    rename_at(libc::AT_FDCWD, old_path_addr, libc::AT_FDCWD, new_path_addr).map(|()| 0)
+    rename_at(
+        libc::AT_FDCWD,
+        old_path_addr,
+        libc::AT_FDCWD,
+        new_path_addr,
+        0,
+    )
+    .map(|()| 0)
 }
 
 // renameat implements linux syscall renameat(2)
@@ -544,10 +804,32 @@ pub fn renameat(regs: &libc::user_regs_struct) -> super::Result {
     let old_path_addr = Addr(regs.rsi);
     let new_dir_fd = regs.rdx as i32;
     let new_path_addr = Addr(regs.r10);
-    rename_at(old_dir_fd, old_path_addr, new_dir_fd, new_path_addr).map(|()| 0)
+    rename_at(old_dir_fd, old_path_addr, new_dir_fd, new_path_addr, 0).map(|()| 0)
+}
+
+// renameat2 implements linux syscall renameat2(2)
+pub fn renameat2(regs: &libc::user_regs_struct) -> super::Result {
+    let old_dir_fd = regs.rdi as i32;
+    let old_path_addr = Addr(regs.rsi);
+    let new_dir_fd = regs.rdx as i32;
+    let new_path_addr = Addr(regs.r10);
+    let flags = regs.r8 as u32;
+    if flags & !(libc::RENAME_NOREPLACE | libc::RENAME_EXCHANGE) != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    if flags & libc::RENAME_NOREPLACE != 0 && flags & libc::RENAME_EXCHANGE != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    rename_at(old_dir_fd, old_path_addr, new_dir_fd, new_path_addr, flags).map(|()| 0)
 }
 
-fn rename_at(old_dir_fd: i32, old_addr: Addr, new_dir_fd: i32, new_addr: Addr) -> SysResult<()> {
+fn rename_at(
+    old_dir_fd: i32,
+    old_addr: Addr,
+    new_dir_fd: i32,
+    new_addr: Addr,
+    flags: u32,
+) -> SysResult<()> {
     let (old_path, _) = copy_in_path(old_addr, false)?;
     let (new_path, _) = copy_in_path(new_addr, false)?;
 
@@ -567,18 +849,127 @@ fn rename_at(old_dir_fd: i32, old_addr: Addr, new_dir_fd: i32, new_addr: Addr) -
                 bail_libc!(libc::EBUSY);
             }
             let ctx = &*context::context();
+            if flags & libc::RENAME_EXCHANGE != 0 {
+                return fs::exchange(root, old_parent, old_name, new_parent, new_name, ctx);
+            }
             fs::rename(
                 root,
                 old_parent,
                 Component::Normal(old_name.as_ref()),
                 new_parent,
                 new_name.to_string(),
+                flags & libc::RENAME_NOREPLACE != 0,
                 ctx,
             )
         })
     })
 }
 
+// link implements linux syscall link(2)
+pub fn link(regs: &libc::user_regs_struct) -> super::Result {
+    let old_path_addr = Addr(regs.rdi);
+    let new_path_addr = Addr(regs.rsi);
+    link_at(
+        libc::AT_FDCWD,
+        old_path_addr,
+        libc::AT_FDCWD,
+        new_path_addr,
+        0,
+    )
+    .map(|()| 0)
+}
+
+// linkat implements linux syscall linkat(2)
+pub fn linkat(regs: &libc::user_regs_struct) -> super::Result {
+    let old_dir_fd = regs.rdi as i32;
+    let old_path_addr = Addr(regs.rsi);
+    let new_dir_fd = regs.rdx as i32;
+    let new_path_addr = Addr(regs.r10);
+    let flags = regs.r8 as i32;
+    link_at(old_dir_fd, old_path_addr, new_dir_fd, new_path_addr, flags).map(|()| 0)
+}
+
+fn link_at(
+    old_dir_fd: i32,
+    old_addr: Addr,
+    new_dir_fd: i32,
+    new_addr: Addr,
+    flags: i32,
+) -> SysResult<()> {
+    if flags & !(libc::AT_SYMLINK_FOLLOW | libc::AT_EMPTY_PATH) != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    let (old_path, _) = copy_in_path(old_addr, flags & libc::AT_EMPTY_PATH != 0)?;
+    let (new_path, is_dir) = copy_in_path(new_addr, false)?;
+    if is_dir {
+        bail_libc!(libc::ENOENT);
+    }
+
+    let target = if old_path.is_empty() {
+        let ctx = context::context();
+        let mut task = ctx.task_mut();
+        let file = task
+            .get_file(old_dir_fd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        let dirent = file.borrow().dirent();
+        dirent
+    } else {
+        let resolve = flags & libc::AT_SYMLINK_FOLLOW != 0;
+        let mut target = None;
+        file_op_on(old_dir_fd, &old_path, resolve, |_, d, _| {
+            target = Some(d.clone());
+            Ok(())
+        })?;
+        target.unwrap()
+    };
+
+    file_op_at(new_dir_fd, &new_path, |root, new_parent, new_name, _| {
+        if !new_parent.borrow().stable_attr().is_directory() {
+            bail_libc!(libc::ENOTDIR);
+        }
+        if new_name.is_empty() || new_name == "." || new_name == ".." {
+            bail_libc!(libc::EEXIST);
+        }
+        let ctx = &*context::context();
+        fs::link(root, &target, new_parent, new_name, ctx)
+    })
+}
+
+// unlink implements linux syscall unlink(2)
+pub fn unlink(regs: &libc::user_regs_struct) -> super::Result {
+    let addr = Addr(regs.rdi);
+    unlink_at(libc::AT_FDCWD, addr).map(|()| 0)
+}
+
+// unlinkat implements linux syscall unlinkat(2)
+pub fn unlinkat(regs: &libc::user_regs_struct) -> super::Result {
+    let dir_fd = regs.rdi as i32;
+    let addr = Addr(regs.rsi);
+    let flags = regs.rdx as i32;
+    if flags & !libc::AT_REMOVEDIR != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    if flags & libc::AT_REMOVEDIR != 0 {
+        logger::warn!("removing directories is not supported");
+        bail_libc!(libc::EPERM);
+    }
+    unlink_at(dir_fd, addr).map(|()| 0)
+}
+
+fn unlink_at(dir_fd: i32, addr: Addr) -> SysResult<()> {
+    let (path, _) = copy_in_path(addr, false)?;
+    file_op_at(dir_fd, &path, |root, parent, name, _| {
+        if !parent.borrow().stable_attr().is_directory() {
+            bail_libc!(libc::ENOTDIR);
+        }
+        if name.is_empty() || name == "." || name == ".." {
+            bail_libc!(libc::EISDIR);
+        }
+        let ctx = &*context::context();
+        fs::unlink(root, parent, name, ctx)
+    })
+}
+
 // dup implements linux syscall dup(2)
 pub fn dup(regs: &libc::user_regs_struct) -> super::Result {
     let fd = regs.rdi as i32;
@@ -596,3 +987,25 @@ pub fn dup(regs: &libc::user_regs_struct) -> super::Result {
     )
     .map(|fd| fd as usize)
 }
+
+// dup2 implements linux syscall dup2(2)
+pub fn dup2(regs: &libc::user_regs_struct) -> super::Result {
+    let oldfd = regs.rdi as i32;
+    let newfd = regs.rsi as i32;
+    let ctx = context::context();
+    let mut task = ctx.task_mut();
+    task.dup_to(oldfd, newfd, 0).map(|fd| fd as usize)
+}
+
+// dup3 implements linux syscall dup3(2)
+pub fn dup3(regs: &libc::user_regs_struct) -> super::Result {
+    let oldfd = regs.rdi as i32;
+    let newfd = regs.rsi as i32;
+    let flags = regs.rdx as i32;
+    if flags & !libc::O_CLOEXEC != 0 || oldfd == newfd {
+        bail_libc!(libc::EINVAL);
+    }
+    let ctx = context::context();
+    let mut task = ctx.task_mut();
+    task.dup_to(oldfd, newfd, flags).map(|fd| fd as usize)
+}
@@ -1,8 +1,4 @@
-use std::{
-    os::unix::prelude::OsStrExt,
-    path::{Path, PathBuf},
-    rc::Rc,
-};
+use std::rc::Rc;
 
 use fs::{attr::PermMask, Context, DirentRef};
 use mem::Addr;
@@ -21,40 +17,44 @@ pub fn getcwd(regs: &libc::user_regs_struct) -> super::Result {
     let root = ctx.root_directory();
     let wd = ctx.working_directory();
 
-    let mut bytes = pathname_for_cwd(root, wd).as_os_str().as_bytes().to_vec();
-    bytes.push(0);
+    let path = pathname_for_cwd(root, wd);
 
-    if bytes.len() > size as usize {
-        bail_libc!(libc::ERANGE);
-    }
     let task = ctx.task();
-    task.copy_out_bytes(addr, &bytes)
+    task.copy_out_string(addr, &path, size as usize)
+        .map_err(|e| match e.code() {
+            libc::ENAMETOOLONG => SysError::new(libc::ERANGE),
+            _ => e,
+        })
 }
 
-// FIXME: naive implementation...
-fn pathname_for_cwd(root: &DirentRef, wd: &DirentRef) -> PathBuf {
+// pathname_for_cwd builds the absolute path of wd by following the parents up to root. Like Linux,
+// the path is prefixed with "(unreachable)" if root can't be reached from wd.
+fn pathname_for_cwd(root: &DirentRef, wd: &DirentRef) -> String {
     let mut cur = wd.clone();
-    let mut path = PathBuf::new();
+    let mut names = Vec::new();
+    let mut prefix = "";
     while Rc::as_ptr(&cur) != Rc::as_ptr(root) {
         let parent = {
             let cur_ref = cur.borrow();
-            let p = Path::new(cur_ref.name());
-            path = p.join(&path);
-            cur_ref.parent().upgrade().unwrap()
+            names.push(cur_ref.name().to_string());
+            cur_ref.parent().upgrade()
         };
-        cur = parent;
+        match parent {
+            Some(parent) => cur = parent,
+            None => {
+                prefix = "(unreachable)";
+                break;
+            }
+        }
     }
-    let p = Path::new("/");
-    p.join(&path)
+    names.reverse();
+    format!("{}/{}", prefix, names.join("/"))
 }
 
 // chdir implements linux syscall chdir(2)
 pub fn chdir(regs: &libc::user_regs_struct) -> super::Result {
     let addr = Addr(regs.rdi);
-    let (path, is_dir) = copy_in_path(addr, false)?;
-    if !is_dir {
-        bail_libc!(libc::ENOTDIR);
-    }
+    let (path, _) = copy_in_path(addr, false)?;
 
     file_op_on(libc::AT_FDCWD, &path, true, |_, dir, _| {
         let d = dir.borrow();
@@ -79,3 +79,37 @@ pub fn chdir(regs: &libc::user_regs_struct) -> super::Result {
     })?;
     Ok(0)
 }
+
+// fchdir implements linux syscall fchdir(2)
+pub fn fchdir(regs: &libc::user_regs_struct) -> super::Result {
+    let fd = regs.rdi as i32;
+
+    let ctx = context::context();
+    let dir = {
+        let mut task = ctx.task_mut();
+        let file = task
+            .get_file(fd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        let file = file.borrow();
+        file.dirent()
+    };
+    {
+        let d = dir.borrow();
+        let inode = d.inode();
+        if !inode.stable_attr().is_directory() {
+            bail_libc!(libc::ENOTDIR);
+        }
+        inode.check_permission(
+            PermMask {
+                read: false,
+                write: false,
+                execute: true,
+            },
+            &*ctx,
+        )?;
+    }
+    drop(ctx);
+    let mut ctx = context::context_mut();
+    ctx.set_working_directory(dir);
+    Ok(0)
+}
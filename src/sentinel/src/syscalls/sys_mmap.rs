@@ -43,7 +43,7 @@ pub fn mmap(regs: &libc::user_regs_struct) -> super::Result {
         map32bit,
         grows_down: flags & libc::MAP_GROWSDOWN != 0,
         precommit: flags & libc::MAP_POPULATE != 0,
-        perms: AccessType::from_prot(prot),
+        perms: AccessType::from_prot(read_implies_exec(prot)),
         max_perms: AccessType::any_access(),
         mlock_mode,
         ..MmapOpts::default()
@@ -99,7 +99,7 @@ pub fn brk(regs: &libc::user_regs_struct) -> super::Result {
 // mprotect implements linux syscall mprotect(2)
 pub fn mprotect(regs: &libc::user_regs_struct) -> super::Result {
     let length = regs.rsi;
-    let prot = regs.rdx as i32;
+    let prot = read_implies_exec(regs.rdx as i32);
     let at = AccessType {
         read: libc::PROT_READ & prot != 0,
         write: libc::PROT_WRITE & prot != 0,
@@ -154,3 +154,142 @@ pub fn mremap(regs: &libc::user_regs_struct) -> super::Result {
     )
     .map(|n| n.0 as usize)
 }
+
+// mincore implements linux syscall mincore(2)
+pub fn mincore(regs: &libc::user_regs_struct) -> super::Result {
+    let addr = Addr(regs.rdi);
+    let length = regs.rsi;
+    let vec_addr = Addr(regs.rdx);
+
+    let ctx = context::context();
+    let vec = ctx.memory_manager().borrow().mincore(addr, length)?;
+    if vec.is_empty() {
+        return Ok(0);
+    }
+    ctx.task().copy_out_bytes(vec_addr, &vec).map(|_| 0)
+}
+
+// madvise implements linux syscall madvise(2)
+pub fn madvise(regs: &libc::user_regs_struct) -> super::Result {
+    let addr = Addr(regs.rdi);
+    let length = regs.rsi;
+    let advice = regs.rdx as i32;
+
+    let mm = {
+        let ctx = context::context();
+        ctx.memory_manager()
+    };
+    match advice {
+        // MADV_FREE lets the kernel reclaim the pages lazily, so dropping them right away is
+        // a valid implementation.
+        libc::MADV_DONTNEED | libc::MADV_FREE => mm.borrow_mut().decommit(addr, length),
+        // the remaining advice is only a hint. sentinel never forks an address space, so
+        // MADV_DONTFORK and MADV_DOFORK have nothing to record either.
+        libc::MADV_NORMAL
+        | libc::MADV_RANDOM
+        | libc::MADV_SEQUENTIAL
+        | libc::MADV_WILLNEED
+        | libc::MADV_DONTFORK
+        | libc::MADV_DOFORK
+        | libc::MADV_HUGEPAGE
+        | libc::MADV_NOHUGEPAGE
+        | libc::MADV_MERGEABLE
+        | libc::MADV_UNMERGEABLE
+        | libc::MADV_DONTDUMP
+        | libc::MADV_DODUMP => mm.borrow().mapped_range(addr, length).map(|_| ()),
+        _ => bail_libc!(libc::EINVAL),
+    }
+    .map(|()| 0)
+}
+
+// MCL_ONFAULT is missing from the libc crate.
+const MCL_ONFAULT: i32 = 4;
+
+// mlock implements linux syscall mlock(2)
+pub fn mlock(regs: &libc::user_regs_struct) -> super::Result {
+    let mm = {
+        let ctx = context::context();
+        ctx.memory_manager()
+    };
+    let mut mm = mm.borrow_mut();
+    mm.mlock(Addr(regs.rdi), regs.rsi, MLockMode::Eager)
+        .map(|()| 0)
+}
+
+// mlock2 implements linux syscall mlock2(2)
+pub fn mlock2(regs: &libc::user_regs_struct) -> super::Result {
+    let flags = regs.rdx as u32;
+    if flags & !libc::MLOCK_ONFAULT != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    let mode = if flags & libc::MLOCK_ONFAULT != 0 {
+        MLockMode::Lazy
+    } else {
+        MLockMode::Eager
+    };
+    let mm = {
+        let ctx = context::context();
+        ctx.memory_manager()
+    };
+    let mut mm = mm.borrow_mut();
+    mm.mlock(Addr(regs.rdi), regs.rsi, mode).map(|()| 0)
+}
+
+// munlock implements linux syscall munlock(2)
+pub fn munlock(regs: &libc::user_regs_struct) -> super::Result {
+    let mm = {
+        let ctx = context::context();
+        ctx.memory_manager()
+    };
+    let mut mm = mm.borrow_mut();
+    mm.mlock(Addr(regs.rdi), regs.rsi, MLockMode::None_)
+        .map(|()| 0)
+}
+
+// mlockall implements linux syscall mlockall(2)
+pub fn mlockall(regs: &libc::user_regs_struct) -> super::Result {
+    let flags = regs.rdi as i32;
+    if flags & !(libc::MCL_CURRENT | libc::MCL_FUTURE | MCL_ONFAULT) != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    let mode = if flags & MCL_ONFAULT != 0 {
+        MLockMode::Lazy
+    } else {
+        MLockMode::Eager
+    };
+    let mm = {
+        let ctx = context::context();
+        ctx.memory_manager()
+    };
+    let mut mm = mm.borrow_mut();
+    mm.mlockall(
+        flags & libc::MCL_CURRENT != 0,
+        flags & libc::MCL_FUTURE != 0,
+        mode,
+    )
+    .map(|()| 0)
+}
+
+// munlockall implements linux syscall munlockall(2)
+pub fn munlockall(_: &libc::user_regs_struct) -> super::Result {
+    let mm = {
+        let ctx = context::context();
+        ctx.memory_manager()
+    };
+    let mut mm = mm.borrow_mut();
+    mm.mlockall(true, true, MLockMode::None_).map(|()| 0)
+}
+
+// read_implies_exec adds PROT_EXEC to readable prot if the task's personality asks for it.
+fn read_implies_exec(prot: i32) -> i32 {
+    if prot & libc::PROT_READ == 0 {
+        return prot;
+    }
+    let ctx = context::context();
+    let task = ctx.task();
+    if task.personality() & libc::READ_IMPLIES_EXEC as u32 != 0 {
+        prot | libc::PROT_EXEC
+    } else {
+        prot
+    }
+}
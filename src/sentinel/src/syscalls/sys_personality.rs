@@ -0,0 +1,17 @@
+use crate::context;
+
+// personality implements linux syscall personality(2)
+//
+// All the bits are recorded, but only ADDR_NO_RANDOMIZE, which applies to the layout of the next
+// image, and READ_IMPLIES_EXEC are acted upon.
+pub fn personality(regs: &libc::user_regs_struct) -> super::Result {
+    let persona = regs.rdi as u32;
+
+    let ctx = context::context();
+    let mut task = ctx.task_mut();
+    let old = task.personality();
+    if persona != 0xffffffff {
+        task.set_personality(persona);
+    }
+    Ok(old as usize)
+}
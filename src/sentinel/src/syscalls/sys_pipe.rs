@@ -22,11 +22,11 @@ pub fn pipe2(regs: &libc::user_regs_struct) -> super::Result {
 }
 
 fn pipe2_impl(addr: Addr, flags: i32) -> SysResult<()> {
-    if flags & !(libc::O_NONBLOCK | libc::O_CLOEXEC) != 0 {
+    if flags & !(libc::O_NONBLOCK | libc::O_CLOEXEC | libc::O_DIRECT) != 0 {
         bail_libc!(libc::EINVAL);
     }
     let (mut r, mut w) = {
-        let mut pipe = PipeRef::new(DEFAULT_PIPE_SIZE);
+        let mut pipe = PipeRef::new(DEFAULT_PIPE_SIZE, flags & libc::O_DIRECT != 0);
         pipe.connect()
     };
     r.set_flags(fs::FileFlags::from_linux_flags(flags).as_settable());
@@ -4,8 +4,10 @@ use limit::Context as LimitContext;
 use mem::Addr;
 use net::Context as NetContext;
 use smoltcp::time::Duration;
+use time::{Clock, MonotonicClock, Time};
 use utils::{bail_libc, SysError, SysResult};
 
+use super::sys_time::{copy_in_timespec, copy_out_timespec, is_timespec_valid};
 use crate::context;
 
 // poll implements linux syscall poll(2)
@@ -14,6 +16,11 @@ pub fn poll(regs: &libc::user_regs_struct) -> super::Result {
     let nfds = regs.rsi;
     let timeout = regs.rdx as i32;
 
+    let timeout = if timeout >= 0 {
+        Some(Time::from_unix(0, timeout as i64 * 1_000_000))
+    } else {
+        None
+    };
     let mut pfds = copy_in_poll_fds(fds_addr, nfds)?;
     let n = poll_block(&mut pfds, timeout)?;
     if nfds > 0 {
@@ -22,27 +29,108 @@ pub fn poll(regs: &libc::user_regs_struct) -> super::Result {
     Ok(n)
 }
 
-fn poll_block(pfds: &mut [libc::pollfd], timeout: i32) -> SysResult<usize> {
+// ppoll implements linux syscall ppoll(2)
+pub fn ppoll(regs: &libc::user_regs_struct) -> super::Result {
+    let fds_addr = Addr(regs.rdi);
+    let nfds = regs.rsi;
+    let timespec_addr = Addr(regs.rdx);
+    let mask_addr = Addr(regs.r10);
+    let mask_size = regs.r8 as i32;
+
+    let timeout = if timespec_addr.0 != 0 {
+        let ts = copy_in_timespec(timespec_addr)?;
+        if !is_timespec_valid(&ts) {
+            bail_libc!(libc::EINVAL);
+        }
+        Some(Time::from_unix(ts.tv_sec, ts.tv_nsec))
+    } else {
+        None
+    };
     let ctx = context::context();
+    let mask = if mask_addr.0 != 0 {
+        Some(ctx.task().copy_in_sig_set(mask_addr, mask_size)?)
+    } else {
+        None
+    };
+    let mut pfds = copy_in_poll_fds(fds_addr, nfds)?;
+
+    // The given mask is only in effect while waiting.
+    let old_mask = mask.map(|mask| {
+        let task = ctx.task();
+        let old_mask = task.signal_mask();
+        task.set_signal_mask(mask);
+        old_mask
+    });
+    let start = MonotonicClock.now();
+    let res = poll_block(&mut pfds, timeout);
+    let elapsed = MonotonicClock.now() - start;
+    if let Some(old_mask) = old_mask {
+        ctx.task().set_signal_mask(old_mask);
+    }
+    let n = res?;
+
+    if nfds > 0 {
+        copy_out_poll_fds(fds_addr, &pfds)?;
+    }
+    // Like linux, copy the unslept time back to the timespec.
+    if let Some(timeout) = timeout {
+        let remaining = (timeout - elapsed).as_libc_timespec();
+        copy_out_timespec(timespec_addr, &remaining)?;
+    }
+    Ok(n)
+}
+
+// poll_block waits until any of pfds gets ready or timeout passes, blocking forever if timeout is
+// None. It returns the number of ready fds, which is 0 on timeout.
+fn poll_block(pfds: &mut [libc::pollfd], timeout: Option<Time>) -> SysResult<usize> {
     let files = {
+        let ctx = context::context();
         let mut task = ctx.task_mut();
         pfds.iter()
             .map(|pfd| task.get_file(pfd.fd))
             .collect::<Vec<_>>()
     };
-    let n = update_readiness(pfds, &files);
-    if n > 0 || timeout == 0 {
+    wait_ready(timeout, || Ok(update_readiness(pfds, &files)))
+}
+
+// wait_ready calls check until it reports a non-zero number of ready fds or timeout passes,
+// blocking forever if timeout is None. It returns 0 on timeout.
+pub(super) fn wait_ready<F: FnMut() -> SysResult<usize>>(
+    timeout: Option<Time>,
+    mut check: F,
+) -> SysResult<usize> {
+    let n = check()?;
+    if n > 0 {
         return Ok(n);
     }
-
-    let duration = if timeout > 0 {
-        Some(Duration::from_millis(timeout as u64))
-    } else {
-        None
+    // wait may return early when the network device gets ready, so keep waiting until something
+    // is ready or the deadline passes.
+    let ctx = context::context();
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => loop {
+            ctx.wait(None);
+            let n = check()?;
+            if n > 0 {
+                return Ok(n);
+            }
+        },
     };
-    ctx.wait(duration);
 
-    Ok(update_readiness(pfds, &files))
+    let deadline = MonotonicClock.now() + timeout;
+    loop {
+        let remaining = deadline - MonotonicClock.now();
+        if remaining == Time::default() {
+            return Ok(0);
+        }
+        let ts = remaining.as_libc_timespec();
+        let micros = ts.tv_sec as u64 * 1_000_000 + (ts.tv_nsec as u64 + 999) / 1000;
+        ctx.wait(Some(Duration::from_micros(micros)));
+        let n = check()?;
+        if n > 0 {
+            return Ok(n);
+        }
+    }
 }
 
 fn update_readiness(pfds: &mut [libc::pollfd], files: &[Option<Rc<RefCell<fs::File>>>]) -> usize {
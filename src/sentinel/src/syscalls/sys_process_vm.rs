@@ -0,0 +1,76 @@
+use mem::{Addr, IoOpts, PAGE_SIZE};
+use utils::{bail_libc, SysError, SysResult};
+
+use crate::context;
+
+// process_vm_readv implements linux syscall process_vm_readv(2)
+pub fn process_vm_readv(regs: &libc::user_regs_struct) -> super::Result {
+    process_vm_rw(regs, false)
+}
+
+// process_vm_writev implements linux syscall process_vm_writev(2)
+pub fn process_vm_writev(regs: &libc::user_regs_struct) -> super::Result {
+    process_vm_rw(regs, true)
+}
+
+fn process_vm_rw(regs: &libc::user_regs_struct, is_write: bool) -> SysResult<usize> {
+    let pid = regs.rdi as i32;
+    let lvec = Addr(regs.rsi);
+    let liovcnt = regs.rdx;
+    let rvec = Addr(regs.r10);
+    let riovcnt = regs.r8;
+    let flags = regs.r9;
+
+    if flags != 0 || liovcnt > libc::UIO_MAXIOV as u64 || riovcnt > libc::UIO_MAXIOV as u64 {
+        bail_libc!(libc::EINVAL);
+    }
+
+    let ctx = context::context();
+    let task = ctx.task();
+    let mut local = task.iovecs_io_sequence(lvec, liovcnt as i32, IoOpts::default())?;
+    if local.num_bytes() == 0 {
+        return Ok(0);
+    }
+    let mut remote = task.copy_in_iovecs(rvec, riovcnt as usize)?;
+
+    // There is no other process in the sandbox whose memory could be accessed.
+    if pid != ctx.tid().as_raw() {
+        bail_libc!(libc::ESRCH);
+    }
+
+    // The remote side is copied at most a page at a time, so that like linux the transfer stops
+    // at the first page which can't be accessed and reports what was copied up to there.
+    let mut buf = [0u8; PAGE_SIZE as usize];
+    let mut done = 0;
+    while local.num_bytes() > 0 && remote.num_bytes() > 0 {
+        let head = remote.head();
+        let want = (head.len() as usize)
+            .min(PAGE_SIZE as usize - Addr(head.start).page_offset() as usize)
+            .min(local.num_bytes());
+        if want == 0 {
+            // Skip an empty iovec.
+            remote.drop_first(0);
+            continue;
+        }
+        let res = if is_write {
+            local
+                .copy_in(&mut buf[..want])
+                .and_then(|n| task.copy_out_bytes(Addr(head.start), &buf[..n]))
+        } else {
+            task.copy_in_bytes(Addr(head.start), &mut buf[..want])
+                .and_then(|n| local.copy_out(&buf[..n]))
+        };
+        let n = match res {
+            Ok(n) => n,
+            Err(_) if done > 0 => break,
+            Err(err) => return Err(err),
+        };
+        done += n;
+        local.drop_first(n);
+        remote.drop_first(n);
+        if n < want {
+            break;
+        }
+    }
+    Ok(done)
+}
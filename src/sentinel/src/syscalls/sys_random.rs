@@ -1,57 +1,54 @@
-use mem::{
-    io::{FromIoReader, Io},
-    Addr, AddrRangeSeqView, IoOpts,
-};
+use mem::{Addr, PAGE_SIZE};
 use rand::RngCore;
-use utils::{bail_libc, err_libc, SysError};
+use utils::{bail_libc, SysError};
 
 use crate::context;
 
 const GRND_NONBLOCK: u64 = 0x1;
 const GRND_RANDOM: u64 = 0x2;
+const GRND_INSECURE: u64 = 0x4;
 
 // getrandom implements linux syscall getrandom(2)
+//
+// The pool of the host is always initialized by the time the sandbox runs, so getrandom never
+// blocks and GRND_NONBLOCK has no effect. GRND_RANDOM draws from the same source as well, as it
+// does since Linux 5.6.
 pub fn getrandom(regs: &libc::user_regs_struct) -> super::Result {
     let buf = Addr(regs.rdi);
     let buflen = regs.rsi;
     let flags = regs.rdx;
 
-    if flags & !(GRND_NONBLOCK | GRND_RANDOM) != 0 {
+    if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0
+        || flags & (GRND_INSECURE | GRND_RANDOM) == GRND_INSECURE | GRND_RANDOM
+    {
         bail_libc!(libc::EINVAL);
     }
 
     let buflen = std::cmp::min(buflen, i32::MAX as u64);
-    let ar = buf
-        .to_range(buflen)
+    buf.to_range(buflen)
         .ok_or_else(|| SysError::new(libc::EFAULT))?;
 
-    let min = std::cmp::min(256, buflen as i32);
+    // The buffer is filled a page at a time, so that the bytes before an unmapped page are
+    // still reported.
     let ctx = context::context();
-    let mm = ctx.memory_manager();
-    let mut mm = mm.borrow_mut();
-    let ar = &[ar];
-    match mm.copy_out_from(
-        AddrRangeSeqView::from_slice(ar),
-        &mut FromIoReader {
-            reader: Box::new(RandReader),
-        },
-        &IoOpts {
-            ignore_permissions: false,
-        },
-    ) {
-        Ok(n) if n >= min as usize => Ok(n),
-        Ok(_) => err_libc!(libc::EAGAIN),
-        Err(err) => Err(err),
-    }
-}
-
-struct RandReader;
-
-impl std::io::Read for RandReader {
-    // TODO: naive implementation
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let mut rng = rand::thread_rng();
-        rng.fill_bytes(buf);
-        Ok(buf.len())
+    let task = ctx.task();
+    let mut rng = rand::thread_rng();
+    let mut chunk = [0; PAGE_SIZE as usize];
+    let mut done = 0;
+    while done < buflen {
+        let addr = buf + Addr(done);
+        let n = std::cmp::min(PAGE_SIZE as u64 - addr.page_offset(), buflen - done) as usize;
+        rng.fill_bytes(&mut chunk[..n]);
+        match task.copy_out_bytes(addr, &chunk[..n]) {
+            Ok(m) => {
+                done += m as u64;
+                if m < n {
+                    break;
+                }
+            }
+            Err(err) if done == 0 => return Err(err),
+            Err(_) => break,
+        }
     }
+    Ok(done as usize)
 }
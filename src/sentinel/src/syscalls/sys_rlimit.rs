@@ -1,3 +1,4 @@
+use auth::Context as AuthContext;
 use limit::{is_valid_resource, Context as LimitContext, Limit};
 use mem::Addr;
 use platform::Context as PlatformContext;
@@ -32,9 +33,10 @@ pub fn prlimit64(regs: &libc::user_regs_struct) -> super::Result {
         bail_libc!(libc::EINVAL);
     }
 
-    // This works because we only target single threaded application.
+    // This works because we only target single threaded application. Limits of other
+    // processes are not ours to read or change.
     if tid > 0 && ctx.tid().as_raw() != tid {
-        bail_libc!(libc::ESRCH);
+        bail_libc!(libc::EPERM);
     }
 
     let old_lim = prlimit64_impl(resource, new_lim)?;
@@ -68,7 +70,9 @@ fn prlimit64_impl(resource: u32, new_lim: Option<Limit>) -> SysResult<Limit> {
                 bail_libc!(libc::EPERM);
             }
             let ctx = context::context();
-            let privileged = true;
+            let privileged = ctx
+                .credentials()
+                .has_capability(&linux::Capability::cap_sys_resource());
             let mut lim = ctx.limits_mut();
             Ok(lim.set_resource(resource, new_lim, privileged)?)
         }
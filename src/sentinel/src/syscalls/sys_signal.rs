@@ -60,6 +60,18 @@ pub fn rt_sigaction(args: &libc::user_regs_struct) -> super::Result {
     Ok(0)
 }
 
+// rt_sigreturn implements linux syscall rt_sigreturn(2)
+pub fn rt_sigreturn(regs: &mut libc::user_regs_struct) -> super::Result {
+    let ctx = context::context();
+    let mut task = ctx.task_mut();
+    task.set_regs(*regs);
+    task.signal_return()?;
+    *regs = task.regs();
+    // All the registers including rax have been restored, so hand rax back as the return value to
+    // keep it intact.
+    Ok(regs.rax as usize)
+}
+
 // rt_sigprocmask implements linux syscall rt_sigprocmask(2)
 pub fn rt_sigprocmask(args: &libc::user_regs_struct) -> super::Result {
     let how = args.rdi as i32;
@@ -1,7 +1,7 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, os::unix::prelude::RawFd, rc::Rc};
 
 use fs::{
-    socket::{build_socket_file, SocketFile},
+    socket::{build_socket_file, build_socket_file_pair, SocketFile},
     SettableFileFlags,
 };
 use mem::{Addr, IoOpts};
@@ -22,7 +22,6 @@ pub fn socket(regs: &libc::user_regs_struct) -> super::Result {
     let ctx = context::context();
     let mut socket = build_socket_file(domain, stype & 0xf, protocol, &*ctx)?;
     socket.set_flags(SettableFileFlags {
-        direct: false,
         non_blocking: stype & libc::SOCK_NONBLOCK != 0,
         append: false,
         async_: false,
@@ -40,6 +39,51 @@ pub fn socket(regs: &libc::user_regs_struct) -> super::Result {
     .map(|n| n as usize)
 }
 
+// socketpair implements linux syscall socketpair(2)
+pub fn socketpair(regs: &libc::user_regs_struct) -> super::Result {
+    let domain = regs.rdi as i32;
+    let stype = regs.rsi as i32;
+    let protocol = regs.rdx as i32;
+    let sv_addr = Addr(regs.r10);
+
+    if stype & !(0xf | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC) != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+
+    let ctx = context::context();
+    let (mut a, mut b) = build_socket_file_pair(domain, stype & 0xf, protocol, &*ctx)?;
+    let flags = SettableFileFlags {
+        non_blocking: stype & libc::SOCK_NONBLOCK != 0,
+        append: false,
+        async_: false,
+    };
+    a.set_flags(flags);
+    b.set_flags(flags);
+
+    let mut task = ctx.task_mut();
+    let fds = task.fd_table_mut().new_fds(
+        0,
+        &[&Rc::new(RefCell::new(a)), &Rc::new(RefCell::new(b))],
+        fs::FdFlags {
+            close_on_exec: stype & libc::SOCK_CLOEXEC != 0,
+        },
+    )?;
+    debug_assert_eq!(fds.len(), 2);
+    let bytes = &[
+        fds[0].to_le_bytes().as_slice(),
+        fds[1].to_le_bytes().as_slice(),
+    ]
+    .concat();
+    task.copy_out_bytes(sv_addr, bytes)
+        .map_err(|e| {
+            for fd in fds {
+                let _ = task.close_fd(fd);
+            }
+            e
+        })
+        .map(|_| 0)
+}
+
 const MAX_SOCKET_ADDR_LEN: u32 = 200;
 fn copy_in_address(task: &Task, addr: Addr, addr_len: u32) -> SysResult<Vec<u8>> {
     if addr_len > MAX_SOCKET_ADDR_LEN {
@@ -90,6 +134,24 @@ pub fn bind(regs: &libc::user_regs_struct) -> super::Result {
     socket.bind(&addr, &*ctx).map(|()| 0)
 }
 
+// shutdown implements linux syscall shutdown(2)
+pub fn shutdown(regs: &libc::user_regs_struct) -> super::Result {
+    let sockfd = regs.rdi as i32;
+    let how = regs.rsi as i32;
+
+    let ctx = context::context();
+    let file = {
+        let mut task = ctx.task_mut();
+        task.get_file(sockfd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?
+    };
+    let file = file.borrow();
+    let socket = file
+        .file_operations::<SocketFile>()
+        .ok_or_else(|| SysError::new(libc::ENOTSOCK))?;
+    socket.shutdown(how, &*ctx).map(|()| 0)
+}
+
 // listen implements linux syscall listen(2)
 pub fn listen(regs: &libc::user_regs_struct) -> super::Result {
     let sockfd = regs.rdi as i32;
@@ -157,7 +219,7 @@ fn send_to(
             ignore_permissions: false,
         },
     )?;
-    socket.send_msg(&mut src, dest.as_deref(), flags, &*ctx)
+    socket.send_msg(&mut src, dest.as_deref(), flags, &[], &*ctx)
 }
 
 // recvfrom implements linux syscall recvfrom(2)
@@ -259,9 +321,9 @@ pub fn setsockopt(regs: &libc::user_regs_struct) -> super::Result {
         task.get_file(sockfd)
             .ok_or_else(|| SysError::new(libc::EBADF))?
     };
-    let file = file.borrow();
+    let mut file = file.borrow_mut();
     let socket = file
-        .file_operations::<SocketFile>()
+        .file_operations_mut::<SocketFile>()
         .ok_or_else(|| SysError::new(libc::ENOTSOCK))?;
     if !(0..=MAX_OPT_LEN).contains(&optlen) {
         bail_libc!(libc::EINVAL);
@@ -286,21 +348,21 @@ pub fn getsockopt(regs: &libc::user_regs_struct) -> super::Result {
         task.get_file(sockfd)
             .ok_or_else(|| SysError::new(libc::EBADF))?
     };
-    let file = file.borrow();
+    let mut file = file.borrow_mut();
     let socket = file
-        .file_operations::<SocketFile>()
+        .file_operations_mut::<SocketFile>()
         .ok_or_else(|| SysError::new(libc::ENOTSOCK))?;
     let task = ctx.task();
     let optlen = {
         let mut optlen = [0; 4];
         task.copy_in_bytes(optlen_addr, &mut optlen)?;
-        i32::from_be_bytes(optlen)
+        i32::from_le_bytes(optlen)
     };
     if optlen < 0 {
         bail_libc!(libc::EINVAL);
     }
     let v = socket.get_sock_opt(level, optname, optlen as u32, &*ctx)?;
-    task.copy_out_bytes(optlen_addr, &v.len().to_le_bytes())?;
+    task.copy_out_bytes(optlen_addr, &(v.len() as u32).to_le_bytes())?;
     task.copy_out_bytes(optval_addr, &v).map(|_| 0)
 }
 
@@ -363,6 +425,14 @@ pub fn sendmmsg(regs: &libc::user_regs_struct) -> super::Result {
 
 fn send_single_msg(sock: &SocketFile, msg: libc::msghdr, flags: i32) -> SysResult<usize> {
     let ctx = context::context();
+    let fds = {
+        let mut task = ctx.task_mut();
+        copy_in_scm_rights(
+            &mut *task,
+            Addr(msg.msg_control as u64),
+            msg.msg_controllen as usize,
+        )?
+    };
     let task = ctx.task();
     let mut src = task.iovecs_io_sequence(
         Addr(msg.msg_iov as u64),
@@ -379,7 +449,215 @@ fn send_single_msg(sock: &SocketFile, msg: libc::msghdr, flags: i32) -> SysResul
             Some(buf)
         }
     };
-    sock.send_msg(&mut src, to.as_deref(), flags, &*ctx)
+    sock.send_msg(&mut src, to.as_deref(), flags, &fds, &*ctx)
+}
+
+// sendmsg implements linux syscall sendmsg(2)
+pub fn sendmsg(regs: &libc::user_regs_struct) -> super::Result {
+    let sockfd = regs.rdi as i32;
+    let msg_addr = Addr(regs.rsi);
+    let mut flags = regs.rdx as i32;
+
+    let ctx = context::context();
+    let file = {
+        let mut task = ctx.task_mut();
+        task.get_file(sockfd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?
+    };
+    let file = file.borrow();
+    let socket = file
+        .file_operations::<SocketFile>()
+        .ok_or_else(|| SysError::new(libc::ENOTSOCK))?;
+    if file.flags().non_blocking {
+        flags |= libc::MSG_DONTWAIT;
+    }
+    let msg = copy_in_msghdr(msg_addr)?;
+    send_single_msg(socket, msg, flags)
+}
+
+// recvmsg implements linux syscall recvmsg(2)
+pub fn recvmsg(regs: &libc::user_regs_struct) -> super::Result {
+    let sockfd = regs.rdi as i32;
+    let msg_addr = Addr(regs.rsi);
+    let mut flags = regs.rdx as i32;
+
+    let ctx = context::context();
+    let file = {
+        let mut task = ctx.task_mut();
+        task.get_file(sockfd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?
+    };
+    let file = file.borrow();
+    let socket = file
+        .file_operations::<SocketFile>()
+        .ok_or_else(|| SysError::new(libc::ENOTSOCK))?;
+    if file.flags().non_blocking {
+        flags |= libc::MSG_DONTWAIT;
+    }
+    let msg = copy_in_msghdr(msg_addr)?;
+    let control_len = msg.msg_controllen as usize;
+    if control_len > MAX_CONTROL_LEN {
+        bail_libc!(libc::ENOBUFS);
+    }
+    let mut dst = ctx.task().iovecs_io_sequence(
+        Addr(msg.msg_iov as u64),
+        msg.msg_iovlen as i32,
+        IoOpts {
+            ignore_permissions: false,
+        },
+    )?;
+    let src_addr_and_len = if msg.msg_name.is_null() {
+        None
+    } else {
+        Some((
+            Addr(msg.msg_name as u64),
+            msg_addr + Addr(MSGHDR_NAMELEN_OFFSET),
+        ))
+    };
+    let (n, files, mut msg_flags) =
+        socket.recv_msg_with_fds(&mut dst, flags, control_len, src_addr_and_len, &*ctx)?;
+
+    let mut task = ctx.task_mut();
+    let mut fds = Vec::with_capacity(files.len());
+    for file in files {
+        let file = Rc::new(RefCell::new(file));
+        let fd_flags = fs::FdFlags {
+            close_on_exec: flags & libc::MSG_CMSG_CLOEXEC != 0,
+        };
+        match task.new_fd_from(0, &file, fd_flags) {
+            Ok(fd) => fds.push(fd),
+            Err(_) => {
+                if let Some(socket) = file.borrow_mut().file_operations_mut::<SocketFile>() {
+                    socket.close_socket(&*ctx);
+                }
+                msg_flags |= libc::MSG_CTRUNC;
+            }
+        }
+    }
+    let control = scm_rights_bytes(&fds, control_len);
+    if !control.is_empty() {
+        task.copy_out_bytes(Addr(msg.msg_control as u64), &control)?;
+    }
+    task.copy_out_bytes(
+        msg_addr + Addr(MSGHDR_CONTROLLEN_OFFSET),
+        &control.len().to_le_bytes(),
+    )?;
+    task.copy_out_bytes(
+        msg_addr + Addr(MSGHDR_FLAGS_OFFSET),
+        &msg_flags.to_le_bytes(),
+    )?;
+    Ok(n)
+}
+
+// Offsets of the fields of msghdr that recvmsg(2) writes back.
+const MSGHDR_NAMELEN_OFFSET: u64 = 8;
+const MSGHDR_CONTROLLEN_OFFSET: u64 = 40;
+const MSGHDR_FLAGS_OFFSET: u64 = 48;
+
+// MAX_CONTROL_LEN caps the size of the control messages of sendmsg(2) and recvmsg(2).
+const MAX_CONTROL_LEN: usize = 10 * 1024 * 1024;
+// SCM_MAX_FD is the maximum number of file descriptors passed in a single SCM_RIGHTS message.
+const SCM_MAX_FD: usize = 253;
+static CMSGHDR_SIZE: usize = std::mem::size_of::<libc::cmsghdr>();
+
+fn copy_in_msghdr(addr: Addr) -> SysResult<libc::msghdr> {
+    let ctx = context::context();
+    let task = ctx.task();
+    let mut buf = vec![0; MSGHDR_SIZE];
+    task.copy_in_bytes(addr, &mut buf)?;
+    Ok(unsafe { std::ptr::read_unaligned(buf.as_ptr() as *const libc::msghdr) })
+}
+
+fn cmsg_align(len: usize) -> usize {
+    let align = std::mem::size_of::<usize>();
+    (len + align - 1) & !(align - 1)
+}
+
+// copy_in_scm_rights reads the control messages of a msghdr and returns the host file descriptors
+// of the guest files passed as SCM_RIGHTS. Only Unix domain sockets have a host file descriptor,
+// and the receiver turns every descriptor it gets back into a socket, so passing any other file
+// fails with EINVAL. Linux would pass it; this is a limitation of sentinel.
+fn copy_in_scm_rights(task: &mut Task, addr: Addr, len: usize) -> SysResult<Vec<RawFd>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    if len > MAX_CONTROL_LEN {
+        bail_libc!(libc::ENOBUFS);
+    }
+    let mut control = vec![0; len];
+    task.copy_in_bytes(addr, &mut control)?;
+
+    let mut host_fds = Vec::new();
+    for fd in parse_scm_rights(&control)? {
+        let file = task
+            .get_file(fd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        let file = file.borrow();
+        let host_fd = file
+            .file_operations::<SocketFile>()
+            .and_then(|socket| socket.host_fd());
+        match host_fd {
+            Some(host_fd) => host_fds.push(host_fd),
+            None => {
+                logger::warn!(
+                    "SCM_RIGHTS only passes unix domain sockets, rejecting fd {} with EINVAL",
+                    fd
+                );
+                bail_libc!(libc::EINVAL);
+            }
+        }
+    }
+    Ok(host_fds)
+}
+
+// parse_scm_rights returns the file descriptors in the SCM_RIGHTS messages of control. Messages of
+// levels other than SOL_SOCKET are ignored, as Linux does.
+fn parse_scm_rights(control: &[u8]) -> SysResult<Vec<i32>> {
+    let mut fds = Vec::new();
+    let mut off = 0;
+    while off + CMSGHDR_SIZE <= control.len() {
+        let hdr =
+            unsafe { std::ptr::read_unaligned(control[off..].as_ptr() as *const libc::cmsghdr) };
+        let msg_len = hdr.cmsg_len as usize;
+        if msg_len < CMSGHDR_SIZE || off + msg_len > control.len() {
+            bail_libc!(libc::EINVAL);
+        }
+        if hdr.cmsg_level == libc::SOL_SOCKET {
+            if hdr.cmsg_type != libc::SCM_RIGHTS {
+                logger::warn!("control message type {} is not supported", hdr.cmsg_type);
+                bail_libc!(libc::EINVAL);
+            }
+            let data = &control[off + cmsg_align(CMSGHDR_SIZE)..off + msg_len];
+            fds.extend(
+                data.chunks_exact(4)
+                    .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            );
+            if fds.len() > SCM_MAX_FD {
+                bail_libc!(libc::EINVAL);
+            }
+        }
+        off += cmsg_align(msg_len);
+    }
+    Ok(fds)
+}
+
+// scm_rights_bytes builds a SCM_RIGHTS control message passing fds, cut to len bytes.
+fn scm_rights_bytes(fds: &[i32], len: usize) -> Vec<u8> {
+    if fds.is_empty() {
+        return Vec::new();
+    }
+    let header_len = cmsg_align(CMSGHDR_SIZE);
+    let msg_len = header_len + fds.len() * 4;
+    let mut control = Vec::with_capacity(cmsg_align(msg_len));
+    control.extend_from_slice(&msg_len.to_le_bytes());
+    control.extend_from_slice(&libc::SOL_SOCKET.to_le_bytes());
+    control.extend_from_slice(&libc::SCM_RIGHTS.to_le_bytes());
+    control.resize(header_len, 0);
+    for fd in fds {
+        control.extend_from_slice(&fd.to_le_bytes());
+    }
+    control.resize(std::cmp::min(cmsg_align(msg_len), len), 0);
+    control
 }
 
 // accept implements linux syscall accept(2)
@@ -409,9 +687,10 @@ fn accept_impl(sockfd: i32, sock_addr: Addr, len_addr: Addr, flags: i32) -> supe
         task.get_file_and_fd_flags(sockfd)
             .ok_or_else(|| SysError::new(libc::EBADF))?
     };
-    let file = file.borrow();
+    let mut file = file.borrow_mut();
+    let file_flags = *file.flags();
     let socket = file
-        .file_operations::<SocketFile>()
+        .file_operations_mut::<SocketFile>()
         .ok_or_else(|| SysError::new(libc::ENOTSOCK))?;
     let addr_and_len = if sock_addr.0 == 0 {
         None
@@ -419,6 +698,6 @@ fn accept_impl(sockfd: i32, sock_addr: Addr, len_addr: Addr, flags: i32) -> supe
         Some((sock_addr, len_addr))
     };
     socket
-        .accept(*file.flags(), fd_flags, addr_and_len, &*ctx)
+        .accept(file_flags, fd_flags, addr_and_len, &*ctx)
         .map(|fd| fd as usize)
 }
@@ -1,12 +1,21 @@
 use std::{cell::RefCell, rc::Rc};
 
-use fs::{attr::stat_from_attrs, DirentRef, File};
+use auth::Context as AuthContext;
+use fs::{
+    attr::{stat_from_attrs, PermMask, TimeSpec},
+    Context, DirentRef, File,
+};
+use linux::Capability;
 use mem::Addr;
-use utils::{bail_libc, SysError, SysResult};
+use time::{Context as TimeContext, Time};
+use utils::{bail_libc, err_libc, SysError, SysResult};
 
 use crate::context;
 
-use super::sys_file::{copy_in_path, file_op_on};
+use super::{
+    sys_file::{copy_in_path, file_op_on},
+    sys_time::copy_in_timespec,
+};
 
 // stat implements linux syscall stat(2)
 pub fn stat(regs: &libc::user_regs_struct) -> super::Result {
@@ -111,3 +120,145 @@ pub fn lstat(regs: &libc::user_regs_struct) -> super::Result {
     })
     .map(|()| 0)
 }
+
+// statfs implements linux syscall statfs(2)
+pub fn statfs(regs: &libc::user_regs_struct) -> super::Result {
+    let addr = Addr(regs.rdi);
+    let statfs_addr = Addr(regs.rsi);
+
+    let (path, _) = copy_in_path(addr, false)?;
+    file_op_on(libc::AT_FDCWD, &path, true, |_, d, _| {
+        statfs_impl(d, statfs_addr)
+    })
+    .map(|()| 0)
+}
+
+// fstatfs implements linux syscall fstatfs(2)
+pub fn fstatfs(regs: &libc::user_regs_struct) -> super::Result {
+    let fd = regs.rdi as i32;
+    let statfs_addr = Addr(regs.rsi);
+    let file = {
+        let ctx = context::context();
+        let mut task = ctx.task_mut();
+        task.get_file(fd).ok_or_else(|| SysError::new(libc::EBADF))
+    }?;
+    let dirent = file.borrow().dirent();
+    statfs_impl(&dirent, statfs_addr).map(|()| 0)
+}
+
+fn statfs_impl(d: &DirentRef, statfs_addr: Addr) -> SysResult<()> {
+    let ctx = &*context::context();
+    let st = d.borrow().inode().mount_source().statfs(ctx)?;
+    let b = unsafe {
+        std::slice::from_raw_parts(
+            &st as *const _ as *const u8,
+            std::mem::size_of::<libc::statfs>(),
+        )
+    };
+    let task = ctx.task();
+    task.copy_out_bytes(statfs_addr, b).map(|_| ())
+}
+
+// utimensat implements linux syscall utimensat(2), which also serves futimens(3) with a null path.
+pub fn utimensat(regs: &libc::user_regs_struct) -> super::Result {
+    let dirfd = regs.rdi as i32;
+    let path_addr = Addr(regs.rsi);
+    let times_addr = Addr(regs.rdx);
+    let flags = regs.r10 as i32;
+
+    if flags & !(libc::AT_SYMLINK_NOFOLLOW | libc::AT_EMPTY_PATH) != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    let (ts, explicit) = copy_in_utimens(times_addr)?;
+
+    let path = if path_addr.0 == 0 {
+        if dirfd == libc::AT_FDCWD {
+            bail_libc!(libc::EFAULT);
+        }
+        String::new()
+    } else {
+        copy_in_path(path_addr, flags & libc::AT_EMPTY_PATH != 0)?.0
+    };
+
+    match path.as_str() {
+        "" => {
+            let dirent = {
+                let ctx = context::context();
+                if dirfd == libc::AT_FDCWD {
+                    ctx.working_directory().clone()
+                } else {
+                    let mut task = ctx.task_mut();
+                    let file = task
+                        .get_file(dirfd)
+                        .ok_or_else(|| SysError::new(libc::EBADF))?;
+                    let dirent = file.borrow().dirent();
+                    dirent
+                }
+            };
+            set_timestamps_impl(&dirent, ts, explicit).map(|()| 0)
+        }
+        path => {
+            let resolve = flags & libc::AT_SYMLINK_NOFOLLOW == 0;
+            file_op_on(dirfd, path, resolve, |_, d, _| {
+                set_timestamps_impl(d, ts, explicit)
+            })
+            .map(|()| 0)
+        }
+    }
+}
+
+// copy_in_utimens reads the access and modification times given to utimensat(2), resolving
+// UTIME_NOW and UTIME_OMIT. It also tells whether either is set to an explicit value, which only
+// the owner of the file may do.
+fn copy_in_utimens(addr: Addr) -> SysResult<(TimeSpec, bool)> {
+    let now = context::context().now();
+    if addr.0 == 0 {
+        let ts = TimeSpec {
+            access_time: Some(now),
+            modification_time: Some(now),
+        };
+        return Ok((ts, false));
+    }
+    let atime = copy_in_timespec(addr)?;
+    let mtime = copy_in_timespec(addr + Addr(std::mem::size_of::<libc::timespec>() as u64))?;
+    let mut explicit = false;
+    let mut resolve = |t: libc::timespec| match t.tv_nsec {
+        libc::UTIME_OMIT => Ok(None),
+        libc::UTIME_NOW => Ok(Some(now)),
+        nsec if (0..1_000_000_000).contains(&nsec) => {
+            explicit = true;
+            Ok(Some(Time::from_unix(t.tv_sec, nsec)))
+        }
+        _ => err_libc!(libc::EINVAL),
+    };
+    let ts = TimeSpec {
+        access_time: resolve(atime)?,
+        modification_time: resolve(mtime)?,
+    };
+    Ok((ts, explicit))
+}
+
+fn set_timestamps_impl(d: &DirentRef, ts: TimeSpec, explicit: bool) -> SysResult<()> {
+    if ts.is_empty() {
+        return Ok(());
+    }
+    let ctx = &*context::context();
+    let mut d = d.borrow_mut();
+    let inode = d.inode_mut();
+    if inode.mount_source().flags().read_only {
+        bail_libc!(libc::EROFS);
+    }
+    let is_owner = inode.unstable_attr()?.owner.uid == ctx.credentials().effective_kuid;
+    if !is_owner && !inode.check_capability(&Capability::fowner(), ctx) {
+        if explicit {
+            bail_libc!(libc::EPERM);
+        }
+        let write = PermMask {
+            read: false,
+            write: true,
+            execute: false,
+        };
+        inode.check_permission(write, ctx)?;
+    }
+    inode.set_timestamps(ts, ctx)
+}
@@ -1,6 +1,6 @@
 use mem::Addr;
 use platform::Context;
-use time::{Clock, HostClock};
+use time::{Clock, MonotonicClock};
 use utils::{bail_libc, err_libc, SysError, SysResult};
 
 use crate::context;
@@ -44,14 +44,31 @@ pub fn clock_nanosleep(regs: &libc::user_regs_struct) -> super::Result {
     }
 
     let c = get_clock(clock_id)?;
-    let start = c.now();
+    let request = time::Time::from_unix(request.tv_sec, request.tv_nsec);
+    if flags & libc::TIMER_ABSTIME != 0 {
+        // remain is left untouched for absolute sleeps, as on linux.
+        c.sleep_until(request);
+        return Ok(0);
+    }
+    sleep_for(&*c, request, remain)
+}
 
+// nanosleep implements linux syscall nanosleep(2)
+pub fn nanosleep(regs: &libc::user_regs_struct) -> super::Result {
+    let request = Addr(regs.rdi);
+    let remain = Addr(regs.rsi);
+
+    let request = copy_in_timespec(request)?;
+    if !is_timespec_valid(&request) {
+        bail_libc!(libc::EINVAL);
+    }
     let request = time::Time::from_unix(request.tv_sec, request.tv_nsec);
-    let duration = if flags & libc::TIMER_ABSTIME != 0 {
-        request - c.now()
-    } else {
-        request
-    };
+    sleep_for(&MonotonicClock, request, remain)
+}
+
+// sleep_for sleeps for duration on c and copies out the unslept time to remain, if given.
+fn sleep_for(c: &dyn Clock, duration: time::Time, remain: Addr) -> super::Result {
+    let start = c.now();
     c.sleep(duration);
     let now = c.now();
     if now - start < duration && remain.0 != 0 {
@@ -63,7 +80,7 @@ pub fn clock_nanosleep(regs: &libc::user_regs_struct) -> super::Result {
 }
 
 // FIXME: naive implementation (return appropriate clock according to the given clock_id)
-fn get_clock(clock_id: i32) -> SysResult<HostClock> {
+fn get_clock(clock_id: i32) -> SysResult<Box<dyn Clock>> {
     let ctx = context::context();
     if clock_id < 0 {
         if !is_valid_cpu_clock(clock_id) {
@@ -78,19 +95,21 @@ fn get_clock(clock_id: i32) -> SysResult<HostClock> {
             bail_libc!(libc::EINVAL);
         }
         match clock_id as u64 & linux::CLOCK_MASK {
-            linux::CPUCLOCK_VIRT => Ok(ctx.real_time_clock()),
-            linux::CPUCLOCK_PROF | linux::CPUCLOCK_SCHED => Ok(ctx.real_time_clock()),
+            linux::CPUCLOCK_VIRT => Ok(Box::new(ctx.real_time_clock())),
+            linux::CPUCLOCK_PROF | linux::CPUCLOCK_SCHED => Ok(Box::new(ctx.real_time_clock())),
             _ => err_libc!(libc::EINVAL),
         }
     } else {
         match clock_id {
-            libc::CLOCK_REALTIME | libc::CLOCK_REALTIME_COARSE => Ok(ctx.real_time_clock()),
+            libc::CLOCK_REALTIME | libc::CLOCK_REALTIME_COARSE => {
+                Ok(Box::new(ctx.real_time_clock()))
+            }
             libc::CLOCK_MONOTONIC
             | libc::CLOCK_MONOTONIC_COARSE
             | libc::CLOCK_MONOTONIC_RAW
-            | libc::CLOCK_BOOTTIME => Ok(ctx.real_time_clock()),
-            libc::CLOCK_PROCESS_CPUTIME_ID => Ok(ctx.real_time_clock()),
-            libc::CLOCK_THREAD_CPUTIME_ID => Ok(ctx.real_time_clock()),
+            | libc::CLOCK_BOOTTIME => Ok(Box::new(MonotonicClock)),
+            libc::CLOCK_PROCESS_CPUTIME_ID => Ok(Box::new(ctx.real_time_clock())),
+            libc::CLOCK_THREAD_CPUTIME_ID => Ok(Box::new(ctx.real_time_clock())),
             _ => err_libc!(libc::EINVAL),
         }
     }
@@ -104,7 +123,7 @@ fn is_valid_cpu_clock(c: i32) -> bool {
     }
 }
 
-fn copy_in_timespec(addr: Addr) -> SysResult<libc::timespec> {
+pub(super) fn copy_in_timespec(addr: Addr) -> SysResult<libc::timespec> {
     let ctx = context::context();
     let task = ctx.task();
     let mut buf = vec![0; 16];
@@ -119,13 +138,13 @@ fn copy_in_timespec(addr: Addr) -> SysResult<libc::timespec> {
     })
 }
 
-fn copy_out_timespec(addr: Addr, ts: &libc::timespec) -> SysResult<usize> {
+pub(super) fn copy_out_timespec(addr: Addr, ts: &libc::timespec) -> SysResult<usize> {
     let ctx = context::context();
     let task = ctx.task();
     let src = [ts.tv_sec.to_le_bytes(), ts.tv_nsec.to_le_bytes()].concat();
     task.copy_out_bytes(addr, &src)
 }
 
-fn is_timespec_valid(ts: &libc::timespec) -> bool {
+pub(super) fn is_timespec_valid(ts: &libc::timespec) -> bool {
     ts.tv_sec >= 0 && ts.tv_nsec >= 0 && ts.tv_nsec < 1_000_000_000
 }
@@ -1,7 +1,15 @@
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+use auth::{credentials::Credentials, Context as AuthContext};
+use utils::{bail_libc, SysError, SysResult};
+
 use crate::context;
 
 use mem::Addr;
 
+// NEW_UTS_LEN is the maximum length of each field of utsname, excluding the NUL terminator.
+const NEW_UTS_LEN: usize = 64;
+
 // uname implements linux syscall uname(2)
 pub fn uname(regs: &libc::user_regs_struct) -> super::Result {
     let ctx = context::context();
@@ -10,19 +18,22 @@ pub fn uname(regs: &libc::user_regs_struct) -> super::Result {
     let task = ctx.task();
     let uts = task.uts_namespace();
 
-    fn string_to_field(s: &str) -> [i8; 65] {
-        let mut field = [0; 65];
-        let bytes = s.as_bytes();
-        field[..bytes.len()].clone_from_slice(&bytes.iter().map(|b| *b as i8).collect::<Vec<i8>>());
+    // string_to_field truncates s so that the field stays NUL terminated.
+    fn string_to_field(s: &str) -> [i8; NEW_UTS_LEN + 1] {
+        let mut field = [0; NEW_UTS_LEN + 1];
+        let len = std::cmp::min(s.len(), NEW_UTS_LEN);
+        for (f, b) in field.iter_mut().zip(&s.as_bytes()[..len]) {
+            *f = *b as i8;
+        }
         field
     }
 
     let utsname = libc::utsname {
         sysname: string_to_field(&version.sysname),
-        nodename: string_to_field(uts.host_name()),
+        nodename: string_to_field(&uts.host_name()),
         release: string_to_field(&version.release),
         version: string_to_field(&version.version),
-        machine: string_to_field("x86_64"),
+        machine: string_to_field(&version.machine),
         domainname: string_to_field(uts.domain_name()),
     };
 
@@ -35,3 +46,86 @@ pub fn uname(regs: &libc::user_regs_struct) -> super::Result {
 
     task.copy_out_bytes(Addr(regs.rdi), utsname).map(|_| 0)
 }
+
+// check_set_name checks that the task holding creds may change a name of the UTS namespace to
+// the one of length len, and returns the length.
+fn check_set_name(creds: &Credentials, len: i32) -> SysResult<usize> {
+    if !creds.has_capability(&linux::Capability::cap_sys_admin()) {
+        bail_libc!(libc::EPERM);
+    }
+    if len < 0 || len as usize > NEW_UTS_LEN {
+        bail_libc!(libc::EINVAL);
+    }
+    Ok(len as usize)
+}
+
+// copy_in_name copies in the name given to sethostname(2) or setdomainname(2). Like Linux, the
+// name ends at the first NUL byte if any.
+fn copy_in_name(addr: Addr, len: i32) -> SysResult<Vec<u8>> {
+    let ctx = context::context();
+    let len = check_set_name(ctx.credentials(), len)?;
+    let mut name = vec![0; len];
+    if ctx.task().copy_in_bytes(addr, &mut name)? < len {
+        bail_libc!(libc::EFAULT);
+    }
+    if let Some(nul) = name.iter().position(|b| *b == 0) {
+        name.truncate(nul);
+    }
+    Ok(name)
+}
+
+// sethostname implements linux syscall sethostname(2)
+pub fn sethostname(regs: &libc::user_regs_struct) -> super::Result {
+    let addr = Addr(regs.rdi);
+    let len = regs.rsi as i32;
+
+    let name = copy_in_name(addr, len)?;
+    nix::unistd::sethostname(OsStr::from_bytes(&name)).map_err(SysError::from_nix_errno)?;
+    Ok(0)
+}
+
+// setdomainname implements linux syscall setdomainname(2)
+pub fn setdomainname(regs: &libc::user_regs_struct) -> super::Result {
+    let addr = Addr(regs.rdi);
+    let len = regs.rsi as i32;
+
+    let name = copy_in_name(addr, len)?;
+    let ctx = context::context();
+    let mut task = ctx.task_mut();
+    task.uts_namespace_mut()
+        .set_domain_name(String::from_utf8_lossy(&name).into_owned());
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use auth::{
+        id::{Kgid, Kuid},
+        user_namespace::UserNamespace,
+    };
+
+    use super::*;
+
+    #[test]
+    fn set_name_needs_cap_sys_admin() {
+        let ns = Rc::new(UserNamespace::new_root());
+        let root = Credentials::new_root(Rc::clone(&ns));
+        assert_eq!(check_set_name(&root, 8), Ok(8));
+        assert_eq!(check_set_name(&root, NEW_UTS_LEN as i32), Ok(NEW_UTS_LEN));
+        assert_eq!(
+            check_set_name(&root, NEW_UTS_LEN as i32 + 1),
+            Err(SysError::new(libc::EINVAL))
+        );
+        assert_eq!(check_set_name(&root, -1), Err(SysError::new(libc::EINVAL)));
+
+        let user = Credentials::new_user(Kuid(1000), Kgid(1000), None, ns);
+        assert_eq!(check_set_name(&user, 8), Err(SysError::new(libc::EPERM)));
+        // The permission is checked before the length.
+        assert_eq!(
+            check_set_name(&user, NEW_UTS_LEN as i32 + 1),
+            Err(SysError::new(libc::EPERM))
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use fs::File;
 use mem::{Addr, IoOpts, IoSequence};
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, cmp::min, rc::Rc};
 use utils::{bail_libc, SysError, SysErrorKind, SysResult};
 
-use crate::context;
+use crate::{context, kernel::task::MAX_RW_COUNT};
 
 // write implements linux syscall write(2)
 pub fn write(regs: &libc::user_regs_struct) -> super::Result {
@@ -58,3 +58,120 @@ pub fn writev(regs: &libc::user_regs_struct) -> super::Result {
     let mut src = task.iovecs_io_sequence(addr, count, IoOpts::default())?;
     writev_impl(&file, &mut src)
 }
+
+// copy_file_range implements linux syscall copy_file_range(2)
+pub fn copy_file_range(regs: &libc::user_regs_struct) -> super::Result {
+    let in_fd = regs.rdi as i32;
+    let in_offset_addr = Addr(regs.rsi);
+    let out_fd = regs.rdx as i32;
+    let out_offset_addr = Addr(regs.r10);
+    let len = min(regs.r8, MAX_RW_COUNT) as usize;
+    let flags = regs.r9 as u32;
+
+    if flags != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+
+    let ctx = &*context::context();
+    let (in_file, out_file) = {
+        let mut task = ctx.task_mut();
+        let in_file = task
+            .get_file(in_fd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        let out_file = task
+            .get_file(out_fd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        (in_file, out_file)
+    };
+    let in_file = in_file.borrow();
+    let out_file = out_file.borrow();
+    if !in_file.flags().read || !out_file.flags().write || out_file.flags().append {
+        bail_libc!(libc::EBADF);
+    }
+    let in_attr = in_file.dirent().borrow().inode().stable_attr();
+    let out_attr = out_file.dirent().borrow().inode().stable_attr();
+    if in_attr.is_directory() || out_attr.is_directory() {
+        bail_libc!(libc::EISDIR);
+    }
+    if !in_attr.is_regular() || !out_attr.is_regular() {
+        bail_libc!(libc::EINVAL);
+    }
+
+    let in_offset = copy_in_offset(in_offset_addr)?;
+    let out_offset = copy_in_offset(out_offset_addr)?;
+    if in_attr.device_id == out_attr.device_id && in_attr.inode_id == out_attr.inode_id {
+        let src = in_offset.unwrap_or_else(|| in_file.offset());
+        let dst = out_offset.unwrap_or_else(|| out_file.offset());
+        if src < dst.saturating_add(len as i64) && dst < src.saturating_add(len as i64) {
+            bail_libc!(libc::EINVAL);
+        }
+    }
+
+    let n = out_file.copy_range_from(&in_file, in_offset, out_offset, len, ctx)?;
+    if let Some(offset) = in_offset {
+        copy_out_offset(in_offset_addr, offset + n as i64)?;
+    }
+    if let Some(offset) = out_offset {
+        copy_out_offset(out_offset_addr, offset + n as i64)?;
+    }
+    Ok(n)
+}
+
+// sendfile implements linux syscall sendfile(2)
+pub fn sendfile(regs: &libc::user_regs_struct) -> super::Result {
+    let out_fd = regs.rdi as i32;
+    let in_fd = regs.rsi as i32;
+    let offset_addr = Addr(regs.rdx);
+    let count = min(regs.r10, MAX_RW_COUNT) as usize;
+
+    let ctx = &*context::context();
+    let (in_file, out_file) = {
+        let mut task = ctx.task_mut();
+        let in_file = task
+            .get_file(in_fd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        let out_file = task
+            .get_file(out_fd)
+            .ok_or_else(|| SysError::new(libc::EBADF))?;
+        (in_file, out_file)
+    };
+    let in_file = in_file.borrow();
+    let out_file = out_file.borrow();
+    if !in_file.flags().read || !out_file.flags().write {
+        bail_libc!(libc::EBADF);
+    }
+    if out_file.flags().append {
+        bail_libc!(libc::EINVAL);
+    }
+
+    let offset = copy_in_offset(offset_addr)?;
+    if offset.is_some() && !in_file.flags().pread {
+        bail_libc!(libc::ESPIPE);
+    }
+    let n = out_file.copy_range_from(&in_file, offset, None, count, ctx)?;
+    if let Some(offset) = offset {
+        copy_out_offset(offset_addr, offset + n as i64)?;
+    }
+    Ok(n)
+}
+
+// copy_in_offset reads the loff_t at addr, where a null addr means the file offset.
+fn copy_in_offset(addr: Addr) -> SysResult<Option<i64>> {
+    if addr.0 == 0 {
+        return Ok(None);
+    }
+    let mut b = [0; std::mem::size_of::<i64>()];
+    let ctx = context::context();
+    ctx.task().copy_in_bytes(addr, &mut b)?;
+    let offset = i64::from_le_bytes(b);
+    if offset < 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    Ok(Some(offset))
+}
+
+fn copy_out_offset(addr: Addr, offset: i64) -> SysResult<()> {
+    let ctx = context::context();
+    ctx.task().copy_out_bytes(addr, &offset.to_le_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use fs::DirentRef;
+use mem::Addr;
+use utils::{bail_libc, err_libc, SysError, SysResult};
+
+use crate::context;
+
+use super::sys_file::{copy_in_path, file_op_on};
+
+// getxattr implements linux syscall getxattr(2)
+pub fn getxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = path_dirent(Addr(regs.rdi), true)?;
+    get_xattr(&d, Addr(regs.rsi), Addr(regs.rdx), regs.r10 as usize)
+}
+
+// lgetxattr implements linux syscall lgetxattr(2)
+pub fn lgetxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = path_dirent(Addr(regs.rdi), false)?;
+    get_xattr(&d, Addr(regs.rsi), Addr(regs.rdx), regs.r10 as usize)
+}
+
+// fgetxattr implements linux syscall fgetxattr(2)
+pub fn fgetxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = fd_dirent(regs.rdi as i32)?;
+    get_xattr(&d, Addr(regs.rsi), Addr(regs.rdx), regs.r10 as usize)
+}
+
+// setxattr implements linux syscall setxattr(2)
+pub fn setxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = path_dirent(Addr(regs.rdi), true)?;
+    set_xattr(
+        &d,
+        Addr(regs.rsi),
+        Addr(regs.rdx),
+        regs.r10 as usize,
+        regs.r8 as i32,
+    )
+}
+
+// lsetxattr implements linux syscall lsetxattr(2)
+pub fn lsetxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = path_dirent(Addr(regs.rdi), false)?;
+    set_xattr(
+        &d,
+        Addr(regs.rsi),
+        Addr(regs.rdx),
+        regs.r10 as usize,
+        regs.r8 as i32,
+    )
+}
+
+// fsetxattr implements linux syscall fsetxattr(2)
+pub fn fsetxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = fd_dirent(regs.rdi as i32)?;
+    set_xattr(
+        &d,
+        Addr(regs.rsi),
+        Addr(regs.rdx),
+        regs.r10 as usize,
+        regs.r8 as i32,
+    )
+}
+
+// listxattr implements linux syscall listxattr(2)
+pub fn listxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = path_dirent(Addr(regs.rdi), true)?;
+    list_xattr(&d, Addr(regs.rsi), regs.rdx as usize)
+}
+
+// llistxattr implements linux syscall llistxattr(2)
+pub fn llistxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = path_dirent(Addr(regs.rdi), false)?;
+    list_xattr(&d, Addr(regs.rsi), regs.rdx as usize)
+}
+
+// flistxattr implements linux syscall flistxattr(2)
+pub fn flistxattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = fd_dirent(regs.rdi as i32)?;
+    list_xattr(&d, Addr(regs.rsi), regs.rdx as usize)
+}
+
+// removexattr implements linux syscall removexattr(2)
+pub fn removexattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = path_dirent(Addr(regs.rdi), true)?;
+    remove_xattr(&d, Addr(regs.rsi))
+}
+
+// lremovexattr implements linux syscall lremovexattr(2)
+pub fn lremovexattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = path_dirent(Addr(regs.rdi), false)?;
+    remove_xattr(&d, Addr(regs.rsi))
+}
+
+// fremovexattr implements linux syscall fremovexattr(2)
+pub fn fremovexattr(regs: &libc::user_regs_struct) -> super::Result {
+    let d = fd_dirent(regs.rdi as i32)?;
+    remove_xattr(&d, Addr(regs.rsi))
+}
+
+// path_dirent resolves the path at addr, following the symbolic link at its end if resolve is
+// set.
+fn path_dirent(addr: Addr, resolve: bool) -> SysResult<DirentRef> {
+    let (path, is_dir) = copy_in_path(addr, false)?;
+    let mut dirent = None;
+    file_op_on(libc::AT_FDCWD, &path, resolve || is_dir, |_, d, _| {
+        dirent = Some(d.clone());
+        Ok(())
+    })?;
+    Ok(dirent.unwrap())
+}
+
+fn fd_dirent(fd: i32) -> SysResult<DirentRef> {
+    let ctx = context::context();
+    let mut task = ctx.task_mut();
+    let file = task
+        .get_file(fd)
+        .ok_or_else(|| SysError::new(libc::EBADF))?;
+    let dirent = file.borrow().dirent();
+    Ok(dirent)
+}
+
+fn copy_in_name(addr: Addr) -> SysResult<String> {
+    let ctx = context::context();
+    let mut task = ctx.task_mut();
+    match task.copy_in_string(addr, linux::XATTR_NAME_MAX + 1) {
+        Err(err) if err.code() == libc::ENAMETOOLONG => err_libc!(libc::ERANGE),
+        ret => ret,
+    }
+}
+
+// copy_out_value copies value out to the buffer of size bytes at addr. A zero size only asks for
+// the length of value.
+fn copy_out_value(addr: Addr, size: usize, value: &[u8]) -> super::Result {
+    if size == 0 {
+        return Ok(value.len());
+    }
+    if value.len() > size {
+        bail_libc!(libc::ERANGE);
+    }
+    let ctx = context::context();
+    let task = ctx.task();
+    task.copy_out_bytes(addr, value)?;
+    Ok(value.len())
+}
+
+fn get_xattr(d: &DirentRef, name_addr: Addr, value_addr: Addr, size: usize) -> super::Result {
+    let name = copy_in_name(name_addr)?;
+    let ctx = &*context::context();
+    let value = d.borrow().inode().get_xattr(&name, ctx)?;
+    copy_out_value(value_addr, size, &value)
+}
+
+fn set_xattr(
+    d: &DirentRef,
+    name_addr: Addr,
+    value_addr: Addr,
+    size: usize,
+    flags: i32,
+) -> super::Result {
+    if flags & !(libc::XATTR_CREATE | libc::XATTR_REPLACE) != 0 {
+        bail_libc!(libc::EINVAL);
+    }
+    if size > linux::XATTR_SIZE_MAX {
+        bail_libc!(libc::E2BIG);
+    }
+    let name = copy_in_name(name_addr)?;
+    let ctx = &*context::context();
+    let mut value = vec![0; size];
+    if ctx.task().copy_in_bytes(value_addr, &mut value)? < size {
+        bail_libc!(libc::EFAULT);
+    }
+    d.borrow_mut()
+        .inode_mut()
+        .set_xattr(&name, &value, flags, ctx)
+        .map(|()| 0)
+}
+
+fn list_xattr(d: &DirentRef, list_addr: Addr, size: usize) -> super::Result {
+    let ctx = &*context::context();
+    let names = d.borrow().inode().list_xattr(ctx)?;
+    let mut list = Vec::new();
+    for name in names {
+        list.extend_from_slice(name.as_bytes());
+        list.push(0);
+    }
+    if list.len() > linux::XATTR_LIST_MAX {
+        bail_libc!(libc::E2BIG);
+    }
+    copy_out_value(list_addr, size, &list)
+}
+
+fn remove_xattr(d: &DirentRef, name_addr: Addr) -> super::Result {
+    let name = copy_in_name(name_addr)?;
+    let ctx = &*context::context();
+    d.borrow_mut()
+        .inode_mut()
+        .remove_xattr(&name, ctx)
+        .map(|()| 0)
+}
@@ -93,10 +93,36 @@ fn get_clone_flag(namespace_type: LinuxNamespaceType) -> CloneFlags {
 pub struct SentinelConfig {
     hooks: Option<Hooks>,
     pub state: State,
+    #[serde(default)]
+    pub uname: UnameConfig,
 }
 
 static SENTINEL_CONFIG_NAME: &str = "sentinel_config.json";
 
+static UNAME_SYSNAME_ANNOTATION: &str = "dev.sentinel.uname.sysname";
+static UNAME_RELEASE_ANNOTATION: &str = "dev.sentinel.uname.release";
+static UNAME_MACHINE_ANNOTATION: &str = "dev.sentinel.uname.machine";
+
+// UnameConfig overrides the fields of uname(2) the guest sees. They are given as the
+// annotations "dev.sentinel.uname.{sysname,release,machine}" of the spec.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct UnameConfig {
+    pub sysname: Option<String>,
+    pub release: Option<String>,
+    pub machine: Option<String>,
+}
+
+impl UnameConfig {
+    fn from_annotations(annotations: Option<&HashMap<String, String>>) -> Self {
+        let get = |key: &str| annotations.and_then(|a| a.get(key)).cloned();
+        Self {
+            sysname: get(UNAME_SYSNAME_ANNOTATION),
+            release: get(UNAME_RELEASE_ANNOTATION),
+            machine: get(UNAME_MACHINE_ANNOTATION),
+        }
+    }
+}
+
 macro_rules! define_run_hooks {
     ($fn_name:ident, $fn:ident) => {
         pub fn $fn_name(&self) -> anyhow::Result<()> {
@@ -131,6 +157,7 @@ impl SentinelConfig {
         Self {
             hooks: spec.hooks().clone(),
             state,
+            uname: UnameConfig::from_annotations(spec.annotations().as_ref()),
         }
     }
 
@@ -229,6 +256,28 @@ impl State {
     pub fn container_id(&self) -> &str {
         &self.id
     }
+
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
+    }
+
+    pub fn status(&self) -> &ContainerStatus {
+        &self.status
+    }
+
+    // refresh_status marks the container stopped once its process is gone. A container that is
+    // still being created may not have a process yet, so it's left as is.
+    pub fn refresh_status(&mut self) {
+        if matches!(self.status, ContainerStatus::Creating) {
+            return;
+        }
+        let alive = self
+            .pid
+            .map_or(false, |pid| signal::kill(Pid::from_raw(pid), None).is_ok());
+        if !alive {
+            self.status = ContainerStatus::Stopped;
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -245,3 +294,82 @@ impl Default for ContainerStatus {
         Self::Creating
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use oci_spec::runtime::{HookBuilder, HooksBuilder};
+
+    use super::*;
+
+    #[test]
+    fn prestart_and_poststart_hooks() {
+        let dir = std::env::temp_dir().join(format!("sentinel-hooks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let marker = dir.join("marker");
+        // The hook writes the state it is given on stdin to the marker file.
+        let write_marker = HookBuilder::default()
+            .path("/bin/sh")
+            .args(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "cat > \"$MARKER\"".to_string(),
+            ])
+            .env(vec![format!("MARKER={}", marker.display())])
+            .build()
+            .unwrap();
+        let fail = HookBuilder::default().path("/bin/false").build().unwrap();
+        let mut config = SentinelConfig {
+            hooks: Some(
+                HooksBuilder::default()
+                    .prestart(vec![fail])
+                    .poststart(vec![write_marker])
+                    .build()
+                    .unwrap(),
+            ),
+            ..Default::default()
+        };
+
+        assert!(config.run_prestart_hooks().is_err());
+
+        config.state.set_status(ContainerStatus::Running);
+        config.run_poststart_hooks().unwrap();
+        let state = std::fs::read_to_string(&marker).unwrap();
+        assert!(state.contains("\"status\":\"running\""), "{}", state);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saved_state() {
+        let dir = std::env::temp_dir().join(format!("sentinel-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+        let mut config = SentinelConfig::default();
+        config.state.id = "test".to_string();
+        config.state.set_pid(Some(pid));
+        config.state.set_status(ContainerStatus::Running);
+        config.save(&dir).unwrap();
+
+        let mut config = SentinelConfig::load(&dir).unwrap();
+        config.state.refresh_status();
+        assert_eq!(config.state.pid(), Some(pid));
+        assert!(matches!(config.state.status(), ContainerStatus::Running));
+        let state: serde_json::Value = serde_json::to_value(&config.state).unwrap();
+        assert_eq!(state["id"], "test");
+        assert_eq!(state["status"], "running");
+        assert_eq!(state["pid"], pid);
+        assert!(state.get("ociVersion").is_some() && state.get("bundle").is_some());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        let mut config = SentinelConfig::load(&dir).unwrap();
+        config.state.refresh_status();
+        assert!(matches!(config.state.status(), ContainerStatus::Stopped));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,4 +1,7 @@
 use std::ops::{Add, Sub};
+use std::time::Instant;
+
+use once_cell::sync::Lazy;
 
 #[derive(Clone, Copy, Default, Debug, PartialEq, PartialOrd)]
 pub struct Time {
@@ -35,6 +38,10 @@ impl Time {
     pub fn seconds(&self) -> i64 {
         (self.ns / (1e9 as u128)) as i64
     }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.ns.checked_sub(rhs.ns).map(|ns| Time { ns })
+    }
 }
 
 impl Add for Time {
@@ -46,11 +53,12 @@ impl Add for Time {
     }
 }
 
+// Times are unsigned, so subtracting a later time yields zero instead of underflowing.
 impl Sub for Time {
     type Output = Time;
     fn sub(self, rhs: Self) -> Self::Output {
         Time {
-            ns: self.ns - rhs.ns,
+            ns: self.ns.saturating_sub(rhs.ns),
         }
     }
 }
@@ -58,6 +66,17 @@ impl Sub for Time {
 pub trait Clock {
     fn now(&self) -> Time;
     fn sleep(&self, duration: Time);
+    // sleep_until sleeps until the clock reaches deadline, returning at once if it already has.
+    // The remaining time is recomputed after each wakeup, since the clock may have been set
+    // while sleeping.
+    fn sleep_until(&self, deadline: Time) {
+        while let Some(remaining) = deadline.checked_sub(self.now()) {
+            if remaining == Time::default() {
+                break;
+            }
+            self.sleep(remaining);
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -81,6 +100,71 @@ impl Clock for HostClock {
     }
 }
 
+// MONOTONIC_BASE is the instant MonotonicClock counts from, taken the first time it is read.
+static MONOTONIC_BASE: Lazy<Instant> = Lazy::new(Instant::now);
+
+// MonotonicClock never goes backward, unlike HostClock which follows the host's wall clock.
+#[derive(Clone, Copy, Debug)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Time {
+        Time {
+            ns: MONOTONIC_BASE.elapsed().as_nanos(),
+        }
+    }
+
+    fn sleep(&self, duration: Time) {
+        std::thread::sleep(std::time::Duration::from_nanos(duration.ns as u64));
+    }
+}
+
 pub trait Context {
+    // now returns the realtime clock's current time.
     fn now(&self) -> Time;
+    fn monotonic_now(&self) -> Time;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monotonic_clock_never_decreases() {
+        let mut prev = MonotonicClock.now();
+        for _ in 0..1000 {
+            let now = MonotonicClock.now();
+            assert!(now >= prev);
+            prev = now;
+        }
+        MonotonicClock.sleep(Time::from_unix(0, 1_000_000));
+        assert!(MonotonicClock.now() - prev >= Time::from_unix(0, 1_000_000));
+    }
+
+    #[test]
+    fn sleep_until_past_deadline() {
+        let start = std::time::Instant::now();
+        let now = HostClock.now();
+        HostClock.sleep_until(now - Time::from_unix(10, 0));
+        HostClock.sleep_until(Time::default());
+        MonotonicClock.sleep_until(Time::default());
+        assert!(start.elapsed() < std::time::Duration::from_millis(100));
+    }
+
+    #[test]
+    fn sleep_until_future_deadline() {
+        let deadline = MonotonicClock.now() + Time::from_unix(0, 10_000_000);
+        MonotonicClock.sleep_until(deadline);
+        assert!(MonotonicClock.now() >= deadline);
+    }
+
+    #[test]
+    fn sub_does_not_underflow() {
+        let earlier = Time::from_unix(1, 0);
+        let later = Time::from_unix(2, 0);
+        assert_eq!(later - earlier, Time::from_unix(1, 0));
+        assert_eq!(earlier - later, Time::default());
+        assert_eq!(earlier.checked_sub(later), None);
+        assert_eq!(later.checked_sub(earlier), Some(Time::from_unix(1, 0)));
+    }
 }
@@ -1,6 +1,6 @@
 pub mod memory;
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
 pub enum MemoryKind {
     System,
     Anonymous,
@@ -89,7 +89,7 @@ impl SysError {
 
     pub fn exceeds_file_size_limit() -> Self {
         Self {
-            code: -1,
+            code: libc::EFBIG,
             desc: Some("exceeds file size limit".to_string()),
             kind: SysErrorKind::ExceedsFileSizeLimit,
         }
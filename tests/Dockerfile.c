@@ -5,4 +5,59 @@ COPY ./app/c/* /home/
 RUN gcc -o /home/echo /home/echo.c
 RUN gcc -o /home/hello_world /home/hello_world.c
 RUN gcc -o /home/open /home/open.c
+RUN gcc -o /home/segv_handler /home/segv_handler.c
+RUN gcc -o /home/listen_poll /home/listen_poll.c
+RUN gcc -o /home/accept /home/accept.c
+RUN gcc -o /home/reuseaddr /home/reuseaddr.c
+RUN gcc -o /home/so_error /home/so_error.c
+RUN gcc -o /home/sockbuf /home/sockbuf.c
+RUN gcc -o /home/fionread /home/fionread.c
+RUN gcc -o /home/rlimit_fsize /home/rlimit_fsize.c
+RUN gcc -o /home/fpu_signal /home/fpu_signal.c
+RUN gcc -o /home/udp_sockname /home/udp_sockname.c
+RUN gcc -o /home/close_rebind /home/close_rebind.c
+RUN gcc -o /home/broadcast /home/broadcast.c
+RUN gcc -o /home/dup2 /home/dup2.c
+RUN gcc -o /home/fcntl /home/fcntl.c
+RUN gcc -o /home/symlink /home/symlink.c
+RUN gcc -o /home/getcwd /home/getcwd.c
+RUN gcc -o /home/chdir /home/chdir.c
+RUN gcc -o /home/mincore /home/mincore.c
+RUN gcc -o /home/madvise /home/madvise.c
+RUN gcc -o /home/socketpair /home/socketpair.c
+RUN gcc -o /home/scm_rights /home/scm_rights.c
+RUN gcc -o /home/mounts /home/mounts.c
+RUN gcc -o /home/seccomp /home/seccomp.c
+RUN gcc -o /home/masked_paths /home/masked_paths.c
+RUN gcc -o /home/prlimit /home/prlimit.c
+RUN gcc -o /home/multicast /home/multicast.c
+RUN gcc -o /home/sock_timeout /home/sock_timeout.c
+RUN gcc -o /home/eventfd /home/eventfd.c
+RUN gcc -o /home/pipe_packet /home/pipe_packet.c
+RUN gcc -o /home/ppoll /home/ppoll.c
+RUN gcc -o /home/epoll /home/epoll.c
+RUN gcc -o /home/fallocate /home/fallocate.c
+RUN gcc -o /home/getrandom /home/getrandom.c
+RUN gcc -o /home/proc_self /home/proc_self.c
+RUN gcc -o /home/uname /home/uname.c
+RUN gcc -o /home/dev_files /home/dev_files.c
+RUN gcc -o /home/statfs /home/statfs.c
+RUN gcc -o /home/utimensat /home/utimensat.c
+RUN gcc -o /home/renameat2 /home/renameat2.c
+RUN gcc -o /home/link /home/link.c
+RUN gcc -o /home/xattr /home/xattr.c
+RUN gcc -o /home/copy_file_range /home/copy_file_range.c
+RUN gcc -o /home/sendfile /home/sendfile.c
+RUN gcc -o /home/flock /home/flock.c
+RUN gcc -o /home/fcntl_lock /home/fcntl_lock.c
+RUN gcc -o /home/mlock /home/mlock.c
+RUN gcc -o /home/map_populate /home/map_populate.c
+RUN gcc -o /home/growsdown /home/growsdown.c
+RUN gcc -o /home/mprotect /home/mprotect.c
+RUN gcc -o /home/mremap /home/mremap.c
+RUN gcc -o /home/process_vm /home/process_vm.c
+RUN gcc -o /home/bind_port /home/bind_port.c
+RUN gcc -o /home/ipv6_tcp /home/ipv6_tcp.c
+RUN gcc -o /home/tcp_info /home/tcp_info.c
+RUN gcc -o /home/sock_type /home/sock_type.c
 CMD ["bash"]
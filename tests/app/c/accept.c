@@ -0,0 +1,93 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+int main() {
+  int listener = socket(AF_INET, SOCK_STREAM, 0);
+  if (listener < 0) {
+    printf("socket failed\n");
+    return 1;
+  }
+
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8081);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  if (bind(listener, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("bind failed\n");
+    return 1;
+  }
+  if (listen(listener, 2) < 0) {
+    printf("listen failed\n");
+    return 1;
+  }
+
+  int flags = fcntl(listener, F_GETFL);
+  fcntl(listener, F_SETFL, flags | O_NONBLOCK);
+  if (accept(listener, NULL, NULL) >= 0 ||
+      (errno != EAGAIN && errno != EWOULDBLOCK)) {
+    printf("non-blocking accept on an empty queue did not fail with EAGAIN\n");
+    return 1;
+  }
+  printf("empty queue: EAGAIN\n");
+  fcntl(listener, F_SETFL, flags);
+
+  int clients[2];
+  for (int i = 0; i < 2; i++) {
+    clients[i] = socket(AF_INET, SOCK_STREAM, 0);
+    if (connect(clients[i], (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+      printf("connect %d failed\n", i);
+      return 1;
+    }
+  }
+
+  for (int i = 0; i < 2; i++) {
+    struct sockaddr_in peer;
+    socklen_t len = sizeof(peer);
+    int conn = accept(listener, (struct sockaddr *)&peer, &len);
+    if (conn < 0) {
+      printf("accept %d failed\n", i);
+      return 1;
+    }
+    printf("accepted %d from %s\n", i, inet_ntoa(peer.sin_addr));
+    close(conn);
+  }
+
+  // A SOCK_NONBLOCK listener fails right away on an empty queue and takes a pending connection
+  // without blocking.
+  int nb_listener = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+  addr.sin_port = htons(8092);
+  if (nb_listener < 0 ||
+      bind(nb_listener, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+      listen(nb_listener, 1) < 0) {
+    printf("non-blocking listener setup failed\n");
+    return 1;
+  }
+  if (accept(nb_listener, NULL, NULL) >= 0 ||
+      (errno != EAGAIN && errno != EWOULDBLOCK)) {
+    printf("SOCK_NONBLOCK accept on an empty queue did not fail with EAGAIN\n");
+    return 1;
+  }
+  printf("SOCK_NONBLOCK empty queue: EAGAIN\n");
+  int client = socket(AF_INET, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("connect to the non-blocking listener failed\n");
+    return 1;
+  }
+  int conn = accept(nb_listener, NULL, NULL);
+  printf("SOCK_NONBLOCK pending connection: %s\n", conn >= 0 ? "accepted" : "failed");
+  close(conn);
+  close(client);
+  close(nb_listener);
+
+  close(clients[0]);
+  close(clients[1]);
+  close(listener);
+  return 0;
+}
@@ -0,0 +1,44 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int bind_socket(int type, int port) {
+  int fd = socket(AF_INET, type, 0);
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(port);
+  addr.sin_addr.s_addr = htonl(INADDR_ANY);
+  if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    int err = errno;
+    close(fd);
+    errno = err;
+    return -1;
+  }
+  return fd;
+}
+
+int main() {
+  // A port in the ephemeral range is reserved once bound.
+  int first = bind_socket(SOCK_STREAM, 50000);
+  printf("first bind: %d\n", first >= 0);
+  int ret = bind_socket(SOCK_STREAM, 50000);
+  printf("second bind: %d, EADDRINUSE: %d\n", ret, errno == EADDRINUSE);
+
+  // The port is only taken for the protocol it was bound with.
+  int udp = bind_socket(SOCK_DGRAM, 50000);
+  printf("udp bind: %d\n", udp >= 0);
+
+  // Closing the socket releases the port.
+  close(first);
+  int again = bind_socket(SOCK_STREAM, 50000);
+  printf("bind after close: %d\n", again >= 0);
+
+  close(again);
+  close(udp);
+  return 0;
+}
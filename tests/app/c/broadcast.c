@@ -0,0 +1,46 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+int main() {
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8089);
+  addr.sin_addr.s_addr = htonl(INADDR_BROADCAST);
+
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  int on = -1;
+  socklen_t len = sizeof(on);
+  getsockopt(fd, SOL_SOCKET, SO_BROADCAST, &on, &len);
+  printf("SO_BROADCAST by default: %d\n", on);
+
+  if (sendto(fd, "hello", 5, 0, (struct sockaddr *)&addr, sizeof(addr)) < 0 && errno == EACCES) {
+    printf("broadcast without SO_BROADCAST: EACCES\n");
+  } else {
+    printf("broadcast without SO_BROADCAST did not fail with EACCES\n");
+  }
+
+  on = 1;
+  if (setsockopt(fd, SOL_SOCKET, SO_BROADCAST, &on, sizeof(on)) < 0) {
+    printf("setsockopt failed\n");
+    return 1;
+  }
+  on = -1;
+  len = sizeof(on);
+  getsockopt(fd, SOL_SOCKET, SO_BROADCAST, &on, &len);
+  printf("SO_BROADCAST after setsockopt: %d\n", on);
+
+  // Whether the datagram goes out depends on the routes, so only check it isn't refused.
+  if (sendto(fd, "hello", 5, 0, (struct sockaddr *)&addr, sizeof(addr)) < 0 && errno == EACCES) {
+    printf("broadcast with SO_BROADCAST failed with EACCES\n");
+  } else {
+    printf("broadcast with SO_BROADCAST was attempted\n");
+  }
+  close(fd);
+  return 0;
+}
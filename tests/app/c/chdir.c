@@ -0,0 +1,46 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+static void print_cwd(void) {
+  char buf[256];
+  if (getcwd(buf, sizeof(buf)) == NULL) {
+    printf("getcwd failed\n");
+    return;
+  }
+  printf("cwd: %s\n", buf);
+}
+
+int main() {
+  printf("chdir(\"/etc\"): %d\n", chdir("/etc"));
+  int fd = open("passwd", O_RDONLY);
+  printf("open(\"passwd\") relative to /etc: %s\n", fd >= 0 ? "ok" : "failed");
+  close(fd);
+  if (chdir("/etc/passwd") < 0 && errno == ENOTDIR) {
+    printf("chdir to a regular file: ENOTDIR\n");
+  }
+
+  // ".." never leaves the root.
+  printf("chdir(\"/../..\"): %d\n", chdir("/../.."));
+  print_cwd();
+
+  int dir = open("/usr", O_RDONLY | O_DIRECTORY);
+  printf("fchdir: %d\n", fchdir(dir));
+  print_cwd();
+  fd = open("bin", O_RDONLY | O_DIRECTORY);
+  printf("open(\"bin\") relative to /usr: %s\n", fd >= 0 ? "ok" : "failed");
+  close(fd);
+  close(dir);
+
+  fd = open("/etc/passwd", O_RDONLY);
+  if (fchdir(fd) < 0 && errno == ENOTDIR) {
+    printf("fchdir on a regular file: ENOTDIR\n");
+  }
+  close(fd);
+  if (fchdir(100) < 0 && errno == EBADF) {
+    printf("fchdir on a closed fd: EBADF\n");
+  }
+  print_cwd();
+  return 0;
+}
@@ -0,0 +1,51 @@
+#include <arpa/inet.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+// bind_twice binds a socket of type to port, closes it, and binds a fresh one to the same port.
+static void bind_twice(const char *name, int type, int port) {
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(port);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+  for (int i = 0; i < 2; i++) {
+    int fd = socket(AF_INET, type, 0);
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+      printf("%s: bind #%d failed\n", name, i + 1);
+      return;
+    }
+    if (type == SOCK_STREAM && listen(fd, 1) < 0) {
+      printf("%s: listen #%d failed\n", name, i + 1);
+      return;
+    }
+    close(fd);
+  }
+  printf("%s: port is reusable after close\n", name);
+}
+
+int main() {
+  bind_twice("tcp", SOCK_STREAM, 8087);
+  bind_twice("udp", SOCK_DGRAM, 8087);
+
+  // Closing one of two descriptors for a socket keeps the socket bound.
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8088);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  bind(fd, (struct sockaddr *)&addr, sizeof(addr));
+  int dup_fd = dup(fd);
+  close(fd);
+  int other = socket(AF_INET, SOCK_DGRAM, 0);
+  printf("bind while a dup is open: %s\n",
+         bind(other, (struct sockaddr *)&addr, sizeof(addr)) < 0 ? "fails" : "succeeds");
+  close(dup_fd);
+  close(other);
+  return 0;
+}
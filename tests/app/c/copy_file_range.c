@@ -0,0 +1,52 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#define SIZE 10000
+
+int main() {
+  const char *src_path = "/tmp/copy_file_range_src";
+  const char *dst_path = "/tmp/copy_file_range_dst";
+  int src = open(src_path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+  int dst = open(dst_path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+
+  static char pattern[SIZE], buf[SIZE];
+  for (int i = 0; i < SIZE; i++) {
+    pattern[i] = i % 251;
+  }
+  write(src, pattern, SIZE);
+  lseek(src, 0, SEEK_SET);
+
+  // Both offsets are taken from and advance the files.
+  ssize_t n = copy_file_range(src, NULL, dst, NULL, 4000, 0);
+  printf("copy: %ld, src offset: %ld, dst offset: %ld\n", (long)n,
+         (long)lseek(src, 0, SEEK_CUR), (long)lseek(dst, 0, SEEK_CUR));
+
+  // Given offsets are advanced instead of the file offsets.
+  off64_t off_in = 4000, off_out = 4000;
+  n = copy_file_range(src, &off_in, dst, &off_out, SIZE, 0);
+  printf("copy: %ld, off_in: %ld, off_out: %ld, dst offset: %ld\n", (long)n,
+         (long)off_in, (long)off_out, (long)lseek(dst, 0, SEEK_CUR));
+
+  n = copy_file_range(src, &off_in, dst, &off_out, SIZE, 0);
+  printf("at EOF: %ld\n", (long)n);
+
+  n = pread(dst, buf, SIZE, 0);
+  printf("read: %ld, same: %d\n", (long)n, memcmp(buf, pattern, SIZE) == 0);
+
+  off_in = 0;
+  off_out = 10;
+  n = copy_file_range(src, &off_in, src, &off_out, 100, 0);
+  printf("overlap: %ld, EINVAL: %d\n", (long)n, errno == EINVAL);
+  n = copy_file_range(src, NULL, dst, NULL, 1, 1);
+  printf("flags: %ld, EINVAL: %d\n", (long)n, errno == EINVAL);
+
+  close(src);
+  close(dst);
+  unlink(src_path);
+  unlink(dst_path);
+  return 0;
+}
@@ -0,0 +1,54 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+static void print_device(const char *path) {
+  struct stat st;
+  if (stat(path, &st) != 0) {
+    printf("stat %s failed\n", path);
+    return;
+  }
+  printf("%s: chr %d, %u:%u\n", path, S_ISCHR(st.st_mode), major(st.st_rdev),
+         minor(st.st_rdev));
+}
+
+int main() {
+  print_device("/dev/null");
+  print_device("/dev/zero");
+  print_device("/dev/full");
+  print_device("/dev/random");
+  print_device("/dev/urandom");
+
+  char buf[16];
+  int fd = open("/dev/zero", O_RDONLY);
+  for (int i = 0; i < (int)sizeof(buf); i++) {
+    buf[i] = 1;
+  }
+  ssize_t n = read(fd, buf, sizeof(buf));
+  int all_zero = n == sizeof(buf);
+  for (int i = 0; i < (int)sizeof(buf); i++) {
+    all_zero &= buf[i] == 0;
+  }
+  printf("/dev/zero reads zeros: %d\n", all_zero);
+  close(fd);
+
+  fd = open("/dev/full", O_RDWR);
+  n = write(fd, "a", 1);
+  printf("/dev/full write fails with ENOSPC: %d\n", n == -1 && errno == ENOSPC);
+  n = read(fd, buf, sizeof(buf));
+  printf("/dev/full reads %zd bytes\n", n);
+  close(fd);
+
+  fd = open("/dev/null", O_RDWR);
+  printf("/dev/null write: %zd\n", write(fd, buf, sizeof(buf)));
+  printf("/dev/null read: %zd\n", read(fd, buf, sizeof(buf)));
+  close(fd);
+
+  fd = open("/dev/urandom", O_RDONLY);
+  printf("/dev/urandom reads %zd bytes\n", read(fd, buf, sizeof(buf)));
+  close(fd);
+  return 0;
+}
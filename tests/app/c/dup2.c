@@ -0,0 +1,72 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+int main() {
+  // dup2 with oldfd == newfd is a no-op, while dup3 rejects it.
+  printf("dup2(1, 1) = %d\n", dup2(1, 1));
+  if (dup3(1, 1, 0) < 0 && errno == EINVAL) {
+    printf("dup3(1, 1, 0): EINVAL\n");
+  }
+  if (dup2(100, 100) < 0 && errno == EBADF) {
+    printf("dup2(100, 100): EBADF\n");
+  }
+  if (dup2(100, 5) < 0 && errno == EBADF) {
+    printf("dup2(100, 5): EBADF\n");
+  }
+
+  int fd = dup3(1, 20, O_CLOEXEC);
+  printf("dup3(1, 20, O_CLOEXEC) = %d, cloexec = %d\n", fd, (fcntl(fd, F_GETFD) & FD_CLOEXEC) != 0);
+  fd = dup2(1, 20);
+  printf("dup2(1, 20) = %d, cloexec = %d\n", fd, (fcntl(fd, F_GETFD) & FD_CLOEXEC) != 0);
+  close(20);
+
+  // Redirect stdout into a pipe and restore it afterwards.
+  int fds[2];
+  if (pipe(fds) < 0) {
+    printf("pipe failed\n");
+    return 1;
+  }
+  fflush(stdout);
+  int saved = dup(1);
+  dup2(fds[1], 1);
+  close(fds[1]);
+  printf("redirected");
+  fflush(stdout);
+  dup2(saved, 1);
+  close(saved);
+
+  char buf[32];
+  memset(buf, 0, sizeof(buf));
+  ssize_t n = read(fds[0], buf, sizeof(buf) - 1);
+  printf("read %zd bytes from pipe: %s\n", n, buf);
+  close(fds[0]);
+
+  // Redirect stdout into a host file. Closing the original descriptor must leave the file open
+  // through fd 1.
+  const char *path = "dup2_tmp";
+  int file = open(path, O_CREAT | O_WRONLY | O_TRUNC, 0644);
+  if (file < 0) {
+    printf("open failed\n");
+    return 1;
+  }
+  fflush(stdout);
+  saved = dup(1);
+  dup2(file, 1);
+  close(file);
+  printf("into file");
+  fflush(stdout);
+  dup2(saved, 1);
+  close(saved);
+
+  file = open(path, O_RDONLY);
+  memset(buf, 0, sizeof(buf));
+  n = read(file, buf, sizeof(buf) - 1);
+  printf("read %zd bytes from file: %s\n", n, buf);
+  close(file);
+  unlink(path);
+  return 0;
+}
@@ -0,0 +1,115 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/epoll.h>
+#include <unistd.h>
+
+static void do_wait(int epfd, const char *name) {
+  struct epoll_event ev[4];
+  int n = epoll_wait(epfd, ev, 4, 0);
+  if (n < 0) {
+    printf("%s: %s\n", name, strerror(errno));
+    return;
+  }
+  printf("%s: %d", name, n);
+  for (int i = 0; i < n; i++) {
+    printf(" [events=%#x data=%llu]", ev[i].events,
+           (unsigned long long)ev[i].data.u64);
+  }
+  printf("\n");
+}
+
+static void do_ctl(int epfd, int op, int fd, unsigned int events,
+                   const char *name) {
+  struct epoll_event ev = {.events = events, .data.u64 = fd};
+  if (epoll_ctl(epfd, op, fd, &ev) < 0) {
+    printf("%s: %s\n", name, strerror(errno));
+  } else {
+    printf("%s: ok\n", name);
+  }
+}
+
+static void drain(int fd) {
+  char buf[64];
+  while (read(fd, buf, sizeof(buf)) > 0) {
+  }
+}
+
+int main(void) {
+  int fds[2];
+  if (pipe2(fds, O_NONBLOCK) < 0) {
+    perror("pipe2");
+    return 1;
+  }
+
+  printf("level-triggered\n");
+  int epfd = epoll_create1(EPOLL_CLOEXEC);
+  do_ctl(epfd, EPOLL_CTL_ADD, fds[0], EPOLLIN, "add");
+  do_wait(epfd, "empty");
+  write(fds[1], "a", 1);
+  do_wait(epfd, "first");
+  do_wait(epfd, "second");
+  drain(fds[0]);
+  do_wait(epfd, "drained");
+  close(epfd);
+
+  printf("edge-triggered\n");
+  epfd = epoll_create1(0);
+  do_ctl(epfd, EPOLL_CTL_ADD, fds[0], EPOLLIN | EPOLLET, "add");
+  write(fds[1], "a", 1);
+  do_wait(epfd, "first");
+  do_wait(epfd, "second");
+  drain(fds[0]);
+  do_wait(epfd, "drained");
+  write(fds[1], "b", 1);
+  do_wait(epfd, "refilled");
+  do_wait(epfd, "again");
+  drain(fds[0]);
+  close(epfd);
+
+  printf("one-shot\n");
+  epfd = epoll_create1(0);
+  do_ctl(epfd, EPOLL_CTL_ADD, fds[0], EPOLLIN | EPOLLONESHOT, "add");
+  write(fds[1], "a", 1);
+  do_wait(epfd, "first");
+  do_wait(epfd, "second");
+  do_ctl(epfd, EPOLL_CTL_MOD, fds[0], EPOLLIN | EPOLLONESHOT, "rearm");
+  do_wait(epfd, "rearmed");
+  do_wait(epfd, "again");
+  drain(fds[0]);
+  do_ctl(epfd, EPOLL_CTL_DEL, fds[0], 0, "del");
+  do_ctl(epfd, EPOLL_CTL_ADD, fds[1], EPOLLOUT, "add writer");
+  do_wait(epfd, "writable");
+
+  printf("errors\n");
+  do_ctl(epfd, EPOLL_CTL_ADD, fds[1], EPOLLOUT, "add twice");
+  do_ctl(epfd, EPOLL_CTL_MOD, fds[0], EPOLLIN, "mod unknown");
+  do_ctl(epfd, EPOLL_CTL_DEL, fds[0], 0, "del unknown");
+  do_ctl(epfd, EPOLL_CTL_ADD, epfd, EPOLLIN, "add self");
+  do_ctl(fds[0], EPOLL_CTL_ADD, fds[1], EPOLLOUT, "not epoll");
+  do_ctl(epfd, EPOLL_CTL_MOD, fds[1], EPOLLOUT | EPOLLEXCLUSIVE,
+         "mod exclusive");
+  int file = open("/etc/passwd", O_RDONLY);
+  do_ctl(epfd, EPOLL_CTL_ADD, file, EPOLLIN, "regular file");
+  close(file);
+  struct epoll_event ev;
+  int n = epoll_wait(epfd, &ev, 0, 0);
+  printf("maxevents 0: %d %s\n", n, strerror(errno));
+  do_ctl(epfd, EPOLL_CTL_DEL, fds[1], 0, "del writer");
+
+  printf("pwait\n");
+  do_ctl(epfd, EPOLL_CTL_ADD, fds[0], EPOLLIN, "add");
+  sigset_t mask, cur;
+  sigemptyset(&mask);
+  sigaddset(&mask, SIGUSR1);
+  n = epoll_pwait(epfd, &ev, 1, 10, &mask);
+  sigprocmask(SIG_SETMASK, NULL, &cur);
+  printf("timeout: %d blocked after: %d\n", n, sigismember(&cur, SIGUSR1));
+  write(fds[1], "a", 1);
+  n = epoll_pwait(epfd, &ev, 1, -1, &mask);
+  printf("ready: %d events=%#x\n", n, ev.events);
+  return 0;
+}
@@ -0,0 +1,61 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/eventfd.h>
+#include <unistd.h>
+
+static void do_read(int fd) {
+  uint64_t v;
+  if (read(fd, &v, sizeof(v)) < 0) {
+    printf("read: %s\n", strerror(errno));
+  } else {
+    printf("read: %llu\n", (unsigned long long)v);
+  }
+}
+
+static void do_write(int fd, uint64_t v) {
+  if (write(fd, &v, sizeof(v)) < 0) {
+    printf("write %llu: %s\n", (unsigned long long)v, strerror(errno));
+  } else {
+    printf("write %llu: ok\n", (unsigned long long)v);
+  }
+}
+
+int main(void) {
+  printf("counter\n");
+  int fd = eventfd(3, EFD_NONBLOCK | EFD_CLOEXEC);
+  if (fd < 0) {
+    perror("eventfd");
+    return 1;
+  }
+  printf("nonblock: %d\n", (fcntl(fd, F_GETFL) & O_NONBLOCK) != 0);
+  printf("cloexec: %d\n", (fcntl(fd, F_GETFD) & FD_CLOEXEC) != 0);
+  do_write(fd, 2);
+  do_read(fd);
+  do_read(fd);
+  do_write(fd, UINT64_MAX - 1);
+  do_write(fd, 1);
+  do_write(fd, UINT64_MAX);
+  uint32_t small;
+  if (read(fd, &small, sizeof(small)) < 0) {
+    printf("short read: %s\n", strerror(errno));
+  }
+  close(fd);
+
+  printf("semaphore\n");
+  fd = eventfd(2, EFD_NONBLOCK | EFD_SEMAPHORE);
+  printf("cloexec: %d\n", (fcntl(fd, F_GETFD) & FD_CLOEXEC) != 0);
+  do_read(fd);
+  do_write(fd, 4);
+  for (int i = 0; i < 6; i++) {
+    do_read(fd);
+  }
+  close(fd);
+
+  if (eventfd(0, 0x1234) < 0) {
+    printf("invalid flags: %s\n", strerror(errno));
+  }
+  return 0;
+}
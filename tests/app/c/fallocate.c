@@ -0,0 +1,76 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static void do_fallocate(int fd, int mode, off_t offset, off_t len,
+                         const char *name) {
+  if (fallocate(fd, mode, offset, len) < 0) {
+    printf("%s: %s\n", name, strerror(errno));
+  } else {
+    printf("%s: ok\n", name);
+  }
+}
+
+static void print_size(int fd) {
+  struct stat st;
+  if (fstat(fd, &st) < 0) {
+    printf("fstat: %s\n", strerror(errno));
+    return;
+  }
+  printf("size: %lld\n", (long long)st.st_size);
+}
+
+int main(void) {
+  const char *filename = "fallocate_tmp";
+  int fd = open(filename, O_CREAT | O_TRUNC | O_RDWR, 0644);
+  if (fd < 0) {
+    printf("open failed\n");
+    return 1;
+  }
+
+  // Preallocating grows the file, and the new range reads back as zeros.
+  do_fallocate(fd, 0, 0, 10000, "allocate");
+  print_size(fd);
+  char buf[16];
+  memset(buf, 'x', sizeof(buf));
+  ssize_t n = pread(fd, buf, sizeof(buf), 5000);
+  printf("pread: %zd zero: %d\n", n, buf[0] == 0 && buf[15] == 0);
+
+  do_fallocate(fd, FALLOC_FL_KEEP_SIZE, 8192, 10000, "keep size");
+  print_size(fd);
+  do_fallocate(fd, 0, 100, 10, "within size");
+  print_size(fd);
+
+  // Punching a hole zeroes the range without shrinking the file.
+  pwrite(fd, "0123456789", 10, 4090);
+  do_fallocate(fd, FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE, 4092, 4,
+               "punch hole");
+  print_size(fd);
+  memset(buf, 0, sizeof(buf));
+  pread(fd, buf, 10, 4090);
+  for (int i = 0; i < 10; i++) {
+    buf[i] = buf[i] ? buf[i] : '.';
+  }
+  printf("after punch: %.10s\n", buf);
+
+  printf("errors\n");
+  do_fallocate(fd, 0, 0, 0, "zero length");
+  do_fallocate(fd, 0, -1, 10, "negative offset");
+  do_fallocate(fd, FALLOC_FL_PUNCH_HOLE, 0, 10, "punch without keep size");
+  do_fallocate(fd, 0x100, 0, 10, "unknown mode");
+  close(fd);
+
+  fd = open(filename, O_RDONLY);
+  do_fallocate(fd, 0, 0, 10, "read-only");
+  close(fd);
+  unlink(filename);
+
+  int fds[2];
+  pipe(fds);
+  do_fallocate(fds[1], 0, 0, 10, "pipe");
+  return 0;
+}
@@ -0,0 +1,43 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+#define STATUS_MASK (O_ACCMODE | O_NONBLOCK | O_APPEND)
+
+int main() {
+  int fds[2];
+  if (pipe(fds) < 0) {
+    printf("pipe failed\n");
+    return 1;
+  }
+
+  printf("F_GETFL: %#x\n", fcntl(fds[0], F_GETFL) & STATUS_MASK);
+  // The access mode can't be changed by F_SETFL.
+  fcntl(fds[0], F_SETFL, O_NONBLOCK | O_WRONLY);
+  printf("F_GETFL after F_SETFL: %#x\n", fcntl(fds[0], F_GETFL) & STATUS_MASK);
+  char c;
+  if (read(fds[0], &c, 1) < 0 && errno == EAGAIN) {
+    printf("read on empty non-blocking pipe: EAGAIN\n");
+  }
+
+  printf("F_GETFD: %d\n", fcntl(fds[0], F_GETFD));
+  fcntl(fds[0], F_SETFD, FD_CLOEXEC);
+  printf("F_GETFD after F_SETFD: %d\n", fcntl(fds[0], F_GETFD));
+
+  int fd = fcntl(fds[0], F_DUPFD, 10);
+  printf("F_DUPFD 10: %d, F_GETFD: %d\n", fd, fcntl(fd, F_GETFD));
+  fd = fcntl(fds[0], F_DUPFD_CLOEXEC, 10);
+  printf("F_DUPFD_CLOEXEC 10: %d, F_GETFD: %d\n", fd, fcntl(fd, F_GETFD));
+  if (fcntl(fds[0], F_DUPFD, -1) < 0 && errno == EINVAL) {
+    printf("F_DUPFD -1: EINVAL\n");
+  }
+  if (fcntl(100, F_GETFD) < 0 && errno == EBADF) {
+    printf("F_GETFD on a closed fd: EBADF\n");
+  }
+  if (fcntl(fds[0], 9999) < 0 && errno == EINVAL) {
+    printf("unknown command: EINVAL\n");
+  }
+  return 0;
+}
@@ -0,0 +1,53 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+static int set_lock(int fd, int cmd, short type, short whence, off_t start,
+                    off_t len) {
+  struct flock fl;
+  memset(&fl, 0, sizeof(fl));
+  fl.l_type = type;
+  fl.l_whence = whence;
+  fl.l_start = start;
+  fl.l_len = len;
+  return fcntl(fd, cmd, &fl);
+}
+
+int main() {
+  const char *path = "/tmp/fcntl_lock_test";
+  int rw = open(path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+  int ro = open(path, O_RDONLY);
+  write(rw, "0123456789", 10);
+
+  printf("F_SETLK F_WRLCK: %d\n", set_lock(rw, F_SETLK, F_WRLCK, SEEK_SET, 0, 5));
+  printf("F_SETLKW F_RDLCK to EOF: %d\n",
+         set_lock(rw, F_SETLKW, F_RDLCK, SEEK_END, -5, 0));
+
+  // Record locks belong to the process, so its own locks never conflict.
+  struct flock fl;
+  memset(&fl, 0, sizeof(fl));
+  fl.l_type = F_WRLCK;
+  fl.l_whence = SEEK_SET;
+  fl.l_len = 0;
+  int ret = fcntl(ro, F_GETLK, &fl);
+  printf("F_GETLK: %d, F_UNLCK: %d\n", ret, fl.l_type == F_UNLCK);
+
+  printf("F_SETLK F_RDLCK read only: %d\n",
+         set_lock(ro, F_SETLK, F_RDLCK, SEEK_CUR, 2, -2));
+  ret = set_lock(ro, F_SETLK, F_WRLCK, SEEK_SET, 0, 1);
+  printf("F_WRLCK read only: %d, EBADF: %d\n", ret, errno == EBADF);
+  ret = set_lock(rw, F_SETLK, 42, SEEK_SET, 0, 1);
+  printf("bad type: %d, EINVAL: %d\n", ret, errno == EINVAL);
+  ret = set_lock(rw, F_SETLK, F_RDLCK, 42, 0, 1);
+  printf("bad whence: %d, EINVAL: %d\n", ret, errno == EINVAL);
+  ret = set_lock(rw, F_SETLK, F_RDLCK, SEEK_SET, 2, -5);
+  printf("before start: %d, EINVAL: %d\n", ret, errno == EINVAL);
+  printf("F_UNLCK: %d\n", set_lock(rw, F_SETLK, F_UNLCK, SEEK_SET, 0, 0));
+
+  close(ro);
+  close(rw);
+  unlink(path);
+  return 0;
+}
@@ -0,0 +1,52 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+int main() {
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8085);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+  int receiver = socket(AF_INET, SOCK_DGRAM, 0);
+  if (bind(receiver, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("bind failed\n");
+    return 1;
+  }
+
+  int pending = -1;
+  ioctl(receiver, FIONREAD, &pending);
+  printf("FIONREAD before send: %d\n", pending);
+
+  int on = 1;
+  if (ioctl(receiver, FIONBIO, &on) < 0) {
+    printf("FIONBIO failed\n");
+    return 1;
+  }
+  char buf[16];
+  if (recv(receiver, buf, sizeof(buf), 0) >= 0 || errno != EAGAIN) {
+    printf("recv on an empty non-blocking socket did not fail with EAGAIN\n");
+    return 1;
+  }
+  printf("recv after FIONBIO: EAGAIN\n");
+
+  int sender = socket(AF_INET, SOCK_DGRAM, 0);
+  sendto(sender, "hello", 5, 0, (struct sockaddr *)&addr, sizeof(addr));
+  on = 0;
+  ioctl(receiver, FIONBIO, &on);
+  // Wait for the datagram without consuming it.
+  recv(receiver, buf, sizeof(buf), MSG_PEEK);
+
+  ioctl(receiver, FIONREAD, &pending);
+  printf("FIONREAD after send: %d\n", pending);
+
+  close(sender);
+  close(receiver);
+  return 0;
+}
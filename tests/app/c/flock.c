@@ -0,0 +1,41 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/file.h>
+#include <unistd.h>
+
+int main() {
+  const char *path = "/tmp/flock_test";
+  int a = open(path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+  int b = open(path, O_RDWR);
+
+  printf("a LOCK_EX: %d\n", flock(a, LOCK_EX));
+  int ret = flock(b, LOCK_EX | LOCK_NB);
+  printf("b LOCK_EX|LOCK_NB: %d, EWOULDBLOCK: %d\n", ret, errno == EWOULDBLOCK);
+  ret = flock(b, LOCK_SH | LOCK_NB);
+  printf("b LOCK_SH|LOCK_NB: %d, EWOULDBLOCK: %d\n", ret, errno == EWOULDBLOCK);
+
+  // A duplicated descriptor shares the lock of its open file description.
+  int c = dup(a);
+  printf("dup LOCK_EX|LOCK_NB: %d\n", flock(c, LOCK_EX | LOCK_NB));
+
+  // Shared locks may be held by several open files, but block an exclusive one.
+  printf("a LOCK_SH: %d\n", flock(a, LOCK_SH));
+  printf("b LOCK_SH|LOCK_NB: %d\n", flock(b, LOCK_SH | LOCK_NB));
+  ret = flock(a, LOCK_EX | LOCK_NB);
+  printf("a LOCK_EX|LOCK_NB: %d, EWOULDBLOCK: %d\n", ret, errno == EWOULDBLOCK);
+  printf("b LOCK_UN: %d\n", flock(b, LOCK_UN));
+  printf("a LOCK_EX|LOCK_NB: %d\n", flock(a, LOCK_EX | LOCK_NB));
+
+  // Closing every descriptor of the open file releases its lock.
+  close(a);
+  close(c);
+  printf("b LOCK_EX|LOCK_NB after close: %d\n", flock(b, LOCK_EX | LOCK_NB));
+
+  ret = flock(b, 0);
+  printf("invalid: %d, EINVAL: %d\n", ret, errno == EINVAL);
+
+  close(b);
+  unlink(path);
+  return 0;
+}
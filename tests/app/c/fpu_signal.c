@@ -0,0 +1,40 @@
+#define _GNU_SOURCE
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <ucontext.h>
+
+static long valid = 42;
+
+static void handler(int sig, siginfo_t *info, void *ucontext) {
+  ucontext_t *uc = ucontext;
+  // Retry the faulting load from a valid address.
+  uc->uc_mcontext.gregs[REG_RAX] = (greg_t)&valid;
+  __asm__ volatile("pxor %%xmm8, %%xmm8" ::: "xmm8");
+}
+
+int main() {
+  struct sigaction act;
+  memset(&act, 0, sizeof(act));
+  act.sa_sigaction = handler;
+  act.sa_flags = SA_SIGINFO;
+  sigemptyset(&act.sa_mask);
+  if (sigaction(SIGSEGV, &act, NULL) < 0) {
+    printf("sigaction failed\n");
+    return 1;
+  }
+
+  unsigned long in = 0x0123456789abcdef, out, loaded;
+  __asm__ volatile("movq %2, %%xmm8\n\t"
+                   "movq $0xdead0000, %%rax\n\t"
+                   "movq (%%rax), %%rax\n\t"
+                   "movq %%rax, %1\n\t"
+                   "movq %%xmm8, %0\n\t"
+                   : "=r"(out), "=r"(loaded)
+                   : "r"(in)
+                   : "rax", "xmm8", "memory");
+  printf("loaded %ld after the fault\n", (long)loaded);
+  printf("xmm8 %s across the signal handler\n",
+         out == in ? "was preserved" : "was clobbered");
+  return 0;
+}
@@ -0,0 +1,34 @@
+#include <errno.h>
+#include <stdio.h>
+#include <unistd.h>
+
+static void print_cwd(void) {
+  char buf[256];
+  if (getcwd(buf, sizeof(buf)) == NULL) {
+    printf("getcwd failed\n");
+    return;
+  }
+  printf("cwd: %s\n", buf);
+}
+
+int main() {
+  printf("chdir(\"/usr/bin\"): %d\n", chdir("/usr/bin"));
+  print_cwd();
+  printf("chdir(\"..\"): %d\n", chdir(".."));
+  print_cwd();
+  printf("chdir(\"bin/\"): %d\n", chdir("bin/"));
+  print_cwd();
+  printf("chdir(\"/\"): %d\n", chdir("/"));
+  print_cwd();
+
+  chdir("/usr");
+  char small[4];
+  if (getcwd(small, sizeof(small)) == NULL && errno == ERANGE) {
+    printf("getcwd with a small buffer: ERANGE\n");
+  }
+  if (chdir("/nonexistent") < 0 && errno == ENOENT) {
+    printf("chdir to a missing directory: ENOENT\n");
+  }
+  print_cwd();
+  return 0;
+}
@@ -0,0 +1,63 @@
+#include <errno.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define GRND_NONBLOCK 0x1
+#define GRND_RANDOM 0x2
+#define GRND_INSECURE 0x4
+
+static long sys_getrandom(void *buf, size_t len, unsigned int flags) {
+  return syscall(SYS_getrandom, buf, len, flags);
+}
+
+int main() {
+  long page_size = sysconf(_SC_PAGESIZE);
+  size_t length = 1 << 20;
+
+  unsigned char *buf = malloc(length);
+  if (buf == NULL) {
+    printf("malloc failed\n");
+    return 1;
+  }
+  long ret = sys_getrandom(buf, length, 0);
+  printf("large buffer: %d\n", ret == (long)length);
+  // Not a single byte being set in the last page is practically impossible.
+  int nonzero = 0;
+  for (size_t i = length - page_size; i < length; i++) {
+    nonzero |= buf[i];
+  }
+  printf("last page filled: %d\n", nonzero != 0);
+
+  ret = sys_getrandom(buf, 16, GRND_NONBLOCK);
+  printf("GRND_NONBLOCK: %ld\n", ret);
+  ret = sys_getrandom(buf, 16, GRND_RANDOM);
+  printf("GRND_RANDOM: %ld\n", ret);
+  ret = sys_getrandom(buf, 0, 0);
+  printf("empty buffer: %ld\n", ret);
+
+  errno = 0;
+  ret = sys_getrandom(buf, 16, 0x80);
+  printf("unknown flag: %ld (%s)\n", ret, errno == EINVAL ? "EINVAL" : "?");
+  errno = 0;
+  ret = sys_getrandom(buf, 16, GRND_INSECURE | GRND_RANDOM);
+  printf("GRND_INSECURE | GRND_RANDOM: %ld (%s)\n", ret,
+         errno == EINVAL ? "EINVAL" : "?");
+
+  // The buffer runs into an unmapped page.
+  unsigned char *p = mmap(NULL, 2 * page_size, PROT_READ | PROT_WRITE,
+                          MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+  if (p == MAP_FAILED) {
+    printf("mmap failed\n");
+    return 1;
+  }
+  munmap(p + page_size, page_size);
+  ret = sys_getrandom(p + page_size - 100, 200, 0);
+  printf("partial: %ld\n", ret);
+  errno = 0;
+  ret = sys_getrandom(p + page_size, 200, 0);
+  printf("unmapped: %ld (%s)\n", ret, errno == EFAULT ? "EFAULT" : "?");
+  return 0;
+}
@@ -0,0 +1,30 @@
+#include <stdio.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+int main() {
+  long page = sysconf(_SC_PAGESIZE);
+  size_t reserve = 512 * page;
+  unsigned char vec[5];
+
+  // Keep the area below the mapping free, as linux refuses to grow a mapping
+  // too close to the one below it.
+  char *r = mmap(NULL, reserve + page, PROT_NONE, MAP_PRIVATE | MAP_ANONYMOUS,
+                 -1, 0);
+  munmap(r, reserve);
+  char *p = mmap(r + reserve, page, PROT_READ | PROT_WRITE,
+                 MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED | MAP_GROWSDOWN, -1,
+                 0);
+  printf("mapped: %d\n", p == r + reserve);
+  printf("mincore before: %d\n", mincore(p - page, 2 * page, vec));
+
+  // Touching the pages right below the mapping extends it down to them.
+  p[-1] = 'a';
+  p[-3 * page] = 'b';
+  printf("below: %c %c\n", p[-1], p[-3 * page]);
+  printf("mincore grown: %d\n", mincore(p - 3 * page, 4 * page, vec));
+  printf("mincore past: %d\n", mincore(p - 4 * page, 5 * page, vec));
+
+  munmap(p - 3 * page, 4 * page);
+  return 0;
+}
@@ -0,0 +1,101 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+int main() {
+  int listener = socket(AF_INET6, SOCK_STREAM, 0);
+  if (listener < 0) {
+    printf("socket failed\n");
+    return 1;
+  }
+
+  struct sockaddr_in6 addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin6_family = AF_INET6;
+  addr.sin6_port = htons(8090);
+  addr.sin6_addr = in6addr_loopback;
+  // An address shorter than sockaddr_in6 without sin6_scope_id is rejected.
+  if (bind(listener, (struct sockaddr *)&addr, 20) == 0 || errno != EINVAL) {
+    printf("bind with a short address did not fail with EINVAL\n");
+    return 1;
+  }
+  if (bind(listener, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("bind failed\n");
+    return 1;
+  }
+  if (listen(listener, 1) < 0) {
+    printf("listen failed\n");
+    return 1;
+  }
+
+  struct sockaddr_in6 name;
+  socklen_t len = sizeof(name);
+  if (getsockname(listener, (struct sockaddr *)&name, &len) < 0) {
+    printf("getsockname failed\n");
+    return 1;
+  }
+  char buf[INET6_ADDRSTRLEN];
+  inet_ntop(AF_INET6, &name.sin6_addr, buf, sizeof(buf));
+  printf("listening: family %s, %s port %d\n",
+         name.sin6_family == AF_INET6 ? "AF_INET6" : "other", buf,
+         ntohs(name.sin6_port));
+
+  int client = socket(AF_INET6, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("connect failed\n");
+    return 1;
+  }
+  int conn = accept(listener, NULL, NULL);
+  if (conn < 0) {
+    printf("accept failed\n");
+    return 1;
+  }
+
+  len = sizeof(name);
+  if (getsockname(client, (struct sockaddr *)&name, &len) < 0) {
+    printf("getsockname on the client failed\n");
+    return 1;
+  }
+  printf("client name: family %s, len %d, ephemeral port %s\n",
+         name.sin6_family == AF_INET6 ? "AF_INET6" : "other", (int)len,
+         ntohs(name.sin6_port) != 0 ? "yes" : "no");
+  int client_port = ntohs(name.sin6_port);
+
+  len = sizeof(name);
+  if (getpeername(conn, (struct sockaddr *)&name, &len) < 0) {
+    printf("getpeername failed\n");
+    return 1;
+  }
+  printf("peer of accepted: family %s, same port as client %s\n",
+         name.sin6_family == AF_INET6 ? "AF_INET6" : "other",
+         ntohs(name.sin6_port) == client_port ? "yes" : "no");
+
+  len = sizeof(name);
+  if (getpeername(client, (struct sockaddr *)&name, &len) < 0) {
+    printf("getpeername on the client failed\n");
+    return 1;
+  }
+  inet_ntop(AF_INET6, &name.sin6_addr, buf, sizeof(buf));
+  printf("peer of client: %s port %d\n", buf, ntohs(name.sin6_port));
+
+  char c = 'x';
+  if (write(client, &c, 1) != 1) {
+    printf("write failed\n");
+    return 1;
+  }
+  c = 0;
+  if (read(conn, &c, 1) != 1) {
+    printf("read failed\n");
+    return 1;
+  }
+  printf("received %c\n", c);
+
+  close(conn);
+  close(client);
+  close(listener);
+  return 0;
+}
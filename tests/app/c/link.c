@@ -0,0 +1,56 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static void print_file(const char *path) {
+  char buf[32] = {0};
+  int fd = open(path, O_RDONLY);
+  if (fd < 0) {
+    printf("%s: missing\n", path);
+    return;
+  }
+  read(fd, buf, sizeof(buf) - 1);
+  close(fd);
+  printf("%s: %s\n", path, buf);
+}
+
+int main() {
+  const char *a = "/tmp/link_a";
+  const char *b = "/tmp/link_b";
+  int fd = open(a, O_CREAT | O_RDWR | O_TRUNC, 0644);
+  write(fd, "shared", 6);
+
+  printf("link: %d\n", link(a, b));
+  struct stat st;
+  stat(b, &st);
+  printf("nlink: %ld\n", (long)st.st_nlink);
+  print_file(b);
+
+  int ret = link(a, b);
+  printf("existing: %d, EEXIST: %d\n", ret, errno == EEXIST);
+  ret = link("/tmp", "/tmp/link_dir");
+  printf("directory: %d, EPERM: %d\n", ret, errno == EPERM);
+  ret = link(a, "/dev/shm/link_a");
+  printf("cross mount: %d, EXDEV: %d\n", ret, errno == EXDEV);
+
+  printf("unlink: %d\n", unlink(a));
+  stat(b, &st);
+  printf("nlink: %ld\n", (long)st.st_nlink);
+  print_file(a);
+  print_file(b);
+
+  // The data stays reachable through the open descriptor after the last name is gone.
+  printf("unlink: %d\n", unlink(b));
+  fstat(fd, &st);
+  printf("nlink: %ld\n", (long)st.st_nlink);
+  char buf[8] = {0};
+  pread(fd, buf, sizeof(buf) - 1, 0);
+  printf("read: %s\n", buf);
+  ret = unlink(b);
+  printf("missing: %d, ENOENT: %d\n", ret, errno == ENOENT);
+  close(fd);
+  return 0;
+}
@@ -0,0 +1,60 @@
+#include <arpa/inet.h>
+#include <netinet/in.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+int main() {
+  int listener = socket(AF_INET, SOCK_STREAM, 0);
+  if (listener < 0) {
+    printf("socket failed\n");
+    return 1;
+  }
+
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8080);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  if (bind(listener, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("bind failed\n");
+    return 1;
+  }
+  if (listen(listener, 1) < 0) {
+    printf("listen failed\n");
+    return 1;
+  }
+
+  struct pollfd pfd = {.fd = listener, .events = POLLIN};
+  if (poll(&pfd, 1, 0) != 0) {
+    printf("listener is ready before any connection\n");
+    return 1;
+  }
+
+  int client = socket(AF_INET, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("connect failed\n");
+    return 1;
+  }
+
+  pfd.revents = 0;
+  if (poll(&pfd, 1, 1000) != 1 || !(pfd.revents & POLLIN)) {
+    printf("listener is not readable after connect\n");
+    return 1;
+  }
+  printf("listener readable before accept\n");
+
+  int conn = accept(listener, NULL, NULL);
+  if (conn < 0) {
+    printf("accept failed\n");
+    return 1;
+  }
+  printf("accepted\n");
+
+  close(conn);
+  close(client);
+  close(listener);
+  return 0;
+}
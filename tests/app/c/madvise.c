@@ -0,0 +1,45 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+int main() {
+  long page_size = sysconf(_SC_PAGESIZE);
+  size_t length = 2 * page_size;
+
+  char *p = mmap(NULL, length, PROT_READ | PROT_WRITE,
+                 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+  if (p == MAP_FAILED) {
+    printf("mmap failed\n");
+    return 1;
+  }
+  memset(p, 0xaa, length);
+
+  printf("MADV_DONTNEED: %d\n", madvise(p, page_size, MADV_DONTNEED));
+  printf("first page after MADV_DONTNEED: %#x\n", (unsigned char)p[0]);
+  printf("second page after MADV_DONTNEED: %#x\n", (unsigned char)p[page_size]);
+
+  // the pages fault in again on the next write.
+  p[0] = 1;
+  printf("first page after rewrite: %d\n", p[0]);
+
+  printf("MADV_WILLNEED: %d\n", madvise(p, length, MADV_WILLNEED));
+  printf("MADV_SEQUENTIAL: %d\n", madvise(p, length, MADV_SEQUENTIAL));
+  printf("MADV_NORMAL: %d\n", madvise(p, length, MADV_NORMAL));
+
+  if (madvise(p + 1, page_size, MADV_DONTNEED) < 0 && errno == EINVAL) {
+    printf("unaligned address: EINVAL\n");
+  }
+  if (madvise(p, length, 12345) < 0 && errno == EINVAL) {
+    printf("unknown advice: EINVAL\n");
+  }
+
+  munmap(p + page_size, page_size);
+  if (madvise(p, length, MADV_DONTNEED) < 0 && errno == ENOMEM) {
+    printf("range with a hole: ENOMEM\n");
+  }
+
+  munmap(p, page_size);
+  return 0;
+}
@@ -0,0 +1,52 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define NPAGES 4
+
+int main() {
+  long page = sysconf(_SC_PAGESIZE);
+  size_t length = NPAGES * page;
+  unsigned char vec[NPAGES];
+
+  char *p = mmap(NULL, length, PROT_READ | PROT_WRITE,
+                 MAP_PRIVATE | MAP_ANONYMOUS | MAP_POPULATE, -1, 0);
+  mincore(p, length, vec);
+  printf("anonymous resident: %d %d %d %d\n", vec[0] & 1, vec[1] & 1,
+         vec[2] & 1, vec[3] & 1);
+  p[page] = 'a';
+  printf("anonymous: %d %c\n", p[0], p[page]);
+  munmap(p, length);
+
+  const char *path = "/tmp/map_populate_test";
+  int fd = open(path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+  char buf[16] = "file contents";
+  write(fd, buf, sizeof(buf));
+  ftruncate(fd, length);
+
+  // Writes to a populated private mapping stay private.
+  p = mmap(NULL, length, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_POPULATE,
+           fd, 0);
+  mincore(p, length, vec);
+  printf("private resident: %d %d %d %d\n", vec[0] & 1, vec[1] & 1,
+         vec[2] & 1, vec[3] & 1);
+  printf("private: %s\n", p);
+  memcpy(p, "changed", 7);
+  printf("private after write: %s\n", p);
+  pread(fd, buf, sizeof(buf), 0);
+  printf("file after write: %s\n", buf);
+  munmap(p, length);
+
+  p = mmap(NULL, length, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_POPULATE,
+           fd, 0);
+  memcpy(p, "shared", 6);
+  pread(fd, buf, sizeof(buf), 0);
+  printf("file after shared write: %s\n", buf);
+  munmap(p, length);
+
+  close(fd);
+  unlink(path);
+  return 0;
+}
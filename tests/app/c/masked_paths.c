@@ -0,0 +1,45 @@
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+int main(void) {
+  // /proc/kcore is masked by binding /dev/null over it.
+  char buf[16];
+  int fd = open("/proc/kcore", O_RDONLY);
+  if (fd < 0) {
+    printf("open /proc/kcore failed: %s\n", strerror(errno));
+  } else {
+    printf("read /proc/kcore: %zd\n", read(fd, buf, sizeof(buf)));
+    close(fd);
+  }
+
+  // /sys/firmware is masked by an empty tmpfs.
+  DIR *dir = opendir("/sys/firmware");
+  if (dir == NULL) {
+    printf("opendir /sys/firmware failed: %s\n", strerror(errno));
+  } else {
+    int entries = 0;
+    struct dirent *d;
+    while ((d = readdir(dir)) != NULL) {
+      if (strcmp(d->d_name, ".") != 0 && strcmp(d->d_name, "..") != 0) {
+        entries++;
+      }
+    }
+    printf("entries in /sys/firmware: %d\n", entries);
+    closedir(dir);
+  }
+
+  // /proc/sys is read-only.
+  fd = open("/proc/sys/kernel/hostname", O_WRONLY);
+  if (fd < 0) {
+    printf("open /proc/sys/kernel/hostname for writing failed: %s\n",
+           strerror(errno));
+  } else {
+    printf("opened /proc/sys/kernel/hostname for writing\n");
+    close(fd);
+  }
+  return 0;
+}
@@ -0,0 +1,54 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define NPAGES 4
+
+static void print_vec(const char *label, unsigned char *vec) {
+  printf("%s:", label);
+  for (int i = 0; i < NPAGES; i++) {
+    printf(" %d", vec[i] & 1);
+  }
+  printf("\n");
+}
+
+int main() {
+  long page_size = sysconf(_SC_PAGESIZE);
+  size_t length = NPAGES * page_size;
+  unsigned char vec[NPAGES];
+
+  char *p = mmap(NULL, length, PROT_READ | PROT_WRITE,
+                 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+  if (p == MAP_FAILED) {
+    printf("mmap failed\n");
+    return 1;
+  }
+
+  if (mincore(p, length, vec) < 0) {
+    printf("mincore failed: %d\n", errno);
+    return 1;
+  }
+  print_vec("fresh mapping", vec);
+
+  for (int i = 0; i < NPAGES; i++) {
+    p[i * page_size] = 1;
+  }
+  if (mincore(p, length, vec) < 0) {
+    printf("mincore failed: %d\n", errno);
+    return 1;
+  }
+  print_vec("after touching", vec);
+
+  if (mincore(p + 1, page_size, vec) < 0 && errno == EINVAL) {
+    printf("unaligned address: EINVAL\n");
+  }
+
+  munmap(p + page_size, page_size);
+  if (mincore(p, length, vec) < 0 && errno == ENOMEM) {
+    printf("range with a hole: ENOMEM\n");
+  }
+
+  munmap(p, length);
+  return 0;
+}
@@ -0,0 +1,41 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main() {
+  long page = sysconf(_SC_PAGESIZE);
+  char *p = mmap(NULL, 4 * page, PROT_READ | PROT_WRITE,
+                 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+  unsigned char vec[4];
+
+  // Locking faults the pages in right away.
+  printf("mlock: %d\n", mlock(p + page, 2 * page));
+  mincore(p, 4 * page, vec);
+  printf("mincore: %d %d\n", vec[1] & 1, vec[2] & 1);
+  printf("munlock: %d\n", munlock(p + page, 2 * page));
+
+  // Unaligned ranges cover every page they touch.
+  printf("mlock unaligned: %d\n", mlock(p + 3 * page + 1, 1));
+  printf("munlock unaligned: %d\n", munlock(p + 3 * page + 1, 1));
+
+  munmap(p + 2 * page, page);
+  int ret = mlock(p, 4 * page);
+  printf("mlock hole: %d, ENOMEM: %d\n", ret, errno == ENOMEM);
+
+  ret = syscall(SYS_mlock2, p, page, 1 /* MLOCK_ONFAULT */);
+  printf("mlock2 onfault: %d\n", ret);
+  ret = syscall(SYS_mlock2, p, page, 2);
+  printf("mlock2 invalid: %d, EINVAL: %d\n", ret, errno == EINVAL);
+
+  printf("mlockall future: %d\n", mlockall(MCL_FUTURE));
+  printf("munlockall: %d\n", munlockall());
+  ret = mlockall(0);
+  printf("mlockall none: %d, EINVAL: %d\n", ret, errno == EINVAL);
+  ret = mlockall(8);
+  printf("mlockall invalid: %d, EINVAL: %d\n", ret, errno == EINVAL);
+
+  munmap(p, 4 * page);
+  return 0;
+}
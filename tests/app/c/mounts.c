@@ -0,0 +1,40 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main(void) {
+  struct stat shm, dev, parent;
+  if (stat("/dev/shm", &shm) < 0 || stat("/dev", &dev) < 0 ||
+      stat("/dev/shm/..", &parent) < 0) {
+    printf("stat failed: %d\n", errno);
+    return 1;
+  }
+  printf("/dev/shm is a directory: %d\n", S_ISDIR(shm.st_mode));
+  printf("/dev/shm mode: %o\n", shm.st_mode & 07777);
+  printf("/dev/shm/.. is /dev: %d\n",
+         parent.st_ino == dev.st_ino && parent.st_dev == dev.st_dev);
+
+  int fd = open("/dev/shm/mounts", O_CREAT | O_RDWR | O_TRUNC, 0644);
+  if (fd < 0) {
+    printf("open failed: %d\n", errno);
+    return 1;
+  }
+  const char *msg = "written to tmpfs";
+  write(fd, msg, strlen(msg));
+  close(fd);
+
+  char buf[32];
+  fd = open("/dev/shm/mounts", O_RDONLY);
+  ssize_t n = read(fd, buf, sizeof(buf) - 1);
+  buf[n < 0 ? 0 : n] = '\0';
+  printf("read %zd bytes: %s\n", n, buf);
+  close(fd);
+
+  fd = open("/etc/hostname", O_RDONLY);
+  printf("/etc/hostname is readable: %d\n", fd >= 0 && read(fd, buf, 1) == 1);
+  close(fd);
+  return 0;
+}
@@ -0,0 +1,46 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+int main() {
+  long page = sysconf(_SC_PAGESIZE);
+  const char *path = "/tmp/mprotect_test";
+  int fd = open(path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+  ftruncate(fd, 3 * page);
+  close(fd);
+
+  // A shared mapping of a read-only file may never become writable.
+  fd = open(path, O_RDONLY);
+  char *p = mmap(NULL, 3 * page, PROT_READ, MAP_SHARED, fd, 0);
+  int ret = mprotect(p, page, PROT_READ | PROT_WRITE);
+  printf("shared read-only to write: %d, EACCES: %d\n", ret, errno == EACCES);
+  munmap(p, 3 * page);
+
+  // A private one may, since writes never reach the file.
+  p = mmap(NULL, 3 * page, PROT_READ, MAP_PRIVATE, fd, 0);
+  printf("private read-only to write: %d\n",
+         mprotect(p, page, PROT_READ | PROT_WRITE));
+  p[0] = 'a';
+  printf("written: %c\n", p[0]);
+  munmap(p, 3 * page);
+  close(fd);
+
+  // Only the protected pages change.
+  p = mmap(NULL, 3 * page, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS,
+           -1, 0);
+  printf("middle to none: %d\n", mprotect(p + page, page, PROT_NONE));
+  p[0] = 'b';
+  p[2 * page] = 'c';
+  printf("around: %c %c\n", p[0], p[2 * page]);
+  ret = mprotect(p + 1, page, PROT_READ);
+  printf("unaligned: %d, EINVAL: %d\n", ret, errno == EINVAL);
+  munmap(p + 2 * page, page);
+  ret = mprotect(p, 3 * page, PROT_READ);
+  printf("hole: %d, ENOMEM: %d\n", ret, errno == ENOMEM);
+
+  munmap(p, 2 * page);
+  unlink(path);
+  return 0;
+}
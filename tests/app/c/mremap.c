@@ -0,0 +1,41 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+int main() {
+  long page = sysconf(_SC_PAGESIZE);
+  char *p = mmap(NULL, 2 * page, PROT_READ | PROT_WRITE,
+                 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+  char *next = p + page;
+  munmap(p, page);
+  p = mmap(p, page, PROT_READ | PROT_WRITE,
+           MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED, -1, 0);
+  p[0] = 'a';
+  next[0] = 'b';
+
+  // The page after the mapping is taken, so it can only grow by moving.
+  char *q = mremap(p, page, 2 * page, 0);
+  printf("grow in place: %d, ENOMEM: %d\n", q == MAP_FAILED, errno == ENOMEM);
+  q = mremap(p, page, 2 * page, MREMAP_MAYMOVE);
+  printf("grow by moving: moved %d, %c\n", q != p, q[0]);
+  q[2 * page - 1] = 'c';
+
+  // A fixed destination replaces the mapping there.
+  char *r = mremap(q, 2 * page, page, MREMAP_MAYMOVE | MREMAP_FIXED, next);
+  printf("move to fixed: %d, %c\n", r == next, r[0]);
+  r = mremap(next, page, page, MREMAP_FIXED, p);
+  printf("fixed without maymove: %d, EINVAL: %d\n", r == MAP_FAILED,
+         errno == EINVAL);
+
+  // Shrinking unmaps the tail in place.
+  p = mremap(next, page, 2 * page, MREMAP_MAYMOVE);
+  r = mremap(p, 2 * page, page, 0);
+  unsigned char vec[2];
+  int ret = mincore(p, 2 * page, vec);
+  printf("shrink: %d, tail unmapped: %d\n", r == p, ret == -1 && errno == ENOMEM);
+
+  munmap(p, page);
+  return 0;
+}
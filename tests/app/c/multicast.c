@@ -0,0 +1,36 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static void membership(int fd, int name, const char *group, socklen_t len,
+                       const char *what) {
+  struct ip_mreq mreq;
+  mreq.imr_multiaddr.s_addr = inet_addr(group);
+  mreq.imr_interface.s_addr = htonl(INADDR_ANY);
+  if (setsockopt(fd, IPPROTO_IP, name, &mreq, len) < 0) {
+    printf("%s: %s\n", what, strerror(errno));
+  } else {
+    printf("%s: ok\n", what);
+  }
+}
+
+int main(void) {
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  if (fd < 0) {
+    perror("socket");
+    return 1;
+  }
+  socklen_t len = sizeof(struct ip_mreq);
+  membership(fd, IP_ADD_MEMBERSHIP, "239.1.2.3", len, "join");
+  membership(fd, IP_ADD_MEMBERSHIP, "239.1.2.3", len, "join again");
+  membership(fd, IP_ADD_MEMBERSHIP, "10.1.2.3", len, "join unicast");
+  membership(fd, IP_ADD_MEMBERSHIP, "239.1.2.4", len - 1, "join short");
+  membership(fd, IP_DROP_MEMBERSHIP, "239.1.2.3", len, "leave");
+  membership(fd, IP_DROP_MEMBERSHIP, "239.1.2.3", len, "leave again");
+  close(fd);
+  return 0;
+}
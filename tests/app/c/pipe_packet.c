@@ -0,0 +1,60 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+static void do_read(int fd, size_t len) {
+  char buf[8192];
+  ssize_t n = read(fd, buf, len);
+  if (n < 0) {
+    printf("read %zu: %s\n", len, strerror(errno));
+  } else {
+    printf("read %zu: %zd\n", len, n);
+  }
+}
+
+static void run(int flags, const char *mode) {
+  int fds[2];
+  if (pipe2(fds, flags) < 0) {
+    printf("pipe2: %s\n", strerror(errno));
+    return;
+  }
+  printf("%s\n", mode);
+  for (int i = 0; i < 2; i++) {
+    int fl = fcntl(fds[i], F_GETFL);
+    printf("fd %d direct: %d nonblock: %d\n", i, (fl & O_DIRECT) != 0,
+           (fl & O_NONBLOCK) != 0);
+  }
+  printf("cloexec: %d %d\n", (fcntl(fds[0], F_GETFD) & FD_CLOEXEC) != 0,
+         (fcntl(fds[1], F_GETFD) & FD_CLOEXEC) != 0);
+
+  write(fds[1], "abc", 3);
+  write(fds[1], "defgh", 5);
+  do_read(fds[0], 100);
+  do_read(fds[0], 100);
+
+  // A short read takes the head of a packet and discards the rest of it.
+  write(fds[1], "123456", 6);
+  write(fds[1], "78", 2);
+  do_read(fds[0], 2);
+  do_read(fds[0], 100);
+  do_read(fds[0], 100);
+
+  // Writes are split into packets of PIPE_BUF bytes.
+  char big[5000];
+  memset(big, 'x', sizeof(big));
+  write(fds[1], big, sizeof(big));
+  do_read(fds[0], sizeof(big) + 100);
+  do_read(fds[0], sizeof(big) + 100);
+
+  close(fds[0]);
+  close(fds[1]);
+}
+
+int main(void) {
+  run(O_NONBLOCK, "stream");
+  run(O_NONBLOCK | O_DIRECT | O_CLOEXEC, "packet");
+  return 0;
+}
@@ -0,0 +1,57 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+static long do_ppoll(struct pollfd *fds, nfds_t nfds, struct timespec *ts,
+                     sigset_t *mask, size_t sigsetsize) {
+  return syscall(SYS_ppoll, fds, nfds, ts, mask, sigsetsize);
+}
+
+int main(void) {
+  int fds[2];
+  if (pipe(fds) < 0) {
+    perror("pipe");
+    return 1;
+  }
+  struct pollfd pfd = {.fd = fds[0], .events = POLLIN};
+
+  // No fd is ready, so a short timeout expires and ppoll returns 0.
+  struct timespec ts = {.tv_sec = 0, .tv_nsec = 10 * 1000 * 1000};
+  long n = do_ppoll(&pfd, 1, &ts, NULL, 0);
+  printf("timeout: %ld revents: %d\n", n, pfd.revents);
+  // Unlike select, ppoll leaves the timeout untouched.
+  printf("ts: %ld %ld\n", (long)ts.tv_sec, ts.tv_nsec);
+
+  // A zero timeout returns immediately.
+  ts.tv_nsec = 0;
+  n = do_ppoll(&pfd, 1, &ts, NULL, 0);
+  printf("zero: %ld\n", n);
+
+  // The signal mask is only applied while waiting.
+  sigset_t mask, cur;
+  sigemptyset(&mask);
+  sigaddset(&mask, SIGUSR1);
+  ts.tv_nsec = 10 * 1000 * 1000;
+  n = do_ppoll(&pfd, 1, &ts, &mask, 8);
+  sigprocmask(SIG_SETMASK, NULL, &cur);
+  printf("sigmask: %ld blocked after: %d\n", n, sigismember(&cur, SIGUSR1));
+
+  n = do_ppoll(&pfd, 1, &ts, &mask, 4);
+  printf("bad sigsetsize: %ld %s\n", n, n < 0 ? strerror(errno) : "");
+
+  struct timespec bad = {.tv_sec = 0, .tv_nsec = 1000 * 1000 * 1000};
+  n = do_ppoll(&pfd, 1, &bad, NULL, 0);
+  printf("bad timeout: %ld %s\n", n, n < 0 ? strerror(errno) : "");
+
+  // A ready fd is reported.
+  write(fds[1], "x", 1);
+  n = do_ppoll(&pfd, 1, NULL, NULL, 0);
+  printf("ready: %ld revents: %d\n", n, pfd.revents);
+  return 0;
+}
@@ -0,0 +1,39 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/resource.h>
+
+int main(void) {
+  struct rlimit old, new, cur;
+  if (prlimit(0, RLIMIT_NOFILE, NULL, &old) < 0) {
+    printf("prlimit get failed: %s\n", strerror(errno));
+    return 1;
+  }
+
+  // Lowering the soft limit is always allowed, and the previous limit is written back.
+  struct rlimit prev;
+  new.rlim_cur = 512;
+  new.rlim_max = old.rlim_max;
+  if (prlimit(0, RLIMIT_NOFILE, &new, &prev) < 0) {
+    printf("prlimit set failed: %s\n", strerror(errno));
+    return 1;
+  }
+  printf("previous limit matches: %d\n",
+         prev.rlim_cur == old.rlim_cur && prev.rlim_max == old.rlim_max);
+
+  getrlimit(RLIMIT_NOFILE, &cur);
+  printf("soft limit: %llu\n", (unsigned long long)cur.rlim_cur);
+
+  // The soft limit may not exceed the hard limit.
+  new.rlim_cur = 256;
+  new.rlim_max = 128;
+  if (prlimit(0, RLIMIT_NOFILE, &new, NULL) < 0) {
+    printf("soft above hard: %s\n", strerror(errno));
+  }
+
+  if (prlimit(0, 1000, NULL, &cur) < 0) {
+    printf("invalid resource: %s\n", strerror(errno));
+  }
+  return 0;
+}
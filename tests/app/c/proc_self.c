@@ -0,0 +1,57 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+int main() {
+  int local = 0;
+  unsigned long addr = (unsigned long)&local;
+
+  FILE *f = fopen("/proc/self/maps", "r");
+  if (f == NULL) {
+    printf("fopen /proc/self/maps failed\n");
+    return 1;
+  }
+  char line[512];
+  int found = 0, contains_local = 0;
+  while (fgets(line, sizeof(line), f) != NULL) {
+    if (strstr(line, "[stack]") == NULL) {
+      continue;
+    }
+    found = 1;
+    unsigned long start, end;
+    char perms[5];
+    if (sscanf(line, "%lx-%lx %4s", &start, &end, perms) == 3) {
+      contains_local =
+          start <= addr && addr < end && strncmp(perms, "rw", 2) == 0;
+    }
+  }
+  fclose(f);
+  printf("stack line found: %d\n", found);
+  printf("stack line contains a local variable: %d\n", contains_local);
+
+  f = fopen("/proc/self/status", "r");
+  if (f == NULL) {
+    printf("fopen /proc/self/status failed\n");
+    return 1;
+  }
+  while (fgets(line, sizeof(line), f) != NULL) {
+    if (strncmp(line, "Name:", 5) == 0) {
+      printf("%s", line);
+    }
+  }
+  fclose(f);
+
+  f = fopen("/proc/self/stat", "r");
+  if (f == NULL) {
+    printf("fopen /proc/self/stat failed\n");
+    return 1;
+  }
+  int pid = 0;
+  char comm[32];
+  int n = fscanf(f, "%d %31s", &pid, comm);
+  fclose(f);
+  printf("stat pid matches: %d\n", n == 2 && pid == getpid());
+  printf("stat comm: %s\n", comm);
+  return 0;
+}
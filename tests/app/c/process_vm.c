@@ -0,0 +1,52 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+int main() {
+  pid_t pid = getpid();
+  char src[] = "hello, process_vm";
+  char a[8] = {0};
+  char b[16] = {0};
+
+  // Scatter one remote buffer over two local ones.
+  struct iovec local[2] = {{a, 7}, {b, sizeof(b)}};
+  struct iovec remote[1] = {{src, sizeof(src)}};
+  ssize_t n = process_vm_readv(pid, local, 2, remote, 1, 0);
+  printf("readv: %zd '%s' '%s'\n", n, a, b);
+
+  // Gather two local buffers into one remote.
+  char dst[32] = {0};
+  struct iovec wlocal[2] = {{"abc", 3}, {"def", 4}};
+  struct iovec wremote[1] = {{dst, sizeof(dst)}};
+  n = process_vm_writev(pid, wlocal, 2, wremote, 1, 0);
+  printf("writev: %zd '%s'\n", n, dst);
+
+  // The transfer stops at the first unmapped remote page.
+  long page = sysconf(_SC_PAGESIZE);
+  char *p = mmap(NULL, 2 * page, PROT_READ | PROT_WRITE,
+                 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+  munmap(p + page, page);
+  memset(p, 'x', page);
+  char big[64];
+  struct iovec blocal[1] = {{big, sizeof(big)}};
+  struct iovec bremote[1] = {{p + page - 10, 20}};
+  n = process_vm_readv(pid, blocal, 1, bremote, 1, 0);
+  printf("partial: %zd\n", n);
+  bremote[0].iov_base = p + page;
+  n = process_vm_readv(pid, blocal, 1, bremote, 1, 0);
+  printf("unmapped: %zd, EFAULT: %d\n", n, errno == EFAULT);
+
+  n = process_vm_readv(pid, blocal, 0, remote, 1, 0);
+  printf("empty local: %zd\n", n);
+  n = process_vm_readv(pid, local, 1, remote, 1, 1);
+  printf("flags: %zd, EINVAL: %d\n", n, errno == EINVAL);
+  n = process_vm_readv(4194304, local, 1, remote, 1, 0);
+  printf("no such process: %zd, ESRCH: %d\n", n, errno == ESRCH);
+
+  munmap(p, page);
+  return 0;
+}
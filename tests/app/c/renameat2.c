@@ -0,0 +1,69 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#ifndef RENAME_NOREPLACE
+#define RENAME_NOREPLACE (1 << 0)
+#endif
+#ifndef RENAME_EXCHANGE
+#define RENAME_EXCHANGE (1 << 1)
+#endif
+
+static int renameat2_(const char *oldpath, const char *newpath, unsigned int flags) {
+  return syscall(SYS_renameat2, AT_FDCWD, oldpath, AT_FDCWD, newpath, flags);
+}
+
+static void write_file(const char *path, const char *content) {
+  int fd = open(path, O_CREAT | O_WRONLY | O_TRUNC, 0644);
+  write(fd, content, strlen(content));
+  close(fd);
+}
+
+static void print_file(const char *path) {
+  char buf[32] = {0};
+  int fd = open(path, O_RDONLY);
+  if (fd < 0) {
+    printf("%s: missing\n", path);
+    return;
+  }
+  read(fd, buf, sizeof(buf) - 1);
+  close(fd);
+  printf("%s: %s\n", path, buf);
+}
+
+int main() {
+  const char *a = "/tmp/renameat2_a";
+  const char *b = "/tmp/renameat2_b";
+  const char *c = "/tmp/renameat2_c";
+  write_file(a, "first");
+  write_file(b, "second");
+
+  int ret = renameat2_(a, b, RENAME_NOREPLACE);
+  printf("noreplace existing: %d, EEXIST: %d\n", ret, errno == EEXIST);
+  print_file(a);
+  print_file(b);
+
+  printf("exchange: %d\n", renameat2_(a, b, RENAME_EXCHANGE));
+  print_file(a);
+  print_file(b);
+
+  ret = renameat2_(a, c, RENAME_EXCHANGE);
+  printf("exchange missing: %d, ENOENT: %d\n", ret, errno == ENOENT);
+
+  ret = renameat2_(a, b, RENAME_NOREPLACE | RENAME_EXCHANGE);
+  printf("both flags: %d, EINVAL: %d\n", ret, errno == EINVAL);
+
+  printf("noreplace: %d\n", renameat2_(a, c, RENAME_NOREPLACE));
+  print_file(a);
+  print_file(c);
+
+  printf("replace: %d\n", renameat2_(c, b, 0));
+  print_file(b);
+  print_file(c);
+
+  unlink(b);
+  return 0;
+}
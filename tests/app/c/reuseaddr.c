@@ -0,0 +1,68 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int bind_socket(int reuse) {
+  int fd = socket(AF_INET, SOCK_STREAM, 0);
+  if (fd < 0) {
+    return -1;
+  }
+  if (reuse) {
+    int one = 1;
+    setsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &one, sizeof(one));
+  }
+
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8082);
+  addr.sin_addr.s_addr = htonl(INADDR_ANY);
+  if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    close(fd);
+    return -1;
+  }
+  return fd;
+}
+
+int main() {
+  int first = bind_socket(1);
+  if (first < 0) {
+    printf("first bind failed\n");
+    return 1;
+  }
+
+  int val = 0;
+  socklen_t len = sizeof(val);
+  getsockopt(first, SOL_SOCKET, SO_REUSEADDR, &val, &len);
+  printf("SO_REUSEADDR: %d\n", val);
+
+  if (bind_socket(0) >= 0) {
+    printf("bind without SO_REUSEADDR succeeded\n");
+    return 1;
+  }
+  printf("bind without SO_REUSEADDR: %s\n",
+         errno == EADDRINUSE ? "EADDRINUSE" : "other error");
+
+  int second = bind_socket(1);
+  if (second < 0) {
+    printf("bind with SO_REUSEADDR failed\n");
+    return 1;
+  }
+  printf("bind with SO_REUSEADDR: ok\n");
+
+  // The port stays bound as long as one of the sockets is open.
+  close(second);
+  if (bind_socket(0) >= 0) {
+    printf("bind after closing one socket succeeded\n");
+    return 1;
+  }
+  printf("bind after closing one socket: %s\n",
+         errno == EADDRINUSE ? "EADDRINUSE" : "other error");
+
+  close(first);
+  return 0;
+}
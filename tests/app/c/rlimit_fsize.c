@@ -0,0 +1,44 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/resource.h>
+#include <unistd.h>
+
+int main() {
+  // Linux raises SIGXFSZ when the limit is hit; ignore it to observe EFBIG.
+  signal(SIGXFSZ, SIG_IGN);
+
+  const char *filename = "fsize_tmp";
+  int fd = open(filename, O_CREAT | O_TRUNC | O_WRONLY, 0644);
+  if (fd < 0) {
+    printf("open failed\n");
+    return 1;
+  }
+
+  struct rlimit lim = {.rlim_cur = 16, .rlim_max = RLIM_INFINITY};
+  if (setrlimit(RLIMIT_FSIZE, &lim) < 0) {
+    printf("setrlimit failed\n");
+    return 1;
+  }
+
+  const char *content = "0123456789abcdefghij";
+  int across = write(fd, content, strlen(content));
+  int at = write(fd, content, strlen(content));
+  int at_errno = errno;
+  int pwrite_across = pwrite(fd, content, 4, 14);
+
+  // Lift the limit again, since stdout may be a regular file too.
+  lim.rlim_cur = RLIM_INFINITY;
+  setrlimit(RLIMIT_FSIZE, &lim);
+
+  printf("write across the limit: %d\n", across);
+  printf("write at the limit: %d (%s)\n", at,
+         at_errno == EFBIG ? "EFBIG" : "other error");
+  printf("pwrite across the limit: %d\n", pwrite_across);
+
+  close(fd);
+  unlink(filename);
+  return 0;
+}
@@ -0,0 +1,99 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+static int send_fd(int sock, int fd) {
+  char data = 'x';
+  struct iovec iov = {.iov_base = &data, .iov_len = 1};
+  union {
+    char buf[CMSG_SPACE(sizeof(int))];
+    struct cmsghdr align;
+  } control;
+  memset(&control, 0, sizeof(control));
+  struct msghdr msg = {
+      .msg_iov = &iov,
+      .msg_iovlen = 1,
+      .msg_control = control.buf,
+      .msg_controllen = sizeof(control.buf),
+  };
+  struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+  cmsg->cmsg_level = SOL_SOCKET;
+  cmsg->cmsg_type = SCM_RIGHTS;
+  cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+  memcpy(CMSG_DATA(cmsg), &fd, sizeof(int));
+  return sendmsg(sock, &msg, 0);
+}
+
+static int recv_fd(int sock) {
+  char data;
+  struct iovec iov = {.iov_base = &data, .iov_len = 1};
+  union {
+    char buf[CMSG_SPACE(sizeof(int))];
+    struct cmsghdr align;
+  } control;
+  struct msghdr msg = {
+      .msg_iov = &iov,
+      .msg_iovlen = 1,
+      .msg_control = control.buf,
+      .msg_controllen = sizeof(control.buf),
+  };
+  ssize_t n = recvmsg(sock, &msg, 0);
+  printf("recvmsg: %zd bytes '%c', controllen == CMSG_SPACE(sizeof(int)): %d, flags: %d\n",
+         n, data, msg.msg_controllen == CMSG_SPACE(sizeof(int)), msg.msg_flags);
+  struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+  if (cmsg == NULL || cmsg->cmsg_level != SOL_SOCKET ||
+      cmsg->cmsg_type != SCM_RIGHTS) {
+    printf("no SCM_RIGHTS message\n");
+    return -1;
+  }
+  int fd;
+  memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+  return fd;
+}
+
+int main() {
+  int channel[2], pair[2];
+  if (socketpair(AF_UNIX, SOCK_STREAM, 0, channel) < 0 ||
+      socketpair(AF_UNIX, SOCK_STREAM, 0, pair) < 0) {
+    printf("socketpair failed\n");
+    return 1;
+  }
+
+  printf("sendmsg: %d\n", send_fd(channel[0], pair[0]));
+  close(pair[0]);
+  int received = recv_fd(channel[1]);
+  if (received < 0) {
+    return 1;
+  }
+
+  // the received fd refers to the same socket as the one sent.
+  const char *msg = "through the passed fd";
+  write(received, msg, strlen(msg));
+  char buf[64];
+  ssize_t n = read(pair[1], buf, sizeof(buf) - 1);
+  buf[n < 0 ? 0 : n] = '\0';
+  printf("read %zd bytes: %s\n", n, buf);
+
+  // a plain message carries no control data.
+  write(channel[0], "y", 1);
+  char c;
+  char control[64];
+  struct iovec iov = {.iov_base = &c, .iov_len = 1};
+  struct msghdr plain = {
+      .msg_iov = &iov,
+      .msg_iovlen = 1,
+      .msg_control = control,
+      .msg_controllen = sizeof(control),
+  };
+  n = recvmsg(channel[1], &plain, 0);
+  printf("plain recvmsg: %zd bytes '%c', controllen: %zu\n", n, c,
+         (size_t)plain.msg_controllen);
+
+  if (send_fd(channel[0], 12345) < 0 && errno == EBADF) {
+    printf("sending a bad fd: EBADF\n");
+  }
+  return 0;
+}
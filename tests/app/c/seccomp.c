@@ -0,0 +1,15 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main(void) {
+  // keyctl(2) is denied by the default seccomp profile of Docker.
+  errno = 0;
+  long ret = syscall(SYS_keyctl, 0 /* KEYCTL_GET_KEYRING_ID */, -1, 0);
+  printf("keyctl returned %ld: %s\n", ret, strerror(errno));
+
+  printf("getpid succeeded: %d\n", getpid() > 0);
+  return 0;
+}
@@ -0,0 +1,36 @@
+#include <setjmp.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+
+static sigjmp_buf env;
+static volatile void *fault_addr;
+
+static void handler(int sig, siginfo_t *info, void *ucontext) {
+  fault_addr = info->si_addr;
+  siglongjmp(env, sig);
+}
+
+int main() {
+  struct sigaction act;
+  memset(&act, 0, sizeof(act));
+  act.sa_sigaction = handler;
+  act.sa_flags = SA_SIGINFO;
+  sigemptyset(&act.sa_mask);
+  if (sigaction(SIGSEGV, &act, NULL) < 0) {
+    printf("sigaction failed\n");
+    return 1;
+  }
+
+  volatile int *wild = (int *)0xdead0000;
+  int sig = sigsetjmp(env, 1);
+  if (sig == 0) {
+    printf("value: %d\n", *wild);
+    printf("wild pointer access did not fault\n");
+    return 1;
+  }
+  printf("caught signal %d at %s address\n", sig,
+         fault_addr == (void *)wild ? "the expected" : "an unexpected");
+  printf("continuing after the fault\n");
+  return 0;
+}
@@ -0,0 +1,72 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/sendfile.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#define SIZE 5000
+
+int main() {
+  const char *path = "/tmp/sendfile_test";
+  int fd = open(path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+  static char pattern[SIZE], buf[SIZE];
+  for (int i = 0; i < SIZE; i++) {
+    pattern[i] = i % 251;
+  }
+  write(fd, pattern, SIZE);
+
+  int sv[2];
+  socketpair(AF_UNIX, SOCK_STREAM, 0, sv);
+
+  // A given offset is advanced instead of the file offset.
+  off_t offset = 0;
+  ssize_t n = sendfile(sv[0], fd, &offset, 3000);
+  printf("sendfile: %ld, offset: %ld, file offset: %ld\n", (long)n,
+         (long)offset, (long)lseek(fd, 0, SEEK_CUR));
+
+  // Without an offset, the file offset is used and advanced up to EOF.
+  lseek(fd, 3000, SEEK_SET);
+  n = sendfile(sv[0], fd, NULL, SIZE);
+  printf("sendfile: %ld, file offset: %ld\n", (long)n,
+         (long)lseek(fd, 0, SEEK_CUR));
+  n = sendfile(sv[0], fd, NULL, SIZE);
+  printf("at EOF: %ld\n", (long)n);
+
+  size_t total = 0;
+  while (total < SIZE) {
+    n = read(sv[1], buf + total, SIZE - total);
+    if (n <= 0) {
+      break;
+    }
+    total += n;
+  }
+  printf("received: %ld, same: %d\n", (long)total,
+         memcmp(buf, pattern, SIZE) == 0);
+
+  // A full non-blocking socket ends the transfer with EAGAIN.
+  fcntl(sv[0], F_SETFL, O_NONBLOCK);
+  size_t sent = 0;
+  for (;;) {
+    offset = 0;
+    n = sendfile(sv[0], fd, &offset, SIZE);
+    if (n < 0) {
+      break;
+    }
+    sent += n;
+  }
+  printf("sent some: %d, EAGAIN: %d\n", sent > 0, errno == EAGAIN);
+
+  int ro = open(path, O_RDONLY);
+  offset = 0;
+  n = sendfile(ro, fd, &offset, 1);
+  printf("read only output: %ld, EBADF: %d\n", (long)n, errno == EBADF);
+  close(ro);
+
+  close(sv[0]);
+  close(sv[1]);
+  close(fd);
+  unlink(path);
+  return 0;
+}
@@ -0,0 +1,75 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <netinet/in.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int so_error(int fd) {
+  int val = -1;
+  socklen_t len = sizeof(val);
+  if (getsockopt(fd, SOL_SOCKET, SO_ERROR, &val, &len) < 0) {
+    return -1;
+  }
+  return val;
+}
+
+int main() {
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8083);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+  int listener = socket(AF_INET, SOCK_STREAM, 0);
+  if (bind(listener, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+      listen(listener, 1) < 0) {
+    printf("listen failed\n");
+    return 1;
+  }
+
+  int client = socket(AF_INET, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("connect failed\n");
+    return 1;
+  }
+  printf("SO_ERROR after connect: %d\n", so_error(client));
+  close(client);
+  close(listener);
+
+  // Nothing listens on this port, so the connection is refused.
+  addr.sin_port = htons(8084);
+  client = socket(AF_INET, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) == 0) {
+    printf("connect to a closed port succeeded\n");
+    return 1;
+  }
+  printf("connect to a closed port: %s\n",
+         errno == ECONNREFUSED ? "ECONNREFUSED" : "other error");
+  printf("SO_ERROR after refused connect: %d\n", so_error(client));
+  close(client);
+
+  // A non-blocking connect reports the refusal through poll and SO_ERROR.
+  addr.sin_port = htons(8085);
+  client = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) == 0 ||
+      errno != EINPROGRESS) {
+    printf("non-blocking connect did not fail with EINPROGRESS\n");
+    return 1;
+  }
+  printf("non-blocking connect: EINPROGRESS\n");
+  struct pollfd pfd = {.fd = client, .events = POLLOUT};
+  if (poll(&pfd, 1, 5000) != 1) {
+    printf("poll did not report the refused connect\n");
+    return 1;
+  }
+  printf("poll: POLLERR %s\n", pfd.revents & POLLERR ? "set" : "unset");
+  printf("SO_ERROR: %s\n",
+         so_error(client) == ECONNREFUSED ? "ECONNREFUSED" : "other");
+  printf("SO_ERROR read again: %d\n", so_error(client));
+  close(client);
+  return 0;
+}
@@ -0,0 +1,56 @@
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/time.h>
+#include <unistd.h>
+
+static void print_timeout(int fd, int name, const char *what) {
+  struct timeval tv;
+  socklen_t len = sizeof(tv);
+  if (getsockopt(fd, SOL_SOCKET, name, &tv, &len) < 0) {
+    printf("%s: %s\n", what, strerror(errno));
+    return;
+  }
+  printf("%s: %ld.%06ld\n", what, (long)tv.tv_sec, (long)tv.tv_usec);
+}
+
+static void set_timeout(int fd, int name, long sec, long usec) {
+  struct timeval tv = {.tv_sec = sec, .tv_usec = usec};
+  if (setsockopt(fd, SOL_SOCKET, name, &tv, sizeof(tv)) < 0) {
+    printf("setsockopt: %s\n", strerror(errno));
+  }
+}
+
+int main(void) {
+  int types[] = {SOCK_STREAM, SOCK_DGRAM, SOCK_SEQPACKET};
+  const char *names[] = {"stream", "dgram", "seqpacket"};
+  for (int i = 0; i < 3; i++) {
+    int fds[2];
+    if (socketpair(AF_UNIX, types[i], 0, fds) < 0) {
+      perror("socketpair");
+      return 1;
+    }
+    printf("%s\n", names[i]);
+    print_timeout(fds[0], SO_RCVTIMEO, "initial rcvtimeo");
+    set_timeout(fds[0], SO_RCVTIMEO, 2, 500000);
+    set_timeout(fds[0], SO_SNDTIMEO, 1, 0);
+    print_timeout(fds[0], SO_RCVTIMEO, "rcvtimeo");
+    print_timeout(fds[0], SO_SNDTIMEO, "sndtimeo");
+    set_timeout(fds[0], SO_RCVTIMEO, 0, 0);
+    print_timeout(fds[0], SO_RCVTIMEO, "cleared rcvtimeo");
+    char c = 0;
+    printf("write: %zd\n", write(fds[0], "x", 1));
+    ssize_t n = read(fds[1], &c, 1);
+    printf("read: %zd %c\n", n, c);
+    close(fds[0]);
+    close(fds[1]);
+  }
+
+  int fd = socket(AF_INET, SOCK_STREAM, 0);
+  print_timeout(fd, SO_RCVTIMEO, "tcp rcvtimeo");
+  print_timeout(fd, SO_SNDTIMEO, "tcp sndtimeo");
+  close(fd);
+  return 0;
+}
@@ -0,0 +1,61 @@
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int get_int(int fd, int name) {
+  int v = -1;
+  socklen_t len = sizeof(v);
+  if (getsockopt(fd, SOL_SOCKET, name, &v, &len) < 0) {
+    return -1;
+  }
+  return v;
+}
+
+static void describe(const char *what, int fd) {
+  int type = get_int(fd, SO_TYPE);
+  int domain = get_int(fd, SO_DOMAIN);
+  int protocol = get_int(fd, SO_PROTOCOL);
+  printf("%s: type %s, domain %s, protocol %s\n", what,
+         type == SOCK_STREAM  ? "SOCK_STREAM"
+         : type == SOCK_DGRAM ? "SOCK_DGRAM"
+                              : "other",
+         domain == AF_INET    ? "AF_INET"
+         : domain == AF_INET6 ? "AF_INET6"
+         : domain == AF_UNIX  ? "AF_UNIX"
+                              : "other",
+         protocol == IPPROTO_TCP   ? "IPPROTO_TCP"
+         : protocol == IPPROTO_UDP ? "IPPROTO_UDP"
+         : protocol == 0           ? "0"
+                                   : "other");
+}
+
+int main() {
+  int udp = socket(AF_INET, SOCK_DGRAM, 0);
+  if (udp < 0) {
+    printf("socket failed\n");
+    return 1;
+  }
+  describe("udp", udp);
+
+  // The flags given with the type are not part of SO_TYPE.
+  int tcp = socket(AF_INET6, SOCK_STREAM | SOCK_NONBLOCK | SOCK_CLOEXEC, 0);
+  describe("tcp6", tcp);
+
+  int unix_fd = socket(AF_UNIX, SOCK_STREAM, 0);
+  describe("unix", unix_fd);
+
+  int v = SOCK_STREAM;
+  if (setsockopt(udp, SOL_SOCKET, SO_TYPE, &v, sizeof(v)) == 0 ||
+      errno != ENOPROTOOPT) {
+    printf("setsockopt(SO_TYPE) did not fail with ENOPROTOOPT\n");
+    return 1;
+  }
+  printf("SO_TYPE is read-only\n");
+
+  close(udp);
+  close(tcp);
+  close(unix_fd);
+  return 0;
+}
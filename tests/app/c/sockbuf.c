@@ -0,0 +1,61 @@
+#include <netinet/in.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int get_buf(int fd, int name) {
+  int val = 0;
+  socklen_t len = sizeof(val);
+  getsockopt(fd, SOL_SOCKET, name, &val, &len);
+  return val;
+}
+
+static int check(const char *label, int type, int name, int size) {
+  int fd = socket(AF_INET, type, 0);
+  if (fd < 0) {
+    printf("%s: socket failed\n", label);
+    return 1;
+  }
+  if (setsockopt(fd, SOL_SOCKET, name, &size, sizeof(size)) < 0) {
+    printf("%s: setsockopt failed\n", label);
+    return 1;
+  }
+  // Linux may double the value, so only check that the new size took effect.
+  int got = get_buf(fd, name);
+  printf("%s %d: %s\n", label, size, got >= size ? "ok" : "too small");
+  close(fd);
+  return 0;
+}
+
+// check_listening sets the buffer size on a listening socket, which must not fail.
+static int check_listening(int name, int size) {
+  int fd = socket(AF_INET, SOCK_STREAM, 0);
+  struct sockaddr_in addr = {0};
+  addr.sin_family = AF_INET;
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  addr.sin_port = htons(8086);
+  if (fd < 0 || bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+      listen(fd, 4) < 0) {
+    printf("listening: setup failed\n");
+    return 1;
+  }
+  if (setsockopt(fd, SOL_SOCKET, name, &size, sizeof(size)) < 0) {
+    printf("listening: setsockopt failed\n");
+    return 1;
+  }
+  int got = get_buf(fd, name);
+  printf("listening %d: %s\n", size, got >= size ? "ok" : "too small");
+  close(fd);
+  return 0;
+}
+
+int main() {
+  if (check("tcp SO_SNDBUF", SOCK_STREAM, SO_SNDBUF, 131072) ||
+      check("tcp SO_RCVBUF", SOCK_STREAM, SO_RCVBUF, 131072) ||
+      check("udp SO_SNDBUF", SOCK_DGRAM, SO_SNDBUF, 131072) ||
+      check("udp SO_RCVBUF", SOCK_DGRAM, SO_RCVBUF, 131072) ||
+      check_listening(SO_RCVBUF, 131072)) {
+    return 1;
+  }
+  return 0;
+}
@@ -0,0 +1,49 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static void exchange(int type, const char *name) {
+  int sv[2];
+  if (socketpair(AF_UNIX, type, 0, sv) < 0) {
+    printf("%s: socketpair failed: %d\n", name, errno);
+    return;
+  }
+
+  char buf[32];
+  const char *msg = "ping";
+  write(sv[0], msg, strlen(msg));
+  ssize_t n = read(sv[1], buf, sizeof(buf) - 1);
+  buf[n < 0 ? 0 : n] = '\0';
+  printf("%s: sv[1] read %zd bytes: %s\n", name, n, buf);
+
+  msg = "pong";
+  write(sv[1], msg, strlen(msg));
+  n = read(sv[0], buf, sizeof(buf) - 1);
+  buf[n < 0 ? 0 : n] = '\0';
+  printf("%s: sv[0] read %zd bytes: %s\n", name, n, buf);
+
+  close(sv[0]);
+  close(sv[1]);
+}
+
+int main() {
+  exchange(SOCK_STREAM, "SOCK_STREAM");
+  exchange(SOCK_DGRAM, "SOCK_DGRAM");
+
+  int sv[2];
+  if (socketpair(AF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0, sv) == 0) {
+    char c;
+    if (read(sv[0], &c, 1) < 0 && errno == EAGAIN) {
+      printf("SOCK_NONBLOCK: read on an empty socket: EAGAIN\n");
+    }
+    close(sv[0]);
+    close(sv[1]);
+  }
+
+  if (socketpair(AF_INET, SOCK_STREAM, 0, sv) < 0 && errno == EOPNOTSUPP) {
+    printf("AF_INET: EOPNOTSUPP\n");
+  }
+  return 0;
+}
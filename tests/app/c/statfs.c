@@ -0,0 +1,35 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/magic.h>
+#include <stdio.h>
+#include <sys/statfs.h>
+#include <unistd.h>
+
+int main() {
+  struct statfs st;
+  if (statfs("/dev/shm", &st) != 0) {
+    printf("statfs /dev/shm failed\n");
+    return 1;
+  }
+  printf("/dev/shm is tmpfs: %d\n", st.f_type == TMPFS_MAGIC);
+  printf("/dev/shm namelen: %ld\n", (long)st.f_namelen);
+  printf("/dev/shm free <= total: %d\n", st.f_bfree <= st.f_blocks);
+
+  int fd = open("/dev/shm", O_RDONLY | O_DIRECTORY);
+  if (fd < 0 || fstatfs(fd, &st) != 0) {
+    printf("fstatfs /dev/shm failed\n");
+    return 1;
+  }
+  printf("fstatfs /dev/shm is tmpfs: %d\n", st.f_type == TMPFS_MAGIC);
+  close(fd);
+
+  if (statfs("/proc", &st) != 0) {
+    printf("statfs /proc failed\n");
+    return 1;
+  }
+  printf("/proc is procfs: %d\n", st.f_type == PROC_SUPER_MAGIC);
+
+  int ret = statfs("/nonexistent", &st);
+  printf("statfs /nonexistent: %d, ENOENT: %d\n", ret, errno == ENOENT);
+  return 0;
+}
@@ -0,0 +1,45 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+int main() {
+  const char *content = "symlink target";
+  int fd = open("symlink_target", O_CREAT | O_WRONLY, 0644);
+  write(fd, content, strlen(content));
+  close(fd);
+
+  printf("symlink: %d\n", symlink("symlink_target", "symlink_link"));
+  char buf[64];
+  memset(buf, 0, sizeof(buf));
+  ssize_t n = readlink("symlink_link", buf, sizeof(buf));
+  printf("readlink: %zd %s\n", n, buf);
+  if (readlink("symlink_target", buf, sizeof(buf)) < 0 && errno == EINVAL) {
+    printf("readlink on a regular file: EINVAL\n");
+  }
+
+  memset(buf, 0, sizeof(buf));
+  fd = open("symlink_link", O_RDONLY);
+  n = read(fd, buf, sizeof(buf));
+  printf("read through the link: %zd %s\n", n, buf);
+  close(fd);
+
+  if (symlink("symlink_target", "symlink_link") < 0 && errno == EEXIST) {
+    printf("symlink over an existing name: EEXIST\n");
+  }
+  if (open("symlink_link", O_RDONLY | O_NOFOLLOW) < 0 && errno == ELOOP) {
+    printf("open with O_NOFOLLOW: ELOOP\n");
+  }
+
+  symlink("symlink_loop", "symlink_loop");
+  if (open("symlink_loop", O_RDONLY) < 0 && errno == ELOOP) {
+    printf("open of a self-referencing link: ELOOP\n");
+  }
+
+  symlink("symlink_missing", "symlink_dangling");
+  if (open("symlink_dangling", O_RDONLY) < 0 && errno == ENOENT) {
+    printf("open of a dangling link: ENOENT\n");
+  }
+  return 0;
+}
@@ -0,0 +1,96 @@
+#include <arpa/inet.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static const char *state_name(int state) {
+  switch (state) {
+  case TCP_ESTABLISHED:
+    return "ESTABLISHED";
+  case TCP_LISTEN:
+    return "LISTEN";
+  case TCP_CLOSE:
+    return "CLOSE";
+  default:
+    return "other";
+  }
+}
+
+static int print_state(const char *name, int fd) {
+  struct tcp_info info;
+  memset(&info, 0, sizeof(info));
+  socklen_t len = sizeof(info);
+  if (getsockopt(fd, IPPROTO_TCP, TCP_INFO, &info, &len) < 0) {
+    printf("getsockopt(TCP_INFO) on %s failed\n", name);
+    return -1;
+  }
+  printf("%s: %s\n", name, state_name(info.tcpi_state));
+  return 0;
+}
+
+int main() {
+  int listener = socket(AF_INET, SOCK_STREAM, 0);
+  if (listener < 0) {
+    printf("socket failed\n");
+    return 1;
+  }
+  if (print_state("unconnected", listener) < 0) {
+    return 1;
+  }
+
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8091);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  if (bind(listener, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("bind failed\n");
+    return 1;
+  }
+  if (listen(listener, 1) < 0) {
+    printf("listen failed\n");
+    return 1;
+  }
+  if (print_state("listener", listener) < 0) {
+    return 1;
+  }
+
+  int client = socket(AF_INET, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("connect failed\n");
+    return 1;
+  }
+  int conn = accept(listener, NULL, NULL);
+  if (conn < 0) {
+    printf("accept failed\n");
+    return 1;
+  }
+  if (print_state("client", client) < 0 ||
+      print_state("accepted", conn) < 0) {
+    return 1;
+  }
+
+  struct tcp_info info;
+  socklen_t len = sizeof(info);
+  getsockopt(client, IPPROTO_TCP, TCP_INFO, &info, &len);
+  printf("snd_mss set: %s, rcv_space set: %s\n",
+         info.tcpi_snd_mss > 0 ? "yes" : "no",
+         info.tcpi_rcv_space > 0 ? "yes" : "no");
+
+  // A shorter buffer gets the head of the structure.
+  unsigned char state = 0;
+  len = 1;
+  if (getsockopt(client, IPPROTO_TCP, TCP_INFO, &state, &len) < 0) {
+    printf("getsockopt(TCP_INFO) with a short buffer failed\n");
+    return 1;
+  }
+  printf("truncated: len %d, %s\n", (int)len, state_name(state));
+
+  close(conn);
+  close(client);
+  close(listener);
+  return 0;
+}
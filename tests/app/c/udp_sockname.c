@@ -0,0 +1,65 @@
+#include <arpa/inet.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static void print_name(const char *label, struct sockaddr_in *addr) {
+  printf("%s: family %s, %s:%d\n", label,
+         addr->sin_family == AF_INET ? "AF_INET" : "other",
+         inet_ntoa(addr->sin_addr), ntohs(addr->sin_port));
+}
+
+int main() {
+  struct sockaddr_in addr;
+  memset(&addr, 0, sizeof(addr));
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(8086);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+  int receiver = socket(AF_INET, SOCK_DGRAM, 0);
+  if (bind(receiver, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("bind failed\n");
+    return 1;
+  }
+
+  int sender = socket(AF_INET, SOCK_DGRAM, 0);
+  struct sockaddr_in name;
+  socklen_t len = sizeof(name);
+  if (getsockname(sender, (struct sockaddr *)&name, &len) < 0) {
+    printf("getsockname on an unbound socket failed\n");
+    return 1;
+  }
+  print_name("unbound", &name);
+
+  sendto(sender, "hello", 5, 0, (struct sockaddr *)&addr, sizeof(addr));
+  len = sizeof(name);
+  if (getsockname(sender, (struct sockaddr *)&name, &len) < 0) {
+    printf("getsockname after send failed\n");
+    return 1;
+  }
+  printf("ephemeral port assigned: %s\n", name.sin_port != 0 ? "yes" : "no");
+
+  char buf[16];
+  struct sockaddr_in from;
+  len = sizeof(from);
+  recvfrom(receiver, buf, sizeof(buf), 0, (struct sockaddr *)&from, &len);
+  printf("datagram came from the reported port: %s\n",
+         from.sin_port == name.sin_port ? "yes" : "no");
+
+  if (connect(sender, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    printf("connect failed\n");
+    return 1;
+  }
+  len = sizeof(name);
+  if (getpeername(sender, (struct sockaddr *)&name, &len) < 0) {
+    printf("getpeername after connect failed\n");
+    return 1;
+  }
+  print_name("peer", &name);
+
+  close(sender);
+  close(receiver);
+  return 0;
+}
@@ -0,0 +1,29 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/utsname.h>
+
+int main() {
+  struct utsname buf;
+  if (uname(&buf) != 0) {
+    printf("uname failed\n");
+    return 1;
+  }
+  printf("sysname: %s\n", buf.sysname);
+  printf("machine: %s\n", buf.machine);
+
+  // The runtime sets the hostname of the container, which is also written to /etc/hostname.
+  char hostname[sizeof(buf.nodename) + 1] = {0};
+  FILE *f = fopen("/etc/hostname", "r");
+  if (f == NULL) {
+    printf("fopen /etc/hostname failed\n");
+    return 1;
+  }
+  if (fgets(hostname, sizeof(hostname), f) == NULL) {
+    printf("fgets /etc/hostname failed\n");
+    return 1;
+  }
+  fclose(f);
+  hostname[strcspn(hostname, "\n")] = '\0';
+  printf("nodename is the hostname: %d\n", strcmp(buf.nodename, hostname) == 0);
+  return 0;
+}
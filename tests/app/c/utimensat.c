@@ -0,0 +1,52 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main() {
+  const char *path = "/tmp/utimensat_test";
+  int fd = open(path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+  if (fd < 0) {
+    printf("open failed\n");
+    return 1;
+  }
+
+  struct timespec times[2] = {
+      {.tv_sec = 100000000, .tv_nsec = 1000},
+      {.tv_sec = 1000000000, .tv_nsec = 42},
+  };
+  printf("futimens: %d\n", futimens(fd, times));
+  struct stat st;
+  fstat(fd, &st);
+  printf("atime: %ld.%ld\n", (long)st.st_atim.tv_sec, st.st_atim.tv_nsec);
+  printf("mtime: %ld.%ld\n", (long)st.st_mtim.tv_sec, st.st_mtim.tv_nsec);
+
+  // UTIME_OMIT leaves the access time untouched.
+  struct timespec omit_atime[2] = {
+      {.tv_nsec = UTIME_OMIT},
+      {.tv_sec = 1234567890, .tv_nsec = 0},
+  };
+  printf("utimensat: %d\n", utimensat(AT_FDCWD, path, omit_atime, 0));
+  fstat(fd, &st);
+  printf("atime: %ld.%ld\n", (long)st.st_atim.tv_sec, st.st_atim.tv_nsec);
+  printf("mtime: %ld.%ld\n", (long)st.st_mtim.tv_sec, st.st_mtim.tv_nsec);
+
+  // A null times sets both to now.
+  printf("utimensat now: %d\n", utimensat(AT_FDCWD, path, NULL, 0));
+  fstat(fd, &st);
+  printf("mtime is recent: %d\n", st.st_mtim.tv_sec > 1234567890);
+
+  struct timespec bad[2] = {
+      {.tv_sec = 0, .tv_nsec = 1000000000},
+      {.tv_nsec = UTIME_OMIT},
+  };
+  int ret = utimensat(AT_FDCWD, path, bad, 0);
+  printf("invalid nsec: %d, EINVAL: %d\n", ret, errno == EINVAL);
+  ret = utimensat(AT_FDCWD, "/tmp/utimensat_nonexistent", NULL, 0);
+  printf("nonexistent: %d, ENOENT: %d\n", ret, errno == ENOENT);
+
+  close(fd);
+  unlink(path);
+  return 0;
+}
@@ -0,0 +1,44 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/xattr.h>
+#include <unistd.h>
+
+int main() {
+  const char *path = "/tmp/xattr_test";
+  int fd = open(path, O_CREAT | O_RDWR | O_TRUNC, 0644);
+
+  printf("setxattr: %d\n", setxattr(path, "user.a", "hello", 5, 0));
+  printf("size: %ld\n", (long)getxattr(path, "user.a", NULL, 0));
+
+  char buf[64] = {0};
+  ssize_t ret = getxattr(path, "user.a", buf, 2);
+  printf("small buffer: %ld, ERANGE: %d\n", (long)ret, errno == ERANGE);
+  ret = fgetxattr(fd, "user.a", buf, sizeof(buf));
+  printf("fgetxattr: %ld, %.*s\n", (long)ret, (int)ret, buf);
+
+  ret = setxattr(path, "user.a", "x", 1, XATTR_CREATE);
+  printf("create existing: %ld, EEXIST: %d\n", (long)ret, errno == EEXIST);
+  ret = setxattr(path, "user.b", "x", 1, XATTR_REPLACE);
+  printf("replace missing: %ld, ENODATA: %d\n", (long)ret, errno == ENODATA);
+
+  char list[256] = {0};
+  printf("list size: %ld\n", (long)listxattr(path, NULL, 0));
+  ret = listxattr(path, list, 1);
+  printf("small list: %ld, ERANGE: %d\n", (long)ret, errno == ERANGE);
+  ret = flistxattr(fd, list, sizeof(list));
+  printf("flistxattr: %ld, %s\n", (long)ret, list);
+
+  printf("removexattr: %d\n", removexattr(path, "user.a"));
+  ret = getxattr(path, "user.a", buf, sizeof(buf));
+  printf("removed: %ld, ENODATA: %d\n", (long)ret, errno == ENODATA);
+
+  ret = setxattr(path, "foo.a", "x", 1, 0);
+  printf("unknown namespace: %ld, EOPNOTSUPP: %d\n", (long)ret,
+         errno == EOPNOTSUPP);
+
+  close(fd);
+  unlink(path);
+  return 0;
+}
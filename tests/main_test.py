@@ -120,6 +120,61 @@ if __name__ == '__main__':
     test_simple_binaries(client, 'echo', '/echo And in the end, \
         the love you take is equal to the love you make')
     # test_simple_binaries(client, 'open', '')
+    test_simple_binaries(client, 'segv_handler', '/segv_handler')
+    test_simple_binaries(client, 'listen_poll', '/listen_poll')
+    test_simple_binaries(client, 'accept', '/accept')
+    test_simple_binaries(client, 'reuseaddr', '/reuseaddr')
+    test_simple_binaries(client, 'so_error', '/so_error')
+    test_simple_binaries(client, 'sockbuf', '/sockbuf')
+    test_simple_binaries(client, 'fionread', '/fionread')
+    test_simple_binaries(client, 'rlimit_fsize', '/rlimit_fsize')
+    test_simple_binaries(client, 'fpu_signal', '/fpu_signal')
+    test_simple_binaries(client, 'udp_sockname', '/udp_sockname')
+    test_simple_binaries(client, 'close_rebind', '/close_rebind')
+    test_simple_binaries(client, 'broadcast', '/broadcast')
+    test_simple_binaries(client, 'dup2', '/dup2')
+    test_simple_binaries(client, 'fcntl', '/fcntl')
+    test_simple_binaries(client, 'symlink', '/symlink')
+    test_simple_binaries(client, 'getcwd', '/getcwd')
+    test_simple_binaries(client, 'chdir', '/chdir')
+    test_simple_binaries(client, 'mincore', '/mincore')
+    test_simple_binaries(client, 'madvise', '/madvise')
+    test_simple_binaries(client, 'socketpair', '/socketpair')
+    test_simple_binaries(client, 'scm_rights', '/scm_rights')
+    test_simple_binaries(client, 'mounts', '/mounts')
+    test_simple_binaries(client, 'seccomp', '/seccomp')
+    test_simple_binaries(client, 'masked_paths', '/masked_paths')
+    test_simple_binaries(client, 'prlimit', '/prlimit')
+    test_simple_binaries(client, 'multicast', '/multicast')
+    test_simple_binaries(client, 'sock_timeout', '/sock_timeout')
+    test_simple_binaries(client, 'eventfd', '/eventfd')
+    test_simple_binaries(client, 'pipe_packet', '/pipe_packet')
+    test_simple_binaries(client, 'ppoll', '/ppoll')
+    test_simple_binaries(client, 'epoll', '/epoll')
+    test_simple_binaries(client, 'fallocate', '/fallocate')
+    test_simple_binaries(client, 'getrandom', '/getrandom')
+    test_simple_binaries(client, 'proc_self', '/proc_self')
+    test_simple_binaries(client, 'uname', '/uname')
+    test_simple_binaries(client, 'dev_files', '/dev_files')
+    test_simple_binaries(client, 'statfs', '/statfs')
+    test_simple_binaries(client, 'utimensat', '/utimensat')
+    test_simple_binaries(client, 'renameat2', '/renameat2')
+    test_simple_binaries(client, 'link', '/link')
+    test_simple_binaries(client, 'xattr', '/xattr')
+    test_simple_binaries(client, 'copy_file_range', '/copy_file_range')
+    test_simple_binaries(client, 'sendfile', '/sendfile')
+    test_simple_binaries(client, 'flock', '/flock')
+    test_simple_binaries(client, 'fcntl_lock', '/fcntl_lock')
+    test_simple_binaries(client, 'mlock', '/mlock')
+    test_simple_binaries(client, 'map_populate', '/map_populate')
+    test_simple_binaries(client, 'growsdown', '/growsdown')
+    test_simple_binaries(client, 'mprotect', '/mprotect')
+    test_simple_binaries(client, 'mremap', '/mremap')
+    test_simple_binaries(client, 'process_vm', '/process_vm')
+    test_simple_binaries(client, 'bind_port', '/bind_port')
+    test_simple_binaries(client, 'ipv6_tcp', '/ipv6_tcp')
+    test_simple_binaries(client, 'tcp_info', '/tcp_info')
+    test_simple_binaries(client, 'sock_type', '/sock_type')
 
     python = Language('python', 'py')
     ruby = Language('ruby', 'rb')